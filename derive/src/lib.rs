@@ -0,0 +1,169 @@
+//! Derive macros for [`serde-lua-table`](https://github.com/Nerixyz/serde-lua-table).
+//!
+//! Use them through the `derive` feature of `serde-lua-table`, which re-exports them.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr};
+
+/// Implements `LuaComments` from `#[lua(comment = "...")]` attributes on the fields of a struct.
+///
+/// The comment is looked up by the key the field is written with, so `#[serde(rename = "...")]`
+/// is taken into account.
+#[proc_macro_derive(LuaComments, attributes(lua))]
+pub fn derive_lua_comments(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_comments(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements `LuaFieldStyles` from `#[lua(key_style = "bare")]`, `#[lua(key_style = "bracket")]`
+/// and `#[lua(hex)]` attributes on the fields of a struct.
+///
+/// Like `LuaComments`, the style is looked up by the key the field is written with.
+/// `FieldStyles::for_type` only asks about the keys of the struct's own table, so nested tables
+/// with keys of the same name aren't styled.
+#[proc_macro_derive(LuaFieldStyles, attributes(lua))]
+pub fn derive_lua_field_styles(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_field_styles(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// The `#[lua(...)]` attributes of a field.
+struct FieldAttrs {
+    /// The key the field is written with.
+    key: String,
+    comment: Option<LitStr>,
+    /// `Bare` or `Bracketed`.
+    key_style: Option<syn::Ident>,
+    hex: bool,
+}
+
+fn expand_comments(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let arms = field_attrs(&input, "LuaComments")?
+        .into_iter()
+        .filter_map(|attrs| {
+            let key = attrs.key;
+            let comment = attrs.comment?;
+            Some(quote! { #key => ::core::option::Option::Some(#comment), })
+        });
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::serde_lua_table::LuaComments for #name #ty_generics #where_clause {
+            fn lua_comment(key: &str) -> ::core::option::Option<&'static str> {
+                match key {
+                    #(#arms)*
+                    _ => ::core::option::Option::None,
+                }
+            }
+        }
+    })
+}
+
+fn expand_field_styles(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let arms = field_attrs(&input, "LuaFieldStyles")?
+        .into_iter()
+        .filter(|attrs| attrs.key_style.is_some() || attrs.hex)
+        .map(|attrs| {
+            let key = attrs.key;
+            let hex = attrs.hex;
+            let key_style = match attrs.key_style {
+                Some(style) => quote! {
+                    ::core::option::Option::Some(::serde_lua_table::KeyStyle::#style)
+                },
+                None => quote! { ::core::option::Option::None },
+            };
+            quote! {
+                #key => ::serde_lua_table::FieldStyle { key_style: #key_style, hex: #hex },
+            }
+        });
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::serde_lua_table::LuaFieldStyles for #name #ty_generics #where_clause {
+            fn lua_field_style(key: &str) -> ::serde_lua_table::FieldStyle {
+                match key {
+                    #(#arms)*
+                    _ => ::serde_lua_table::FieldStyle::default(),
+                }
+            }
+        }
+    })
+}
+
+/// Parses the attributes of the named fields of a struct.
+fn field_attrs(input: &DeriveInput, derive: &str) -> syn::Result<Vec<FieldAttrs>> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            format!("{derive} can only be derived for structs"),
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            format!("{derive} needs named fields"),
+        ));
+    };
+
+    let mut all = Vec::new();
+    for field in &fields.named {
+        let mut attrs = FieldAttrs {
+            key: field
+                .ident
+                .as_ref()
+                .map(|ident| ident.to_string())
+                .unwrap_or_default(),
+            comment: None,
+            key_style: None,
+            hex: false,
+        };
+        for attr in &field.attrs {
+            if attr.path().is_ident("lua") {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("comment") {
+                        attrs.comment = Some(meta.value()?.parse::<LitStr>()?);
+                    } else if meta.path.is_ident("key_style") {
+                        let style = meta.value()?.parse::<LitStr>()?;
+                        let variant = match style.value().as_str() {
+                            "bare" => "Bare",
+                            "bracket" => "Bracketed",
+                            _ => {
+                                return Err(syn::Error::new_spanned(
+                                    style,
+                                    "expected `\"bare\"` or `\"bracket\"`",
+                                ))
+                            }
+                        };
+                        attrs.key_style = Some(syn::Ident::new(variant, style.span()));
+                    } else if meta.path.is_ident("hex") {
+                        attrs.hex = true;
+                    } else {
+                        return Err(meta.error(
+                            "unknown lua attribute, expected `comment`, `key_style` or `hex`",
+                        ));
+                    }
+                    Ok(())
+                })?;
+            } else if attr.path().is_ident("serde") {
+                // Other serde attributes are none of our business, so errors are ignored.
+                let _ = attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("rename") {
+                        attrs.key = meta.value()?.parse::<LitStr>()?.value();
+                    } else if meta.input.peek(syn::Token![=]) {
+                        meta.value()?.parse::<syn::Expr>()?;
+                    }
+                    Ok(())
+                });
+            }
+        }
+        all.push(attrs);
+    }
+    Ok(all)
+}
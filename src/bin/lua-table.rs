@@ -0,0 +1,403 @@
+//! Converts data files to lua tables and formats lua data files.
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use serde_lua_table::{
+    value::{self, Change},
+    CompactFormatter, LuaVersion, PathSegment, PrettyFormatter, Serializer, Value, WithHeader,
+};
+use std::{
+    error::Error,
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+#[derive(Parser)]
+#[command(name = "lua-table", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Converts a data file between JSON, TOML, YAML and lua.
+    Convert(ConvertArgs),
+    /// Rewrites a lua data file with one entry per line, keeping its comments.
+    Fmt(FormatArgs),
+    /// Rewrites a lua data file without any whitespace.
+    Minify(FormatArgs),
+    /// Checks that a lua data file can be read.
+    Check(CheckArgs),
+    /// Prints the fields that differ between two lua data files, ignoring how they are written.
+    Diff(DiffArgs),
+}
+
+#[derive(Args)]
+struct ConvertArgs {
+    /// File to read, standard input if missing.
+    input: Option<PathBuf>,
+    /// File to write, standard output if missing.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Format of the input, guessed from its extension if missing.
+    #[arg(long)]
+    from: Option<Format>,
+    /// Format of the output, guessed from its extension if missing and lua otherwise.
+    #[arg(long)]
+    to: Option<Format>,
+    /// Write lua without any whitespace.
+    #[arg(long)]
+    compact: bool,
+    /// Prefix lua with `return ` so it can be loaded as a chunk.
+    #[arg(long = "return")]
+    with_return: bool,
+}
+
+#[derive(Args)]
+struct FormatArgs {
+    /// File to read, standard input if missing.
+    input: Option<PathBuf>,
+    /// File to write, standard output if missing.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct CheckArgs {
+    /// File to read, standard input if missing.
+    input: Option<PathBuf>,
+    /// Also warn about duplicate keys, tables with entries with and without keys, integers that
+    /// the lua version can't hold, NaN, deeply nested tables and arrays with holes.
+    #[arg(long)]
+    lint: bool,
+    /// The lua version that loads the file.
+    #[arg(long, default_value = "5.4")]
+    lua: Version,
+}
+
+#[derive(Args)]
+struct DiffArgs {
+    old: PathBuf,
+    new: PathBuf,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Version {
+    #[value(name = "5.1")]
+    Lua51,
+    #[value(name = "5.2")]
+    Lua52,
+    #[value(name = "5.3")]
+    Lua53,
+    #[value(name = "5.4")]
+    Lua54,
+    Luau,
+    Luajit,
+}
+
+impl From<Version> for LuaVersion {
+    fn from(version: Version) -> Self {
+        match version {
+            Version::Lua51 => LuaVersion::Lua51,
+            Version::Lua52 => LuaVersion::Lua52,
+            Version::Lua53 => LuaVersion::Lua53,
+            Version::Lua54 => LuaVersion::Lua54,
+            Version::Luau => LuaVersion::Luau,
+            Version::Luajit => LuaVersion::LuaJit,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Json,
+    Toml,
+    Yaml,
+    Lua,
+}
+
+impl Format {
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "json" => Some(Self::Json),
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "lua" => Some(Self::Lua),
+            _ => None,
+        }
+    }
+}
+
+/// A parsed input file.
+enum Document {
+    Json(serde_json::Value),
+    Toml(toml::Value),
+    Yaml(serde_yaml::Value),
+    Lua(Value),
+}
+
+impl Serialize for Document {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            Self::Json(value) => value.serialize(serializer),
+            Self::Toml(value) => value.serialize(serializer),
+            Self::Yaml(value) => value.serialize(serializer),
+            Self::Lua(value) => value.serialize(serializer),
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    run(Cli::parse().command)
+}
+
+/// Runs `command` and prints its error. Like diff(1), `diff` exits with 1 if the files differ and
+/// with 2 if they can't be compared.
+fn run(command: Command) -> ExitCode {
+    let (result, failure) = match command {
+        Command::Convert(args) => (convert(args).map(|()| false), 1),
+        Command::Fmt(args) => (format(args, false).map(|()| false), 1),
+        Command::Minify(args) => (format(args, true).map(|()| false), 1),
+        Command::Check(args) => (check(args).map(|()| false), 1),
+        Command::Diff(args) => (diff(args), 2),
+    };
+    match result {
+        Ok(differ) => ExitCode::from(u8::from(differ)),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(failure)
+        }
+    }
+}
+
+fn convert(args: ConvertArgs) -> Result<(), Box<dyn Error>> {
+    let from = args
+        .from
+        .or_else(|| args.input.as_deref().and_then(Format::from_path))
+        .ok_or("can't guess the input format, pass --from")?;
+    let to = args
+        .to
+        .or_else(|| args.output.as_deref().and_then(Format::from_path))
+        .unwrap_or(Format::Lua);
+
+    let input = read_input(args.input.as_deref())?;
+    let document = match from {
+        Format::Json => Document::Json(serde_json::from_str(&input)?),
+        Format::Toml => Document::Toml(toml::from_str(&input)?),
+        Format::Yaml => Document::Yaml(serde_yaml::from_str(&input)?),
+        Format::Lua => Document::Lua(serde_lua_table::from_str_chunk(&input)?.1),
+    };
+
+    let mut output = Vec::new();
+    match to {
+        Format::Json if args.compact => serde_json::to_writer(&mut output, &document)?,
+        Format::Json => serde_json::to_writer_pretty(&mut output, &document)?,
+        Format::Toml => output = toml::to_string_pretty(&document)?.into_bytes(),
+        Format::Yaml => serde_yaml::to_writer(&mut output, &document)?,
+        Format::Lua => write_lua(&mut output, &document, &args)?,
+    }
+    write_output(args.output.as_deref(), output)
+}
+
+/// Rewrites a lua file in the same kind of chunk, so modules still return their value and saved
+/// variables still assign their globals.
+fn format(args: FormatArgs, compact: bool) -> Result<(), Box<dyn Error>> {
+    let input = read_input(args.input.as_deref())?;
+    let output = if compact {
+        serde_lua_table::minify_str(&input)?
+    } else {
+        serde_lua_table::format_str(&input)?
+    };
+    write_output(args.output.as_deref(), output.into_bytes())
+}
+
+/// Prints syntax errors and lint warnings with the line they are in.
+fn check(args: CheckArgs) -> Result<(), Box<dyn Error>> {
+    let input = read_input(args.input.as_deref())?;
+    if !args.lint {
+        if let Err(e) = serde_lua_table::validate_str(&input) {
+            return Err(e.render(&input).into());
+        }
+        return Ok(());
+    }
+    match serde_lua_table::lint(&input, args.lua.into()) {
+        Ok(warnings) => {
+            for warning in warnings {
+                eprintln!("warning: {}", warning.render(&input));
+            }
+            Ok(())
+        }
+        Err(e) => Err(e.render(&input).into()),
+    }
+}
+
+/// Prints one line per change, `+` for added, `-` for removed and `~` for changed fields, and
+/// returns whether there were any.
+fn diff(args: DiffArgs) -> Result<bool, Box<dyn Error>> {
+    let old = fs::read_to_string(&args.old)?;
+    let new = fs::read_to_string(&args.new)?;
+    let (_, old): (_, Value) = serde_lua_table::from_str_chunk(&old)?;
+    let (_, new): (_, Value) = serde_lua_table::from_str_chunk(&new)?;
+
+    let changes = value::diff(&old, &new);
+    let mut output = Vec::new();
+    for change in &changes {
+        let path = display_path(change.path());
+        match change {
+            Change::Added { value, .. } => {
+                writeln!(output, "+ {path} = {}", serde_lua_table::to_string(value)?)?
+            }
+            Change::Removed { value, .. } => {
+                writeln!(output, "- {path} = {}", serde_lua_table::to_string(value)?)?
+            }
+            Change::Changed { old, new, .. } => writeln!(
+                output,
+                "~ {path} = {} -> {}",
+                serde_lua_table::to_string(old)?,
+                serde_lua_table::to_string(new)?
+            )?,
+        }
+    }
+    io::stdout().write_all(&output)?;
+    Ok(!changes.is_empty())
+}
+
+/// Writes a path like `settings.ui.colors[3]`, with keys that aren't identifiers in brackets.
+fn display_path(path: &[PathSegment]) -> String {
+    let mut display = String::new();
+    for (i, segment) in path.iter().enumerate() {
+        match segment {
+            PathSegment::Key(key) if !LuaVersion::default().is_identifier(key) => {
+                display.push_str(&format!("[{key:?}]"))
+            }
+            PathSegment::Key(key) if i == 0 => display.push_str(key),
+            segment => display.push_str(&segment.to_string()),
+        }
+    }
+    if display.is_empty() {
+        display.push_str("(value)");
+    }
+    display
+}
+
+fn read_input(path: Option<&Path>) -> io::Result<String> {
+    let mut input = String::new();
+    match path {
+        Some(path) => input = fs::read_to_string(path)?,
+        None => _ = io::stdin().read_to_string(&mut input)?,
+    }
+    Ok(input)
+}
+
+fn write_output(path: Option<&Path>, mut output: Vec<u8>) -> Result<(), Box<dyn Error>> {
+    if !output.ends_with(b"\n") {
+        output.push(b'\n');
+    }
+    match path {
+        Some(path) => fs::write(path, output)?,
+        None => io::stdout().write_all(&output)?,
+    }
+    Ok(())
+}
+
+fn write_lua(
+    output: &mut Vec<u8>,
+    document: &Document,
+    args: &ConvertArgs,
+) -> Result<(), serde_lua_table::SerError> {
+    let header: &[u8] = if args.with_return { b"return " } else { b"" };
+    if args.compact {
+        let formatter = WithHeader::new(CompactFormatter, header);
+        document.serialize(&mut Serializer::with_formatter(output, formatter))
+    } else {
+        let formatter = WithHeader::new(PrettyFormatter::new(), header);
+        document.serialize(&mut Serializer::with_formatter(output, formatter))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs `lua-table convert` with `args` on a file named `name` containing `input`, and returns
+    /// what it wrote to the output file.
+    fn run_convert(name: &str, input: &str, output: &str, args: &[&str]) -> String {
+        let dir = std::env::temp_dir().join(format!("lua-table-convert-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (input_path, output_path) = (dir.join(name), dir.join(output));
+        fs::write(&input_path, input).unwrap();
+
+        let mut argv = vec!["lua-table", "convert", input_path.to_str().unwrap(), "-o"];
+        argv.push(output_path.to_str().unwrap());
+        argv.extend(args);
+        let Command::Convert(args) = Cli::try_parse_from(argv).unwrap().command else {
+            unreachable!()
+        };
+        convert(args).unwrap();
+        fs::read_to_string(output_path).unwrap()
+    }
+
+    #[test]
+    fn converts_json_to_lua_in_order() {
+        let json = r#"{"name": "app", "tags": ["a", "b"], "id": 1}"#;
+        assert_eq!(
+            run_convert("order.json", json, "order.lua", &["--compact", "--return"]),
+            "return {[\"name\"]=\"app\",[\"tags\"]={\"a\",\"b\"},[\"id\"]=1}\n"
+        );
+    }
+
+    #[test]
+    fn converts_lua_to_the_format_of_the_output_file() {
+        let lua = "return { scale = 1.5, names = { 'x' } }";
+        let json: serde_json::Value =
+            serde_json::from_str(&run_convert("data.lua", lua, "data.json", &[])).unwrap();
+        assert_eq!(json, serde_json::json!({"scale": 1.5, "names": ["x"]}));
+        let toml = run_convert("data2.lua", lua, "data.toml", &[]);
+        assert!(toml.contains("scale = 1.5"), "{toml}");
+    }
+
+    #[test]
+    fn guesses_formats_from_extensions() {
+        assert!(Format::from_path(Path::new("a.yml")) == Some(Format::Yaml));
+        assert!(Format::from_path(Path::new("a.LUA")).is_none());
+        let args = ConvertArgs {
+            input: None,
+            output: None,
+            from: None,
+            to: None,
+            compact: false,
+            with_return: false,
+        };
+        let err = convert(args).unwrap_err();
+        assert_eq!(err.to_string(), "can't guess the input format, pass --from");
+    }
+
+    #[test]
+    fn diff_exits_like_diff() {
+        let dir = std::env::temp_dir().join(format!("lua-table-diff-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (old, new) = (dir.join("old.lua"), dir.join("new.lua"));
+        fs::write(&old, "return { a = 1, b = 2 }").unwrap();
+        fs::write(&new, "return {b=2,a=1}").unwrap();
+
+        let diff = |old: &Path, new: &Path| {
+            let argv = [
+                "lua-table",
+                "diff",
+                old.to_str().unwrap(),
+                new.to_str().unwrap(),
+            ];
+            run(Cli::try_parse_from(argv).unwrap().command)
+        };
+        assert_eq!(diff(&old, &new), ExitCode::SUCCESS);
+        fs::write(&new, "return { a = 1, b = 3 }").unwrap();
+        assert_eq!(diff(&old, &new), ExitCode::from(1));
+        assert_eq!(diff(&old, &dir.join("missing.lua")), ExitCode::from(2));
+    }
+}
@@ -0,0 +1,78 @@
+//! Precompiling serialized values to lua bytecode.
+//!
+//! Loading bytecode skips the parser, which makes large data files load a lot faster. The
+//! bytecode is produced by the lua that [`mlua`](::mlua) is built with and can only be loaded by
+//! the same lua version.
+//!
+//! ```
+//! use mlua::Lua;
+//!
+//! let bytecode = serde_lua_table::bytecode::to_bytecode(&[1, 2, 3]).unwrap();
+//!
+//! let lua = Lua::new();
+//! let value: Vec<i32> = lua.load(&bytecode).eval().unwrap();
+//! assert_eq!(value, [1, 2, 3]);
+//! ```
+
+use crate::{CompactFormatter, SerError, Serializer, WithHeader};
+use ::mlua::Lua;
+use serde::Serialize;
+
+#[derive(thiserror::Error, Debug)]
+pub enum BytecodeError {
+    #[error(transparent)]
+    Ser(#[from] SerError),
+    #[error("Lua error: {0}")]
+    Lua(#[from] ::mlua::Error),
+}
+
+/// Serializes `value` as a chunk returning it and compiles the chunk to bytecode without debug
+/// information.
+///
+/// # Errors
+///
+/// Fails if `value` can't be serialized or if the serialized chunk doesn't compile, e.g. because
+/// it has too many constants.
+pub fn to_bytecode<T>(value: &T) -> Result<Vec<u8>, BytecodeError>
+where
+    T: ?Sized + Serialize,
+{
+    let mut ser =
+        Serializer::with_formatter(Vec::new(), WithHeader::new(CompactFormatter, b"return "));
+    value.serialize(&mut ser)?;
+    let source = ser.into_inner();
+
+    let lua = Lua::new();
+    let function = lua.load(&source).into_function()?;
+    Ok(function.dump(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_bytecode, BytecodeError};
+    use ::mlua::{Lua, Table};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn compiles_tables_that_load_without_the_parser() {
+        let value = BTreeMap::from([("name", "a\nb"), ("end", "")]);
+        let bytecode = to_bytecode(&value).unwrap();
+        assert!(bytecode.starts_with(b"\x1bLua"));
+
+        let lua = Lua::new();
+        let loaded: Table = lua
+            .load(&bytecode)
+            .set_mode(::mlua::ChunkMode::Binary)
+            .eval()
+            .unwrap();
+        assert_eq!(loaded.get::<_, String>("name").unwrap(), "a\nb");
+        assert_eq!(loaded.get::<_, String>("end").unwrap(), "");
+    }
+
+    #[test]
+    fn fails_on_values_that_do_not_serialize() {
+        let key = BTreeMap::from([(vec![1], 1)]);
+        let err = to_bytecode(&key).map(drop);
+        assert!(matches!(err, Err(BytecodeError::Ser(_))), "{err:?}");
+    }
+}
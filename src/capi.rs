@@ -0,0 +1,245 @@
+//! A C interface for hosts that aren't written in Rust.
+//!
+//! The interface converts JSON documents from and to lua, since C has no values `serde` could
+//! serialize. Build the library with
+//!
+//! ```sh
+//! cargo rustc --release --features capi --crate-type cdylib
+//! ```
+//!
+//! and generate the header with `cbindgen --config cbindgen.toml --output lua_table.h`.
+//!
+//! Every function returns a [`LuaTableStatus`]. Strings returned through an out pointer are
+//! owned by the caller and must be released with [`lua_table_string_free`], never with `free`.
+
+use crate::{
+    from_str, to_string, to_string_pretty,
+    value::{Key, Value},
+    SerError,
+};
+use std::{
+    ffi::{c_char, CStr, CString},
+    ptr,
+};
+
+/// The result of a call.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LuaTableStatus {
+    Ok = 0,
+    /// A pointer argument was null.
+    NullPointer = 1,
+    /// The input wasn't valid UTF-8.
+    InvalidUtf8 = 2,
+    /// The input wasn't valid JSON.
+    InvalidJson = 3,
+    /// The value couldn't be written as lua, e.g. because of a number out of range.
+    Serialize = 4,
+    /// The output contained a NUL byte and can't be returned as a C string.
+    InteriorNul = 5,
+    /// The input wasn't a valid lua value.
+    InvalidLua = 6,
+}
+
+impl From<SerError> for LuaTableStatus {
+    fn from(_: SerError) -> Self {
+        LuaTableStatus::Serialize
+    }
+}
+
+/// Converts the NUL terminated JSON document `json` to a lua table.
+///
+/// On success `*out` points to the NUL terminated lua source, otherwise it is set to null.
+/// `pretty` selects the pretty-printed output if it isn't zero.
+///
+/// ```
+/// use serde_lua_table::capi::*;
+/// use std::ffi::CStr;
+///
+/// let mut out = std::ptr::null_mut();
+/// let status = unsafe { lua_table_to_string(c"{\"a\":[1,2]}".as_ptr(), 0, &mut out) };
+/// assert_eq!(status, LuaTableStatus::Ok);
+/// assert_eq!(unsafe { CStr::from_ptr(out) }, c"{[\"a\"]={1,2}}");
+/// unsafe { lua_table_string_free(out) };
+///
+/// let status = unsafe { lua_table_to_string(c"{".as_ptr(), 0, &mut out) };
+/// assert_eq!(status, LuaTableStatus::InvalidJson);
+/// assert!(out.is_null());
+/// ```
+///
+/// # Safety
+///
+/// `json` must be null or point to a NUL terminated string, and `out` must be null or valid
+/// for writes.
+#[no_mangle]
+pub unsafe extern "C" fn lua_table_to_string(
+    json: *const c_char,
+    pretty: i32,
+    out: *mut *mut c_char,
+) -> LuaTableStatus {
+    if out.is_null() {
+        return LuaTableStatus::NullPointer;
+    }
+    *out = ptr::null_mut();
+    if json.is_null() {
+        return LuaTableStatus::NullPointer;
+    }
+
+    match convert(CStr::from_ptr(json), pretty != 0) {
+        Ok(lua) => {
+            *out = lua.into_raw();
+            LuaTableStatus::Ok
+        }
+        Err(status) => status,
+    }
+}
+
+/// Converts the NUL terminated lua source `lua` to a JSON document.
+///
+/// The source is a table or another value, optionally returned or assigned to a variable like
+/// the input of [`from_str`](crate::from_str). Tables with keys become JSON objects with string
+/// keys, all others become arrays. Strings that aren't UTF-8 have their invalid bytes replaced
+/// and numbers that JSON can't represent, like `0/0`, become `null`.
+///
+/// On success `*out` points to the NUL terminated JSON, otherwise it is set to null. `pretty`
+/// selects the pretty-printed output if it isn't zero.
+///
+/// ```
+/// use serde_lua_table::capi::*;
+/// use std::ffi::CStr;
+///
+/// let mut out = std::ptr::null_mut();
+/// let status = unsafe { lua_table_parse_to_json(c"{a = {1, 2}}".as_ptr(), 0, &mut out) };
+/// assert_eq!(status, LuaTableStatus::Ok);
+/// assert_eq!(unsafe { CStr::from_ptr(out) }, c"{\"a\":[1,2]}");
+/// unsafe { lua_table_string_free(out) };
+///
+/// let status = unsafe { lua_table_parse_to_json(c"{a = }".as_ptr(), 0, &mut out) };
+/// assert_eq!(status, LuaTableStatus::InvalidLua);
+/// assert!(out.is_null());
+/// ```
+///
+/// # Safety
+///
+/// `lua` must be null or point to a NUL terminated string, and `out` must be null or valid
+/// for writes.
+#[no_mangle]
+pub unsafe extern "C" fn lua_table_parse_to_json(
+    lua: *const c_char,
+    pretty: i32,
+    out: *mut *mut c_char,
+) -> LuaTableStatus {
+    if out.is_null() {
+        return LuaTableStatus::NullPointer;
+    }
+    *out = ptr::null_mut();
+    if lua.is_null() {
+        return LuaTableStatus::NullPointer;
+    }
+
+    match parse(CStr::from_ptr(lua), pretty != 0) {
+        Ok(json) => {
+            *out = json.into_raw();
+            LuaTableStatus::Ok
+        }
+        Err(status) => status,
+    }
+}
+
+/// Releases a string returned by this library. Passing null does nothing.
+///
+/// # Safety
+///
+/// `s` must be null or a string returned by this library that hasn't been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn lua_table_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Returns a static, NUL terminated description of `status`.
+#[no_mangle]
+pub extern "C" fn lua_table_status_message(status: LuaTableStatus) -> *const c_char {
+    let message: &'static CStr = match status {
+        LuaTableStatus::Ok => c"ok",
+        LuaTableStatus::NullPointer => c"a pointer argument was null",
+        LuaTableStatus::InvalidUtf8 => c"the input isn't valid UTF-8",
+        LuaTableStatus::InvalidJson => c"the input isn't valid JSON",
+        LuaTableStatus::Serialize => c"the value can't be written as lua",
+        LuaTableStatus::InteriorNul => c"the output contains a NUL byte",
+        LuaTableStatus::InvalidLua => c"the input isn't a valid lua value",
+    };
+    message.as_ptr()
+}
+
+fn convert(json: &CStr, pretty: bool) -> Result<CString, LuaTableStatus> {
+    let json = json.to_str().map_err(|_| LuaTableStatus::InvalidUtf8)?;
+    let value: serde_json::Value =
+        serde_json::from_str(json).map_err(|_| LuaTableStatus::InvalidJson)?;
+    let lua = if pretty {
+        to_string_pretty(&value)?
+    } else {
+        to_string(&value)?
+    };
+    CString::new(lua).map_err(|_| LuaTableStatus::InteriorNul)
+}
+
+fn parse(lua: &CStr, pretty: bool) -> Result<CString, LuaTableStatus> {
+    let lua = lua.to_str().map_err(|_| LuaTableStatus::InvalidUtf8)?;
+    let value: Value = from_str(lua).map_err(|_| LuaTableStatus::InvalidLua)?;
+    let value = to_json(&value);
+    let json = if pretty {
+        serde_json::to_string_pretty(&value)
+    } else {
+        serde_json::to_string(&value)
+    }
+    .map_err(|_| LuaTableStatus::Serialize)?;
+    CString::new(json).map_err(|_| LuaTableStatus::InteriorNul)
+}
+
+fn to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Nil => serde_json::Value::Null,
+        Value::Boolean(b) => (*b).into(),
+        Value::Integer(i) => (*i).into(),
+        Value::Number(n) => (*n).into(),
+        Value::RawNumber(n) => serde_json::from_str(n).unwrap_or_else(|_| value.as_f64().into()),
+        Value::String(s) => s.as_str().into(),
+        Value::Bytes(b) => String::from_utf8_lossy(b).into(),
+        Value::Table(t) if t.fields().is_empty() => t.sequence().iter().map(to_json).collect(),
+        Value::Table(t) => t
+            .iter()
+            .map(|(key, value)| {
+                let key = match key {
+                    Key::Integer(i) => i.to_string(),
+                    Key::String(s) => s.to_string(),
+                };
+                (key, to_json(value))
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(lua: &CStr) -> String {
+        parse(lua, false).unwrap().into_string().unwrap()
+    }
+
+    #[test]
+    fn parses_values_json_has_no_equivalent_for() {
+        assert_eq!(parse_str(c"{0/0, '\\255a', nil}"), r#"[null,"�a"]"#);
+        assert_eq!(parse_str(c"{1, nil, 3}"), r#"{"1":1,"3":3}"#);
+        assert_eq!(parse_str(c"return {}"), "[]");
+    }
+
+    #[test]
+    fn drops_null_fields_on_the_way_through_lua() {
+        let json = c"{\"a\":[1,2.5,\"x\"],\"b\":{\"c\":null}}";
+        let lua = convert(json, true).unwrap();
+        assert_eq!(parse_str(&lua), r#"{"a":[1,2.5,"x"],"b":[]}"#);
+    }
+}
@@ -0,0 +1,220 @@
+//! A trailing checksum comment to detect edits and corruption of generated files.
+//!
+//! [`ChecksumWriter`] collects everything written through it and appends
+//! `-- checksum: xxh3:<hash>` when it's finished. The comment doesn't change what the file
+//! evaluates to. The hash covers the text in the form [`normalize_str`](crate::normalize_str)
+//! writes it, so files that are reformatted, e.g. by `lua-table fmt`, still verify, while
+//! changing a value doesn't.
+//!
+//! ```
+//! use serde_lua_table::checksum;
+//!
+//! let written = checksum::to_string(&[1, 2, 3]).unwrap();
+//! assert_eq!(written, "{1,2,3}\n-- checksum: xxh3:6e286f9844088ba9\n");
+//! assert!(checksum::verify_checksum(&written).is_ok());
+//!
+//! let reformatted = written.replace("{1,2,3}", "{ 1, 2, 3, } -- numbers");
+//! assert!(checksum::verify_checksum(&reformatted).is_ok());
+//!
+//! let edited = written.replace('3', "4");
+//! assert!(checksum::verify_checksum(&edited).is_err());
+//! ```
+
+use crate::{SerError, Serializer};
+use serde::Serialize;
+use std::io;
+
+const PREFIX: &str = "\n-- checksum: ";
+const ALGORITHM: &str = "xxh3:";
+
+/// Why a checksum couldn't be verified.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ChecksumError {
+    #[error("no checksum comment found")]
+    Missing,
+    #[error("unsupported checksum algorithm in {0:?}")]
+    UnsupportedAlgorithm(String),
+    #[error("malformed checksum {0:?}")]
+    Malformed(String),
+    /// The text before the comment isn't lua data, so it has no normalized form to hash.
+    #[error("the checksummed text can't be read: {0}")]
+    Unreadable(String),
+    /// The text was changed after the checksum was written.
+    #[error("checksum mismatch: expected {expected:016x}, computed {computed:016x}")]
+    Mismatch { expected: u64, computed: u64 },
+}
+
+/// Passes writes through to the inner writer and keeps them to hash their normalized form.
+pub struct ChecksumWriter<W> {
+    inner: W,
+    written: Vec<u8>,
+}
+
+impl<W> ChecksumWriter<W>
+where
+    W: io::Write,
+{
+    pub fn new(inner: W) -> Self {
+        ChecksumWriter {
+            inner,
+            written: Vec::new(),
+        }
+    }
+
+    /// Appends the checksum comment and returns the inner writer.
+    ///
+    /// # Errors
+    ///
+    /// Fails if writing fails or if the text written so far isn't lua data.
+    pub fn finish(mut self) -> Result<W, SerError> {
+        let hash = std::str::from_utf8(&self.written)
+            .map_err(|e| e.to_string())
+            .and_then(|text| hash(text).map_err(|e| e.to_string()))
+            .map_err(SerError::Custom)?;
+        writeln!(self.inner, "{}{}{:016x}", PREFIX, ALGORITHM, hash)?;
+        Ok(self.inner)
+    }
+}
+
+impl<W> io::Write for ChecksumWriter<W>
+where
+    W: io::Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written.extend_from_slice(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Serialize the given data structure as a string of lua followed by a checksum comment.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with non-string keys.
+pub fn to_string<T>(value: &T) -> Result<String, SerError>
+where
+    T: ?Sized + Serialize,
+{
+    let mut writer = ChecksumWriter::new(Vec::with_capacity(128));
+    value.serialize(&mut Serializer::new(&mut writer))?;
+    into_string(writer)
+}
+
+/// Serialize the given data structure as a pretty-printed string of lua followed by a checksum
+/// comment.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with non-string keys.
+pub fn to_string_pretty<T>(value: &T) -> Result<String, SerError>
+where
+    T: ?Sized + Serialize,
+{
+    let mut writer = ChecksumWriter::new(Vec::with_capacity(128));
+    value.serialize(&mut Serializer::pretty(&mut writer))?;
+    into_string(writer)
+}
+
+/// Hashes the normalized form of `text`.
+fn hash(text: &str) -> Result<u64, crate::DeError> {
+    Ok(xxhash_rust::xxh3::xxh3_64(
+        crate::normalize_str(text)?.as_bytes(),
+    ))
+}
+
+fn into_string(writer: ChecksumWriter<Vec<u8>>) -> Result<String, SerError> {
+    let vec = writer.finish()?;
+    let string = unsafe {
+        // Safety: We do not emit invalid UTF-8 and the comment is ASCII.
+        String::from_utf8_unchecked(vec)
+    };
+    Ok(string)
+}
+
+/// Checks that `text` ends with a checksum comment matching the normalized text before it.
+///
+/// # Errors
+///
+/// Fails if there is no checksum comment, it or the text before it can't be read, or it
+/// doesn't match.
+pub fn verify_checksum(text: &str) -> Result<(), ChecksumError> {
+    let (body, comment) = text.rsplit_once(PREFIX).ok_or(ChecksumError::Missing)?;
+    let comment = comment.trim_end();
+    let hex = comment
+        .strip_prefix(ALGORITHM)
+        .ok_or_else(|| ChecksumError::UnsupportedAlgorithm(comment.to_owned()))?;
+    let expected =
+        u64::from_str_radix(hex, 16).map_err(|_| ChecksumError::Malformed(hex.to_owned()))?;
+
+    let computed = hash(body).map_err(|e| ChecksumError::Unreadable(e.to_string()))?;
+    match expected == computed {
+        true => Ok(()),
+        false => Err(ChecksumError::Mismatch { expected, computed }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_string, to_string_pretty, verify_checksum, ChecksumError};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn verifies_its_own_output_and_still_reads() {
+        let value = BTreeMap::from([("a", vec![1, 2]), ("b", vec![])]);
+        for lua in [
+            to_string(&value).unwrap(),
+            to_string_pretty(&value).unwrap(),
+        ] {
+            verify_checksum(&lua).unwrap();
+            verify_checksum(&lua.replace('\n', "\r\n")).unwrap();
+            let read: BTreeMap<String, Vec<i32>> = crate::from_str(&lua).unwrap();
+            assert_eq!(read.len(), 2);
+        }
+    }
+
+    #[test]
+    fn verifies_reformatted_files() {
+        let lua = to_string_pretty(&BTreeMap::from([("b", "x"), ("a", "y")])).unwrap();
+        let (body, comment) = lua.split_at(lua.find("\n-- checksum").unwrap());
+        for body in [
+            crate::format_str(body).unwrap().trim_end().to_owned(),
+            crate::minify_str(body).unwrap(),
+            "-- reordered\n{ b = [[x]], a = 'y' }".to_owned(),
+        ] {
+            verify_checksum(&format!("{body}{comment}")).unwrap();
+        }
+        assert!(matches!(
+            verify_checksum(&format!("{{ a = 'y', b = 'z' }}{comment}")),
+            Err(ChecksumError::Mismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn reports_why_a_checksum_fails() {
+        let lua = to_string(&[1, 2]).unwrap();
+        assert!(matches!(
+            verify_checksum(&lua.replace("{1,2}", "{1,3}")),
+            Err(ChecksumError::Mismatch { .. })
+        ));
+        assert_eq!(verify_checksum("{1,2}"), Err(ChecksumError::Missing));
+        assert_eq!(
+            verify_checksum("{}\n-- checksum: md5:00"),
+            Err(ChecksumError::UnsupportedAlgorithm("md5:00".to_owned()))
+        );
+        assert_eq!(
+            verify_checksum("{}\n-- checksum: xxh3:xyz\n"),
+            Err(ChecksumError::Malformed("xyz".to_owned()))
+        );
+        assert!(matches!(
+            verify_checksum("{1,\n-- checksum: xxh3:00\n"),
+            Err(ChecksumError::Unreadable(_))
+        ));
+    }
+}
@@ -0,0 +1,496 @@
+//! Rust source for lua data files, for build scripts.
+//!
+//! [`generate`] reads a lua data file and writes it as a `static` together with the structs its
+//! tables need, so the data can be compiled into a binary instead of being parsed at runtime.
+//! The types are inferred like [`typegen::infer_schema`] infers them: tables with string keys
+//! become structs named after their field, sequences become slices and strings become
+//! `&'static str`.
+//!
+//! ```
+//! let source = serde_lua_table::codegen::generate(
+//!     "GAME_DATA",
+//!     "return { version = 2, items = { { name = 'iron', stack = 100 }, { name = 'wood' } } }",
+//! )
+//! .unwrap();
+//! assert!(source.contains("pub struct Items {\n    pub name: &'static str,\n    pub stack: Option<i64>,\n}"));
+//! assert!(source.contains("pub static GAME_DATA: GameData = GameData {\n    version: 2,"));
+//! ```
+//!
+//! In a build script, the output is written to `OUT_DIR` and included with `include!`:
+//!
+//! ```no_run
+//! // build.rs
+//! let source = serde_lua_table::codegen::generate_file("GAME_DATA", "data/items.lua").unwrap();
+//! let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("items.rs");
+//! std::fs::write(out, source).unwrap();
+//! println!("cargo:rerun-if-changed=data/items.lua");
+//! ```
+//!
+//! ```ignore
+//! // src/main.rs
+//! include!(concat!(env!("OUT_DIR"), "/items.rs"));
+//! ```
+
+use crate::{
+    typegen::{self, Record, Shape},
+    value::{Key, Table, Value},
+    DeError, KeyCase, PathSegment,
+};
+use std::{fmt::Write, io, path::Path};
+
+/// Why no rust source could be generated.
+#[derive(Debug, thiserror::Error)]
+pub enum CodegenError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Lua(#[from] DeError),
+    /// The value at `path` has no rust type, for example because a sequence mixes strings and
+    /// numbers.
+    #[error("can't generate rust code for {}: {reason}", display_path(path))]
+    Unsupported {
+        path: Vec<PathSegment>,
+        reason: &'static str,
+    },
+    /// A field isn't a valid rust identifier in snake case, or two fields of a record have the
+    /// same one.
+    #[error("the field {field:?} of {record} has no rust name of its own")]
+    FieldName { record: String, field: String },
+    /// A struct would contain itself. It may only do so in a slice.
+    #[error("the record {0} contains itself outside of a sequence")]
+    Recursive(String),
+}
+
+/// Generates rust source with a `static` called `name` holding the data in `lua`, and the
+/// structs its type needs. The outermost struct is `name` in pascal case, the others are named
+/// after the field they are in.
+///
+/// `lua` is read like [`from_str_chunk`](crate::from_str_chunk) reads it, so it may be a value,
+/// a `return` statement or assignments to globals.
+///
+/// # Errors
+///
+/// Fails if `lua` is invalid, or if the data has no rust type. See [`CodegenError`].
+pub fn generate(name: &str, lua: &str) -> Result<String, CodegenError> {
+    let (_, value) = crate::from_str_chunk::<Value>(lua)?;
+    let shape = typegen::infer(&value, &KeyCase::PascalCase.apply(name));
+    let records = Record::collect(&shape)
+        .into_iter()
+        .map(Struct::new)
+        .collect::<Result<Vec<_>, _>>()?;
+    for record in &records {
+        if contains(
+            &record.record.fields,
+            &record.record.name,
+            &records,
+            &mut Vec::new(),
+        ) {
+            return Err(CodegenError::Recursive(record.record.name.clone()));
+        }
+    }
+
+    let mut writer = ValueWriter {
+        records: &records,
+        path: Vec::new(),
+        indent: 0,
+        out: String::new(),
+    };
+    writer.value(&value, &shape)?;
+
+    let mut out = String::from("// Generated from lua data by serde_lua_table::codegen.\n");
+    for record in &records {
+        out.push_str("\n#[derive(Clone, Copy, Debug, PartialEq)]\n");
+        writeln!(out, "pub struct {} {{", record.record.name).unwrap();
+        for ((_, field), rust_name) in record.record.fields.iter().zip(&record.rust_names) {
+            writeln!(out, "    pub {}: {},", rust_name, type_name(field)).unwrap();
+        }
+        out.push_str("}\n");
+    }
+    writeln!(
+        out,
+        "\npub static {}: {} = {};",
+        name,
+        type_name(&shape),
+        writer.out
+    )
+    .unwrap();
+    Ok(out)
+}
+
+/// Reads the lua data file at `path` and generates rust source for it like [`generate`].
+///
+/// # Errors
+///
+/// Fails if the file can't be read, or like [`generate`].
+pub fn generate_file(name: &str, path: impl AsRef<Path>) -> Result<String, CodegenError> {
+    generate(name, &std::fs::read_to_string(path)?)
+}
+
+/// A record with the rust names of its fields.
+struct Struct {
+    record: Record,
+    rust_names: Vec<String>,
+}
+
+impl Struct {
+    fn new(record: Record) -> Result<Self, CodegenError> {
+        let mut rust_names: Vec<String> = Vec::with_capacity(record.fields.len());
+        for (field, _) in &record.fields {
+            match rust_name(field) {
+                Some(name) if !rust_names.contains(&name) => rust_names.push(name),
+                _ => {
+                    return Err(CodegenError::FieldName {
+                        record: record.name.clone(),
+                        field: field.clone(),
+                    })
+                }
+            }
+        }
+        Ok(Struct { record, rust_names })
+    }
+}
+
+/// Returns `field` in snake case as a rust identifier, if it can be one.
+fn rust_name(field: &str) -> Option<String> {
+    const KEYWORDS: &[&str] = &[
+        "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do",
+        "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in",
+        "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+        "return", "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe",
+        "unsized", "use", "virtual", "where", "while", "yield",
+    ];
+
+    let name = KeyCase::SnakeCase.apply(field);
+    let valid = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !matches!(&*name, "_" | "self" | "super" | "crate");
+    if !valid {
+        None
+    } else if KEYWORDS.contains(&&*name) {
+        Some(format!("r#{}", name))
+    } else {
+        Some(name.into_owned())
+    }
+}
+
+/// Returns whether a value of one of `fields` contains the record `name` without a slice in
+/// between.
+fn contains(
+    fields: &[(String, Shape)],
+    name: &str,
+    records: &[Struct],
+    visiting: &mut Vec<String>,
+) -> bool {
+    fields.iter().any(|(_, shape)| {
+        let mut found = false;
+        inline_records(shape, &mut |record| {
+            if found || visiting.iter().any(|n| *n == record) {
+                return;
+            }
+            found = record == name || {
+                let fields = &find(records, record).record.fields;
+                visiting.push(record.to_owned());
+                let found = contains(fields, name, records, visiting);
+                visiting.pop();
+                found
+            };
+        });
+        found
+    })
+}
+
+/// Calls `f` with the name of every record stored inline in a value of `shape`.
+fn inline_records(shape: &Shape, f: &mut impl FnMut(&str)) {
+    match shape {
+        Shape::Optional(inner) => inline_records(inner, f),
+        Shape::Tuple(elements) => {
+            for element in elements {
+                inline_records(element, f);
+            }
+        }
+        Shape::Record(record) => f(&record.name),
+        _ => (),
+    }
+}
+
+/// Returns the merged record called `name`.
+fn find<'a>(records: &'a [Struct], name: &str) -> &'a Struct {
+    records
+        .iter()
+        .find(|r| r.record.name == name)
+        .expect("records are collected from the shape")
+}
+
+fn type_name(shape: &Shape) -> String {
+    match shape {
+        Shape::Unknown => "&'static [()]".to_owned(),
+        Shape::Any => unreachable!("values of different types are rejected while writing them"),
+        Shape::Nil => "()".to_owned(),
+        Shape::Boolean => "bool".to_owned(),
+        Shape::Integer => "i64".to_owned(),
+        Shape::Number => "f64".to_owned(),
+        Shape::String => "&'static str".to_owned(),
+        Shape::Optional(inner) => format!("Option<{}>", type_name(inner)),
+        Shape::Array(element) => format!("&'static [{}]", type_name(element)),
+        Shape::Tuple(elements) => {
+            let elements: String = elements.iter().map(|e| type_name(e) + ",").collect();
+            format!("({})", elements)
+        }
+        Shape::Map(key, value) => {
+            format!("&'static [({}, {})]", type_name(key), type_name(value))
+        }
+        Shape::Record(record) => record.name.clone(),
+    }
+}
+
+/// Writes values as rust expressions of the type [`type_name`] gives their shape.
+struct ValueWriter<'a> {
+    records: &'a [Struct],
+    path: Vec<PathSegment>,
+    indent: usize,
+    out: String,
+}
+
+impl ValueWriter<'_> {
+    fn value(&mut self, value: &Value, shape: &Shape) -> Result<(), CodegenError> {
+        match (value, shape) {
+            (Value::Nil, Shape::Nil) => self.out.push_str("()"),
+            (Value::Nil, Shape::Optional(_)) => self.out.push_str("None"),
+            (value, Shape::Optional(inner)) => {
+                self.out.push_str("Some(");
+                self.value(value, inner)?;
+                self.out.push(')');
+            }
+            (Value::Boolean(b), Shape::Boolean) => write!(self.out, "{}", b).unwrap(),
+            (Value::Integer(i), Shape::Integer) => write!(self.out, "{}", i).unwrap(),
+            (Value::Integer(i), Shape::Number) => self.float(*i as f64),
+            (Value::Number(n), Shape::Number) => self.float(*n),
+            (Value::RawNumber(_), Shape::Integer | Shape::Number) => {
+                match (value.as_i64(), shape) {
+                    (Some(i), Shape::Integer) => write!(self.out, "{}", i).unwrap(),
+                    _ => self.float(value.as_f64().unwrap_or(f64::NAN)),
+                }
+            }
+            (Value::String(s), Shape::String) => write!(self.out, "{:?}", s).unwrap(),
+            (Value::Bytes(_), Shape::String) => {
+                return Err(self.unsupported("the string isn't valid UTF-8"))
+            }
+            (Value::Table(_), Shape::Unknown) => self.out.push_str("&[]"),
+            (Value::Table(table), Shape::Array(element)) => {
+                self.out.push_str("&[");
+                for (key, value) in table.iter() {
+                    self.element(key, |w| w.value(value, element))?;
+                }
+                self.close("]");
+            }
+            (Value::Table(table), Shape::Tuple(elements)) => {
+                self.out.push('(');
+                for ((key, value), element) in table.iter().zip(elements) {
+                    self.element(key, |w| w.value(value, element))?;
+                }
+                self.close(")");
+            }
+            (Value::Table(table), Shape::Map(key_shape, value_shape)) => {
+                self.out.push_str("&[");
+                for (key, value) in table.iter() {
+                    self.element(key.clone(), |w| {
+                        w.out.push('(');
+                        w.key(&key, key_shape)?;
+                        w.out.push_str(", ");
+                        w.value(value, value_shape)?;
+                        w.out.push(')');
+                        Ok(())
+                    })?;
+                }
+                self.close("]");
+            }
+            (Value::Table(table), Shape::Record(record)) => self.record(table, &record.name)?,
+            _ => return Err(self.unsupported("it has values of different types")),
+        }
+        Ok(())
+    }
+
+    fn record(&mut self, table: &Table, name: &str) -> Result<(), CodegenError> {
+        let record = find(self.records, name);
+        write!(self.out, "{} {{", name).unwrap();
+        for ((field, shape), rust_name) in record.record.fields.iter().zip(&record.rust_names) {
+            let key = Key::from(field.as_str());
+            let value = table.get(&key).unwrap_or(&Value::Nil);
+            self.element(key, |w| {
+                write!(w.out, "{}: ", rust_name).unwrap();
+                w.value(value, shape)
+            })?;
+        }
+        self.close("}");
+        Ok(())
+    }
+
+    fn key(&mut self, key: &Key, shape: &Shape) -> Result<(), CodegenError> {
+        match (key, shape) {
+            (Key::Integer(i), Shape::Integer) => write!(self.out, "{}", i).unwrap(),
+            (Key::String(s), Shape::String) => write!(self.out, "{:?}", s).unwrap(),
+            _ => return Err(self.unsupported("its keys have different types")),
+        }
+        Ok(())
+    }
+
+    fn float(&mut self, n: f64) {
+        if n.is_nan() {
+            self.out.push_str("f64::NAN");
+        } else if n.is_infinite() {
+            self.out.push_str(if n > 0.0 {
+                "f64::INFINITY"
+            } else {
+                "f64::NEG_INFINITY"
+            });
+        } else {
+            write!(self.out, "{:?}", n).unwrap();
+        }
+    }
+
+    /// Writes an element of a table on its own line, with `key` on the path while it's written.
+    fn element(
+        &mut self,
+        key: Key,
+        write: impl FnOnce(&mut Self) -> Result<(), CodegenError>,
+    ) -> Result<(), CodegenError> {
+        self.indent += 1;
+        self.newline();
+        self.path.push(match key {
+            Key::Integer(i) => PathSegment::Index(i),
+            Key::String(s) => PathSegment::Key(s.to_string()),
+        });
+        write(self)?;
+        self.path.pop();
+        self.out.push(',');
+        self.indent -= 1;
+        Ok(())
+    }
+
+    /// Closes a table, on its own line unless it's empty.
+    fn close(&mut self, delimiter: &str) {
+        if !matches!(self.out.chars().last(), Some('[' | '(' | '{')) {
+            self.newline();
+        }
+        self.out.push_str(delimiter);
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push_str("    ");
+        }
+    }
+
+    fn unsupported(&self, reason: &'static str) -> CodegenError {
+        CodegenError::Unsupported {
+            path: self.path.clone(),
+            reason,
+        }
+    }
+}
+
+fn display_path(path: &[PathSegment]) -> String {
+    let mut out = String::new();
+    for segment in path {
+        match segment {
+            PathSegment::Key(key) if out.is_empty() => out.push_str(key),
+            PathSegment::Key(key) => write!(out, ".{}", key).unwrap(),
+            PathSegment::Index(i) => write!(out, "[{}]", i).unwrap(),
+        }
+    }
+    if out.is_empty() {
+        out.push_str("the value");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate, CodegenError};
+
+    #[test]
+    fn generates_structs_and_a_static() {
+        let lua = "items = {\n  { name = 'iron', stack = 100, weight = 1.5 },\n  { name = 'wood', weight = 2 },\n}\nversion = 2\n";
+        assert_eq!(
+            generate("GAME_DATA", lua).unwrap(),
+            r#"// Generated from lua data by serde_lua_table::codegen.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Items {
+    pub name: &'static str,
+    pub stack: Option<i64>,
+    pub weight: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GameData {
+    pub items: &'static [Items],
+    pub version: i64,
+}
+
+pub static GAME_DATA: GameData = GameData {
+    items: &[
+        Items {
+            name: "iron",
+            stack: Some(100),
+            weight: 1.5,
+        },
+        Items {
+            name: "wood",
+            stack: None,
+            weight: 2.0,
+        },
+    ],
+    version: 2,
+};
+"#
+        );
+    }
+
+    #[test]
+    fn writes_maps_as_slices_of_pairs() {
+        let source = generate("COSTS", "{ [10] = 'a', [20] = 'b' }").unwrap();
+        assert!(source.ends_with(
+            "pub static COSTS: &'static [(i64, &'static str)] = &[\n    (10, \"a\"),\n    (20, \"b\"),\n];\n"
+        ));
+    }
+
+    #[test]
+    fn renames_fields_to_rust_identifiers() {
+        let source = generate(
+            "CONFIG",
+            "{ maxSpeed = math.huge, type = 'car', tags = {} }",
+        )
+        .unwrap();
+        assert!(source.contains(
+            "    pub max_speed: f64,\n    pub r#type: &'static str,\n    pub tags: &'static [()],\n"
+        ));
+        assert!(source.contains("max_speed: f64::INFINITY,"));
+        assert!(source.contains("tags: &[],"));
+    }
+
+    #[test]
+    fn rejects_data_without_a_rust_type() {
+        let error = generate("DATA", "{ list = { 1, 'two' } }").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "can't generate rust code for list[1]: it has values of different types"
+        );
+        assert!(matches!(
+            generate("DATA", "{ ['1st'] = true }"),
+            Err(CodegenError::FieldName { .. })
+        ));
+        assert!(matches!(
+            generate("DATA", "{ next = { next = { value = 1 } } }"),
+            Err(CodegenError::Recursive(name)) if name == "Next"
+        ));
+        assert!(matches!(
+            generate("DATA", "{ a = "),
+            Err(CodegenError::Lua(_))
+        ));
+    }
+}
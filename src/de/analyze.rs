@@ -0,0 +1,167 @@
+use super::{
+    syntax::{Node, NodeKind},
+    Chunk, DeError, Deserializer, SliceRead,
+};
+use crate::PathSegment;
+
+/// The sizes of the values in a lua file, see [`analyze`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// The number of values, counting tables and every value in them.
+    pub values: usize,
+    /// The number of tables.
+    pub tables: usize,
+    /// The bytes all strings are written in, with their quotes.
+    pub string_bytes: usize,
+    /// How deep the tables are nested, the outermost tables are at 1.
+    pub max_depth: usize,
+    /// Every table, largest first.
+    pub tables_by_size: Vec<TableStats>,
+}
+
+/// The size of a table in a lua file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TableStats {
+    /// The path from the outermost value to the table.
+    pub path: Vec<PathSegment>,
+    /// The bytes the table is written in.
+    pub bytes: usize,
+    /// The number of entries of the table itself.
+    pub entries: usize,
+    /// The number of values in the table, counting nested ones and the table.
+    pub values: usize,
+    /// The bytes the strings in the table are written in, counting nested ones.
+    pub string_bytes: usize,
+}
+
+/// Reads a lua file like [`from_str_chunk`](super::from_str_chunk) does and counts its values,
+/// to find what makes a file large. The variables of a chunk of assignments are the outermost
+/// values, not a table.
+///
+/// ```
+/// use serde_lua_table::PathSegment;
+///
+/// let lua = "Small = { 1 }\nLarge = { names = { 'a', 'b', 'c' } }";
+/// let stats = serde_lua_table::analyze(lua).unwrap();
+/// assert_eq!((stats.values, stats.tables, stats.max_depth), (7, 3, 2));
+/// let largest = &stats.tables_by_size[0];
+/// assert_eq!(largest.path, [PathSegment::Key("Large".to_owned())]);
+/// assert_eq!((largest.entries, largest.values, largest.string_bytes), (1, 5, 9));
+/// ```
+///
+/// # Errors
+///
+/// Fails like [`validate_str`](super::validate_str) if the file isn't lua that can be read.
+pub fn analyze(s: &str) -> Result<Stats, DeError> {
+    let (chunk, node) = Deserializer::new(SliceRead::new(s.as_bytes())).parse_syntax()?;
+    let mut analyzer = Analyzer {
+        stats: Stats::default(),
+        path: Vec::new(),
+    };
+    let totals = analyzer.visit(&node, usize::from(chunk != Chunk::Globals));
+    let mut stats = analyzer.stats;
+    stats.values = totals.values;
+    stats.string_bytes = totals.string_bytes;
+    stats.tables = stats.tables_by_size.len();
+    // The sort is stable, so tables of the same size stay in the order they end in the input.
+    stats
+        .tables_by_size
+        .sort_by_key(|table| std::cmp::Reverse(table.bytes));
+    Ok(stats)
+}
+
+/// The counts of a value and the values in it.
+struct Totals {
+    values: usize,
+    string_bytes: usize,
+}
+
+struct Analyzer {
+    stats: Stats,
+    /// The path to the current value.
+    path: Vec<PathSegment>,
+}
+
+impl Analyzer {
+    /// Counts `node`, which is nested `depth` deep. The chunk of a globals file is at 0.
+    fn visit(&mut self, node: &Node, depth: usize) -> Totals {
+        let mut totals = Totals {
+            values: usize::from(depth > 0),
+            string_bytes: 0,
+        };
+        match &node.kind {
+            NodeKind::String => totals.string_bytes = node.span.len(),
+            NodeKind::Table(fields) => {
+                for field in fields {
+                    self.path.push(field.key.key().segment());
+                    let field = self.visit(&field.value, depth + 1);
+                    self.path.pop();
+                    totals.values += field.values;
+                    totals.string_bytes += field.string_bytes;
+                }
+                if depth > 0 {
+                    self.stats.max_depth = self.stats.max_depth.max(depth);
+                    self.stats.tables_by_size.push(TableStats {
+                        path: self.path.clone(),
+                        bytes: node.span.len(),
+                        entries: fields.len(),
+                        values: totals.values,
+                        string_bytes: totals.string_bytes,
+                    });
+                }
+            }
+            _ => {}
+        }
+        totals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::analyze;
+    use crate::PathSegment;
+
+    #[test]
+    fn counts_nested_tables_and_strings() {
+        let lua = "return { 'ab', { x = { [[long]], {} } }, n = 1 }";
+        let stats = analyze(lua).unwrap();
+        assert_eq!(stats.values, 7);
+        assert_eq!(stats.tables, 4);
+        assert_eq!(stats.string_bytes, 12);
+        assert_eq!(stats.max_depth, 4);
+        let paths: Vec<_> = stats
+            .tables_by_size
+            .iter()
+            .map(|table| (table.path.clone(), table.entries, table.values))
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                (vec![], 3, 7),
+                (vec![PathSegment::Index(2)], 1, 4),
+                (
+                    vec![PathSegment::Index(2), PathSegment::Key("x".to_owned())],
+                    2,
+                    3
+                ),
+                (
+                    vec![
+                        PathSegment::Index(2),
+                        PathSegment::Key("x".to_owned()),
+                        PathSegment::Index(2)
+                    ],
+                    0,
+                    1
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_values_without_tables() {
+        let stats = analyze("'text'").unwrap();
+        assert_eq!((stats.values, stats.tables, stats.max_depth), (1, 0, 0));
+        assert_eq!(stats.string_bytes, 6);
+        assert!(analyze("{ 1, ").is_err());
+    }
+}
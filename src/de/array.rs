@@ -0,0 +1,216 @@
+use super::{table::Elements, DeError, Deserializer, Read};
+use serde::Deserialize;
+use std::marker::PhantomData;
+
+/// Reads an input that is one large sequence, like `{ {...}, {...} }` or `return { ... }`, an
+/// element at a time.
+///
+/// Only the element that is currently deserialized is kept in memory, so files larger than the
+/// memory can be processed when reading from an [`IoRead`](super::IoRead).
+///
+/// ```
+/// use serde::Deserialize;
+/// use serde_lua_table::{ArrayDeserializer, SliceRead};
+///
+/// #[derive(Deserialize)]
+/// struct Item {
+///     id: u32,
+/// }
+///
+/// let lua = "return {\n  { id = 1 },\n  { id = 2 },\n  { id = 3 },\n}\n";
+/// let ids = ArrayDeserializer::new(SliceRead::new(lua.as_bytes()))
+///     .seq_iter::<Item>()
+///     .map(|item| item.unwrap().id)
+///     .collect::<Vec<_>>();
+/// assert_eq!(ids, [1, 2, 3]);
+/// ```
+pub struct ArrayDeserializer<'de, R> {
+    de: Deserializer<R>,
+    lifetime: PhantomData<&'de ()>,
+}
+
+impl<'de, R> ArrayDeserializer<'de, R>
+where
+    R: Read<'de>,
+{
+    pub fn new(read: R) -> Self {
+        Self::from_deserializer(Deserializer::new(read))
+    }
+
+    pub(super) fn from_deserializer(de: Deserializer<R>) -> Self {
+        ArrayDeserializer {
+            de,
+            lifetime: PhantomData,
+        }
+    }
+
+    /// Returns an iterator that deserializes the elements of the sequence as `T`.
+    ///
+    /// Iteration stops after the first error. The input is checked to end after the sequence.
+    pub fn seq_iter<T>(self) -> SeqIter<'de, R, T>
+    where
+        T: Deserialize<'de>,
+    {
+        SeqIter {
+            de: self.de,
+            elements: None,
+            done: false,
+            output: PhantomData,
+            lifetime: PhantomData,
+        }
+    }
+}
+
+/// The iterator returned by [`ArrayDeserializer::seq_iter`].
+pub struct SeqIter<'de, R, T> {
+    de: Deserializer<R>,
+    /// The state of the sequence, once its opening brace was read.
+    elements: Option<Elements>,
+    done: bool,
+    output: PhantomData<T>,
+    lifetime: PhantomData<&'de ()>,
+}
+
+impl<'de, R, T> SeqIter<'de, R, T>
+where
+    R: Read<'de>,
+    T: Deserialize<'de>,
+{
+    /// Returns the number of bytes consumed so far, i.e. the offset right after the last element.
+    pub fn byte_offset(&self) -> usize {
+        self.de.read.offset()
+    }
+
+    /// Consumes everything up to the opening brace of the sequence.
+    fn begin(&mut self) -> Result<(), DeError> {
+        self.de.parse_whitespace()?;
+        if self.de.peek_word(b"return")? {
+            self.de.check_allowed("`return`")?;
+            self.de.parse_identifier()?;
+        }
+        match self.de.parse_whitespace()? {
+            Some(b'{') => self.de.enter_table(),
+            Some(_) => Err(self.de.error("expected a sequence")),
+            None => Err(self.de.eof()),
+        }
+    }
+
+    /// Consumes the closing brace and checks that only an optional `;` follows.
+    fn finish(&mut self) -> Result<(), DeError> {
+        self.de.end_table()?;
+        if self.de.parse_whitespace()? == Some(b';') {
+            self.de.read.discard();
+        }
+        self.de.end()
+    }
+
+    fn next_element(&mut self) -> Result<Option<T>, DeError> {
+        let elements = match &mut self.elements {
+            Some(elements) => elements,
+            None => {
+                self.begin()?;
+                self.elements.insert(Elements::default())
+            }
+        };
+        match elements.next(&mut self.de, PhantomData)? {
+            Some(element) => Ok(Some(element)),
+            None => self.finish().map(|()| None),
+        }
+    }
+}
+
+impl<'de, R, T> Iterator for SeqIter<'de, R, T>
+where
+    R: Read<'de>,
+    T: Deserialize<'de>,
+{
+    type Item = Result<T, DeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.next_element();
+        self.done = !matches!(result, Ok(Some(_)));
+        result.transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ArrayDeserializer;
+    use crate::{DeError, DeOptions, Deserializer, DuplicateKeys, IoRead, PathSegment, SliceRead};
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    fn points(lua: &str) -> Vec<Result<Point, DeError>> {
+        ArrayDeserializer::new(SliceRead::new(lua.as_bytes()))
+            .seq_iter()
+            .collect()
+    }
+
+    #[test]
+    fn reads_values_and_returned_sequences() {
+        for lua in [
+            "{ {x = 1, y = 2}, {x = 3, y = 4} }",
+            "-- points\nreturn { {x = 1, y = 2}; [2] = {x = 3, y = 4}, };\n",
+        ] {
+            let points: Vec<Point> = points(lua).into_iter().map(Result::unwrap).collect();
+            assert_eq!(
+                points,
+                [Point { x: 1, y: 2 }, Point { x: 3, y: 4 }],
+                "{}",
+                lua
+            );
+        }
+        assert!(points("{}").is_empty());
+    }
+
+    #[test]
+    fn reads_elements_from_a_reader_one_at_a_time() {
+        let lua = format!("{{{}}}", "{ x = 1, y = 2 },".repeat(10_000));
+        let mut iter = ArrayDeserializer::new(IoRead::new(lua.as_bytes())).seq_iter::<Point>();
+        iter.next().unwrap().unwrap();
+        assert_eq!(iter.byte_offset(), 1 + "{ x = 1, y = 2 }".len());
+        assert_eq!(iter.count(), 9_999);
+    }
+
+    #[test]
+    fn stops_after_the_first_error() {
+        let results = points("{ {x = 1, y = 2}, {x = 'a', y = 4}, {x = 5, y = 6} }");
+        assert_eq!(results.len(), 2);
+        let error = results[1].as_ref().unwrap_err();
+        assert_eq!(
+            error.path(),
+            [PathSegment::Index(2), PathSegment::Key("x".into())]
+        );
+
+        let results = points("{ {x = 1, y = 2} } trailing");
+        assert_eq!(results.len(), 2);
+        assert!(results[1]
+            .as_ref()
+            .unwrap_err()
+            .to_string()
+            .starts_with("trailing characters"));
+
+        let results = points("{ x = {x = 1, y = 2} }");
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_err());
+    }
+
+    #[test]
+    fn keeps_the_options_of_the_deserializer() {
+        let lua = "{ {x = 1, y = 2, x = 3} }";
+        let options = DeOptions::new().with_duplicate_keys(DuplicateKeys::FirstWins);
+        let mut iter = Deserializer::with_options(SliceRead::new(lua.as_bytes()), options)
+            .into_array()
+            .seq_iter::<Point>();
+        assert_eq!(iter.next().unwrap().unwrap(), Point { x: 1, y: 2 });
+        assert!(iter.next().is_none());
+    }
+}
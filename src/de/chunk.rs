@@ -0,0 +1,263 @@
+use super::{
+    is_identifier_start,
+    key::{Key, KeyDeserializer, OwnedKey},
+    table::ENTRY_SIZE,
+    DeError, Deserializer, DuplicateKeys, Read,
+};
+use serde::{
+    de::{self, DeserializeSeed, IgnoredAny, Visitor},
+    forward_to_deserialize_any, Deserialize,
+};
+use std::collections::HashSet;
+
+/// The statement a lua file holds its value in, see [`from_str_chunk`](super::from_str_chunk).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Chunk {
+    /// The file is just the value, like `{...}`.
+    Value,
+    /// The file returns the value, like `return {...}`. This is how modules are written.
+    Return,
+    /// The file assigns global variables, like `Name = {...}`. The value is the table of the
+    /// variables. This is how saved variables and `.rockspec` files are written.
+    Globals,
+}
+
+/// Reads a chunk of assignments to global variables like `a = 1 b = {}` as a table of the
+/// variables. The assignments are the entries of the table, in order.
+pub(super) struct GlobalsDeserializer<'a, R> {
+    pub(super) de: &'a mut Deserializer<R>,
+}
+
+impl<'de, R> de::Deserializer<'de> for GlobalsDeserializer<'_, R>
+where
+    R: Read<'de>,
+{
+    type Error = DeError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_map(GlobalsAccess {
+            de: self.de,
+            key: Key::Integer(0),
+            key_buf: Vec::new(),
+            seen: HashSet::new(),
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier
+        ignored_any
+    }
+}
+
+/// The assignments of a [`GlobalsDeserializer`]. Names that aren't identifiers can be assigned
+/// through `_G`, like `_G["two words"] = 1`, which is how [`Globals`](crate::Globals) writes
+/// them.
+struct GlobalsAccess<'a, 'de, R> {
+    de: &'a mut Deserializer<R>,
+    /// The name of the current variable.
+    key: Key<'de>,
+    /// The current name, if it is a string that couldn't be borrowed.
+    key_buf: Vec<u8>,
+    /// The names assigned so far, unless duplicates are passed on.
+    seen: HashSet<OwnedKey>,
+}
+
+impl<'de, R> GlobalsAccess<'_, 'de, R>
+where
+    R: Read<'de>,
+{
+    /// Adds the position and the current name to an error.
+    fn locate(&self, error: DeError) -> DeError {
+        error
+            .at(self.de.read.position())
+            .in_segment(|| self.key.segment(&self.key_buf))
+    }
+}
+
+impl<'de, R> Deserializer<R>
+where
+    R: Read<'de>,
+{
+    /// Parses the target of an assignment to a global variable up to the `=`, or returns `None`
+    /// at the end of the input.
+    pub(super) fn parse_global(&mut self, buf: &mut Vec<u8>) -> Result<Option<Key<'de>>, DeError> {
+        // Statements may be separated by semicolons.
+        while self.parse_whitespace()? == Some(b';') {
+            self.read.discard();
+        }
+        match self.parse_whitespace()? {
+            None => return Ok(None),
+            Some(b) if is_identifier_start(b) => {}
+            Some(_) => return Err(self.error("expected a name")),
+        }
+        if self.peek_word(b"local")? {
+            self.parse_identifier()?;
+            if !self.parse_whitespace()?.is_some_and(is_identifier_start) {
+                return Err(self.error("expected a name"));
+            }
+        }
+        if self.peek_keyword_value()? || self.peek_word(b"return")? {
+            return Err(self.error("expected a name"));
+        }
+
+        let key = if self.peek_word(b"_G")? {
+            self.parse_identifier()?;
+            if self.parse_whitespace()? == Some(b'[') {
+                self.read.discard();
+                self.parse_key(buf)?
+            } else {
+                Key::Borrowed(b"_G")
+            }
+        } else {
+            Key::string(self.parse_identifier()?, buf)
+        };
+        self.expect(b'=')?;
+        Ok(Some(key))
+    }
+}
+
+impl<'de, R> de::MapAccess<'de> for GlobalsAccess<'_, 'de, R>
+where
+    R: Read<'de>,
+{
+    type Error = DeError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, DeError>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        loop {
+            self.key = match self.de.parse_global(&mut self.key_buf)? {
+                Some(key) => key,
+                None => return Ok(None),
+            };
+            self.key = self
+                .key
+                .with_case(self.de.options.key_case(), &mut self.key_buf);
+            self.de.use_memory(ENTRY_SIZE).map_err(|e| self.locate(e))?;
+            let policy = self.de.options.duplicate_keys();
+            if policy == DuplicateKeys::LastWins
+                || self.seen.insert(self.key.to_owned(&self.key_buf))
+            {
+                break;
+            }
+            if policy == DuplicateKeys::Error {
+                return Err(self.locate(DeError::Syntax("duplicate variable".to_owned())));
+            }
+            IgnoredAny::deserialize(&mut *self.de).map_err(|e| self.locate(e))?;
+        }
+        let key = KeyDeserializer {
+            key: self.key,
+            buf: &self.key_buf,
+        };
+        seed.deserialize(key).map(Some).map_err(|e| self.locate(e))
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, DeError>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        seed.deserialize(&mut *self.de).map_err(|e| self.locate(e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_str_chunk, Chunk, DeOptions, Deserializer, DuplicateKeys, SliceRead};
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn tells_chunks_apart() {
+        for (lua, expected) in [
+            ("{1, 2}", Chunk::Value),
+            ("-- dump\nreturn {1, 2};", Chunk::Return),
+            ("nil", Chunk::Value),
+            ("math.huge", Chunk::Value),
+        ] {
+            let (chunk, _) = from_str_chunk::<crate::Value>(lua).unwrap();
+            assert_eq!(chunk, expected, "{}", lua);
+        }
+    }
+
+    #[test]
+    fn reads_assignments_as_a_table_of_globals() {
+        let lua = "A = {1}\nlocal B = {2};\n_G[\"C d\"] = {3} _G = {4}\n";
+        let (chunk, globals) = from_str_chunk::<BTreeMap<String, Vec<u8>>>(lua).unwrap();
+        assert_eq!(chunk, Chunk::Globals);
+        assert_eq!(
+            globals,
+            BTreeMap::from([
+                ("A".to_owned(), vec![1]),
+                ("B".to_owned(), vec![2]),
+                ("C d".to_owned(), vec![3]),
+                ("_G".to_owned(), vec![4]),
+            ])
+        );
+    }
+
+    #[test]
+    fn reads_globals_written_by_the_globals_formatter() {
+        let lua = "name = {\n  \"a\"\n}\n_G[\"two words\"] = {}\n";
+        let (_, globals) = from_str_chunk::<BTreeMap<String, Vec<String>>>(lua).unwrap();
+        assert_eq!(globals["name"], ["a"]);
+        assert!(globals["two words"].is_empty());
+    }
+
+    #[test]
+    fn reports_the_variable_of_errors() {
+        let error = from_str_chunk::<BTreeMap<String, u8>>("A = 1\nB = 300\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid value: integer `300`, expected u8 at B, line 2, column 8"
+        );
+
+        let error = from_str_chunk::<BTreeMap<String, u8>>("A = 1\nreturn 2").unwrap_err();
+        assert_eq!(error.to_string(), "expected a name at line 2, column 1");
+    }
+
+    #[test]
+    fn applies_the_duplicate_policy_to_variables() {
+        #[derive(Deserialize)]
+        struct Globals {
+            a: u8,
+        }
+
+        let options = DeOptions::new().with_duplicate_keys(DuplicateKeys::FirstWins);
+        let mut de = Deserializer::with_options(SliceRead::new(b"a = 1 a = 2"), options);
+        let (_, globals) = crate::de::deserialize_any_chunk::<_, Globals>(&mut de).unwrap();
+        assert_eq!(globals.a, 1);
+    }
+
+    #[test]
+    fn rejects_chunks_in_strict_mode() {
+        let options = DeOptions::new().with_strict(true);
+        for (lua, message) in [
+            (
+                "return {}",
+                "`return` isn't allowed in strict mode at line 1, column 1",
+            ),
+            (
+                "local a = 1",
+                "an assignment isn't allowed in strict mode at line 1, column 1",
+            ),
+            (
+                "{ 0/0 }",
+                "`0/0` isn't allowed in strict mode at [1], line 1, column 3",
+            ),
+        ] {
+            let mut de =
+                Deserializer::with_options(SliceRead::new(lua.as_bytes()), options.clone());
+            let error = crate::de::deserialize_chunk::<_, crate::Value>(&mut de).unwrap_err();
+            assert_eq!(error.to_string(), message, "{}", lua);
+            let mut de =
+                Deserializer::with_options(SliceRead::new(lua.as_bytes()), options.clone());
+            let error = crate::de::deserialize_any_chunk::<_, crate::Value>(&mut de).unwrap_err();
+            assert_eq!(error.to_string(), message, "{}", lua);
+        }
+    }
+}
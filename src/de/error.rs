@@ -0,0 +1,379 @@
+use crate::PathSegment;
+use std::{
+    fmt::{self, Display},
+    io,
+    sync::Arc,
+};
+
+#[derive(thiserror::Error, Debug, Clone)]
+pub enum DeError {
+    #[error("Io Error: {0}")]
+    Io(Arc<io::Error>),
+    #[error("{0}")]
+    Custom(String),
+    #[error("{0}")]
+    Syntax(String),
+    #[error("Tables are nested deeper than {0} levels")]
+    DepthLimitExceeded(usize),
+    /// The parsed values need more memory than
+    /// [`DeOptions::with_memory_limit`](super::DeOptions::with_memory_limit) allows.
+    #[error("Parsed values take more than {0} bytes")]
+    MemoryLimitExceeded(usize),
+    /// Another error with the position in the input and the path to the value it occurred in.
+    #[error("{source} at {}", location(.path, .position))]
+    Located {
+        source: Box<DeError>,
+        position: Position,
+        path: Vec<PathSegment>,
+    },
+}
+
+impl DeError {
+    /// Returns where in the input the error occurred, if known.
+    pub fn position(&self) -> Option<Position> {
+        match self {
+            DeError::Located { position, .. } => Some(*position),
+            _ => None,
+        }
+    }
+
+    /// Returns the path from the outermost value to the value the error occurred in, e.g.
+    /// `settings.ui.colors[3]`.
+    ///
+    /// ```
+    /// use serde_lua_table::PathSegment;
+    /// use std::collections::HashMap;
+    ///
+    /// let input = "{\n  ui = {\n    colors = {1, 2, 300},\n  },\n}";
+    /// let error = serde_lua_table::from_str::<HashMap<String, HashMap<String, Vec<u8>>>>(input)
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     error.path(),
+    ///     [PathSegment::Key("ui".into()), PathSegment::Key("colors".into()), PathSegment::Index(3)]
+    /// );
+    /// assert_eq!(error.position().unwrap().line, 3);
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     "invalid value: integer `300`, expected u8 at ui.colors[3], line 3, column 24"
+    /// );
+    /// ```
+    pub fn path(&self) -> &[PathSegment] {
+        match self {
+            DeError::Located { path, .. } => path,
+            _ => &[],
+        }
+    }
+
+    /// Writes the error followed by the line of `source` it occurred in, with a caret under the
+    /// position that is labeled with the error. Errors without a position are only the message.
+    ///
+    /// ```
+    /// let input = "{\n  a = 1,\n  b = ,\n}";
+    /// let error = serde_lua_table::from_str::<serde_lua_table::Value>(input).unwrap_err();
+    /// assert_eq!(
+    ///     error.render(input),
+    ///     "expected a value at b, line 3, column 7\n  |\n3 |   b = ,\n  |       ^ expected a value"
+    /// );
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        match self {
+            DeError::Located {
+                source: error,
+                position,
+                ..
+            } => snippet(self, source, *position, 1, error),
+            _ => self.to_string(),
+        }
+    }
+
+    /// Adds `position` to the error, unless it already has one.
+    pub(super) fn at(self, position: Position) -> Self {
+        match self {
+            DeError::Located { .. } => self,
+            source => DeError::Located {
+                source: Box::new(source),
+                position,
+                path: Vec::new(),
+            },
+        }
+    }
+
+    /// Prepends `segment` to the path of a located error.
+    pub(super) fn in_segment(mut self, segment: impl FnOnce() -> PathSegment) -> Self {
+        if let DeError::Located { path, .. } = &mut self {
+            path.insert(0, segment());
+        }
+        self
+    }
+}
+
+impl serde::de::Error for DeError {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: Display,
+    {
+        Self::Custom(msg.to_string())
+    }
+}
+
+/// Codes, help and the location for applications reporting errors with miette.
+///
+/// miette needs the input to show the location, attach it with [`DeError::with_source_code`].
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for DeError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        let code = match self {
+            DeError::Io(_) => "serde_lua_table::io",
+            DeError::Custom(_) => "serde_lua_table::invalid_value",
+            DeError::Syntax(_) => "serde_lua_table::syntax",
+            DeError::DepthLimitExceeded(_) => "serde_lua_table::depth_limit_exceeded",
+            DeError::MemoryLimitExceeded(_) => "serde_lua_table::memory_limit_exceeded",
+            DeError::Located { source, .. } => return source.code(),
+        };
+        Some(Box::new(code))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        let help = match self {
+            DeError::Io(_) | DeError::Custom(_) | DeError::Syntax(_) => return None,
+            DeError::DepthLimitExceeded(_) => "check the input for unexpected nesting",
+            DeError::MemoryLimitExceeded(_) => {
+                "raise the limit with `DeOptions::with_memory_limit` if the input is trusted"
+            }
+            DeError::Located { source, .. } => return source.help(),
+        };
+        Some(Box::new(help))
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        match self {
+            DeError::Located {
+                source, position, ..
+            } => Some(Box::new(std::iter::once(miette::LabeledSpan::at_offset(
+                position.offset,
+                source.to_string(),
+            )))),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "miette")]
+impl DeError {
+    /// Attaches the input the error occurred in, so miette shows the lines around it.
+    ///
+    /// ```
+    /// let input = "{a = 1,\n b = }";
+    /// let error = serde_lua_table::from_str::<serde_lua_table::Value>(input).unwrap_err();
+    /// let report = miette::Report::new(error.with_source_code(input));
+    /// assert_eq!(report.labels().unwrap().next().unwrap().offset(), 13);
+    /// ```
+    pub fn with_source_code(self, source_code: impl Into<String>) -> SourcedDeError {
+        SourcedDeError {
+            error: self,
+            source_code: source_code.into(),
+        }
+    }
+}
+
+/// A [`DeError`] together with the input it occurred in, see [`DeError::with_source_code`].
+#[cfg(feature = "miette")]
+#[derive(thiserror::Error, Debug, Clone)]
+#[error("{error}")]
+pub struct SourcedDeError {
+    error: DeError,
+    source_code: String,
+}
+
+#[cfg(feature = "miette")]
+impl SourcedDeError {
+    /// Returns the error without the input.
+    pub fn into_inner(self) -> DeError {
+        self.error
+    }
+}
+
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for SourcedDeError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.error.code()
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.error.help()
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        self.error.labels()
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        Some(&self.source_code)
+    }
+}
+
+/// A position in the input of a [`Deserializer`](super::Deserializer).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Position {
+    /// Number of bytes before the position.
+    pub offset: usize,
+    /// The line, starting at 1.
+    pub line: usize,
+    /// The byte in the line, starting at 1.
+    pub column: usize,
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// Appends the line at `position` to `message` and underlines `len` bytes of it, followed by
+/// `label`.
+pub(super) fn snippet(
+    message: &dyn Display,
+    source: &str,
+    position: Position,
+    len: usize,
+    label: &dyn Display,
+) -> String {
+    let line = source.lines().nth(position.line - 1).unwrap_or_default();
+    let number = position.line.to_string();
+    let gutter = " ".repeat(number.len());
+    // Keep tabs, so the carets line up with the line above.
+    let indent: String = line
+        .get(..position.column - 1)
+        .unwrap_or(line)
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let len = len.clamp(1, (line.len() + 1).saturating_sub(position.column).max(1));
+    format!(
+        "{message}\n{gutter} |\n{number} | {line}\n{gutter} | {indent}{} {label}",
+        "^".repeat(len)
+    )
+}
+
+/// Writes the location of an error, e.g. `settings.ui.colors[3], line 4, column 12`.
+pub(super) struct Location<'a> {
+    path: &'a [PathSegment],
+    position: &'a Position,
+}
+
+pub(super) fn location<'a>(path: &'a [PathSegment], position: &'a Position) -> Location<'a> {
+    Location { path, position }
+}
+
+impl Display for Location<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((first, rest)) = self.path.split_first() {
+            match first {
+                PathSegment::Key(key) => f.write_str(key)?,
+                index => write!(f, "{index}")?,
+            }
+            for segment in rest {
+                write!(f, "{segment}")?;
+            }
+            f.write_str(", ")?;
+        }
+        write!(f, "{}", self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Value;
+
+    fn render(input: &str) -> String {
+        crate::from_str::<Value>(input).unwrap_err().render(input)
+    }
+
+    #[test]
+    fn locates_errors_in_nested_values() {
+        use crate::{PathSegment, Position};
+        use std::collections::BTreeMap;
+
+        let input = "{\n  [\"a b\"] = { [5] = { x = 'y' } },\n}";
+        let error = crate::from_str::<BTreeMap<String, BTreeMap<i64, BTreeMap<String, u8>>>>(input)
+            .unwrap_err();
+        assert_eq!(
+            error.path(),
+            [
+                PathSegment::Key("a b".to_owned()),
+                PathSegment::Index(5),
+                PathSegment::Key("x".to_owned())
+            ]
+        );
+        assert_eq!(
+            error.position(),
+            Some(Position {
+                offset: 31,
+                line: 2,
+                column: 30
+            })
+        );
+        assert_eq!(
+            error.to_string(),
+            "invalid type: string \"y\", expected u8 at a b[5].x, line 2, column 30"
+        );
+
+        let error = crate::from_str::<Vec<u8>>("{1, 2").unwrap_err();
+        assert_eq!(error.path(), []);
+        assert_eq!(
+            error.to_string(),
+            "unexpected end of input at line 1, column 6"
+        );
+    }
+
+    #[test]
+    fn renders_the_line_with_a_caret() {
+        assert_eq!(
+            render("{ 'é', = }"),
+            "expected a value at [2], line 1, column 9\n  |\n1 | { 'é', = }\n  |        ^ expected a value"
+        );
+        assert_eq!(
+            render("{\r\n\tx = ,\r\n}"),
+            "expected a value at x, line 2, column 6\n  |\n2 | \tx = ,\n  | \t    ^ expected a value"
+        );
+        assert_eq!(
+            render(""),
+            "unexpected end of input at line 1, column 1\n  |\n1 | \n  | ^ unexpected end of input"
+        );
+    }
+
+    #[test]
+    fn renders_errors_without_a_position_as_the_message() {
+        let error = crate::DeError::Custom("no position".to_owned());
+        assert_eq!(error.render("{}"), "no position");
+    }
+
+    #[cfg(feature = "miette")]
+    #[test]
+    fn renders_parse_errors_with_miette() {
+        use miette::{Diagnostic, NarratableReportHandler};
+
+        let input = "{\n  a = 1,\n  b = ,\n}";
+        let error = crate::from_str::<Value>(input).unwrap_err();
+        assert_eq!(error.code().unwrap().to_string(), "serde_lua_table::syntax");
+
+        let error = error.with_source_code(input);
+        let mut report = String::new();
+        NarratableReportHandler::new()
+            .render_report(&mut report, &error)
+            .unwrap();
+        assert_eq!(
+            report,
+            "expected a value at b, line 3, column 7
+    Diagnostic severity: error
+Begin snippet starting at line 2, column 1
+
+snippet line 2:   a = 1,
+snippet line 3:   b = ,
+    label at line 3, column 7: expected a value
+snippet line 4: }
+diagnostic code: serde_lua_table::syntax
+"
+        );
+    }
+}
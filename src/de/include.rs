@@ -0,0 +1,46 @@
+//! [`include_lua!`](crate::include_lua), which embeds a lua file in the binary.
+
+/// Embeds a lua file in the binary like `include_str!` and deserializes it with
+/// [`from_str_chunk`](crate::from_str_chunk) when the expression is evaluated.
+///
+/// This is only `from_str_chunk(include_str!(..))`: the file is parsed at runtime, so syntax
+/// errors are returned as a [`DeError`](crate::DeError) like a type mismatch is, not reported
+/// when building. The file is deserialized into a [`Value`](crate::Value), or into the type given
+/// after the path. The path is relative to the current file, like the path of `include_str!`.
+///
+/// ```ignore
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Item {
+///     name: String,
+///     price: u32,
+/// }
+///
+/// let items = serde_lua_table::include_lua!("items.lua", Vec<Item>).unwrap();
+/// let any = serde_lua_table::include_lua!("items.lua").unwrap();
+/// ```
+#[macro_export]
+macro_rules! include_lua {
+    ($file:expr $(,)?) => {
+        $crate::include_lua!($file, $crate::Value)
+    };
+    ($file:expr, $ty:ty $(,)?) => {
+        $crate::from_str_chunk::<$ty>(include_str!($file)).map(|(_, value)| value)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Value;
+
+    #[test]
+    fn deserializes_the_included_file() {
+        let value = crate::include_lua!("../../test_example.lua").unwrap();
+        let expected: Value = crate::from_str_chunk(include_str!("../../test_example.lua"))
+            .unwrap()
+            .1;
+        assert_eq!(value, expected);
+        assert!(crate::include_lua!("../../test_example.lua", u32).is_err());
+    }
+}
@@ -0,0 +1,282 @@
+use super::{DeError, Deserializer, Number, Read, Reference};
+use crate::{KeyCase, PathSegment};
+use serde::{
+    de::{
+        self,
+        value::{BorrowedStrDeserializer, StrDeserializer},
+        Visitor,
+    },
+    forward_to_deserialize_any,
+};
+use std::borrow::Cow;
+
+/// The key of a table entry. It's kept until the value is read, so errors can name it.
+#[derive(Clone, Copy)]
+pub(super) enum Key<'de> {
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Borrowed(&'de [u8]),
+    /// A string in the key buffer of the table.
+    Copied,
+}
+
+/// A [`Key`] that outlives the input. Floats are compared by their bits.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(super) enum OwnedKey {
+    Integer(i64),
+    Float(u64),
+    Boolean(bool),
+    String(Vec<u8>),
+}
+
+impl<'de> Key<'de> {
+    /// Keeps a parsed string, copying it into `buf` if it can't be borrowed.
+    pub(super) fn string(string: Reference<'de, '_>, buf: &mut Vec<u8>) -> Self {
+        match string {
+            Reference::Borrowed(bytes) => Key::Borrowed(bytes),
+            Reference::Copied(bytes) => {
+                buf.clear();
+                buf.extend_from_slice(bytes);
+                Key::Copied
+            }
+        }
+    }
+
+    /// Renames a string key, copying it into `buf` if the name changes. Keys that aren't UTF-8
+    /// are kept.
+    pub(super) fn with_case(self, case: KeyCase, buf: &mut Vec<u8>) -> Self {
+        let bytes = match self {
+            Key::Borrowed(bytes) => bytes,
+            Key::Copied => buf,
+            _ => return self,
+        };
+        let Ok(key) = std::str::from_utf8(bytes) else {
+            return self;
+        };
+        match case.apply(key) {
+            Cow::Borrowed(_) => self,
+            Cow::Owned(renamed) => {
+                buf.clear();
+                buf.extend_from_slice(renamed.as_bytes());
+                Key::Copied
+            }
+        }
+    }
+
+    /// Copies the key, to remember which keys a table already had.
+    pub(super) fn to_owned(self, buf: &[u8]) -> OwnedKey {
+        match self {
+            Key::Integer(i) => OwnedKey::Integer(i),
+            Key::Float(n) => OwnedKey::Float(n.to_bits()),
+            Key::Boolean(b) => OwnedKey::Boolean(b),
+            Key::Borrowed(bytes) => OwnedKey::String(bytes.to_vec()),
+            Key::Copied => OwnedKey::String(buf.to_vec()),
+        }
+    }
+
+    pub(super) fn segment(self, buf: &[u8]) -> PathSegment {
+        match self {
+            Key::Integer(i) => PathSegment::Index(i),
+            Key::Float(n) => PathSegment::Key(n.to_string()),
+            Key::Boolean(b) => PathSegment::Key(b.to_string()),
+            Key::Borrowed(bytes) => PathSegment::Key(String::from_utf8_lossy(bytes).into_owned()),
+            Key::Copied => PathSegment::Key(String::from_utf8_lossy(buf).into_owned()),
+        }
+    }
+}
+
+impl OwnedKey {
+    pub(super) fn segment(&self) -> PathSegment {
+        match self {
+            OwnedKey::Integer(i) => PathSegment::Index(*i),
+            OwnedKey::Float(bits) => PathSegment::Key(f64::from_bits(*bits).to_string()),
+            OwnedKey::Boolean(b) => PathSegment::Key(b.to_string()),
+            OwnedKey::String(bytes) => {
+                PathSegment::Key(String::from_utf8_lossy(bytes).into_owned())
+            }
+        }
+    }
+}
+
+impl<'de, R> Deserializer<R>
+where
+    R: Read<'de>,
+{
+    /// Parses the key in brackets like `["name"]` or `[1]`, after the opening bracket.
+    pub(super) fn parse_key(&mut self, buf: &mut Vec<u8>) -> Result<Key<'de>, DeError> {
+        let key = match self.parse_whitespace()? {
+            Some(b) if self.peek_string(b)? => Key::string(self.parse_string()?, buf),
+            Some(b) if self.peek_number(b)? => match self.parse_number()?.integral() {
+                Number::PosInt(n) => i64::try_from(n).map_or(Key::Float(n as f64), Key::Integer),
+                Number::NegInt(n) => Key::Integer(n),
+                Number::Float(n) => Key::Float(n),
+            },
+            Some(_) if self.peek_word(b"true")? => {
+                self.parse_identifier()?;
+                Key::Boolean(true)
+            }
+            Some(_) if self.peek_word(b"false")? => {
+                self.parse_identifier()?;
+                Key::Boolean(false)
+            }
+            Some(_) => return Err(self.error("expected a key")),
+            None => return Err(self.eof()),
+        };
+        self.expect(b']')?;
+        Ok(key)
+    }
+}
+
+/// Deserializes a key of a table.
+pub(super) struct KeyDeserializer<'a, 'de> {
+    pub(super) key: Key<'de>,
+    pub(super) buf: &'a [u8],
+}
+
+macro_rules! deserialize_integer {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, DeError>
+            where
+                V: Visitor<'de>,
+            {
+                match self.key {
+                    Key::Float(n) => Number::Float(n).integral().visit(visitor),
+                    _ => self.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer<'_, 'de> {
+    type Error = DeError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.key {
+            Key::Integer(i) => visitor.visit_i64(i),
+            Key::Float(n) => visitor.visit_f64(n),
+            Key::Boolean(b) => visitor.visit_bool(b),
+            Key::Borrowed(bytes) => match std::str::from_utf8(bytes) {
+                Ok(s) => visitor.visit_borrowed_str(s),
+                Err(_) => visitor.visit_borrowed_bytes(bytes),
+            },
+            Key::Copied => match std::str::from_utf8(self.buf) {
+                Ok(s) => visitor.visit_str(s),
+                Err(_) => visitor.visit_bytes(self.buf),
+            },
+        }
+    }
+
+    deserialize_integer! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+    }
+
+    // A key is never `nil`.
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Reads a unit variant from a string key, which is how the serializer writes enum keys.
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.key {
+            Key::Borrowed(bytes) => match std::str::from_utf8(bytes) {
+                Ok(s) => visitor.visit_enum(BorrowedStrDeserializer::new(s)),
+                Err(_) => self.deserialize_any(visitor),
+            },
+            Key::Copied => match std::str::from_utf8(self.buf) {
+                Ok(s) => visitor.visit_enum(StrDeserializer::new(s)),
+                Err(_) => self.deserialize_any(visitor),
+            },
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i128 u128 f32 f64 char str string bytes byte_buf unit unit_struct
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_reader, from_str, to_string};
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    enum Slot {
+        Head,
+        Feet,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct Id(i64);
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct Name(String);
+
+    #[test]
+    fn reads_enum_keys() {
+        let map = BTreeMap::from([(Slot::Head, 1), (Slot::Feet, 2)]);
+        let lua = to_string(&map).unwrap();
+        assert_eq!(lua, r#"{["Head"]=1,["Feet"]=2}"#);
+        assert_eq!(from_str::<BTreeMap<Slot, i32>>(&lua).unwrap(), map);
+        assert_eq!(
+            from_reader::<_, BTreeMap<Slot, i32>>(lua.as_bytes()).unwrap(),
+            map
+        );
+        assert!(from_str::<BTreeMap<Slot, i32>>(r#"{["Hand"]=1}"#).is_err());
+    }
+
+    #[test]
+    fn reads_newtype_keys() {
+        let ids = BTreeMap::from([(Id(1), 1), (Id(3), 2)]);
+        let lua = to_string(&ids).unwrap();
+        assert_eq!(lua, "{[1]=1,[3]=2}");
+        assert_eq!(from_str::<BTreeMap<Id, i32>>(&lua).unwrap(), ids);
+
+        let names = BTreeMap::from([(Name("a b".into()), 1)]);
+        let lua = to_string(&names).unwrap();
+        assert_eq!(from_str::<BTreeMap<Name, i32>>(&lua).unwrap(), names);
+    }
+
+    #[test]
+    fn reads_option_keys() {
+        let map = from_str::<BTreeMap<Option<String>, i32>>("{a = 1}").unwrap();
+        assert_eq!(map, BTreeMap::from([(Some("a".to_owned()), 1)]));
+    }
+
+    #[test]
+    fn reads_integral_float_keys_as_integers() {
+        let map = from_str::<BTreeMap<u8, i32>>("{[1.0] = 1, [0x2] = 2}").unwrap();
+        assert_eq!(map, BTreeMap::from([(1, 1), (2, 2)]));
+    }
+}
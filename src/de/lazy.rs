@@ -0,0 +1,366 @@
+use super::{
+    deserialize_any_chunk, is_identifier_start,
+    key::{Key, KeyDeserializer},
+    table::Entry,
+    Chunk, DeError, Deserializer, Read, SliceRead,
+};
+use crate::{
+    value::{self, Value},
+    PathMatcher, PathSegment,
+};
+use serde::{de::IgnoredAny, Deserialize};
+use std::{cell::OnceCell, collections::HashMap, ops::Range};
+
+/// A value that is only parsed where it's accessed.
+///
+/// Looking up a field skips over the other fields of its table without building them and
+/// remembers where each field is written, so tools that read a few paths of a large file don't
+/// pay for parsing all of it. Fields and values are parsed once and kept.
+///
+/// Skipped fields are still checked for syntax errors, so listing the fields of the outermost
+/// value finds all of them.
+///
+/// ```
+/// use serde_lua_table::{LazyValue, Value};
+///
+/// let lua = "Settings = { profiles = { default = { scale = 1.5 } } }\nCache = { --[[ huge ]] }\n";
+/// let root = LazyValue::from_str(lua)?;
+/// let profile = root.get("Settings")?.unwrap().get("profiles")?.unwrap();
+/// assert_eq!(profile.raw(), "{ default = { scale = 1.5 } }");
+/// assert_eq!(
+///     profile.get("default")?.unwrap().get("scale")?.unwrap().value()?,
+///     &Value::Number(1.5)
+/// );
+/// # Ok::<(), serde_lua_table::DeError>(())
+/// ```
+#[derive(Debug)]
+pub struct LazyValue<'de> {
+    input: &'de str,
+    span: Range<usize>,
+    chunk: Chunk,
+    fields: OnceCell<Vec<(value::Key, LazyValue<'de>)>>,
+    value: OnceCell<Value>,
+}
+
+impl<'de> LazyValue<'de> {
+    /// Wraps the lua in `s` without parsing it. It's read like
+    /// [`from_str_chunk`](super::from_str_chunk) reads it, so the variables of a chunk of
+    /// assignments are the fields of the outermost value.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &'de str) -> Result<Self, DeError> {
+        let mut de = Deserializer::from_str(s);
+        let chunk = match de.parse_whitespace()? {
+            Some(_) if de.peek_word(b"return")? => Chunk::Return,
+            Some(b) if is_identifier_start(b) && !de.peek_keyword_value()? => Chunk::Globals,
+            _ => Chunk::Value,
+        };
+        Ok(LazyValue::new(s, 0..s.len(), chunk))
+    }
+
+    fn new(input: &'de str, span: Range<usize>, chunk: Chunk) -> Self {
+        LazyValue {
+            input,
+            span,
+            chunk,
+            fields: OnceCell::new(),
+            value: OnceCell::new(),
+        }
+    }
+
+    /// How the value is written, only the outermost value can be something else than
+    /// [`Chunk::Value`].
+    pub fn chunk(&self) -> Chunk {
+        self.chunk
+    }
+
+    /// The lua source of the value, or the whole input for the outermost value.
+    pub fn raw(&self) -> &'de str {
+        &self.input[self.span.clone()]
+    }
+
+    /// Returns the field at `key`, or `None` if the value isn't a table or has no such field.
+    /// Only the tables on the way to the field are parsed.
+    pub fn get(&self, key: impl Into<value::Key>) -> Result<Option<&LazyValue<'de>>, DeError> {
+        let key = key.into();
+        let fields = self.fields()?;
+        Ok(fields.iter().find(|(k, _)| *k == key).map(|(_, v)| v))
+    }
+
+    /// The fields of the value in the order they are written, a later field with the same key
+    /// replaces an earlier one. Empty if the value isn't a table.
+    pub fn fields(&self) -> Result<&[(value::Key, LazyValue<'de>)], DeError> {
+        if let Some(fields) = self.fields.get() {
+            return Ok(fields);
+        }
+        let mut de = self.deserializer();
+        let fields = self
+            .parse_fields(&mut de)
+            .map_err(|e| e.at(de.read.position()))?;
+        Ok(self.fields.get_or_init(|| fields))
+    }
+
+    /// Returns the values whose paths match `pattern` with their paths, in the order they are
+    /// written. Only the tables that can hold a match are parsed.
+    ///
+    /// ```
+    /// use serde_lua_table::{LazyValue, PathSegment};
+    ///
+    /// let lua = "return { items = { { id = 1 }, { id = 2, tags = {} } }, cache = { id = 3 } }";
+    /// let root = LazyValue::from_str(lua)?;
+    /// let ids: Vec<u32> = root
+    ///     .select(&"items[*].id".parse().unwrap())?
+    ///     .into_iter()
+    ///     .map(|(_, value)| value.deserialize())
+    ///     .collect::<Result<_, _>>()?;
+    /// assert_eq!(ids, [1, 2]);
+    /// # Ok::<(), serde_lua_table::DeError>(())
+    /// ```
+    pub fn select(
+        &self,
+        pattern: &PathMatcher,
+    ) -> Result<Vec<(Vec<PathSegment>, &LazyValue<'de>)>, DeError> {
+        let mut selected = Vec::new();
+        self.select_into(pattern, &mut Vec::new(), &mut selected)?;
+        Ok(selected)
+    }
+
+    fn select_into<'a>(
+        &'a self,
+        pattern: &PathMatcher,
+        path: &mut Vec<PathSegment>,
+        selected: &mut Vec<(Vec<PathSegment>, &'a LazyValue<'de>)>,
+    ) -> Result<(), DeError> {
+        if pattern.matches(path) {
+            selected.push((path.clone(), self));
+        }
+        if !pattern.matches_below(path) {
+            return Ok(());
+        }
+        for (key, field) in self.fields()? {
+            path.push(key.segment());
+            if pattern.matches_below(path) {
+                field.select_into(pattern, path, selected)?;
+            }
+            path.pop();
+        }
+        Ok(())
+    }
+
+    /// Parses the value into a [`Value`].
+    pub fn value(&self) -> Result<&Value, DeError> {
+        if let Some(value) = self.value.get() {
+            return Ok(value);
+        }
+        let value = self.deserialize()?;
+        Ok(self.value.get_or_init(|| value))
+    }
+
+    /// Parses the value into `T`, without keeping the result.
+    pub fn deserialize<T>(&self) -> Result<T, DeError>
+    where
+        T: Deserialize<'de>,
+    {
+        let mut de = self.deserializer();
+        // Only the outermost value starts at the beginning of the input, fields are in a table.
+        match self.span.start {
+            0 => deserialize_any_chunk(&mut de).map(|(_, value)| value),
+            _ => T::deserialize(&mut de).map_err(|e| e.at(de.read.position())),
+        }
+    }
+
+    /// Returns a deserializer positioned at the start of the value, so errors point into the
+    /// whole input.
+    fn deserializer(&self) -> Deserializer<SliceRead<'de>> {
+        Deserializer::new(SliceRead::starting_at(
+            self.input.as_bytes(),
+            self.span.start,
+        ))
+    }
+
+    fn parse_fields(
+        &self,
+        de: &mut Deserializer<SliceRead<'de>>,
+    ) -> Result<Vec<(value::Key, LazyValue<'de>)>, DeError> {
+        let mut fields = Fields::default();
+        let mut buf = Vec::new();
+        if self.chunk == Chunk::Globals {
+            loop {
+                de.parse_whitespace()?;
+                let Some(key) = de.parse_global(&mut buf)? else {
+                    break;
+                };
+                let key = value_key(key, &buf)?;
+                fields.insert(key, self.skip_value(de)?);
+            }
+            return Ok(fields.fields);
+        }
+
+        de.parse_whitespace()?;
+        if self.chunk == Chunk::Return {
+            de.parse_identifier()?;
+        }
+        if de.parse_whitespace()? != Some(b'{') {
+            return Ok(Vec::new());
+        }
+        de.enter_table()?;
+        let mut first = true;
+        let mut index = 0;
+        loop {
+            let entry = de.table_entry(first)?;
+            first = false;
+            let key = match entry {
+                Entry::End => break,
+                Entry::Key if de.read.peek()? == Some(b'[') => {
+                    de.read.discard();
+                    let key = de.parse_key(&mut buf)?;
+                    de.expect(b'=')?;
+                    value_key(key, &buf)?
+                }
+                Entry::Key => {
+                    let key = Key::string(de.parse_identifier()?, &mut buf);
+                    de.expect(b'=')?;
+                    value_key(key, &buf)?
+                }
+                Entry::Positional => {
+                    index += 1;
+                    value::Key::Integer(index)
+                }
+            };
+            fields.insert(key, self.skip_value(de)?);
+        }
+        de.end_table()?;
+        Ok(fields.fields)
+    }
+
+    /// Skips the next value and returns it unparsed.
+    fn skip_value(&self, de: &mut Deserializer<SliceRead<'de>>) -> Result<LazyValue<'de>, DeError> {
+        de.parse_whitespace()?;
+        let start = de.read.offset();
+        IgnoredAny::deserialize(&mut *de)?;
+        Ok(LazyValue::new(
+            self.input,
+            start..de.read.offset(),
+            Chunk::Value,
+        ))
+    }
+}
+
+/// The fields of a table, where later fields replace earlier ones with the same key.
+#[derive(Default)]
+struct Fields<'de> {
+    fields: Vec<(value::Key, LazyValue<'de>)>,
+    indices: HashMap<value::Key, usize>,
+}
+
+impl<'de> Fields<'de> {
+    fn insert(&mut self, key: value::Key, value: LazyValue<'de>) {
+        match self.indices.get(&key) {
+            Some(&i) => self.fields[i].1 = value,
+            None => {
+                self.indices.insert(key.clone(), self.fields.len());
+                self.fields.push((key, value));
+            }
+        }
+    }
+}
+
+/// Converts a key like deserializing a [`Value`] does.
+fn value_key(key: Key<'_>, buf: &[u8]) -> Result<value::Key, DeError> {
+    value::Key::deserialize(KeyDeserializer { key, buf })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LazyValue;
+    use crate::{Chunk, Value};
+
+    #[test]
+    fn parses_only_the_tables_on_the_path() {
+        // Listing the fields of the outermost table checks the broken one.
+        let lua = "return { a = { b = { 1, 2, 'three' } }, broken = { = } }";
+        let root = LazyValue::from_str(lua).unwrap();
+        assert_eq!(root.chunk(), Chunk::Return);
+        let a = root.get("a").unwrap_err();
+        assert!(a.to_string().starts_with("expected a value"), "{}", a);
+
+        let lua = "return { a = { b = { 1, 2, 'three' } }, other = { {}, {} } }";
+        let root = LazyValue::from_str(lua).unwrap();
+        let b = root.get("a").unwrap().unwrap().get("b").unwrap().unwrap();
+        assert_eq!(b.raw(), "{ 1, 2, 'three' }");
+        assert_eq!(
+            b.get(3).unwrap().unwrap().value().unwrap(),
+            &Value::from("three")
+        );
+        assert!(b.get(4).unwrap().is_none());
+        assert!(b.get(1).unwrap().unwrap().get("x").unwrap().is_none());
+    }
+
+    #[test]
+    fn selects_only_the_matching_values() {
+        let lua = "A = { x = 1, y = { x = 2 } }\nB = { { x = 3 } }\nC = 'c'\n";
+        let root = LazyValue::from_str(lua).unwrap();
+        let select = |pattern: &str| {
+            root.select(&pattern.parse().unwrap())
+                .unwrap()
+                .into_iter()
+                .map(|(path, value)| {
+                    let path: String = path.iter().map(ToString::to_string).collect();
+                    (path, value.raw())
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(select("A.x"), [(".A.x".to_owned(), "1")]);
+        assert_eq!(
+            select("A.**.x"),
+            [(".A.x".to_owned(), "1"), (".A.y.x".to_owned(), "2")]
+        );
+        assert_eq!(select("C"), [(".C".to_owned(), "'c'")]);
+        assert_eq!(select("*[1].x"), [(".B[1].x".to_owned(), "3")]);
+        assert!(select("A.z").is_empty());
+    }
+
+    #[test]
+    fn reads_globals_as_fields() {
+        let lua = "-- saved\nA = 1\nlocal B = { x = true }\n_G['C'] = 'c'\nA = 2\n";
+        let root = LazyValue::from_str(lua).unwrap();
+        assert_eq!(root.chunk(), Chunk::Globals);
+        let keys: Vec<_> = root
+            .fields()
+            .unwrap()
+            .iter()
+            .map(|(k, _)| k.clone())
+            .collect();
+        assert_eq!(keys, ["A".into(), "B".into(), "C".into()]);
+        assert_eq!(root.get("A").unwrap().unwrap().raw(), "2");
+        assert_eq!(
+            root.value().unwrap(),
+            &crate::from_str_chunk::<Value>(lua).unwrap().1
+        );
+    }
+
+    #[test]
+    fn reports_errors_at_their_position_in_the_whole_input() {
+        let lua = "{\n  a = { 1, 2 },\n  b = { x = { y = 1 }, z = { , } },\n}";
+        let root = LazyValue::from_str(lua).unwrap();
+        let error = root.get("a").unwrap_err();
+        let position = error.position().unwrap();
+        assert_eq!((position.line, position.column), (3, 30));
+
+        let lua = "{\n  a = { x = 'one' },\n}";
+        let root = LazyValue::from_str(lua).unwrap();
+        let x = root.get("a").unwrap().unwrap().get("x").unwrap().unwrap();
+        let error = x.deserialize::<i32>().unwrap_err();
+        let position = error.position().unwrap();
+        assert_eq!((position.line, position.column), (2, 18));
+    }
+
+    #[test]
+    fn deserializes_parts_into_types() {
+        let lua = "{ points = { {1, 2}, {3, 4} }, name = 'n' }";
+        let root = LazyValue::from_str(lua).unwrap();
+        let points: Vec<(i32, i32)> = root.get("points").unwrap().unwrap().deserialize().unwrap();
+        assert_eq!(points, [(1, 2), (3, 4)]);
+        let name: &str = root.get("name").unwrap().unwrap().deserialize().unwrap();
+        assert_eq!(name, "n");
+    }
+}
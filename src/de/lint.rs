@@ -0,0 +1,364 @@
+use super::{
+    error::{location, snippet},
+    key::OwnedKey,
+    syntax::{Field, FieldKey, LineIndex, Node, NodeKind},
+    Chunk, DeError, Deserializer, Number, Position, SliceRead,
+};
+use crate::{LuaVersion, PathSegment};
+use std::{
+    collections::HashSet,
+    fmt::{self, Display},
+    ops::Range,
+};
+
+/// Something in a lua file that can be read but is likely a mistake, see [`lint`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LintWarning {
+    pub kind: LintKind,
+    /// The bytes of the input the warning is about.
+    pub span: Range<usize>,
+    /// Where the span starts.
+    pub position: Position,
+    /// The path from the outermost value to the value the warning is about.
+    pub path: Vec<PathSegment>,
+}
+
+/// The kind of a [`LintWarning`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LintKind {
+    /// A table has the same key twice. Lua only keeps one of the values.
+    DuplicateKey,
+    /// A table has entries with and without keys, so it is neither a sequence nor a map.
+    MixedTable,
+    /// An integer that the numbers of the lua version can't hold exactly.
+    IntegerOutOfRange,
+    /// A NaN written as `0/0`. NaN isn't equal to itself, and lua fails to load it as a key.
+    NanLiteral,
+    /// A table nested more than 32 levels deep, which data rarely is. Only the
+    /// outermost of the tables that are too deep is reported.
+    DeepNesting,
+    /// A table whose entries `1..n` have a gap, from a `nil` entry or a missing index. Lua's
+    /// length operator may stop at any gap, so the table isn't a reliable array.
+    ArrayHole,
+}
+
+/// How deep tables can be nested before [`LintKind::DeepNesting`] is reported.
+const NESTING_LIMIT: usize = 32;
+
+impl LintKind {
+    fn message(self) -> &'static str {
+        match self {
+            LintKind::DuplicateKey => "duplicate key",
+            LintKind::MixedTable => "table has entries with and without keys",
+            LintKind::IntegerOutOfRange => "integer out of range for the lua version",
+            LintKind::NanLiteral => "NaN literal",
+            LintKind::DeepNesting => "table is nested suspiciously deep",
+            LintKind::ArrayHole => "array has a hole",
+        }
+    }
+}
+
+impl LintWarning {
+    /// Writes the warning followed by the line of `source` it is about, with its span underlined
+    /// like [`DeError::render`] does.
+    pub fn render(&self, source: &str) -> String {
+        snippet(
+            self,
+            source,
+            self.position,
+            self.span.len(),
+            &self.kind.message(),
+        )
+    }
+}
+
+impl Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let location = location(&self.path, &self.position);
+        write!(f, "{} at {}", self.kind.message(), location)
+    }
+}
+
+/// Checks a lua file for things that can be read but are likely mistakes, and returns a warning
+/// with the location of each. The file is read like [`from_str_chunk`](super::from_str_chunk)
+/// reads it, `version` is the lua version that loads it.
+///
+/// ```
+/// use serde_lua_table::{LintKind, LuaVersion};
+///
+/// let lua = "{\n  name = 'a',\n  name = 'b',\n  9007199254740993,\n}";
+/// let warnings = serde_lua_table::lint(lua, LuaVersion::Lua51).unwrap();
+/// let kinds: Vec<_> = warnings.iter().map(|w| w.kind).collect();
+/// assert_eq!(
+///     kinds,
+///     [LintKind::MixedTable, LintKind::DuplicateKey, LintKind::IntegerOutOfRange]
+/// );
+/// assert_eq!(warnings[1].to_string(), "duplicate key at name, line 3, column 3");
+/// ```
+///
+/// # Errors
+///
+/// Fails like [`validate_str`](super::validate_str) if the file isn't lua that can be read.
+pub fn lint(s: &str, version: LuaVersion) -> Result<Vec<LintWarning>, DeError> {
+    let (chunk, node) = Deserializer::new(SliceRead::new(s.as_bytes())).parse_syntax()?;
+    let mut linter = Linter {
+        input: s.as_bytes(),
+        lines: LineIndex::new(s.as_bytes()),
+        version,
+        path: Vec::new(),
+        // The variables of a globals file are the outermost values, not its chunk.
+        depth: usize::from(chunk != Chunk::Globals),
+        warnings: Vec::new(),
+    };
+    linter.visit(&node);
+    Ok(linter.warnings)
+}
+
+struct Linter<'a> {
+    input: &'a [u8],
+    lines: LineIndex,
+    version: LuaVersion,
+    /// The path to the current value.
+    path: Vec<PathSegment>,
+    /// How deep the current value is nested, the outermost value is at 1.
+    depth: usize,
+    warnings: Vec<LintWarning>,
+}
+
+impl Linter<'_> {
+    fn warn(&mut self, kind: LintKind, span: Range<usize>) {
+        self.warnings.push(LintWarning {
+            kind,
+            position: self.lines.position(span.start),
+            span,
+            path: self.path.clone(),
+        });
+    }
+
+    fn visit(&mut self, node: &Node) {
+        match &node.kind {
+            NodeKind::Number(Number::Float(n)) if n.is_nan() => {
+                self.warn(LintKind::NanLiteral, node.span.clone());
+            }
+            NodeKind::Number(number) if !self.is_exact(*number, &node.span) => {
+                self.warn(LintKind::IntegerOutOfRange, node.span.clone());
+            }
+            NodeKind::Table(fields) => {
+                if self.depth == NESTING_LIMIT + 1 {
+                    self.warn(LintKind::DeepNesting, node.span.clone());
+                }
+                let positional = fields
+                    .iter()
+                    .any(|field| matches!(field.key, FieldKey::Positional(_)));
+                let keyed = fields
+                    .iter()
+                    .any(|field| matches!(field.key, FieldKey::Keyed { .. }));
+                if positional && keyed {
+                    self.warn(LintKind::MixedTable, node.span.clone());
+                }
+                if let Some(span) = array_hole(fields) {
+                    self.warn(LintKind::ArrayHole, span);
+                }
+
+                let mut seen = HashSet::new();
+                for field in fields {
+                    let key = field.key.key();
+                    self.path.push(key.segment());
+                    if !seen.insert(key) {
+                        let span = match &field.key {
+                            FieldKey::Keyed { span, .. } => span.clone(),
+                            FieldKey::Positional(_) => field.value.span.clone(),
+                        };
+                        self.warn(LintKind::DuplicateKey, span);
+                    }
+                    if let FieldKey::Keyed {
+                        span,
+                        key: OwnedKey::Float(bits),
+                    } = &field.key
+                    {
+                        if f64::from_bits(*bits).is_nan() {
+                            self.warn(LintKind::NanLiteral, span.clone());
+                        }
+                    }
+                    self.depth += 1;
+                    self.visit(&field.value);
+                    self.depth -= 1;
+                    self.path.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns whether the lua version reads a number exactly. Lua 5.3 reads integers beyond
+    /// `i64` as floats, earlier versions only have doubles. LuaJIT's `LL` literals are 64-bit
+    /// integers.
+    fn is_exact(&self, number: Number, span: &Range<usize>) -> bool {
+        let magnitude = match number {
+            Number::PosInt(n) if self.version.has_integers() => return n <= i64::MAX as u64,
+            Number::NegInt(_) if self.version.has_integers() => return true,
+            Number::PosInt(n) => n,
+            Number::NegInt(n) => n.unsigned_abs(),
+            Number::Float(_) => return true,
+        };
+        let literal = &self.input[span.clone()];
+        magnitude <= 1 << 53
+            || (self.version == LuaVersion::LuaJit
+                && literal.len() >= 2
+                && literal[literal.len() - 2..].eq_ignore_ascii_case(b"LL"))
+    }
+}
+
+/// Returns the range of the first entry after a gap in the indices `1..n` of a table, or of the
+/// `nil` entry making the gap.
+fn array_hole(fields: &[Field]) -> Option<Range<usize>> {
+    let index = |field: &Field| match field.key.key() {
+        OwnedKey::Integer(i) if i > 0 => Some(i),
+        _ => None,
+    };
+    let span = |field: &Field| match &field.key {
+        FieldKey::Keyed { span, .. } => span.clone(),
+        FieldKey::Positional(_) => field.value.span.clone(),
+    };
+    let present: HashSet<i64> = fields
+        .iter()
+        .filter(|field| !matches!(field.value.kind, NodeKind::Nil))
+        .filter_map(index)
+        .collect();
+    let missing = (1..).find(|i| !present.contains(i))?;
+    if missing == 1 || !present.iter().any(|&i| i > missing) {
+        return None;
+    }
+    let nil = fields.iter().find(|field| index(field) == Some(missing));
+    let after = || {
+        fields
+            .iter()
+            .filter(|field| index(field).is_some_and(|i| i > missing))
+            .min_by_key(|field| index(field))
+    };
+    nil.or_else(after).map(span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NESTING_LIMIT;
+    use crate::{lint, LintKind, LuaVersion};
+
+    fn kinds(lua: &str, version: LuaVersion) -> Vec<LintKind> {
+        let warnings = lint(lua, version).unwrap();
+        warnings.into_iter().map(|warning| warning.kind).collect()
+    }
+
+    #[test]
+    fn finds_duplicate_keys() {
+        let lua = "{a = 1, ['a'] = 2, [1] = 3, [1.0] = 4, b = {c = 1, c = 2}}";
+        let warnings = lint(lua, LuaVersion::Lua54).unwrap();
+        let found: Vec<_> = warnings
+            .iter()
+            .map(|warning| (&lua[warning.span.clone()], warning.to_string()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("['a']", "duplicate key at a, line 1, column 9".to_owned()),
+                (
+                    "[1.0]",
+                    "duplicate key at [1], line 1, column 29".to_owned()
+                ),
+                ("c", "duplicate key at b.c, line 1, column 52".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_warnings_with_their_span_underlined() {
+        let lua = "{\n  name = 1,\n  ['name'] = 2,\n}";
+        let warnings = lint(lua, LuaVersion::Lua54).unwrap();
+        assert_eq!(
+            warnings[0].render(lua),
+            "duplicate key at name, line 3, column 3\n  |\n3 |   ['name'] = 2,\n  |   ^^^^^^^^ duplicate key"
+        );
+    }
+
+    #[test]
+    fn finds_keys_that_collide_with_positional_entries() {
+        assert_eq!(
+            kinds("{'a', [1] = 'b'}", LuaVersion::Lua54),
+            [LintKind::MixedTable, LintKind::DuplicateKey]
+        );
+        assert!(kinds("{[1] = 'a', [2] = 'b'}", LuaVersion::Lua54).is_empty());
+    }
+
+    #[test]
+    fn checks_integers_against_the_lua_version() {
+        let lua = "{9007199254740993, -9007199254740993, 9223372036854775808}";
+        assert_eq!(
+            kinds(lua, LuaVersion::Lua51),
+            [LintKind::IntegerOutOfRange; 3]
+        );
+        assert_eq!(kinds(lua, LuaVersion::Lua54), [LintKind::IntegerOutOfRange]);
+        assert!(kinds("{9007199254740992, 0x7FFFFFFFFFFFFFFF}", LuaVersion::Lua53).is_empty());
+        assert!(kinds(
+            "{9007199254740993LL, 18446744073709551615ULL}",
+            LuaVersion::LuaJit
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn finds_nan_literals() {
+        assert_eq!(
+            kinds("{0/0, -0/0, [0/0] = 1, math.huge}", LuaVersion::Lua54),
+            [
+                LintKind::MixedTable,
+                LintKind::NanLiteral,
+                LintKind::NanLiteral,
+                LintKind::NanLiteral
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_deeply_nested_tables() {
+        let nested = |depth| format!("{}{}", "{".repeat(depth), "}".repeat(depth));
+        assert!(kinds(&nested(NESTING_LIMIT), LuaVersion::Lua54).is_empty());
+        assert_eq!(
+            kinds(&nested(NESTING_LIMIT + 2), LuaVersion::Lua54),
+            [LintKind::DeepNesting]
+        );
+        let globals = format!("A = {}", nested(NESTING_LIMIT));
+        assert!(kinds(&globals, LuaVersion::Lua54).is_empty());
+    }
+
+    #[test]
+    fn finds_holes_in_arrays() {
+        let warnings = lint("{1, nil, 3}", LuaVersion::Lua54).unwrap();
+        assert_eq!(warnings[0].kind, LintKind::ArrayHole);
+        assert_eq!(warnings[0].span, 4..7);
+        assert_eq!(
+            kinds("{[1] = 'a', [2] = 'b', [4] = 'd'}", LuaVersion::Lua54),
+            [LintKind::ArrayHole]
+        );
+        assert!(kinds("{1, 2, nil}", LuaVersion::Lua54).is_empty());
+        assert!(kinds("{[10] = 'a', [20] = 'b'}", LuaVersion::Lua54).is_empty());
+    }
+
+    #[test]
+    fn lints_the_variables_of_chunks() {
+        let warnings = lint("A = {1, x = 2}\nA = 1", LuaVersion::Lua54).unwrap();
+        let found: Vec<_> = warnings.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            found,
+            [
+                "table has entries with and without keys at A, line 1, column 5",
+                "duplicate key at A, line 2, column 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn fails_on_syntax_errors() {
+        let error = lint("{a = }", LuaVersion::Lua54).unwrap_err();
+        assert_eq!(error.to_string(), "expected a value at line 1, column 6");
+    }
+}
@@ -0,0 +1,1940 @@
+mod analyze;
+mod array;
+mod chunk;
+mod error;
+mod include;
+mod key;
+mod lazy;
+mod lint;
+mod options;
+pub(crate) mod raw_number;
+mod read;
+mod reformat;
+mod stream;
+mod syntax;
+mod table;
+mod variant;
+
+pub use analyze::{analyze, Stats, TableStats};
+pub use array::{ArrayDeserializer, SeqIter};
+pub use chunk::Chunk;
+use chunk::GlobalsDeserializer;
+pub use error::*;
+#[doc(hidden)]
+pub use lazy::LazyValue;
+pub use lint::{lint, LintKind, LintWarning};
+pub use options::{DeOptions, DuplicateKeys, EmptyTables};
+pub use read::{IoRead, Read, SliceRead};
+pub use reformat::{format_str, minify_str, normalize_str};
+use serde::{
+    de::{self, value::SeqDeserializer, DeserializeOwned, IgnoredAny, Visitor},
+    forward_to_deserialize_any, Deserialize,
+};
+use std::{io, mem};
+pub use stream::StreamDeserializer;
+use table::{Entry, MapAccess, SeqAccess};
+use variant::{UnitVariantAccess, VariantAccess};
+
+/// How deep tables can be nested before parsing fails, to keep the parser from overflowing the
+/// stack.
+const DEPTH_LIMIT: u8 = 128;
+
+/// A parsed string, either borrowed from the input or decoded into the scratch buffer.
+enum Reference<'de, 's> {
+    Borrowed(&'de [u8]),
+    Copied(&'s [u8]),
+}
+
+impl<'de> Reference<'de, '_> {
+    /// Returns the bytes if they are borrowed from the input.
+    fn borrowed(&self) -> Option<&'de [u8]> {
+        match *self {
+            Reference::Borrowed(bytes) => Some(bytes),
+            Reference::Copied(_) => None,
+        }
+    }
+}
+
+/// A parsed number, integers are kept exact as long as they fit into 64 bits.
+#[derive(Clone, Copy)]
+enum Number {
+    PosInt(u64),
+    NegInt(i64),
+    Float(f64),
+}
+
+impl Number {
+    fn visit<'de, V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Number::PosInt(n) => visitor.visit_u64(n),
+            Number::NegInt(n) => visitor.visit_i64(n),
+            Number::Float(n) => visitor.visit_f64(n),
+        }
+    }
+
+    /// Turns floats without a fraction into integers, lua 5.1 and LuaJIT only have floats.
+    fn integral(self) -> Self {
+        match self {
+            Number::Float(n) if n.fract() == 0.0 && n >= 0.0 && n < u64::MAX as f64 => {
+                Number::PosInt(n as u64)
+            }
+            Number::Float(n) if n.fract() == 0.0 && n < 0.0 && n >= i64::MIN as f64 => {
+                Number::NegInt(n as i64)
+            }
+            other => other,
+        }
+    }
+}
+
+/// Deserializes lua table constructors, as written by the [`Serializer`](crate::Serializer).
+///
+/// Tables are read as sequences or maps depending on what the type asks for. Entries without a
+/// key get the keys `1`, `2`, … like in lua, so `{"a", "b"}` can be read into a
+/// `HashMap<i64, String>` as well as a `Vec<String>`. Floats without a fraction are accepted as
+/// integers. `nil` is read as `None` and as `()`. Comments are skipped wherever whitespace is
+/// allowed.
+///
+/// Numbers can be written in any form lua accepts, e.g. `0xFF`, `0xA.8p3` or `.5`, as well as
+/// with the `LL` and `ULL` suffixes LuaJIT uses for 64-bit integers. Strings are decoded like
+/// lua decodes them, with all of its escapes and as long strings like `[[...]]`. Infinity and NaN
+/// are read from the expressions `math.huge`, `-math.huge` and `0/0` that dumps write for them.
+///
+/// Lua strings can hold any bytes. Strings that aren't valid UTF-8 are visited as bytes instead
+/// of failing, and strings can be read as sequences of bytes, so `Vec<u8>` and byte buffers can
+/// hold them.
+///
+/// ```
+/// use serde::Deserialize;
+/// use serde_lua_table::Deserializer;
+///
+/// let mut de = Deserializer::from_str(r#"{1, --[[ two ]] 2, 3} -- done"#);
+/// let values = Vec::<u8>::deserialize(&mut de).unwrap();
+/// de.end().unwrap();
+/// assert_eq!(values, [1, 2, 3]);
+/// ```
+///
+/// ```
+/// let values: Vec<Option<u8>> = serde_lua_table::from_str("{1, nil, 3}").unwrap();
+/// assert_eq!(values, [Some(1), None, Some(3)]);
+/// ```
+///
+/// Enums are read the way the serializer writes them: unit variants as strings and the other
+/// variants as tables with the variant as the only key.
+///
+/// ```
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, Debug, PartialEq)]
+/// enum Shape {
+///     Empty,
+///     Circle(f64),
+///     Rect { w: f64, h: f64 },
+/// }
+///
+/// let shapes: Vec<Shape> =
+///     serde_lua_table::from_str(r#"{"Empty", {Circle = 1.5}, {["Rect"] = {w = 2, h = 3}}}"#)
+///         .unwrap();
+/// assert_eq!(
+///     shapes,
+///     [Shape::Empty, Shape::Circle(1.5), Shape::Rect { w: 2.0, h: 3.0 }]
+/// );
+/// ```
+///
+/// Untagged, internally and adjacently tagged enums are buffered through
+/// [`deserialize_any`](de::Deserializer::deserialize_any), which reads a table as a map if any of
+/// its entries has a key and as a sequence otherwise. That makes `{}` an empty sequence and
+/// `{1, 2, x = 3}` a map with the keys `1`, `2` and `"x"`.
+///
+/// ```
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, Debug, PartialEq)]
+/// #[serde(untagged)]
+/// enum Size {
+///     Fixed(u32),
+///     Range { min: u32, max: u32 },
+///     List(Vec<u32>),
+/// }
+///
+/// #[derive(Deserialize, Debug, PartialEq)]
+/// #[serde(tag = "kind", content = "size")]
+/// enum Widget {
+///     Button(Size),
+///     Spacer,
+/// }
+///
+/// let widgets: Vec<Widget> = serde_lua_table::from_str(
+///     r#"{
+///         {kind = "Button", size = 10},
+///         {size = {min = 1, max = 4}, kind = "Button"},
+///         {kind = "Button", size = {1, 2}},
+///         {kind = "Spacer"},
+///     }"#,
+/// )
+/// .unwrap();
+/// assert_eq!(
+///     widgets,
+///     [
+///         Widget::Button(Size::Fixed(10)),
+///         Widget::Button(Size::Range { min: 1, max: 4 }),
+///         Widget::Button(Size::List(vec![1, 2])),
+///         Widget::Spacer,
+///     ]
+/// );
+/// ```
+///
+/// ```
+/// let numbers: Vec<f64> = serde_lua_table::from_str("{0xFF, 0xA.8p3, .5, 1e3}").unwrap();
+/// assert_eq!(numbers, [255.0, 84.0, 0.5, 1000.0]);
+///
+/// let specials: Vec<f64> = serde_lua_table::from_str("{math.huge, -math.huge, 0/0}").unwrap();
+/// assert_eq!(specials[..2], [f64::INFINITY, f64::NEG_INFINITY]);
+/// assert!(specials[2].is_nan());
+///
+/// let strings: Vec<String> =
+///     serde_lua_table::from_str(r#"{"\x41\u{e9}\z   \66", [==[a]]b]==]}"#).unwrap();
+/// assert_eq!(strings, ["AéB", "a]]b"]);
+/// ```
+///
+/// ```
+/// let bytes: Vec<Vec<u8>> = serde_lua_table::from_str(r#"{"\xFF\0", {1, 2}}"#).unwrap();
+/// assert_eq!(bytes, [vec![0xFF, 0], vec![1, 2]]);
+/// ```
+pub struct Deserializer<R> {
+    read: R,
+    scratch: Vec<u8>,
+    remaining_depth: u8,
+    /// Number of bytes the parsed values may still take, see [`DeOptions::with_memory_limit`].
+    remaining_memory: usize,
+    options: DeOptions,
+}
+
+impl<'de> Deserializer<SliceRead<'de>> {
+    /// Creates a deserializer reading from a string.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &'de str) -> Self {
+        Deserializer::new(SliceRead::new(s.as_bytes()))
+    }
+}
+
+impl<R> Deserializer<IoRead<R>>
+where
+    R: io::Read,
+{
+    /// Creates a deserializer reading from an IO stream.
+    pub fn from_reader(reader: R) -> Self {
+        Deserializer::new(IoRead::new(reader))
+    }
+}
+
+impl<'de, R> Deserializer<R>
+where
+    R: Read<'de>,
+{
+    pub fn new(read: R) -> Self {
+        Self::with_options(read, DeOptions::default())
+    }
+
+    pub fn with_options(read: R, options: DeOptions) -> Self {
+        Deserializer {
+            read,
+            scratch: Vec::new(),
+            remaining_depth: DEPTH_LIMIT,
+            remaining_memory: options.memory_limit().unwrap_or(usize::MAX),
+            options,
+        }
+    }
+
+    /// Turns the deserializer into an iterator over the values of `T` in the input.
+    #[allow(clippy::should_implement_trait)]
+    pub fn into_iter<T>(self) -> StreamDeserializer<'de, R, T>
+    where
+        T: Deserialize<'de>,
+    {
+        StreamDeserializer::from_deserializer(self)
+    }
+
+    /// Turns the deserializer into one that reads a single large sequence an element at a time,
+    /// see [`ArrayDeserializer`].
+    pub fn into_array(self) -> ArrayDeserializer<'de, R> {
+        ArrayDeserializer::from_deserializer(self)
+    }
+
+    /// Checks that nothing but whitespace and comments is left in the input. Call this after
+    /// deserializing a value to reject trailing characters.
+    pub fn end(&mut self) -> Result<(), DeError> {
+        match self.parse_whitespace()? {
+            None => Ok(()),
+            Some(_) => Err(self.error("trailing characters")),
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> DeError {
+        DeError::Syntax(message.into()).at(self.read.position())
+    }
+
+    /// Fails if [`DeOptions::with_strict`] only allows literals and tables.
+    fn check_allowed(&self, construct: &str) -> Result<(), DeError> {
+        match self.options.strict() {
+            true => Err(self.error(format!("{construct} isn't allowed in strict mode"))),
+            false => Ok(()),
+        }
+    }
+
+    fn eof(&self) -> DeError {
+        self.error("unexpected end of input")
+    }
+
+    /// Skips whitespace and comments and returns the next byte without consuming it.
+    fn parse_whitespace(&mut self) -> Result<Option<u8>, DeError> {
+        loop {
+            match self.read.peek()? {
+                Some(b' ' | b'\t' | b'\n' | b'\r' | b'\x0b' | b'\x0c') => self.read.discard(),
+                Some(b'-') if self.read.peek_at(1)? == Some(b'-') => self.skip_comment()?,
+                other => return Ok(other),
+            }
+        }
+    }
+
+    /// Skips a `--` line comment or a `--[[ ]]` block comment.
+    fn skip_comment(&mut self) -> Result<(), DeError> {
+        self.read.discard();
+        self.read.discard();
+        if let Some(level) = self.peek_long_bracket()? {
+            self.parse_long_bracket(level)?;
+            return Ok(());
+        }
+        while let Some(b) = self.read.next()? {
+            if b == b'\n' {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the level of the long bracket the input continues with, i.e. the number of `=`
+    /// in `[==[`.
+    fn peek_long_bracket(&mut self) -> Result<Option<usize>, DeError> {
+        self.peek_long_bracket_at(0)
+    }
+
+    /// Returns the level of the long bracket that opens `at` bytes ahead, if there is one.
+    fn peek_long_bracket_at(&mut self, at: usize) -> Result<Option<usize>, DeError> {
+        if self.read.peek_at(at)? != Some(b'[') {
+            return Ok(None);
+        }
+        let mut level = 0;
+        while self.read.peek_at(at + level + 1)? == Some(b'=') {
+            level += 1;
+        }
+        Ok((self.read.peek_at(at + level + 1)? == Some(b'[')).then_some(level))
+    }
+
+    /// Consumes a long bracket of the given level and everything up to the matching closing
+    /// bracket, and returns the text in between.
+    fn parse_long_bracket(&mut self, level: usize) -> Result<Reference<'de, '_>, DeError> {
+        for _ in 0..level + 2 {
+            self.read.discard();
+        }
+        // A newline right after the opening bracket isn't part of the text.
+        if let Some(b'\n' | b'\r') = self.read.peek()? {
+            self.parse_newline()?;
+        }
+        let mut len = 0;
+        let mut plain = true;
+        loop {
+            len = self.read.scan_until(len, b"]\r")?;
+            match self.read.peek_at(len)? {
+                Some(b']') if self.closes_long_bracket(len, level)? => break,
+                Some(b'\r') => {
+                    plain = false;
+                    len += 1;
+                }
+                Some(_) => len += 1,
+                None => return Err(self.error("unfinished long bracket")),
+            }
+        }
+
+        let borrowed = if plain { self.read.borrow(len) } else { None };
+        if borrowed.is_none() {
+            self.scratch.clear();
+            if plain {
+                self.read.copy_to(len, &mut self.scratch)?;
+            } else {
+                // Every kind of line break is read as `\n`, like lua does.
+                let end = self.read.offset() + len;
+                while self.read.offset() < end {
+                    match self.read.peek()? {
+                        Some(b'\n' | b'\r') => self.parse_newline()?,
+                        Some(b) => {
+                            self.read.discard();
+                            self.scratch.push(b);
+                        }
+                        None => break,
+                    }
+                }
+            }
+        }
+        for _ in 0..level + 2 {
+            self.read.discard();
+        }
+        Ok(match borrowed {
+            Some(bytes) => Reference::Borrowed(bytes),
+            None => Reference::Copied(&self.scratch),
+        })
+    }
+
+    /// Returns whether the bytes `at` bytes ahead close a long bracket of the given level.
+    fn closes_long_bracket(&mut self, at: usize, level: usize) -> Result<bool, DeError> {
+        for i in 1..=level {
+            if self.read.peek_at(at + i)? != Some(b'=') {
+                return Ok(false);
+            }
+        }
+        Ok(self.read.peek_at(at + level + 1)? == Some(b']'))
+    }
+
+    /// Consumes a line break, which is `\n`, `\r`, `\r\n` or `\n\r`, and appends `\n` to the
+    /// scratch buffer.
+    fn parse_newline(&mut self) -> Result<(), DeError> {
+        let first = self.read.next()?;
+        match (first, self.read.peek()?) {
+            (Some(b'\n'), Some(b'\r')) | (Some(b'\r'), Some(b'\n')) => self.read.discard(),
+            _ => {}
+        }
+        self.scratch.push(b'\n');
+        Ok(())
+    }
+
+    /// Skips whitespace and consumes `expected`.
+    fn expect(&mut self, expected: u8) -> Result<(), DeError> {
+        match self.parse_whitespace()? {
+            Some(b) if b == expected => {
+                self.read.discard();
+                Ok(())
+            }
+            Some(_) => Err(self.error(format!("expected `{}`", expected as char))),
+            None => Err(self.eof()),
+        }
+    }
+
+    /// Returns whether the input continues with the identifier `word`.
+    fn peek_word(&mut self, word: &[u8]) -> Result<bool, DeError> {
+        for (i, &b) in word.iter().enumerate() {
+            if self.read.peek_at(i)? != Some(b) {
+                return Ok(false);
+            }
+        }
+        Ok(!self
+            .read
+            .peek_at(word.len())?
+            .is_some_and(is_identifier_continue))
+    }
+
+    /// Returns whether the input continues with a value that starts like a name but isn't a key,
+    /// i.e. a keyword or `math.huge`.
+    fn peek_keyword_value(&mut self) -> Result<bool, DeError> {
+        Ok(self.peek_word(b"true")?
+            || self.peek_word(b"false")?
+            || self.peek_word(b"nil")?
+            || self.peek_word(b"math.huge")?)
+    }
+
+    fn parse_identifier(&mut self) -> Result<Reference<'de, '_>, DeError> {
+        let mut len = 0;
+        while self.read.peek_at(len)?.is_some_and(is_identifier_continue) {
+            len += 1;
+        }
+        if let Some(bytes) = self.read.borrow(len) {
+            return Ok(Reference::Borrowed(bytes));
+        }
+        self.scratch.clear();
+        self.read.copy_to(len, &mut self.scratch)?;
+        Ok(Reference::Copied(&self.scratch))
+    }
+
+    /// Returns whether a string starts at the next byte `b`.
+    fn peek_string(&mut self, b: u8) -> Result<bool, DeError> {
+        Ok(matches!(b, b'"' | b'\'') || self.peek_long_bracket()?.is_some())
+    }
+
+    /// Parses a quoted or long string.
+    fn parse_string(&mut self) -> Result<Reference<'de, '_>, DeError> {
+        let borrowed = match self.peek_long_bracket()? {
+            Some(level) => self.parse_long_bracket(level)?,
+            None => self.parse_quoted()?,
+        }
+        .borrowed();
+        self.use_memory(borrowed.map_or(self.scratch.len(), <[u8]>::len))?;
+        Ok(match borrowed {
+            Some(bytes) => Reference::Borrowed(bytes),
+            None => Reference::Copied(&self.scratch),
+        })
+    }
+
+    /// Parses a quoted string, the next byte is the opening quote.
+    fn parse_quoted(&mut self) -> Result<Reference<'de, '_>, DeError> {
+        let quote = self.read.next()?.ok_or_else(|| self.eof())?;
+        self.scratch.clear();
+        loop {
+            let len = self.read.scan_until(0, &[quote, b'\\', b'\n', b'\r'])?;
+            let end = self.read.peek_at(len)?;
+            if end == Some(quote) && self.scratch.is_empty() {
+                if let Some(bytes) = self.read.borrow(len) {
+                    self.read.discard();
+                    return Ok(Reference::Borrowed(bytes));
+                }
+            }
+            self.read.copy_to(len, &mut self.scratch)?;
+            match self.read.next()? {
+                Some(b) if b == quote => return Ok(Reference::Copied(&self.scratch)),
+                Some(b'\\') => self.parse_escape()?,
+                _ => return Err(self.error("unfinished string")),
+            }
+        }
+    }
+
+    /// Decodes the escape after a backslash into the scratch buffer.
+    fn parse_escape(&mut self) -> Result<(), DeError> {
+        let byte = match self.read.peek()?.ok_or_else(|| self.eof())? {
+            b'a' => b'\x07',
+            b'b' => b'\x08',
+            b'f' => b'\x0c',
+            b'n' => b'\n',
+            b'r' => b'\r',
+            b't' => b'\t',
+            b'v' => b'\x0b',
+            b @ (b'\\' | b'"' | b'\'') => b,
+            b'\n' | b'\r' => return self.parse_newline(),
+            b'x' => {
+                self.read.discard();
+                let mut value = 0;
+                for _ in 0..2 {
+                    match self.read.peek()?.and_then(|b| (b as char).to_digit(16)) {
+                        Some(digit) => {
+                            self.read.discard();
+                            value = value * 16 + digit as u8;
+                        }
+                        None => return Err(self.error("hexadecimal digit expected")),
+                    }
+                }
+                self.scratch.push(value);
+                return Ok(());
+            }
+            b'u' => return self.parse_unicode_escape(),
+            b'z' => {
+                self.read.discard();
+                while let Some(b' ' | b'\t' | b'\n' | b'\r' | b'\x0b' | b'\x0c') =
+                    self.read.peek()?
+                {
+                    self.read.discard();
+                }
+                return Ok(());
+            }
+            b'0'..=b'9' => {
+                let mut value = 0u32;
+                for _ in 0..3 {
+                    match self.read.peek()? {
+                        Some(b @ b'0'..=b'9') => {
+                            self.read.discard();
+                            value = value * 10 + u32::from(b - b'0');
+                        }
+                        _ => break,
+                    }
+                }
+                let byte =
+                    u8::try_from(value).map_err(|_| self.error("decimal escape too large"))?;
+                self.scratch.push(byte);
+                return Ok(());
+            }
+            _ => return Err(self.error("invalid escape sequence")),
+        };
+        self.read.discard();
+        self.scratch.push(byte);
+        Ok(())
+    }
+
+    /// Decodes a `\u{XXX}` escape into the scratch buffer, the next byte is the `u`.
+    ///
+    /// Like lua, code points up to 2^31 are accepted and encoded the way UTF-8 was originally
+    /// defined, even if the result isn't valid UTF-8 today.
+    fn parse_unicode_escape(&mut self) -> Result<(), DeError> {
+        self.read.discard();
+        if self.read.next()? != Some(b'{') {
+            return Err(self.error("missing `{` in \\u{xxxx}"));
+        }
+        let mut value = 0u32;
+        let mut digits = 0;
+        while let Some(digit) = self.read.peek()?.and_then(|b| (b as char).to_digit(16)) {
+            self.read.discard();
+            value = value
+                .checked_mul(16)
+                .map(|value| value + digit)
+                .filter(|&value| value < 0x8000_0000)
+                .ok_or_else(|| self.error("UTF-8 value too large"))?;
+            digits += 1;
+        }
+        if digits == 0 {
+            return Err(self.error("hexadecimal digit expected"));
+        }
+        if self.read.next()? != Some(b'}') {
+            return Err(self.error("missing `}` in \\u{xxxx}"));
+        }
+
+        if value < 0x80 {
+            self.scratch.push(value as u8);
+            return Ok(());
+        }
+        let mut bytes = [0; 6];
+        let mut len = 0;
+        // The largest value that still fits into the first byte.
+        let mut max_first = 0x3f;
+        while value > max_first {
+            bytes[5 - len] = 0x80 | (value & 0x3f) as u8;
+            len += 1;
+            value >>= 6;
+            max_first >>= 1;
+        }
+        bytes[5 - len] = ((!max_first << 1) | value) as u8;
+        self.scratch.extend_from_slice(&bytes[5 - len..]);
+        Ok(())
+    }
+
+    /// Returns whether a number starts at the next byte `b`.
+    fn peek_number(&mut self, b: u8) -> Result<bool, DeError> {
+        Ok(match b {
+            b'-' | b'0'..=b'9' => true,
+            b'.' => self.read.peek_at(1)?.is_some_and(|b| b.is_ascii_digit()),
+            b'm' => self.peek_word(b"math.huge")?,
+            _ => false,
+        })
+    }
+
+    /// Returns `true` if the number at `b` is decimal, the only kind that is kept as it was
+    /// written with [`DeOptions::with_raw_numbers`].
+    fn peek_decimal(&mut self, b: u8) -> Result<bool, DeError> {
+        let at = usize::from(b == b'-');
+        Ok(match (self.read.peek_at(at)?, self.read.peek_at(at + 1)?) {
+            (Some(b'0'), Some(b'x' | b'X')) => false,
+            (Some(b'0'..=b'9' | b'.'), _) => true,
+            _ => false,
+        })
+    }
+
+    /// Parses a decimal number and returns it as it was written, without an integer suffix.
+    fn parse_raw_number(&mut self) -> Result<String, DeError> {
+        let negative = self.read.peek()? == Some(b'-');
+        if negative {
+            self.read.discard();
+        }
+        if let Number::PosInt(n) = self.parse_decimal()? {
+            self.parse_integer_suffix(n, negative)?;
+        }
+        if self
+            .read
+            .peek()?
+            .is_some_and(|b| is_identifier_continue(b) || b == b'.')
+        {
+            return Err(self.error("malformed number"));
+        }
+        let digits = String::from_utf8_lossy(&self.scratch);
+        Ok(if negative {
+            format!("-{digits}")
+        } else {
+            digits.into_owned()
+        })
+    }
+
+    fn parse_number(&mut self) -> Result<Number, DeError> {
+        let negative = self.read.peek()? == Some(b'-');
+        if negative {
+            self.read.discard();
+        }
+        // Lua has no literals for infinity and NaN, dumps write them as expressions.
+        if self.peek_word(b"math.huge")? {
+            self.check_allowed("`math.huge`")?;
+            for _ in 0..b"math.huge".len() {
+                self.read.discard();
+            }
+            return Ok(Number::Float(if negative {
+                f64::NEG_INFINITY
+            } else {
+                f64::INFINITY
+            }));
+        }
+        if self.peek_word(b"0/0")? {
+            self.check_allowed("`0/0`")?;
+            for _ in 0..b"0/0".len() {
+                self.read.discard();
+            }
+            return Ok(Number::Float(f64::NAN));
+        }
+        let number = match (self.read.peek()?, self.read.peek_at(1)?) {
+            (Some(b'0'), Some(b'x' | b'X')) => self.parse_hex()?,
+            (Some(b'0'..=b'9' | b'.'), _) => self.parse_decimal()?,
+            _ => return Err(self.error("expected a number")),
+        };
+        let number = match number {
+            Number::PosInt(n) => self.parse_integer_suffix(n, negative)?,
+            Number::Float(n) if negative => Number::Float(-n),
+            other => other,
+        };
+        if self
+            .read
+            .peek()?
+            .is_some_and(|b| is_identifier_continue(b) || b == b'.')
+        {
+            return Err(self.error("malformed number"));
+        }
+        Ok(number)
+    }
+
+    /// Parses the digits of a decimal number like `12`, `.5` or `1.5e-3`.
+    fn parse_decimal(&mut self) -> Result<Number, DeError> {
+        self.scratch.clear();
+        let mut integer = Some(0u64);
+        let mut digits = 0;
+        while let Some(b @ b'0'..=b'9') = self.read.peek()? {
+            self.read.discard();
+            self.scratch.push(b);
+            integer = integer
+                .and_then(|n| n.checked_mul(10))
+                .and_then(|n| n.checked_add(u64::from(b - b'0')));
+            digits += 1;
+        }
+        let mut float = false;
+        if self.read.peek()? == Some(b'.') {
+            self.read.discard();
+            self.scratch.push(b'.');
+            float = true;
+            digits += self.parse_digits()?;
+        }
+        if digits == 0 {
+            return Err(self.error("malformed number"));
+        }
+        if let Some(b @ (b'e' | b'E')) = self.read.peek()? {
+            self.read.discard();
+            self.scratch.push(b);
+            float = true;
+            if let Some(b @ (b'+' | b'-')) = self.read.peek()? {
+                self.read.discard();
+                self.scratch.push(b);
+            }
+            if self.parse_digits()? == 0 {
+                return Err(self.error("malformed number"));
+            }
+        }
+
+        // Decimal integers that don't fit into an integer are read as floats, like lua does.
+        match integer {
+            Some(n) if !float => Ok(Number::PosInt(n)),
+            _ => std::str::from_utf8(&self.scratch)
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .map(Number::Float)
+                .ok_or_else(|| self.error("malformed number")),
+        }
+    }
+
+    /// Parses a hexadecimal number like `0xFF` or `0xA.8p3`, the next bytes are the `0x`.
+    ///
+    /// Integers wrap around like in lua, so `0xFFFFFFFFFFFFFFFF` is `u64::MAX`.
+    fn parse_hex(&mut self) -> Result<Number, DeError> {
+        self.read.discard();
+        self.read.discard();
+        let mut integer = 0u64;
+        // The leading digits of a float that fit into 64 bits, and the power of two to scale them
+        // by.
+        let mut mantissa = 0u64;
+        let mut exponent = 0i32;
+        let mut digits = 0;
+        let mut float = false;
+        loop {
+            match self.read.peek()? {
+                Some(b) if b.is_ascii_hexdigit() => {
+                    self.read.discard();
+                    let digit = u64::from((b as char).to_digit(16).unwrap_or_default());
+                    integer = integer.wrapping_mul(16).wrapping_add(digit);
+                    if mantissa >> 60 == 0 {
+                        mantissa = mantissa * 16 + digit;
+                        if float {
+                            exponent -= 4;
+                        }
+                    } else if !float {
+                        exponent += 4;
+                    }
+                    digits += 1;
+                }
+                Some(b'.') if !float => {
+                    self.read.discard();
+                    float = true;
+                }
+                _ => break,
+            }
+        }
+        if digits == 0 {
+            return Err(self.error("malformed number"));
+        }
+        if let Some(b'p' | b'P') = self.read.peek()? {
+            self.read.discard();
+            float = true;
+            let negative = match self.read.peek()? {
+                Some(b @ (b'+' | b'-')) => {
+                    self.read.discard();
+                    b == b'-'
+                }
+                _ => false,
+            };
+            let mut power = 0i32;
+            let mut digits = 0;
+            while let Some(b @ b'0'..=b'9') = self.read.peek()? {
+                self.read.discard();
+                power = power.saturating_mul(10).saturating_add(i32::from(b - b'0'));
+                digits += 1;
+            }
+            if digits == 0 {
+                return Err(self.error("malformed number"));
+            }
+            exponent = exponent.saturating_add(if negative { -power } else { power });
+        }
+
+        if !float {
+            return Ok(Number::PosInt(integer));
+        }
+        // Scaling in two steps keeps large mantissas with small exponents from underflowing.
+        let half = exponent / 2;
+        Ok(Number::Float(
+            mantissa as f64 * 2f64.powi(half) * 2f64.powi(exponent - half),
+        ))
+    }
+
+    /// Applies the sign to an integer and parses LuaJIT's `LL` and `ULL` suffixes, which make it
+    /// a signed or unsigned 64-bit integer.
+    fn parse_integer_suffix(&mut self, n: u64, negative: bool) -> Result<Number, DeError> {
+        let unsigned = matches!(self.read.peek()?, Some(b'u' | b'U'));
+        let at = usize::from(unsigned);
+        let suffix = matches!(self.read.peek_at(at)?, Some(b'l' | b'L'))
+            && matches!(self.read.peek_at(at + 1)?, Some(b'l' | b'L'));
+        if suffix {
+            for _ in 0..at + 2 {
+                self.read.discard();
+            }
+        }
+        let n = if negative { n.wrapping_neg() } else { n };
+        Ok(match (suffix, unsigned) {
+            (true, true) => Number::PosInt(n),
+            (true, false) if (n as i64) < 0 => Number::NegInt(n as i64),
+            (true, false) => Number::PosInt(n),
+            (false, _) if !negative => Number::PosInt(n),
+            (false, _) if n.wrapping_neg() <= i64::MIN.unsigned_abs() => Number::NegInt(n as i64),
+            // Too small for an integer.
+            (false, _) => Number::Float(-(n.wrapping_neg() as f64)),
+        })
+    }
+
+    /// Copies decimal digits into the scratch buffer and returns how many there were.
+    fn parse_digits(&mut self) -> Result<usize, DeError> {
+        let mut count = 0;
+        while let Some(b @ b'0'..=b'9') = self.read.peek()? {
+            self.read.discard();
+            self.scratch.push(b);
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Records that a table is opened. Fails if that exceeds the depth or the memory limit.
+    fn enter_table(&mut self) -> Result<(), DeError> {
+        if self.remaining_depth == 0 {
+            return Err(
+                DeError::DepthLimitExceeded(usize::from(DEPTH_LIMIT)).at(self.read.position())
+            );
+        }
+        self.use_memory(mem::size_of::<crate::value::Table>())?;
+        self.remaining_depth -= 1;
+        self.read.discard();
+        Ok(())
+    }
+
+    /// Counts `bytes` against the memory limit.
+    fn use_memory(&mut self, bytes: usize) -> Result<(), DeError> {
+        match self.remaining_memory.checked_sub(bytes) {
+            Some(remaining) => {
+                self.remaining_memory = remaining;
+                Ok(())
+            }
+            None => {
+                let limit = self.options.memory_limit().unwrap_or(usize::MAX);
+                Err(DeError::MemoryLimitExceeded(limit).at(self.read.position()))
+            }
+        }
+    }
+
+    /// Consumes the end of a table after its entries have been read.
+    fn end_table(&mut self) -> Result<(), DeError> {
+        self.remaining_depth += 1;
+        if let Some(b',' | b';') = self.parse_whitespace()? {
+            self.read.discard();
+        }
+        match self.parse_whitespace()? {
+            Some(b'}') => {
+                self.read.discard();
+                Ok(())
+            }
+            Some(_) => Err(self.error("expected `}`")),
+            None => Err(self.eof()),
+        }
+    }
+
+    /// Visits a string as `str`, or as bytes if it isn't valid UTF-8, since lua strings can hold
+    /// any bytes.
+    fn visit_string<V>(&mut self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.parse_string()? {
+            Reference::Borrowed(bytes) => match std::str::from_utf8(bytes) {
+                Ok(s) => visitor.visit_borrowed_str(s),
+                Err(_) => visitor.visit_borrowed_bytes(bytes),
+            },
+            Reference::Copied(bytes) => match std::str::from_utf8(bytes) {
+                Ok(s) => visitor.visit_str(s),
+                Err(_) => visitor.visit_bytes(bytes),
+            },
+        }
+    }
+
+    fn invalid_value(&self) -> DeError {
+        self.error("expected a value")
+    }
+}
+
+/// Returns whether `b` can start an identifier.
+fn is_identifier_start(b: u8) -> bool {
+    b.is_ascii_alphabetic() || b == b'_'
+}
+
+fn is_identifier_continue(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+macro_rules! deserialize_integer {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, DeError>
+            where
+                V: Visitor<'de>,
+            {
+                match self.parse_whitespace()? {
+                    Some(b) if self.peek_number(b)? => {
+                        self.parse_number()?.integral().visit(visitor)
+                    }
+                    _ => self.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de, R> de::Deserializer<'de> for &mut Deserializer<R>
+where
+    R: Read<'de>,
+{
+    type Error = DeError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.parse_whitespace()? {
+            Some(b'{') => {
+                self.enter_table()?;
+                // Tables are maps if any of their entries has a key, entries without a key get
+                // the keys `1`, `2`, … then.
+                let value = match self.table_entry(true)? {
+                    Entry::Key => visitor.visit_map(MapAccess::new(self))?,
+                    Entry::Positional if self.table_has_keys()? => {
+                        visitor.visit_map(MapAccess::new(self))?
+                    }
+                    Entry::End if self.options.empty_tables() == EmptyTables::Map => {
+                        visitor.visit_map(MapAccess::new(self))?
+                    }
+                    Entry::Positional | Entry::End => visitor.visit_seq(SeqAccess::new(self))?,
+                };
+                self.end_table()?;
+                Ok(value)
+            }
+            Some(b) if self.peek_string(b)? => self.visit_string(visitor),
+            Some(b) if self.options.raw_numbers() && self.peek_decimal(b)? => {
+                visitor.visit_map(raw_number::RawNumberAccess::new(self.parse_raw_number()?))
+            }
+            Some(b) if self.peek_number(b)? => self.parse_number()?.visit(visitor),
+            Some(b) if is_identifier_start(b) => {
+                if self.peek_word(b"true")? {
+                    self.parse_identifier()?;
+                    visitor.visit_bool(true)
+                } else if self.peek_word(b"false")? {
+                    self.parse_identifier()?;
+                    visitor.visit_bool(false)
+                } else if self.peek_word(b"nil")? {
+                    self.parse_identifier()?;
+                    visitor.visit_unit()
+                } else {
+                    Err(self.invalid_value())
+                }
+            }
+            Some(_) => Err(self.invalid_value()),
+            None => Err(self.eof()),
+        }
+    }
+
+    deserialize_integer! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.parse_whitespace()? {
+            Some(b) if self.peek_string(b)? => match self.parse_string()? {
+                Reference::Borrowed(bytes) => visitor.visit_borrowed_bytes(bytes),
+                Reference::Copied(bytes) => visitor.visit_bytes(bytes),
+            },
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        self.parse_whitespace()?;
+        if self.peek_word(b"nil")? {
+            self.parse_identifier()?;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.parse_whitespace()? {
+            Some(b'{') => {
+                self.enter_table()?;
+                let value = visitor.visit_seq(SeqAccess::new(self))?;
+                self.end_table()?;
+                Ok(value)
+            }
+            // Strings are sequences of bytes, e.g. for `Vec<u8>`.
+            Some(b) if self.peek_string(b)? => {
+                let (Reference::Borrowed(bytes) | Reference::Copied(bytes)) =
+                    self.parse_string()?;
+                let mut seq = SeqDeserializer::new(bytes.iter().copied());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.parse_whitespace()? {
+            Some(b'{') => {
+                self.enter_table()?;
+                let value = visitor.visit_enum(VariantAccess::new(self))?;
+                self.end_table()?;
+                Ok(value)
+            }
+            // Unit variants can also be written as their index, like C-style enums in lua.
+            Some(b) if self.peek_string(b)? || self.peek_number(b)? => {
+                visitor.visit_enum(UnitVariantAccess::new(self))
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.parse_whitespace()? {
+            // Read as a map, which accepts entries with and without keys.
+            Some(b'{') => self.deserialize_map(visitor),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.parse_whitespace()? {
+            Some(b'{') => {
+                self.enter_table()?;
+                let value = visitor.visit_map(MapAccess::new(self))?;
+                self.end_table()?;
+                Ok(value)
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self.parse_whitespace()? {
+            Some(b'{') => {
+                self.enter_table()?;
+                let value = visitor.visit_map(MapAccess::fields(self))?;
+                self.end_table()?;
+                Ok(value)
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i128 u128 f32 f64 char str string unit unit_struct identifier
+    }
+}
+
+/// Deserializes the value of a whole input, which may be a chunk returning it like
+/// `return {...}` or assigning it like `name = {...}`. Returns the name of the assigned variable,
+/// if any.
+fn deserialize_chunk<'de, R, T>(de: &mut Deserializer<R>) -> Result<(Option<String>, T), DeError>
+where
+    R: Read<'de>,
+    T: Deserialize<'de>,
+{
+    parse_chunk(de).map_err(|e| e.at(de.read.position()))
+}
+
+fn parse_chunk<'de, R, T>(de: &mut Deserializer<R>) -> Result<(Option<String>, T), DeError>
+where
+    R: Read<'de>,
+    T: Deserialize<'de>,
+{
+    let mut name = None;
+    let mut chunk = false;
+    if let Some(b) = de.parse_whitespace()? {
+        if de.peek_word(b"return")? {
+            de.check_allowed("`return`")?;
+            de.parse_identifier()?;
+            chunk = true;
+        } else if is_identifier_start(b) && !de.peek_keyword_value()? {
+            de.check_allowed("an assignment")?;
+            if de.peek_word(b"local")? {
+                de.parse_identifier()?;
+                if !de.parse_whitespace()?.is_some_and(is_identifier_start) {
+                    return Err(de.error("expected a name"));
+                }
+            }
+            name = Some(match de.parse_identifier()? {
+                Reference::Borrowed(bytes) | Reference::Copied(bytes) => {
+                    String::from_utf8_lossy(bytes).into_owned()
+                }
+            });
+            de.expect(b'=')?;
+            chunk = true;
+        }
+    }
+    let value = T::deserialize(&mut *de)?;
+    if chunk && de.parse_whitespace()? == Some(b';') {
+        de.read.discard();
+    }
+    de.end()?;
+    Ok((name, value))
+}
+
+/// Deserializes the value of a whole input like [`deserialize_chunk`], but reads chunks of
+/// several assignments as a table of the variables.
+fn deserialize_any_chunk<'de, R, T>(de: &mut Deserializer<R>) -> Result<(Chunk, T), DeError>
+where
+    R: Read<'de>,
+    T: Deserialize<'de>,
+{
+    parse_any_chunk(de).map_err(|e| e.at(de.read.position()))
+}
+
+fn parse_any_chunk<'de, R, T>(de: &mut Deserializer<R>) -> Result<(Chunk, T), DeError>
+where
+    R: Read<'de>,
+    T: Deserialize<'de>,
+{
+    let (chunk, value) = match de.parse_whitespace()? {
+        Some(_) if de.peek_word(b"return")? => {
+            de.check_allowed("`return`")?;
+            de.parse_identifier()?;
+            let value = T::deserialize(&mut *de)?;
+            if de.parse_whitespace()? == Some(b';') {
+                de.read.discard();
+            }
+            (Chunk::Return, value)
+        }
+        Some(b) if is_identifier_start(b) && !de.peek_keyword_value()? => {
+            de.check_allowed("an assignment")?;
+            (
+                Chunk::Globals,
+                T::deserialize(GlobalsDeserializer { de: &mut *de })?,
+            )
+        }
+        _ => (Chunk::Value, T::deserialize(&mut *de)?),
+    };
+    de.end()?;
+    Ok((chunk, value))
+}
+
+/// Deserialize an instance of type `T` from a string of lua.
+///
+/// The string is either a value or a chunk returning or assigning one, so modules like
+/// `return { ... }` and dumps like `Config = { ... }` can be loaded directly. The name of an
+/// assigned variable is ignored, see [`from_str_named`] to get it.
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// struct Player {
+///     name: String,
+///     position: (f64, f64),
+///     items: Vec<u32>,
+/// }
+///
+/// let player = Player {
+///     name: "ferris".to_owned(),
+///     position: (1.5, -2.0),
+///     items: vec![1, 2],
+/// };
+/// let lua = serde_lua_table::to_string(&player).unwrap();
+/// assert_eq!(serde_lua_table::from_str::<Player>(&lua).unwrap(), player);
+///
+/// let player: Player =
+///     serde_lua_table::from_str(r#"{ name = 'ferris'; position = {1.5, -2}, items = {1, 2,} }"#)
+///         .unwrap();
+/// assert_eq!(player.position, (1.5, -2.0));
+///
+/// let module = "-- generated\nreturn { name = 'ferris', position = {0, 0}, items = {} }\n";
+/// assert_eq!(serde_lua_table::from_str::<Player>(module).unwrap().name, "ferris");
+/// ```
+///
+/// # Errors
+///
+/// Deserialization fails if the input isn't a lua value, if it doesn't match the structure
+/// expected by `T`, or if `T`'s implementation of `Deserialize` decides to fail.
+pub fn from_str<'de, T>(s: &'de str) -> Result<T, DeError>
+where
+    T: Deserialize<'de>,
+{
+    deserialize_chunk(&mut Deserializer::from_str(s)).map(|(_, value)| value)
+}
+
+/// Update `existing` from a string of lua, keeping everything the input leaves out.
+///
+/// Tables in the input are merged into the tables of `existing` field by field, so reloading a
+/// config only changes what the file sets. Sequences and all other values are replaced. Like
+/// [`from_str`], the string may also be a chunk returning or assigning the value.
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// struct Window {
+///     width: u32,
+///     height: u32,
+/// }
+///
+/// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// struct Config {
+///     title: String,
+///     window: Window,
+/// }
+///
+/// let mut config = Config {
+///     title: "ferris".to_owned(),
+///     window: Window { width: 800, height: 600 },
+/// };
+/// serde_lua_table::from_str_in_place(&mut config, "{ window = { width = 1024 } }").unwrap();
+/// assert_eq!(config.title, "ferris");
+/// assert_eq!(config.window, Window { width: 1024, height: 600 });
+/// ```
+///
+/// # Errors
+///
+/// Fails if the input isn't a lua value, if `existing` can't be serialized, or if the merged
+/// value doesn't match the structure expected by `T`. `existing` is left unchanged on errors.
+pub fn from_str_in_place<T>(existing: &mut T, s: &str) -> Result<(), DeError>
+where
+    T: serde::Serialize + DeserializeOwned,
+{
+    let update: crate::Value = from_str(s)?;
+    let current = crate::to_string(existing).map_err(de::Error::custom)?;
+    let mut value: crate::Value = from_str(&current)?;
+    value.merge(update);
+    *existing = T::deserialize(&value)?;
+    Ok(())
+}
+
+/// Deserialize an instance of type `T` from a string of lua that assigns it to a variable, like
+/// `name = {...}` or `local name = {...}`, and return the name of the variable.
+///
+/// ```
+/// let (name, value): (String, Vec<u32>) =
+///     serde_lua_table::from_str_named("local Scores = {3, 1, 2}").unwrap();
+/// assert_eq!((name.as_str(), value), ("Scores", vec![3, 1, 2]));
+/// ```
+///
+/// # Errors
+///
+/// Deserialization fails if the input doesn't assign a variable, if the value isn't a lua value,
+/// if it doesn't match the structure expected by `T`, or if `T`'s implementation of
+/// `Deserialize` decides to fail.
+pub fn from_str_named<'de, T>(s: &'de str) -> Result<(String, T), DeError>
+where
+    T: Deserialize<'de>,
+{
+    match deserialize_chunk(&mut Deserializer::from_str(s))? {
+        (Some(name), value) => Ok((name, value)),
+        (None, _) => Err(
+            DeError::Syntax("expected an assignment like `name = ...`".to_owned()).at(Position {
+                offset: 0,
+                line: 1,
+                column: 1,
+            }),
+        ),
+    }
+}
+
+/// Deserialize an instance of type `T` from a whole lua file and return how the file holds it.
+///
+/// Unlike [`from_str`], the file may assign any number of global variables, like the saved
+/// variables of an addon do. They are read as a table of the variables, which
+/// [`Globals`](crate::Globals) writes back. A file that returns the value is a
+/// [`Chunk::Return`], and a file that is just the value is a [`Chunk::Value`].
+///
+/// ```
+/// use serde_lua_table::{Chunk, Value};
+/// use std::collections::BTreeMap;
+///
+/// let lua = "MyAddonDB = { count = 2 }\nMyAddonCharDB = {}\n";
+/// let (chunk, globals): (_, BTreeMap<String, Value>) =
+///     serde_lua_table::from_str_chunk(lua).unwrap();
+/// assert_eq!(chunk, Chunk::Globals);
+/// assert_eq!(globals.len(), 2);
+///
+/// let (chunk, _): (_, Value) = serde_lua_table::from_str_chunk("return { 1 }").unwrap();
+/// assert_eq!(chunk, Chunk::Return);
+/// ```
+///
+/// # Errors
+///
+/// Deserialization fails if the input isn't a lua value or a chunk of assignments, if it doesn't
+/// match the structure expected by `T`, or if `T`'s implementation of `Deserialize` decides to
+/// fail.
+pub fn from_str_chunk<'de, T>(s: &'de str) -> Result<(Chunk, T), DeError>
+where
+    T: Deserialize<'de>,
+{
+    deserialize_any_chunk(&mut Deserializer::from_str(s))
+}
+
+/// Deserialize an instance of type `T` from an IO stream of lua.
+///
+/// Like [`from_str`], the stream may also be a chunk returning or assigning the value.
+///
+/// The input is read in chunks as it is parsed, so large files don't have to be loaded into
+/// memory first. The reader doesn't need to be buffered.
+///
+/// ```
+/// use std::collections::BTreeMap;
+///
+/// let input = std::io::Cursor::new(r#"{ width = 800, height = 600 }"#);
+/// let size: BTreeMap<String, u32> = serde_lua_table::from_reader(input).unwrap();
+/// assert_eq!(size["width"], 800);
+/// ```
+///
+/// # Errors
+///
+/// Deserialization fails if reading fails, if the input isn't a lua value, if it doesn't match
+/// the structure expected by `T`, or if `T`'s implementation of `Deserialize` decides to fail.
+pub fn from_reader<R, T>(reader: R) -> Result<T, DeError>
+where
+    R: io::Read,
+    T: DeserializeOwned,
+{
+    deserialize_chunk(&mut Deserializer::from_reader(reader)).map(|(_, value)| value)
+}
+
+/// Checks that a string is lua that [`from_str`] can read, without deserializing it.
+///
+/// This is a safety net for output that contains [`RawLua`](crate::RawLua) or other text that
+/// didn't go through the serializer. Like `from_str`, the string may be a chunk returning or
+/// assigning the value.
+///
+/// ```
+/// use serde_lua_table::RawLua;
+///
+/// let lua = serde_lua_table::to_string(&[RawLua("0xFF"), RawLua("{1, 2")]).unwrap();
+/// let error = serde_lua_table::validate_str(&lua).unwrap_err();
+/// assert_eq!(error.to_string(), "unexpected end of input at line 1, column 13");
+///
+/// serde_lua_table::validate_str("return {0xFF, {1, 2}}").unwrap();
+/// ```
+///
+/// # Errors
+///
+/// Fails with the location of the first syntax error if the string isn't a lua value.
+pub fn validate_str(s: &str) -> Result<(), DeError> {
+    validate_slice(s.as_bytes())
+}
+
+/// Checks that bytes are lua that [`from_str`] can read, see [`validate_str`]. Strings in the
+/// input may contain any bytes, like the strings lua reads.
+///
+/// # Errors
+///
+/// Fails with the location of the first syntax error if the bytes aren't a lua value.
+pub fn validate_slice(v: &[u8]) -> Result<(), DeError> {
+    deserialize_chunk::<_, IgnoredAny>(&mut Deserializer::new(SliceRead::new(v))).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_reader, from_str, value::Key, Value};
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn reads_test_example_into_value() {
+        let file = std::fs::read_to_string("test_example.lua").unwrap();
+        let (name, value) = crate::from_str_named::<Value>(&file).unwrap();
+        assert_eq!(name, "ALIEN");
+        let table = value.as_table().unwrap();
+        assert_eq!(table.get(&Key::from(4)), Some(&Value::from("a")));
+        assert_eq!(table.get(&Key::from(8)), None);
+
+        let xd = table.get(&Key::from("xd")).unwrap().as_table().unwrap();
+        assert_eq!(xd.get(&Key::from("forsen")), Some(&Value::from(5)));
+        let array = xd.get(&Key::from("array")).unwrap().as_table().unwrap();
+        assert_eq!(array.sequence().len(), 4);
+        let combined = xd.get(&Key::from("combined")).unwrap().as_table().unwrap();
+        assert_eq!(combined.sequence(), [Value::from(0), Value::from(1)]);
+        let a = combined.get(&Key::from("a")).unwrap().as_table().unwrap();
+        assert_eq!(a.sequence(), [Value::from(1), Value::from(2)]);
+
+        assert_eq!(from_reader::<_, Value>(file.as_bytes()).unwrap(), value);
+    }
+
+    /// Returns at most one byte per read, then fails if `fail` is set.
+    struct Trickle<'a> {
+        input: &'a [u8],
+        fail: bool,
+    }
+
+    impl std::io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.input.split_first() {
+                Some((byte, rest)) => {
+                    buf[0] = *byte;
+                    self.input = rest;
+                    Ok(1)
+                }
+                None if self.fail => Err(std::io::Error::other("disconnected")),
+                None => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn reads_tokens_split_across_reads() {
+        let long = "x".repeat(3 * 8192);
+        let lua = format!(
+            "-- comment\n{{ [==[{long}]==], 'esc\\'\\65\\u{{48}}', 0x1F, 1.5e3, --[[ block ]] k = true }}"
+        );
+        let input = Trickle {
+            input: lua.as_bytes(),
+            fail: false,
+        };
+        let value = from_reader::<_, Value>(input).unwrap();
+        assert_eq!(value, from_str::<Value>(&lua).unwrap());
+        let table = value.as_table().unwrap();
+        assert_eq!(table.sequence()[0], Value::from(long));
+        assert_eq!(table.sequence()[1], Value::from("esc'AH"));
+    }
+
+    #[test]
+    fn reports_errors_of_readers() {
+        let input = Trickle {
+            input: b"{ 1, 2",
+            fail: true,
+        };
+        let error = from_reader::<_, Value>(input).unwrap_err();
+        assert!(error.to_string().contains("disconnected"), "{}", error);
+    }
+
+    #[test]
+    fn validates_without_deserializing() {
+        crate::validate_str("Config = {a = 1, 'two', [3] = {}, [ [[x]] ] = math.huge}").unwrap();
+        crate::validate_slice(b"{\"\xFF\", '\xFE'}").unwrap();
+
+        for (lua, message) in [
+            ("", "unexpected end of input at line 1, column 1"),
+            ("{1, 2} 3", "trailing characters at line 1, column 8"),
+            ("{a = }", "expected a value at a, line 1, column 6"),
+            (
+                "{\n  'unfinished\n}",
+                "unfinished string at [1], line 3, column 1",
+            ),
+        ] {
+            let error = crate::validate_str(lua).unwrap_err();
+            assert_eq!(error.to_string(), message, "{}", lua);
+        }
+    }
+
+    #[test]
+    fn skips_comments_wherever_whitespace_is_allowed() {
+        let lua =
+            "-- header\n--[==[ long ]] ]=] ]==]{ --[ line\n a --[[x]] = --[[\n]] 1, -- b\n} -- end";
+        let map = from_str::<BTreeMap<String, u8>>(lua).unwrap();
+        assert_eq!(map, BTreeMap::from([("a".to_owned(), 1)]));
+        assert_eq!(from_str::<Vec<u8>>("{1,--\n2}").unwrap(), [1, 2]);
+
+        let error = from_str::<Value>("{ 1 --[[ open").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unfinished long bracket at line 1, column 9"
+        );
+    }
+
+    #[test]
+    fn reads_every_number_form() {
+        let floats =
+            from_str::<Vec<f64>>("{0x.8, 0x1p-2, 0X1P+4, 0xA.8p3, 5., .5, 1E2, 0x1e}").unwrap();
+        assert_eq!(floats, [0.5, 0.25, 16.0, 84.0, 5.0, 0.5, 100.0, 30.0]);
+        let integers = from_str::<Vec<u64>>(
+            "{0xff, 0xFFFFFFFFFFFFFFFF, 0x10000000000000001, 18446744073709551615ULL, 7ull}",
+        )
+        .unwrap();
+        assert_eq!(integers, [255, u64::MAX, 1, u64::MAX, 7]);
+        assert_eq!(from_str::<i64>("-9223372036854775808LL").unwrap(), i64::MIN);
+        assert_eq!(
+            from_str::<f64>("18446744073709551616").unwrap(),
+            2f64.powi(64)
+        );
+
+        for lua in ["0x", "0xG", "1e", "1e+", "3..2", "1.5.2", "0x1p", "12abc"] {
+            let error = from_str::<f64>(lua).unwrap_err();
+            assert!(
+                error.to_string().starts_with("malformed number"),
+                "{}: {}",
+                lua,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn decodes_strings_like_lua() {
+        let lua = ::mlua::Lua::new();
+        for literal in [
+            r#""\a\b\f\n\r\t\v\\\"\'""#,
+            "'line\\\nbreak\\\r\ncrlf'",
+            r"'\x41\x7a\65\0659\255'",
+            r"'\u{41}\u{e9}\u{10FFFF}\u{7FFFFFFF}'",
+            "'a\\z  \n\t  b'",
+            "[[\nfirst line break skipped]]",
+            "[==[\r\nkeeps ]] and \r\n and \n\r]==]",
+            "[[]]",
+        ] {
+            let expected: ::mlua::String = lua.load(format!("return {literal}")).eval().unwrap();
+            let value = from_str::<Value>(literal).unwrap();
+            assert_eq!(value.as_bytes(), Some(expected.as_bytes()), "{}", literal);
+        }
+
+        let borrowed = from_str::<Vec<&str>>("{[[\nlong]], [=[x]=]}").unwrap();
+        assert_eq!(borrowed, ["long", "x"]);
+        for literal in [
+            r"'\q'",
+            r"'\256'",
+            r"'\xG0'",
+            r"'\u{80000000}'",
+            r"'\u41'",
+            "[=[x]]",
+        ] {
+            assert!(from_str::<Value>(literal).is_err(), "{}", literal);
+        }
+    }
+
+    #[test]
+    fn reads_return_chunks() {
+        for lua in [
+            "return {1, 2}",
+            "-- module\nreturn{1, 2};\n-- end",
+            "return --[[ list ]] {1, 2} ;",
+        ] {
+            assert_eq!(from_str::<Vec<u8>>(lua).unwrap(), [1, 2], "{}", lua);
+        }
+        assert!(from_str::<bool>("return true").unwrap());
+        for lua in ["return", "return {} ;;", "{1, 2};", "returned = 1 2"] {
+            assert!(from_str::<Value>(lua).is_err(), "{}", lua);
+        }
+    }
+
+    #[test]
+    fn reads_assignments() {
+        for (lua, name) in [
+            ("Config = {1, 2}", "Config"),
+            ("-- saved\nlocal  _v2 = {1, 2};", "_v2"),
+            ("localized = {1, 2}", "localized"),
+        ] {
+            let (read, value) = crate::from_str_named::<Vec<u8>>(lua).unwrap();
+            assert_eq!((read.as_str(), value), (name, vec![1, 2]), "{}", lua);
+            assert_eq!(from_str::<Vec<u8>>(lua).unwrap(), [1, 2], "{}", lua);
+        }
+        assert!(!from_str::<bool>("false").unwrap());
+        for lua in ["{1, 2}", "return {1, 2}", "local = 1", "local 1", "x {1}"] {
+            assert!(crate::from_str_named::<Value>(lua).is_err(), "{}", lua);
+        }
+    }
+
+    #[test]
+    fn reads_infinity_and_nan_expressions() {
+        let specials = [f64::INFINITY, f64::NEG_INFINITY, f64::NAN];
+        let read = from_str::<Vec<f32>>(&crate::to_string(&specials).unwrap()).unwrap();
+        assert_eq!(read[..2], [f32::INFINITY, f32::NEG_INFINITY]);
+        assert!(read[2].is_nan());
+        assert!(from_str::<f64>("-0/0").unwrap().is_nan());
+
+        let map =
+            from_str::<BTreeMap<String, f64>>("{ math = math.huge, huge = -math.huge }").unwrap();
+        assert_eq!(map["math"], f64::INFINITY);
+        assert_eq!(map["huge"], f64::NEG_INFINITY);
+
+        for lua in ["math.hugest", "math .huge", "0/1", "math.huge1"] {
+            assert!(from_str::<f64>(lua).is_err(), "{}", lua);
+        }
+        assert!(from_str::<i64>("math.huge").is_err());
+    }
+
+    #[test]
+    fn reads_mixed_tables_as_maps() {
+        for lua in [
+            "{1, 2, x = 3}",
+            "{1, 2, [\"x\"] = 3}",
+            "{1, --[[ x = ]] 2, x = 3}",
+            "{1, 2, [ [[x]] ] = 3}",
+        ] {
+            let value = from_str::<Value>(lua).unwrap();
+            let table = value.as_table().unwrap();
+            assert_eq!(table.sequence().len(), 2, "{}", lua);
+            assert_eq!(table.get(&Key::from("x")), Some(&Value::from(3)), "{}", lua);
+        }
+    }
+
+    #[test]
+    fn reads_tables_without_keys_as_sequences() {
+        for lua in [
+            "{1, {x = 1}, 2}",
+            "{'a = 1', [[b = 2]], [==[c]]=]==]}",
+            "{1, -- x = 1\n 2}",
+            "{}",
+        ] {
+            let value = from_str::<Value>(lua).unwrap();
+            let table = value.as_table().unwrap();
+            assert!(table.fields().is_empty(), "{}", lua);
+        }
+    }
+
+    #[test]
+    fn reads_mixed_tables_from_readers() {
+        let mut lua = String::from("{");
+        for i in 0..5000 {
+            lua.push_str(&format!("{}, ", i));
+        }
+        lua.push_str("last = true}");
+        let value = from_reader::<_, Value>(lua.as_bytes()).unwrap();
+        let table = value.as_table().unwrap();
+        assert_eq!(table.sequence().len(), 5000);
+        assert_eq!(table.get(&Key::from("last")), Some(&Value::from(true)));
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(untagged)]
+    enum Entry {
+        List(Vec<u8>),
+        Map(BTreeMap<String, u8>),
+        Mixed(BTreeMap<Key, u8>),
+    }
+
+    #[test]
+    fn reads_mixed_tables_in_untagged_enums() {
+        let entries = from_str::<Vec<Entry>>("{{1, 2}, {a = 1}, {1, b = 2}}").unwrap();
+        assert_eq!(
+            entries,
+            [
+                Entry::List(vec![1, 2]),
+                Entry::Map(BTreeMap::from([("a".to_owned(), 1)])),
+                Entry::Mixed(BTreeMap::from([(Key::from(1), 1), (Key::from("b"), 2)])),
+            ]
+        );
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Flattened {
+        name: String,
+        #[serde(flatten)]
+        rest: BTreeMap<Key, Value>,
+    }
+
+    #[test]
+    fn reads_mixed_tables_into_flattened_structs() {
+        let value = from_str::<Flattened>("{'a', 'b', name = 'x', count = 2}").unwrap();
+        assert_eq!(value.name, "x");
+        assert_eq!(
+            value.rest,
+            BTreeMap::from([
+                (Key::from(1), Value::from("a")),
+                (Key::from(2), Value::from("b")),
+                (Key::from("count"), Value::from(2)),
+            ])
+        );
+    }
+
+    #[derive(Deserialize, serde::Serialize, Debug, PartialEq)]
+    struct Optional {
+        a: Option<u8>,
+        b: (),
+        c: Option<Option<u8>>,
+    }
+
+    #[test]
+    fn reads_nil_as_none_and_unit() {
+        assert_eq!(from_str::<Option<u8>>("nil").unwrap(), None);
+        assert_eq!(from_str::<Option<u8>>("return nil").unwrap(), None);
+        from_str::<()>("nil").unwrap();
+        assert_eq!(
+            from_str::<Vec<Option<u8>>>("{1, nil, 3}").unwrap(),
+            [Some(1), None, Some(3)]
+        );
+
+        let value = Optional {
+            a: None,
+            b: (),
+            c: Some(None),
+        };
+        let lua = crate::to_string(&value).unwrap();
+        assert_eq!(lua, r#"{["a"]=nil,["b"]=nil,["c"]=nil}"#);
+        // `Some(None)` and `None` are both written as `nil`.
+        assert_eq!(
+            from_str::<Optional>(&lua).unwrap(),
+            Optional { c: None, ..value }
+        );
+    }
+
+    #[test]
+    fn does_not_read_names_starting_with_nil_as_nil() {
+        assert_eq!(
+            from_str::<BTreeMap<String, u8>>("{nil_count = 1}").unwrap(),
+            BTreeMap::from([("nil_count".to_owned(), 1)])
+        );
+        assert!(from_str::<Option<u8>>("{nilx}").is_err());
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Blob {
+        data: Vec<u8>,
+        #[serde(with = "crate::helpers::lua_string_bytes")]
+        raw: Vec<u8>,
+    }
+
+    #[test]
+    fn reads_strings_that_are_not_utf8_as_bytes() {
+        let lua = r#"{data = "\xFF\200", raw = "\xFEa"}"#;
+        let blob = from_str::<Blob>(lua).unwrap();
+        assert_eq!(blob.data, [0xFF, 200]);
+        assert_eq!(blob.raw, [0xFE, b'a']);
+        assert_eq!(from_reader::<_, Blob>(lua.as_bytes()).unwrap(), blob);
+        assert_eq!(from_str::<Vec<u8>>("{1, 2}").unwrap(), [1, 2]);
+
+        let error = from_str::<String>(r#""\xFF""#).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid value: byte array, expected a string at line 1, column 7"
+        );
+    }
+
+    #[test]
+    fn updates_in_place_and_keeps_missing_fields() {
+        #[derive(serde::Serialize, Deserialize, Debug, PartialEq)]
+        struct Config {
+            name: String,
+            plugins: Vec<String>,
+            limits: BTreeMap<String, u32>,
+        }
+
+        let mut config = Config {
+            name: "a".to_owned(),
+            plugins: vec!["x".to_owned(), "y".to_owned()],
+            limits: [("files".to_owned(), 10)].into(),
+        };
+        crate::from_str_in_place(
+            &mut config,
+            "return { plugins = { 'z' }, limits = { depth = 2 } }",
+        )
+        .unwrap();
+        assert_eq!(config.name, "a");
+        assert_eq!(config.plugins, ["z"]);
+        assert_eq!(config.limits.len(), 2);
+
+        assert!(crate::from_str_in_place(&mut config, "{ name = 1 }").is_err());
+        assert_eq!(config.name, "a");
+    }
+
+    #[test]
+    fn limits_the_memory_of_strings_and_tables() {
+        use crate::{DeError, DeOptions, Deserializer, IoRead, SliceRead};
+
+        let limited = |lua: &str, limit| {
+            let options = DeOptions::new().with_memory_limit(limit);
+            let slice = Value::deserialize(&mut Deserializer::with_options(
+                SliceRead::new(lua.as_bytes()),
+                options.clone(),
+            ));
+            let io = Value::deserialize(&mut Deserializer::with_options(
+                IoRead::new(lua.as_bytes()),
+                options,
+            ));
+            assert_eq!(slice.is_ok(), io.is_ok(), "{}", lua);
+            slice
+        };
+        let long = format!("{{ '{}', [[{}]] }}", "a".repeat(1000), "b".repeat(1000));
+        assert!(limited(&long, 4000).is_ok());
+        let error = limited(&long, 1500).unwrap_err();
+        assert!(matches!(
+            error,
+            DeError::Located { ref source, .. } if matches!(**source, DeError::MemoryLimitExceeded(1500))
+        ));
+        assert_eq!(error.path(), [crate::PathSegment::Index(2)]);
+
+        let nested = "{".repeat(100) + &"}".repeat(100);
+        assert!(limited(&nested, 100).is_err());
+        assert!(limited(&nested, 100_000).is_ok());
+    }
+
+    #[test]
+    fn reads_empty_tables_as_configured() {
+        use crate::{DeOptions, Deserializer, EmptyTables, SliceRead};
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        #[serde(untagged)]
+        enum Field {
+            List(Vec<u8>),
+            Map(BTreeMap<String, u8>),
+        }
+
+        fn read<'de, T: Deserialize<'de>>(lua: &'de str, empty_tables: EmptyTables) -> T {
+            let options = DeOptions::new().with_empty_tables(empty_tables);
+            let mut de = Deserializer::with_options(SliceRead::new(lua.as_bytes()), options);
+            T::deserialize(&mut de).unwrap()
+        }
+
+        let lua = "{ {}, { 1 }, { a = 1 } }";
+        let fields = |empty| {
+            vec![
+                empty,
+                Field::List(vec![1]),
+                Field::Map(BTreeMap::from([("a".to_owned(), 1)])),
+            ]
+        };
+        assert_eq!(
+            read::<Vec<Field>>(lua, EmptyTables::Sequence),
+            fields(Field::List(Vec::new()))
+        );
+        assert_eq!(
+            read::<Vec<Field>>(lua, EmptyTables::Map),
+            fields(Field::Map(BTreeMap::new()))
+        );
+        assert!(read::<Vec<u8>>("{}", EmptyTables::Map).is_empty());
+        assert!(read::<BTreeMap<u8, u8>>("{}", EmptyTables::Sequence).is_empty());
+    }
+
+    #[test]
+    fn reads_keys_that_are_not_utf8_as_bytes() {
+        let map = from_str::<BTreeMap<serde_bytes_key::Key, u8>>(r#"{["\xFF"] = 1}"#).unwrap();
+        assert_eq!(map.into_keys().next().unwrap().0, [0xFF]);
+    }
+
+    mod serde_bytes_key {
+        /// A map key that accepts any bytes.
+        #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+        pub(super) struct Key(pub(super) Vec<u8>);
+
+        impl<'de> serde::Deserialize<'de> for Key {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                crate::helpers::lua_string_bytes::deserialize(deserializer).map(Key)
+            }
+        }
+    }
+}
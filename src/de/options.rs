@@ -0,0 +1,245 @@
+use crate::KeyCase;
+
+/// What happens when a table contains the same key twice, like `{a = 1, a = 2}`.
+///
+/// ```
+/// use serde::Deserialize;
+/// use serde_lua_table::{DeOptions, Deserializer, DuplicateKeys, SliceRead};
+/// use std::collections::HashMap;
+///
+/// let input = b"{a = 1, b = 2, a = 3}";
+/// let read = |policy| {
+///     let options = DeOptions::new().with_duplicate_keys(policy);
+///     let mut de = Deserializer::with_options(SliceRead::new(input), options);
+///     HashMap::<String, u8>::deserialize(&mut de).map(|map| map["a"])
+/// };
+/// assert_eq!(read(DuplicateKeys::LastWins).unwrap(), 3);
+/// assert_eq!(read(DuplicateKeys::FirstWins).unwrap(), 1);
+/// assert_eq!(
+///     read(DuplicateKeys::Error).unwrap_err().to_string(),
+///     "duplicate key at a, line 1, column 19"
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DuplicateKeys {
+    /// A repeated key is an error.
+    Error,
+    /// The first entry with a key is kept, later ones are skipped.
+    FirstWins,
+    /// Every entry is passed on in order, so later entries replace earlier ones in maps like they
+    /// do in lua. Types that reject repeated keys themselves, like derived structs, still fail.
+    #[default]
+    LastWins,
+}
+
+/// How an empty table `{}` is read when the type doesn't say whether it expects a sequence or a
+/// map, like the variants of an untagged enum or other types that use `deserialize_any`.
+///
+/// ```
+/// use serde::Deserialize;
+/// use serde_lua_table::{DeOptions, Deserializer, EmptyTables, SliceRead};
+/// use std::collections::HashMap;
+///
+/// #[derive(Deserialize, Debug, PartialEq)]
+/// #[serde(untagged)]
+/// enum Field {
+///     Map(HashMap<String, u8>),
+///     List(Vec<u8>),
+/// }
+///
+/// let read = |empty_tables| {
+///     let options = DeOptions::new().with_empty_tables(empty_tables);
+///     let mut de = Deserializer::with_options(SliceRead::new(b"{}"), options);
+///     Field::deserialize(&mut de).unwrap()
+/// };
+/// assert_eq!(read(EmptyTables::Sequence), Field::List(Vec::new()));
+/// assert_eq!(read(EmptyTables::Map), Field::Map(HashMap::new()));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum EmptyTables {
+    /// `{}` is an empty sequence.
+    #[default]
+    Sequence,
+    /// `{}` is an empty map.
+    Map,
+}
+
+/// Options that change how the [`Deserializer`](super::Deserializer) reads its input.
+#[derive(Clone, Debug, Default)]
+pub struct DeOptions {
+    duplicate_keys: DuplicateKeys,
+    empty_tables: EmptyTables,
+    key_case: KeyCase,
+    enum_tag: Option<&'static str>,
+    raw_numbers: bool,
+    strict: bool,
+    memory_limit: Option<usize>,
+}
+
+impl DeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets what happens when a table contains the same key twice, see [`DuplicateKeys`].
+    pub fn with_duplicate_keys(mut self, duplicate_keys: DuplicateKeys) -> Self {
+        self.duplicate_keys = duplicate_keys;
+        self
+    }
+
+    pub fn duplicate_keys(&self) -> DuplicateKeys {
+        self.duplicate_keys
+    }
+
+    /// Sets how `{}` is read when the type accepts any value, see [`EmptyTables`].
+    pub fn with_empty_tables(mut self, empty_tables: EmptyTables) -> Self {
+        self.empty_tables = empty_tables;
+        self
+    }
+
+    pub fn empty_tables(&self) -> EmptyTables {
+        self.empty_tables
+    }
+
+    /// Renames the string keys of tables and the names of variables before they are matched to
+    /// fields, like [`SerOptions::with_key_case`](crate::SerOptions::with_key_case) does when
+    /// writing. The names of enum variants are left as they are.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_lua_table::{DeOptions, Deserializer, KeyCase, SliceRead};
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Unit {
+    ///     max_speed: u32,
+    /// }
+    ///
+    /// let options = DeOptions::new().with_key_case(KeyCase::SnakeCase);
+    /// let mut de = Deserializer::with_options(SliceRead::new(b"{ maxSpeed = 3 }"), options);
+    /// assert_eq!(Unit::deserialize(&mut de).unwrap().max_speed, 3);
+    /// ```
+    pub fn with_key_case(mut self, key_case: KeyCase) -> Self {
+        self.key_case = key_case;
+        self
+    }
+
+    pub fn key_case(&self) -> KeyCase {
+        self.key_case
+    }
+
+    /// Also reads enum variants written as a table whose first field `tag` names the variant,
+    /// like `{ type = "Circle", radius = 1 }`. The other fields are the fields of the variant.
+    /// Tables without the tag are still read as `{ Circle = { radius = 1 } }`.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_lua_table::{DeOptions, Deserializer, SliceRead};
+    ///
+    /// #[derive(Deserialize, Debug, PartialEq)]
+    /// enum Shape {
+    ///     Circle { radius: u32 },
+    ///     Point,
+    /// }
+    ///
+    /// let input = br#"{ { type = "Circle", radius = 1 }, { Circle = { radius = 2 } }, { type = "Point" }, 1 }"#;
+    /// let options = DeOptions::new().with_enum_tag("type");
+    /// let mut de = Deserializer::with_options(SliceRead::new(input), options);
+    /// assert_eq!(
+    ///     Vec::<Shape>::deserialize(&mut de).unwrap(),
+    ///     [Shape::Circle { radius: 1 }, Shape::Circle { radius: 2 }, Shape::Point, Shape::Point]
+    /// );
+    /// ```
+    pub fn with_enum_tag(mut self, tag: &'static str) -> Self {
+        self.enum_tag = Some(tag);
+        self
+    }
+
+    pub fn enum_tag(&self) -> Option<&'static str> {
+        self.enum_tag
+    }
+
+    /// Keeps decimal numbers as they were written when deserializing a [`Value`](crate::Value),
+    /// as [`Value::RawNumber`](crate::Value::RawNumber). Integers with more digits than an
+    /// `i64` holds and decimals that a float can't represent exactly are written back unchanged.
+    ///
+    /// Only enable this for `Value`s, other types that accept any value would see a map.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_lua_table::{DeOptions, Deserializer, SliceRead, Value};
+    ///
+    /// let input = b"{ id = 123456789012345678901234, price = 0.10, flags = 0xFF }";
+    /// let options = DeOptions::new().with_raw_numbers(true);
+    /// let mut de = Deserializer::with_options(SliceRead::new(input), options);
+    /// let value = Value::deserialize(&mut de).unwrap();
+    /// assert_eq!(
+    ///     serde_lua_table::to_string(&value).unwrap(),
+    ///     r#"{["id"]=123456789012345678901234,["price"]=0.10,["flags"]=255}"#
+    /// );
+    /// ```
+    pub fn with_raw_numbers(mut self, raw_numbers: bool) -> Self {
+        self.raw_numbers = raw_numbers;
+        self
+    }
+
+    pub fn raw_numbers(&self) -> bool {
+        self.raw_numbers
+    }
+
+    /// Only accepts literals and table constructors, for input from untrusted sources. The
+    /// expressions `math.huge` and `0/0`, `return` and assignments to variables are errors that
+    /// name the construct.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_lua_table::{DeOptions, Deserializer, SliceRead, Value};
+    ///
+    /// let read = |input: &str| {
+    ///     let options = DeOptions::new().with_strict(true);
+    ///     let mut de = Deserializer::with_options(SliceRead::new(input.as_bytes()), options);
+    ///     Value::deserialize(&mut de).map_err(|e| e.to_string())
+    /// };
+    /// assert!(read("{ name = 'a', [1] = 0x10, -- comment\n list = { true, nil } }").is_ok());
+    /// assert_eq!(
+    ///     read("{ size = math.huge }").unwrap_err(),
+    ///     "`math.huge` isn't allowed in strict mode at size, line 1, column 10"
+    /// );
+    /// ```
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Fails with [`DeError::MemoryLimitExceeded`](super::DeError::MemoryLimitExceeded) once the
+    /// parsed strings and tables would take more than about `bytes` bytes, so untrusted input
+    /// can't exhaust the memory.
+    ///
+    /// Every string counts with its length, every table and every entry with the size it takes
+    /// in a [`Value`](crate::Value). Short input can still expand a lot: each `0,` in a table
+    /// becomes an entry of 48 bytes.
+    ///
+    /// ```
+    /// use serde_lua_table::{DeOptions, Deserializer, SliceRead, Value};
+    /// use serde::Deserialize;
+    ///
+    /// let input = format!("{{{}}}", "0,".repeat(10_000));
+    /// let options = DeOptions::new().with_memory_limit(64 * 1024);
+    /// let mut de = Deserializer::with_options(SliceRead::new(input.as_bytes()), options);
+    /// assert_eq!(
+    ///     Value::deserialize(&mut de).unwrap_err().to_string(),
+    ///     "Parsed values take more than 65536 bytes at [1365], line 1, column 2730"
+    /// );
+    /// ```
+    pub fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    pub fn memory_limit(&self) -> Option<usize> {
+        self.memory_limit
+    }
+}
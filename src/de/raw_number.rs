@@ -0,0 +1,42 @@
+use super::DeError;
+use serde::de::{self, value::BorrowedStrDeserializer, DeserializeSeed, IntoDeserializer};
+
+/// Name of the only key of the map a raw number is passed as, which [`Value`](crate::Value)
+/// recognizes.
+pub(crate) const TOKEN: &str = "$serde_lua_table::private::RawNumber";
+
+/// Passes a number as it was written as a map from [`TOKEN`] to the number.
+pub(super) struct RawNumberAccess {
+    number: Option<String>,
+}
+
+impl RawNumberAccess {
+    pub(super) fn new(number: String) -> Self {
+        RawNumberAccess {
+            number: Some(number),
+        }
+    }
+}
+
+impl<'de> de::MapAccess<'de> for RawNumberAccess {
+    type Error = DeError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, DeError>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if self.number.is_none() {
+            return Ok(None);
+        }
+        seed.deserialize(BorrowedStrDeserializer::new(TOKEN))
+            .map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, DeError>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let number = self.number.take().unwrap_or_default();
+        seed.deserialize(number.into_deserializer())
+    }
+}
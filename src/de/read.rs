@@ -0,0 +1,297 @@
+use super::{DeError, Position};
+use crate::format::find_any_of;
+use std::{io, sync::Arc};
+
+/// Number of bytes [`IoRead`] reads at once.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// The input of a [`Deserializer`](super::Deserializer), either a [`SliceRead`] or an
+/// [`IoRead`].
+///
+/// This trait is sealed and can't be implemented outside of this crate.
+pub trait Read<'de>: private::Sealed {
+    /// Returns the byte `n` bytes ahead without consuming anything.
+    fn peek_at(&mut self, n: usize) -> Result<Option<u8>, DeError>;
+
+    #[inline]
+    fn peek(&mut self) -> Result<Option<u8>, DeError> {
+        self.peek_at(0)
+    }
+
+    /// Consumes the byte returned by the last `peek`.
+    fn discard(&mut self);
+
+    #[inline]
+    fn next(&mut self) -> Result<Option<u8>, DeError> {
+        let byte = self.peek()?;
+        if byte.is_some() {
+            self.discard();
+        }
+        Ok(byte)
+    }
+
+    /// Number of bytes consumed so far.
+    fn offset(&self) -> usize;
+
+    /// The position after the bytes consumed so far.
+    fn position(&self) -> Position;
+
+    /// Consumes the next `len` bytes and returns them, if they can be borrowed from the input.
+    /// Otherwise, nothing is consumed.
+    fn borrow(&mut self, len: usize) -> Option<&'de [u8]>;
+
+    /// Consumes the next `len` bytes and appends them to `buf`.
+    fn copy_to(&mut self, len: usize, buf: &mut Vec<u8>) -> Result<(), DeError>;
+
+    /// Returns how many bytes ahead the first byte in `stops` is, starting the search `n` bytes
+    /// ahead. Returns the number of bytes left if there is none.
+    fn scan_until(&mut self, n: usize, stops: &[u8]) -> Result<usize, DeError> {
+        let mut len = n;
+        while let Some(b) = self.peek_at(len)? {
+            if stops.contains(&b) {
+                break;
+            }
+            len += 1;
+        }
+        Ok(len)
+    }
+}
+
+/// Reads from a slice that strings can be borrowed from.
+pub struct SliceRead<'de> {
+    slice: &'de [u8],
+    index: usize,
+}
+
+impl<'de> SliceRead<'de> {
+    pub fn new(slice: &'de [u8]) -> Self {
+        SliceRead { slice, index: 0 }
+    }
+
+    /// Starts reading at `index`, positions still count from the start of `slice`.
+    pub(super) fn starting_at(slice: &'de [u8], index: usize) -> Self {
+        SliceRead { slice, index }
+    }
+}
+
+impl private::Sealed for SliceRead<'_> {}
+
+impl<'de> Read<'de> for SliceRead<'de> {
+    #[inline]
+    fn peek_at(&mut self, n: usize) -> Result<Option<u8>, DeError> {
+        Ok(self.slice.get(self.index + n).copied())
+    }
+
+    #[inline]
+    fn discard(&mut self) {
+        self.index += 1;
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.index
+    }
+
+    fn position(&self) -> Position {
+        let consumed = &self.slice[..self.index];
+        let line_start = consumed
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+        Position {
+            offset: self.index,
+            line: consumed.iter().filter(|&&b| b == b'\n').count() + 1,
+            column: self.index - line_start + 1,
+        }
+    }
+
+    #[inline]
+    fn borrow(&mut self, len: usize) -> Option<&'de [u8]> {
+        let bytes = self.slice.get(self.index..self.index + len)?;
+        self.index += len;
+        Some(bytes)
+    }
+
+    #[inline]
+    fn copy_to(&mut self, len: usize, buf: &mut Vec<u8>) -> Result<(), DeError> {
+        let end = (self.index + len).min(self.slice.len());
+        buf.extend_from_slice(&self.slice[self.index..end]);
+        self.index = end;
+        Ok(())
+    }
+
+    #[inline]
+    fn scan_until(&mut self, n: usize, stops: &[u8]) -> Result<usize, DeError> {
+        let rest = self.slice.get(self.index + n..).unwrap_or_default();
+        Ok(n + find_any_of(rest, stops).unwrap_or(rest.len()))
+    }
+}
+
+/// Reads from an [`io::Read`] in chunks, so only the part of the input that is currently parsed
+/// is kept in memory. The reader doesn't need to be buffered.
+pub struct IoRead<R> {
+    reader: R,
+    buf: Vec<u8>,
+    /// Index of the next byte in `buf`.
+    pos: usize,
+    /// Number of bytes in `buf` that have been read.
+    end: usize,
+    /// Number of bytes consumed before the start of `buf`.
+    consumed: usize,
+    /// Number of line breaks consumed.
+    lines: usize,
+    /// Offset of the first byte of the current line.
+    line_start: usize,
+}
+
+impl<R> IoRead<R>
+where
+    R: io::Read,
+{
+    pub fn new(reader: R) -> Self {
+        IoRead {
+            reader,
+            buf: Vec::new(),
+            pos: 0,
+            end: 0,
+            consumed: 0,
+            lines: 0,
+            line_start: 0,
+        }
+    }
+
+    /// Reads until `n + 1` bytes are buffered after the current one or the input ends.
+    fn fill(&mut self, n: usize) -> Result<(), DeError> {
+        while self.end <= self.pos + n {
+            if self.pos > 0 {
+                self.buf.copy_within(self.pos..self.end, 0);
+                self.consumed += self.pos;
+                self.end -= self.pos;
+                self.pos = 0;
+            }
+            if self.end == self.buf.len() {
+                self.buf.resize(self.end + CHUNK_SIZE, 0);
+            }
+            match self.reader.read(&mut self.buf[self.end..]) {
+                Ok(0) => return Ok(()),
+                Ok(read) => self.end += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(DeError::Io(Arc::new(e))),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R> private::Sealed for IoRead<R> {}
+
+impl<'de, R> Read<'de> for IoRead<R>
+where
+    R: io::Read,
+{
+    #[inline]
+    fn peek_at(&mut self, n: usize) -> Result<Option<u8>, DeError> {
+        if self.end <= self.pos + n {
+            self.fill(n)?;
+        }
+        Ok(self.buf[..self.end].get(self.pos + n).copied())
+    }
+
+    #[inline]
+    fn discard(&mut self) {
+        if self.buf[self.pos] == b'\n' {
+            self.lines += 1;
+            self.line_start = self.offset() + 1;
+        }
+        self.pos += 1;
+    }
+
+    #[inline]
+    fn offset(&self) -> usize {
+        self.consumed + self.pos
+    }
+
+    fn position(&self) -> Position {
+        Position {
+            offset: self.offset(),
+            line: self.lines + 1,
+            column: self.offset() - self.line_start + 1,
+        }
+    }
+
+    #[inline]
+    fn borrow(&mut self, _len: usize) -> Option<&'de [u8]> {
+        None
+    }
+
+    fn copy_to(&mut self, len: usize, buf: &mut Vec<u8>) -> Result<(), DeError> {
+        if len > 0 {
+            self.fill(len - 1)?;
+        }
+        let end = (self.pos + len).min(self.end);
+        let bytes = &self.buf[self.pos..end];
+        if let Some(last) = bytes.iter().rposition(|&b| b == b'\n') {
+            self.lines += bytes.iter().filter(|&&b| b == b'\n').count();
+            self.line_start = self.offset() + last + 1;
+        }
+        buf.extend_from_slice(bytes);
+        self.pos = end;
+        Ok(())
+    }
+
+    fn scan_until(&mut self, mut n: usize, stops: &[u8]) -> Result<usize, DeError> {
+        loop {
+            if self.end <= self.pos + n {
+                self.fill(n)?;
+                if self.end <= self.pos + n {
+                    return Ok(n);
+                }
+            }
+            let buffered = &self.buf[self.pos + n..self.end];
+            match find_any_of(buffered, stops) {
+                Some(i) => return Ok(n + i),
+                None => n += buffered.len(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IoRead, Read, SliceRead};
+
+    #[test]
+    fn scans_the_same_in_slices_and_readers() {
+        let mut input = vec![b'a'; 3 * super::CHUNK_SIZE];
+        input[20] = b'"';
+        input[super::CHUNK_SIZE + 3] = b']';
+        input[2 * super::CHUNK_SIZE - 1] = b'\r';
+
+        for (n, stops) in [
+            (0, &b"\""[..]),
+            (21, b"]\r"),
+            (super::CHUNK_SIZE + 4, b"]\r"),
+            (2 * super::CHUNK_SIZE, b"]\r"),
+            (5 * super::CHUNK_SIZE, b"]"),
+        ] {
+            let slice = SliceRead::new(&input).scan_until(n, stops).unwrap();
+            let io = Read::scan_until(&mut IoRead::new(&input[..]), n, stops).unwrap();
+            assert_eq!(slice, io, "{} {:?}", n, stops);
+        }
+        assert_eq!(SliceRead::new(&input).scan_until(0, b"\"").unwrap(), 20);
+        assert_eq!(
+            SliceRead::new(&input).scan_until(21, b"]\r").unwrap(),
+            super::CHUNK_SIZE + 3
+        );
+        assert_eq!(
+            SliceRead::new(&input)
+                .scan_until(2 * super::CHUNK_SIZE, b"]")
+                .unwrap(),
+            input.len()
+        );
+    }
+}
@@ -0,0 +1,546 @@
+//! Reformatting lua text.
+
+use super::{is_identifier_continue, is_identifier_start, DeError, Deserializer, Read, SliceRead};
+use crate::{
+    Chunk, CompactFormatter, Globals, KeyOrder, KeyStyle, LuaVersion, OutputStability, SerOptions,
+    Serializer, Value, WithHeader,
+};
+use serde::{de::Error, Serialize};
+
+/// Reformats a lua data file like [`PrettyFormatter`](crate::PrettyFormatter) writes values,
+/// with one entry per line and two spaces of indentation, and keeps its comments.
+///
+/// The input is checked like [`from_str_chunk`](super::from_str_chunk) reads it, but no value is
+/// built: numbers, strings and the other literals are copied as they were written, so large
+/// files are reformatted without the memory of their values. Comments inside an entry before
+/// its value are moved to the lines before the entry. The output ends with a newline.
+///
+/// ```
+/// let lua = "-- settings\nreturn {size=0x10;name='a', -- the name\n list = {1,2,}}";
+/// assert_eq!(
+///     serde_lua_table::format_str(lua).unwrap(),
+///     "-- settings\nreturn {\n  size = 0x10,\n  name = 'a', -- the name\n  list = {\n    1,\n    2\n  }\n}\n"
+/// );
+/// ```
+///
+/// # Errors
+///
+/// Fails if the input isn't a lua value or a chunk returning or assigning one.
+pub fn format_str(s: &str) -> Result<String, DeError> {
+    reformat(s, false)
+}
+
+/// Writes a lua data file as compactly as it can be written without changing its value.
+///
+/// Comments and the whitespace between tokens are removed, trailing separators are dropped and
+/// keys like `["name"]` are written as `name`. The literals are copied as they were written.
+///
+/// ```
+/// let lua = "-- settings\nreturn {\n  [\"size\"] = 0x10, -- bytes\n  list = { 1, 2, },\n}\n";
+/// assert_eq!(
+///     serde_lua_table::minify_str(lua).unwrap(),
+///     "return{size=0x10,list={1,2}}"
+/// );
+/// ```
+///
+/// # Errors
+///
+/// Fails if the input isn't a lua value or a chunk returning or assigning one.
+pub fn minify_str(s: &str) -> Result<String, DeError> {
+    reformat(s, true)
+}
+
+/// Writes a lua data file the same way for every way of writing its value, for comparing and
+/// hashing files.
+///
+/// The file is read into a [`Value`] and written compactly with the keys sorted and the escapes
+/// of the current release, so the output can be read back and normalizing it again doesn't
+/// change it. Numbers and strings are written like the serializer writes them, comments are
+/// dropped and the chunk is kept.
+///
+/// ```
+/// let a = serde_lua_table::normalize_str("return { b = 0x10, a = 'it\\'s', 1.50 } -- x").unwrap();
+/// let b = serde_lua_table::normalize_str("return {[1] = 1.5, a = \"it's\", b = 16}").unwrap();
+/// assert_eq!(a, b);
+/// assert_eq!(a, "return {[1]=1.5,[\"a\"]=\"it's\",[\"b\"]=16}");
+/// ```
+///
+/// # Errors
+///
+/// Fails if the input isn't a lua value or a chunk returning or assigning one.
+pub fn normalize_str(s: &str) -> Result<String, DeError> {
+    let (chunk, value): (_, Value) = super::from_str_chunk(s)?;
+    let options = SerOptions::new()
+        .with_output_stability(OutputStability::Latest)
+        .with_key_order(KeyOrder::Lexicographic);
+    let mut out = Vec::new();
+    let written = match chunk {
+        Chunk::Value => value.serialize(&mut Serializer::with_options(
+            &mut out,
+            CompactFormatter,
+            options,
+        )),
+        Chunk::Return => {
+            let formatter = WithHeader::new(CompactFormatter, b"return ");
+            value.serialize(&mut Serializer::with_options(&mut out, formatter, options))
+        }
+        Chunk::Globals => {
+            let options = options.with_key_style(KeyStyle::Bare);
+            let formatter = Globals::new(CompactFormatter);
+            value.serialize(&mut Serializer::with_options(&mut out, formatter, options))
+        }
+    };
+    written.map_err(DeError::custom)?;
+    // The serializer only writes valid UTF-8.
+    Ok(String::from_utf8(out).expect("output is UTF-8"))
+}
+
+fn reformat(s: &str, compact: bool) -> Result<String, DeError> {
+    let mut reformatter = Reformatter {
+        de: Deserializer::from_str(s),
+        input: s,
+        out: String::with_capacity(s.len()),
+        compact,
+    };
+    match reformatter.chunk() {
+        Ok(()) => Ok(reformatter.out),
+        Err(e) => Err(e.at(reformatter.de.read.position())),
+    }
+}
+
+/// A comment in the input.
+struct Comment<'a> {
+    text: &'a str,
+    /// Whether the comment is on the same line as the token before it.
+    trailing: bool,
+}
+
+/// Splits comments into the ones on the line of the token before them and the ones after.
+fn split_trailing<'a>(mut comments: Vec<Comment<'a>>) -> (Vec<Comment<'a>>, Vec<Comment<'a>>) {
+    let own_lines = comments
+        .iter()
+        .position(|c| !c.trailing)
+        .unwrap_or(comments.len());
+    let own_lines = comments.split_off(own_lines);
+    (comments, own_lines)
+}
+
+/// Copies the tokens of the input to the output with the layout of the pretty formatter, or
+/// without any layout.
+struct Reformatter<'a> {
+    de: Deserializer<SliceRead<'a>>,
+    input: &'a str,
+    out: String,
+    /// Whether to drop comments and whitespace, see [`minify_str`].
+    compact: bool,
+}
+
+impl<'a> Reformatter<'a> {
+    fn chunk(&mut self) -> Result<(), DeError> {
+        for comment in self.trivia()? {
+            self.out.push_str(comment.text);
+            self.out.push('\n');
+        }
+        match self.de.read.peek()? {
+            None => Err(self.de.eof()),
+            Some(_) if self.de.peek_word(b"return")? => {
+                let mut at = self.out.len();
+                self.de.parse_identifier()?;
+                self.token("return");
+                self.space();
+                self.head_trivia(&mut at, 0)?;
+                self.value(0)?;
+                let mut comments = self.trivia()?;
+                if self.de.read.peek()? == Some(b';') {
+                    self.de.read.discard();
+                    comments.extend(self.trivia()?);
+                }
+                self.end(comments)
+            }
+            Some(b) if is_identifier_start(b) && !self.de.peek_keyword_value()? => self.globals(),
+            Some(_) => {
+                self.value(0)?;
+                let comments = self.trivia()?;
+                self.end(comments)
+            }
+        }
+    }
+
+    /// Writes the assignments of a chunk of globals, one per line.
+    fn globals(&mut self) -> Result<(), DeError> {
+        let mut comments = Vec::new();
+        let mut first = true;
+        loop {
+            // Statements may be separated by semicolons.
+            while self.de.read.peek()? == Some(b';') {
+                self.de.read.discard();
+                comments.extend(self.trivia()?);
+            }
+            let (trailing, own_lines) = split_trailing(comments);
+            if !first {
+                self.trailing(trailing);
+                self.line_break();
+            }
+            for comment in own_lines {
+                self.out.push_str(comment.text);
+                self.out.push('\n');
+            }
+            match self.de.read.peek()? {
+                None => return self.de.end(),
+                Some(b) if is_identifier_start(b) => {}
+                Some(_) => return Err(self.de.error("expected a name")),
+            }
+            first = false;
+
+            let mut at = self.out.len();
+            if self.de.peek_word(b"local")? {
+                self.de.parse_identifier()?;
+                self.token("local");
+                self.space();
+                self.head_trivia(&mut at, 0)?;
+                if !self.de.read.peek()?.is_some_and(is_identifier_start) {
+                    return Err(self.de.error("expected a name"));
+                }
+            }
+            if self.de.peek_keyword_value()? || self.de.peek_word(b"return")? {
+                return Err(self.de.error("expected a name"));
+            }
+            let global = self.de.peek_word(b"_G")?;
+            self.name()?;
+            if global {
+                self.head_trivia(&mut at, 0)?;
+                if self.de.read.peek()? == Some(b'[') {
+                    self.bracket_key(&mut at, 0)?;
+                }
+            }
+            self.assignment(&mut at, 0)?;
+            comments = self.trivia()?;
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<(), DeError> {
+        match self.de.read.peek()? {
+            Some(b'{') => self.table(depth),
+            Some(_) => self.literal(),
+            None => Err(self.de.eof()),
+        }
+    }
+
+    fn table(&mut self, depth: usize) -> Result<(), DeError> {
+        self.de.enter_table()?;
+        self.out.push('{');
+        let mut comments = self.trivia()?;
+        let mut empty = true;
+        loop {
+            match self.de.read.peek()? {
+                Some(b'}') => break,
+                Some(_) => {}
+                None => return Err(self.de.eof()),
+            }
+            self.comments(comments, depth + 1);
+            empty = false;
+            self.newline(depth + 1);
+            self.entry(depth + 1)?;
+
+            comments = self.trivia()?;
+            let separated = matches!(self.de.read.peek()?, Some(b',' | b';'));
+            if separated {
+                self.de.read.discard();
+                comments.extend(self.trivia()?);
+            }
+            match self.de.read.peek()? {
+                Some(b'}') => {}
+                Some(_) if separated => self.out.push(','),
+                Some(_) => return Err(self.de.error("expected `,` or `}`")),
+                None => return Err(self.de.eof()),
+            }
+        }
+        if !empty || !comments.is_empty() {
+            self.comments(comments, depth + 1);
+            self.newline(depth);
+        }
+        self.de.end_table()?;
+        self.out.push('}');
+        Ok(())
+    }
+
+    fn entry(&mut self, depth: usize) -> Result<(), DeError> {
+        let mut at = self.out.len();
+        match self.de.read.peek()? {
+            // `[[` and `[=` start long strings.
+            Some(b'[') if !matches!(self.de.read.peek_at(1)?, Some(b'[' | b'=')) => {
+                let open = self.out.len();
+                self.bracket_key(&mut at, depth)?;
+                if self.compact {
+                    self.bare_key(open);
+                }
+            }
+            Some(b) if is_identifier_start(b) && !self.de.peek_keyword_value()? => self.name()?,
+            _ => return self.value(depth),
+        }
+        self.assignment(&mut at, depth)
+    }
+
+    /// Copies a key in brackets like `["name"]`.
+    fn bracket_key(&mut self, at: &mut usize, depth: usize) -> Result<(), DeError> {
+        self.de.read.discard();
+        self.out.push('[');
+        self.head_trivia(at, depth)?;
+        match self.de.read.peek()? {
+            Some(b'{') => return Err(self.de.error("expected a key")),
+            Some(_) if self.de.peek_word(b"nil")? => return Err(self.de.error("expected a key")),
+            _ => self.literal()?,
+        }
+        self.head_trivia(at, depth)?;
+        self.de.expect(b']')?;
+        self.out.push(']');
+        Ok(())
+    }
+
+    /// Copies the `= value` after a key.
+    fn assignment(&mut self, at: &mut usize, depth: usize) -> Result<(), DeError> {
+        self.head_trivia(at, depth)?;
+        self.de.expect(b'=')?;
+        self.space();
+        self.out.push('=');
+        self.space();
+        self.head_trivia(at, depth)?;
+        self.value(depth)
+    }
+
+    fn name(&mut self) -> Result<(), DeError> {
+        let start = self.de.read.offset();
+        self.de.parse_identifier()?;
+        self.copy_from(start);
+        Ok(())
+    }
+
+    fn literal(&mut self) -> Result<(), DeError> {
+        let start = self.de.read.offset();
+        match self.de.read.peek()? {
+            Some(b) if self.de.peek_string(b)? => {
+                self.de.parse_string()?;
+            }
+            Some(b) if self.de.peek_number(b)? => {
+                self.de.parse_number()?;
+            }
+            Some(_)
+                if self.de.peek_word(b"true")?
+                    || self.de.peek_word(b"false")?
+                    || self.de.peek_word(b"nil")? =>
+            {
+                self.de.parse_identifier()?;
+            }
+            Some(_) => return Err(self.de.invalid_value()),
+            None => return Err(self.de.eof()),
+        }
+        self.copy_from(start);
+        Ok(())
+    }
+
+    /// Skips whitespace and returns the comments in it.
+    fn trivia(&mut self) -> Result<Vec<Comment<'a>>, DeError> {
+        let mut comments = Vec::new();
+        let mut trailing = true;
+        loop {
+            match self.de.read.peek()? {
+                Some(b'\n') => {
+                    self.de.read.discard();
+                    trailing = false;
+                }
+                Some(b' ' | b'\t' | b'\r' | b'\x0b' | b'\x0c') => self.de.read.discard(),
+                Some(b'-') if self.de.read.peek_at(1)? == Some(b'-') => {
+                    let start = self.de.read.offset();
+                    self.de.skip_comment()?;
+                    let text = &self.input[start..self.de.read.offset()];
+                    if !self.compact {
+                        comments.push(Comment {
+                            text: text.trim_end(),
+                            trailing,
+                        });
+                    }
+                    // Line comments end with the newline.
+                    if text.ends_with('\n') {
+                        trailing = false;
+                    }
+                }
+                _ => return Ok(comments),
+            }
+        }
+    }
+
+    /// Skips whitespace inside an entry and moves its comments to the lines before the entry,
+    /// which starts at `at` in the output.
+    fn head_trivia(&mut self, at: &mut usize, depth: usize) -> Result<(), DeError> {
+        for comment in self.trivia()? {
+            let line = format!("{}\n{}", comment.text, "  ".repeat(depth));
+            self.out.insert_str(*at, &line);
+            *at += line.len();
+        }
+        Ok(())
+    }
+
+    /// Writes the comments between two entries of a table, the ones on the line of the entry
+    /// before them stay on that line.
+    fn comments(&mut self, comments: Vec<Comment<'_>>, depth: usize) {
+        let (trailing, own_lines) = split_trailing(comments);
+        self.trailing(trailing);
+        for comment in own_lines {
+            self.newline(depth);
+            self.out.push_str(comment.text);
+        }
+    }
+
+    fn trailing(&mut self, comments: Vec<Comment<'_>>) {
+        for comment in comments {
+            self.out.push(' ');
+            self.out.push_str(comment.text);
+        }
+    }
+
+    /// Writes the comments at the end of the input.
+    fn end(&mut self, comments: Vec<Comment<'_>>) -> Result<(), DeError> {
+        let (trailing, own_lines) = split_trailing(comments);
+        self.trailing(trailing);
+        for comment in own_lines {
+            self.out.push('\n');
+            self.out.push_str(comment.text);
+        }
+        self.line_break();
+        self.de.end()
+    }
+
+    fn newline(&mut self, depth: usize) {
+        if self.compact {
+            return;
+        }
+        self.out.push('\n');
+        for _ in 0..depth {
+            self.out.push_str("  ");
+        }
+    }
+
+    fn line_break(&mut self) {
+        if !self.compact {
+            self.out.push('\n');
+        }
+    }
+
+    fn space(&mut self) {
+        if !self.compact {
+            self.out.push(' ');
+        }
+    }
+
+    /// Writes a token, with a space before it if it would run into the token before it.
+    fn token(&mut self, token: &str) {
+        let joins = |b: &u8| is_identifier_continue(*b) || *b == b'.';
+        let last = self.out.as_bytes().last();
+        if last.is_some_and(joins) && token.as_bytes().first().is_some_and(joins)
+            || last == Some(&b'[') && token.starts_with('[')
+        {
+            self.out.push(' ');
+        }
+        self.out.push_str(token);
+    }
+
+    fn copy_from(&mut self, start: usize) {
+        let input = self.input;
+        self.token(&input[start..self.de.read.offset()]);
+    }
+
+    /// Replaces the key in brackets written at `open` with a bare name if it's a plain string
+    /// that is an identifier.
+    fn bare_key(&mut self, open: usize) {
+        let key = self.out[open + 1..self.out.len() - 1].trim_start();
+        let name = [b'"', b'\'']
+            .iter()
+            .find_map(|&quote| key.strip_prefix(quote as char)?.strip_suffix(quote as char))
+            // Lua 5.4 reserves the most words.
+            .filter(|name| LuaVersion::Lua54.is_identifier(name))
+            .map(str::to_owned);
+        if let Some(name) = name {
+            self.out.truncate(open);
+            self.token(&name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_str, minify_str, normalize_str};
+
+    #[test]
+    fn keeps_comments_and_literals() {
+        let lua = "--[[ header ]]\n{ [ 'a' ]=1.50, -- one\n  -- before two\n  b = --[[ moved ]] [==[x]==] ; {}, { -- empty\n } }";
+        assert_eq!(
+            format_str(lua).unwrap(),
+            "--[[ header ]]\n{\n  ['a'] = 1.50, -- one\n  -- before two\n  --[[ moved ]]\n  b = [==[x]==],\n  {},\n  { -- empty\n  }\n}\n"
+        );
+    }
+
+    #[test]
+    fn formats_globals_and_keeps_the_result_readable() {
+        let lua = "local A={1,2};_G [\"two words\"]= true B = 'x' -- last\n-- end";
+        let formatted = format_str(lua).unwrap();
+        assert_eq!(
+            formatted,
+            "local A = {\n  1,\n  2\n}\n_G[\"two words\"] = true\nB = 'x' -- last\n-- end\n"
+        );
+        assert_eq!(format_str(&formatted).unwrap(), formatted);
+        let (_, value): (_, crate::Value) = crate::from_str_chunk(&formatted).unwrap();
+        let (_, original): (_, crate::Value) = crate::from_str_chunk(lua).unwrap();
+        assert_eq!(value, original);
+    }
+
+    #[test]
+    fn minifies_without_joining_tokens() {
+        let lua = "-- header\nlocal A = { [ [[long]] ] = 1., [\"end\"] = 0x1, ['b c'] = -1, x = .5e1 }\nB = true ; C = nil\n";
+        let minified = minify_str(lua).unwrap();
+        assert_eq!(
+            minified,
+            "local A={[ [[long]]]=1.,[\"end\"]=0x1,['b c']=-1,x=.5e1}B=true C=nil"
+        );
+        let (_, value): (_, crate::Value) = crate::from_str_chunk(&minified).unwrap();
+        let (_, original): (_, crate::Value) = crate::from_str_chunk(lua).unwrap();
+        assert_eq!(value, original);
+        assert_eq!(minify_str("return { 1 }").unwrap(), "return{1}");
+    }
+
+    #[test]
+    fn normalizes_globals_and_nested_tables() {
+        let a = "-- saved\nZ = { y = '\\65', x = { 1, 2, } }\nA = 1e2\n";
+        let b = "A = 100.0; Z = { x = { [2] = 2, [1] = 1 }, y = [[A]] }";
+        assert_eq!(normalize_str(a).unwrap(), normalize_str(b).unwrap());
+        assert_eq!(
+            normalize_str(a).unwrap(),
+            "A = 100.0\nZ = {x={1,2},y=\"A\"}\n"
+        );
+    }
+
+    #[test]
+    fn normalizes_its_own_output_to_itself() {
+        for lua in [
+            "{ a = '\\1\\31\\127', b = 'é\\u{2603}', [1] = '\\0' }",
+            "return { \"\\r\\n\\t\", 'ü' }",
+            "X = '\\8'",
+        ] {
+            let normalized = normalize_str(lua).unwrap();
+            let read = |s| crate::from_str_chunk::<crate::Value>(s).unwrap();
+            assert_eq!(read(&normalized), read(lua), "{}", lua);
+            assert_eq!(normalize_str(&normalized).unwrap(), normalized, "{}", lua);
+        }
+        assert_eq!(
+            normalize_str("{ a = '\\1' }").unwrap(),
+            "{[\"a\"]=\"\\001\"}"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        assert!(format_str("{ 1 2 }").is_err());
+        assert!(format_str("{ [nil] = 1 }").is_err());
+        assert!(format_str("return {} x").is_err());
+        assert!(format_str("-- only a comment").is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use super::{DeError, Deserializer, Read};
+use serde::Deserialize;
+use std::marker::PhantomData;
+
+/// An iterator over the values of an input that contains several of them, separated by
+/// whitespace or comments, e.g. a log with one table per line.
+///
+/// Iteration stops after the first error.
+///
+/// ```
+/// use serde::Deserialize;
+/// use serde_lua_table::Deserializer;
+///
+/// #[derive(Deserialize)]
+/// struct Entry<'a> {
+///     level: &'a str,
+/// }
+///
+/// let log = "{ level = 'info' }\n{ level = 'warn' } -- retried\n{ level = 'info' }\n";
+/// let levels = Deserializer::from_str(log)
+///     .into_iter::<Entry>()
+///     .map(|entry| entry.unwrap().level)
+///     .collect::<Vec<_>>();
+/// assert_eq!(levels, ["info", "warn", "info"]);
+/// ```
+pub struct StreamDeserializer<'de, R, T> {
+    de: Deserializer<R>,
+    failed: bool,
+    output: PhantomData<T>,
+    lifetime: PhantomData<&'de ()>,
+}
+
+impl<'de, R, T> StreamDeserializer<'de, R, T>
+where
+    R: Read<'de>,
+    T: Deserialize<'de>,
+{
+    pub fn new(read: R) -> Self {
+        Self::from_deserializer(Deserializer::new(read))
+    }
+
+    pub(super) fn from_deserializer(de: Deserializer<R>) -> Self {
+        StreamDeserializer {
+            de,
+            failed: false,
+            output: PhantomData,
+            lifetime: PhantomData,
+        }
+    }
+
+    /// Returns the number of bytes consumed so far, i.e. the offset right after the last value.
+    pub fn byte_offset(&self) -> usize {
+        self.de.read.offset()
+    }
+}
+
+impl<'de, R, T> Iterator for StreamDeserializer<'de, R, T>
+where
+    R: Read<'de>,
+    T: Deserialize<'de>,
+{
+    type Item = Result<T, DeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let result = match self.de.parse_whitespace() {
+            Ok(None) => return None,
+            Ok(Some(_)) => T::deserialize(&mut self.de),
+            Err(e) => Err(e),
+        }
+        .map_err(|e| e.at(self.de.read.position()));
+        self.failed = result.is_err();
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Deserializer, IoRead, SliceRead, StreamDeserializer};
+
+    #[test]
+    fn reads_values_one_after_another() {
+        let log = "1 --[[ two ]] {2}\n-- three\n3 ";
+        let mut stream = Deserializer::from_reader(log.as_bytes()).into_iter::<crate::Value>();
+        let mut offsets = Vec::new();
+        while let Some(value) = stream.next() {
+            value.unwrap();
+            offsets.push(stream.byte_offset());
+        }
+        assert_eq!(offsets, [1, 17, 28]);
+
+        let mut empty = StreamDeserializer::<_, u8>::new(SliceRead::new(b" -- nothing"));
+        assert!(empty.next().is_none());
+    }
+
+    #[test]
+    fn stops_after_the_first_error() {
+        let stream = StreamDeserializer::<_, u8>::new(IoRead::new(&b"1 300 3"[..]));
+        let results: Vec<_> = stream
+            .map(|value| value.map_err(|e| e.to_string()))
+            .collect();
+        assert_eq!(
+            results,
+            [
+                Ok(1),
+                Err("invalid value: integer `300`, expected u8 at line 1, column 6".to_owned())
+            ]
+        );
+    }
+}
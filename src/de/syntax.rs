@@ -0,0 +1,187 @@
+use super::{
+    is_identifier_start,
+    key::{Key, OwnedKey},
+    table::Entry,
+    Chunk, DeError, Deserializer, Number, Position, Read,
+};
+use std::ops::Range;
+
+/// A value in the input with the byte range it was written in, for tools that point at the
+/// source instead of deserializing it.
+pub(super) struct Node {
+    pub(super) span: Range<usize>,
+    pub(super) kind: NodeKind,
+}
+
+pub(super) enum NodeKind {
+    Nil,
+    Boolean,
+    Number(Number),
+    String,
+    Table(Vec<Field>),
+}
+
+/// An entry of a table.
+pub(super) struct Field {
+    pub(super) key: FieldKey,
+    pub(super) value: Node,
+}
+
+pub(super) enum FieldKey {
+    /// An entry without a key, with the index lua gives it.
+    Positional(i64),
+    /// An entry with a key like `name = 1` or `["name"] = 1`, with the range of the key.
+    Keyed { span: Range<usize>, key: OwnedKey },
+}
+
+impl FieldKey {
+    /// Returns the key the entry ends up with in lua.
+    pub(super) fn key(&self) -> OwnedKey {
+        match self {
+            FieldKey::Positional(i) => OwnedKey::Integer(*i),
+            FieldKey::Keyed { key, .. } => key.clone(),
+        }
+    }
+}
+
+impl<'de, R> Deserializer<R>
+where
+    R: Read<'de>,
+{
+    /// Parses a whole input like [`from_str_chunk`](super::from_str_chunk) does. The variables
+    /// of a chunk of assignments are the entries of a table spanning the whole chunk.
+    pub(super) fn parse_syntax(&mut self) -> Result<(Chunk, Node), DeError> {
+        let parsed = self.parse_syntax_chunk();
+        parsed.map_err(|e| e.at(self.read.position()))
+    }
+
+    fn parse_syntax_chunk(&mut self) -> Result<(Chunk, Node), DeError> {
+        let (chunk, node) = match self.parse_whitespace()? {
+            Some(_) if self.peek_word(b"return")? => {
+                self.parse_identifier()?;
+                let node = self.parse_node()?;
+                if self.parse_whitespace()? == Some(b';') {
+                    self.read.discard();
+                }
+                (Chunk::Return, node)
+            }
+            Some(b) if is_identifier_start(b) && !self.peek_keyword_value()? => {
+                let start = self.read.offset();
+                let mut fields = Vec::new();
+                let mut buf = Vec::new();
+                loop {
+                    self.parse_whitespace()?;
+                    let key_start = self.read.offset();
+                    let Some(key) = self.parse_global(&mut buf)? else {
+                        break;
+                    };
+                    let key = FieldKey::Keyed {
+                        span: key_start..self.read.offset(),
+                        key: key.to_owned(&buf),
+                    };
+                    let value = self.parse_node()?;
+                    fields.push(Field { key, value });
+                }
+                let span = start..self.read.offset();
+                let kind = NodeKind::Table(fields);
+                (Chunk::Globals, Node { span, kind })
+            }
+            _ => (Chunk::Value, self.parse_node()?),
+        };
+        self.end()?;
+        Ok((chunk, node))
+    }
+
+    fn parse_node(&mut self) -> Result<Node, DeError> {
+        let next = self.parse_whitespace()?;
+        let start = self.read.offset();
+        let kind = match next {
+            Some(b'{') => {
+                self.enter_table()?;
+                NodeKind::Table(self.parse_fields()?)
+            }
+            Some(b) if self.peek_string(b)? => {
+                self.parse_string()?;
+                NodeKind::String
+            }
+            Some(b) if self.peek_number(b)? => NodeKind::Number(self.parse_number()?),
+            Some(_) if self.peek_word(b"true")? || self.peek_word(b"false")? => {
+                self.parse_identifier()?;
+                NodeKind::Boolean
+            }
+            Some(_) if self.peek_word(b"nil")? => {
+                self.parse_identifier()?;
+                NodeKind::Nil
+            }
+            Some(_) => return Err(self.invalid_value()),
+            None => return Err(self.eof()),
+        };
+        let span = start..self.read.offset();
+        Ok(Node { span, kind })
+    }
+
+    /// Parses the entries of a table that was just opened, and its end.
+    fn parse_fields(&mut self) -> Result<Vec<Field>, DeError> {
+        let mut fields = Vec::new();
+        let mut index = 0;
+        let mut buf = Vec::new();
+        loop {
+            let entry = self.table_entry(fields.is_empty())?;
+            let start = self.read.offset();
+            let key = match entry {
+                Entry::End => break,
+                Entry::Key if self.read.peek()? == Some(b'[') => {
+                    self.read.discard();
+                    self.parse_key(&mut buf)?
+                }
+                Entry::Key => Key::string(self.parse_identifier()?, &mut buf),
+                Entry::Positional => {
+                    index += 1;
+                    fields.push(Field {
+                        key: FieldKey::Positional(index),
+                        value: self.parse_node()?,
+                    });
+                    continue;
+                }
+            };
+            let key = FieldKey::Keyed {
+                span: start..self.read.offset(),
+                key: key.to_owned(&buf),
+            };
+            self.expect(b'=')?;
+            fields.push(Field {
+                key,
+                value: self.parse_node()?,
+            });
+        }
+        self.end_table()?;
+        Ok(fields)
+    }
+}
+
+/// The offsets the lines of an input start at, to turn offsets into positions.
+pub(super) struct LineIndex {
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub(super) fn new(input: &[u8]) -> Self {
+        let lines = input
+            .iter()
+            .enumerate()
+            .filter(|&(_, &b)| b == b'\n')
+            .map(|(i, _)| i + 1);
+        LineIndex {
+            starts: std::iter::once(0).chain(lines).collect(),
+        }
+    }
+
+    pub(super) fn position(&self, offset: usize) -> Position {
+        let line = self.starts.partition_point(|&start| start <= offset);
+        Position {
+            offset,
+            line,
+            column: offset - self.starts[line - 1] + 1,
+        }
+    }
+}
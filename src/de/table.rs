@@ -0,0 +1,551 @@
+use super::{
+    is_identifier_start,
+    key::{Key, KeyDeserializer, OwnedKey},
+    DeError, Deserializer, DuplicateKeys, Read,
+};
+use crate::{KeyCase, PathSegment};
+use serde::{
+    de::{self, DeserializeSeed, IgnoredAny},
+    Deserialize,
+};
+use std::{
+    collections::{BTreeMap, HashSet},
+    mem,
+};
+
+/// The memory an entry with a key takes in a [`Table`](crate::value::Table).
+pub(super) const ENTRY_SIZE: usize = mem::size_of::<(crate::value::Key, crate::Value)>();
+
+/// The kind of the next entry of a table.
+pub(super) enum Entry {
+    /// The table is closed.
+    End,
+    /// An entry with a key like `name = 1` or `["name"] = 1`.
+    Key,
+    /// An entry without a key like `1`.
+    Positional,
+}
+
+impl<'de, R> Deserializer<R>
+where
+    R: Read<'de>,
+{
+    /// Skips the separator in front of the next entry and returns what kind of entry it is,
+    /// without consuming it.
+    pub(super) fn table_entry(&mut self, first: bool) -> Result<Entry, DeError> {
+        let mut next = self.parse_whitespace()?;
+        if !first {
+            match next {
+                Some(b',' | b';') => {
+                    self.read.discard();
+                    next = self.parse_whitespace()?;
+                }
+                Some(b'}') => {}
+                Some(_) => return Err(self.error("expected `,` or `}`")),
+                None => return Err(self.eof()),
+            }
+        }
+        match next {
+            Some(b'}') => Ok(Entry::End),
+            // `[[` and `[=` start long strings.
+            Some(b'[') if !matches!(self.read.peek_at(1)?, Some(b'[' | b'=')) => Ok(Entry::Key),
+            Some(b) if is_identifier_start(b) && !self.peek_keyword_value()? => Ok(Entry::Key),
+            Some(_) => Ok(Entry::Positional),
+            None => Err(self.eof()),
+        }
+    }
+}
+
+impl<'de, R> Deserializer<R>
+where
+    R: Read<'de>,
+{
+    /// Looks ahead for an entry with a key in the table that was just opened, without consuming
+    /// anything. Strings, comments and nested tables are skipped.
+    ///
+    /// This has to buffer the rest of the table when reading from an [`IoRead`](super::IoRead).
+    pub(super) fn table_has_keys(&mut self) -> Result<bool, DeError> {
+        let mut at = 0;
+        let mut depth = 0usize;
+        while let Some(b) = self.read.peek_at(at)? {
+            match b {
+                b'{' => depth += 1,
+                b'}' if depth == 0 => return Ok(false),
+                b'}' => depth -= 1,
+                b'=' if depth == 0 => return Ok(true),
+                b'"' | b'\'' => {
+                    at += 1;
+                    while let Some(c) = self.read.peek_at(at)? {
+                        match c {
+                            b'\\' => at += 1,
+                            c if c == b => break,
+                            _ => {}
+                        }
+                        at += 1;
+                    }
+                }
+                b'[' => match self.peek_long_bracket_at(at)? {
+                    Some(level) => at = self.skip_long_bracket_at(at, level)?,
+                    None if depth == 0 => return Ok(true),
+                    None => {}
+                },
+                b'-' if self.read.peek_at(at + 1)? == Some(b'-') => {
+                    at += 2;
+                    match self.peek_long_bracket_at(at)? {
+                        Some(level) => at = self.skip_long_bracket_at(at, level)?,
+                        None => {
+                            while !matches!(self.read.peek_at(at)?, Some(b'\n') | None) {
+                                at += 1;
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+            at += 1;
+        }
+        Ok(false)
+    }
+
+    /// Returns the offset of the last byte of the long bracket of `level` that opens at `at`.
+    fn skip_long_bracket_at(&mut self, mut at: usize, level: usize) -> Result<usize, DeError> {
+        at += level + 2;
+        while let Some(b) = self.read.peek_at(at)? {
+            if b == b']' && self.closes_long_bracket(at, level)? {
+                return Ok(at + level + 1);
+            }
+            at += 1;
+        }
+        Ok(at)
+    }
+}
+
+/// Reads the entries of a table as a sequence. Besides entries without keys, explicit indices
+/// like `{[1] = "a", [2] = "b"}` are accepted in any order, as long as they leave no holes.
+pub(super) struct SeqAccess<'a, R> {
+    de: &'a mut Deserializer<R>,
+    elements: Elements,
+}
+
+impl<'a, R> SeqAccess<'a, R> {
+    pub(super) fn new(de: &'a mut Deserializer<R>) -> Self {
+        SeqAccess {
+            de,
+            elements: Elements::default(),
+        }
+    }
+}
+
+impl<'de, R> de::SeqAccess<'de> for SeqAccess<'_, R>
+where
+    R: Read<'de>,
+{
+    type Error = DeError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, DeError>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.elements.next(self.de, seed)
+    }
+}
+
+/// The state of reading the elements of a sequence, after its opening brace.
+pub(super) struct Elements {
+    first: bool,
+    /// Number of entries read so far.
+    len: i64,
+    /// Number of entries without a key read so far.
+    positional: i64,
+    /// The entries with explicit indices that were read before the entries in front of them.
+    /// They are parsed into values, so their strings can't be borrowed.
+    ahead: BTreeMap<i64, crate::Value>,
+}
+
+impl Default for Elements {
+    fn default() -> Self {
+        Elements {
+            first: true,
+            len: 0,
+            positional: 0,
+            ahead: BTreeMap::new(),
+        }
+    }
+}
+
+impl Elements {
+    /// Reads the next element, or returns `None` at the closing brace without consuming it.
+    pub(super) fn next<'de, R, T>(
+        &mut self,
+        de: &mut Deserializer<R>,
+        seed: T,
+    ) -> Result<Option<T::Value>, DeError>
+    where
+        R: Read<'de>,
+        T: DeserializeSeed<'de>,
+    {
+        loop {
+            if let Some(value) = self.ahead.remove(&(self.len + 1)) {
+                self.len += 1;
+                let len = self.len;
+                return seed
+                    .deserialize(value)
+                    .map(Some)
+                    .map_err(|e| e.in_segment(|| PathSegment::Index(len)));
+            }
+            let entry = de.table_entry(self.first)?;
+            self.first = false;
+            match entry {
+                Entry::End if self.ahead.is_empty() => return Ok(None),
+                Entry::End => return Err(de.error(format!("expected the key [{}]", self.len + 1))),
+                Entry::Key if de.read.peek()? == Some(b'[') => {
+                    de.read.discard();
+                    match de.parse_key(&mut Vec::new())? {
+                        Key::Integer(i) if i == self.len + 1 => de.expect(b'=')?,
+                        Key::Integer(i) if i > self.len + 1 => {
+                            de.expect(b'=')?;
+                            de.use_memory(mem::size_of::<crate::Value>())
+                                .map_err(|e| e.in_segment(|| PathSegment::Index(i)))?;
+                            let value = crate::Value::deserialize(&mut *de).map_err(|e| {
+                                e.at(de.read.position())
+                                    .in_segment(|| PathSegment::Index(i))
+                            })?;
+                            self.ahead.insert(i, value);
+                            continue;
+                        }
+                        _ => return Err(de.error(format!("expected the key [{}]", self.len + 1))),
+                    }
+                }
+                Entry::Key => return Err(de.error("expected a value without a key")),
+                // In lua, entries without a key are numbered on their own and would replace the
+                // entries with explicit indices.
+                Entry::Positional if self.positional != self.len => {
+                    return Err(de.error("expected an explicit index"))
+                }
+                Entry::Positional => self.positional += 1,
+            }
+            break;
+        }
+        self.len += 1;
+        let len = self.len;
+        de.use_memory(mem::size_of::<crate::Value>())
+            .map_err(|e| e.in_segment(|| PathSegment::Index(len)))?;
+        seed.deserialize(&mut *de).map(Some).map_err(|e| {
+            e.at(de.read.position())
+                .in_segment(|| PathSegment::Index(len))
+        })
+    }
+}
+
+/// Reads the entries of a table with their keys. Entries without a key get the keys `1`, `2`, …
+pub(super) struct MapAccess<'a, 'de, R> {
+    de: &'a mut Deserializer<R>,
+    first: bool,
+    /// The key of the last entry without a key.
+    index: i64,
+    /// The key of the current entry.
+    key: Key<'de>,
+    /// The current key, if it is a string that couldn't be borrowed.
+    key_buf: Vec<u8>,
+    /// The keys read so far, unless duplicates are passed on.
+    seen: HashSet<OwnedKey>,
+    key_case: KeyCase,
+    /// Whether the table is read into a struct. Fields are named by strings, so integer keys are
+    /// passed as their digits and derived structs ignore entries without a key like other
+    /// unknown fields.
+    fields: bool,
+}
+
+impl<'a, R> MapAccess<'a, '_, R> {
+    pub(super) fn new(de: &'a mut Deserializer<R>) -> Self {
+        MapAccess {
+            key_case: de.options.key_case(),
+            de,
+            first: true,
+            index: 0,
+            key: Key::Integer(0),
+            key_buf: Vec::new(),
+            seen: HashSet::new(),
+            fields: false,
+        }
+    }
+
+    /// Reads a table holding the fields of a struct.
+    pub(super) fn fields(de: &'a mut Deserializer<R>) -> Self {
+        MapAccess {
+            fields: true,
+            ..MapAccess::new(de)
+        }
+    }
+
+    /// Reads a table holding an enum variant, whose name isn't renamed.
+    pub(super) fn variant(de: &'a mut Deserializer<R>) -> Self {
+        MapAccess {
+            key_case: KeyCase::Unchanged,
+            ..MapAccess::new(de)
+        }
+    }
+}
+
+impl<'de, R> MapAccess<'_, 'de, R>
+where
+    R: Read<'de>,
+{
+    /// Adds the position and the current key to an error.
+    fn locate(&self, error: DeError) -> DeError {
+        error
+            .at(self.de.read.position())
+            .in_segment(|| self.key.segment(&self.key_buf))
+    }
+}
+
+impl<'de, R> de::MapAccess<'de> for MapAccess<'_, 'de, R>
+where
+    R: Read<'de>,
+{
+    type Error = DeError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, DeError>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        loop {
+            let entry = self.de.table_entry(self.first)?;
+            self.first = false;
+            self.key = match entry {
+                Entry::End => return Ok(None),
+                Entry::Key if self.de.read.peek()? == Some(b'[') => {
+                    self.de.read.discard();
+                    let key = self.de.parse_key(&mut self.key_buf)?;
+                    self.de.expect(b'=')?;
+                    key
+                }
+                Entry::Key => {
+                    let key = Key::string(self.de.parse_identifier()?, &mut self.key_buf);
+                    self.de.expect(b'=')?;
+                    key
+                }
+                Entry::Positional => {
+                    self.index += 1;
+                    Key::Integer(self.index)
+                }
+            };
+            self.key = self.key.with_case(self.key_case, &mut self.key_buf);
+            self.de.use_memory(ENTRY_SIZE).map_err(|e| self.locate(e))?;
+            let policy = self.de.options.duplicate_keys();
+            if policy == DuplicateKeys::LastWins
+                || self.seen.insert(self.key.to_owned(&self.key_buf))
+            {
+                break;
+            }
+            if policy == DuplicateKeys::Error {
+                return Err(self.locate(DeError::Syntax("duplicate key".to_owned())));
+            }
+            IgnoredAny::deserialize(&mut *self.de).map_err(|e| self.locate(e))?;
+        }
+        let key = match self.key {
+            Key::Integer(i) if self.fields => {
+                self.key_buf.clear();
+                self.key_buf.extend_from_slice(i.to_string().as_bytes());
+                Key::Copied
+            }
+            key => key,
+        };
+        let key = KeyDeserializer {
+            key,
+            buf: &self.key_buf,
+        };
+        seed.deserialize(key).map(Some).map_err(|e| self.locate(e))
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, DeError>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        seed.deserialize(&mut *self.de).map_err(|e| self.locate(e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_reader, from_str, Value};
+    use serde::Deserialize;
+
+    #[test]
+    fn reads_explicit_indices_as_sequence() {
+        let expected = vec![1, 2, 3];
+        for lua in [
+            "{1, 2, 3}",
+            "{[1] = 1, [2] = 2, [3] = 3}",
+            "{[1]=1,[2]=2,[3]=3,}",
+            "{1, [2] = 2, [3.0] = 3}",
+            "{[0x1] = 1; [2] = 2; [3] = 3}",
+        ] {
+            assert_eq!(from_str::<Vec<i32>>(lua).unwrap(), expected, "{}", lua);
+            assert_eq!(
+                from_reader::<_, Vec<i32>>(lua.as_bytes()).unwrap(),
+                expected,
+                "{}",
+                lua
+            );
+        }
+    }
+
+    #[test]
+    fn reads_explicit_indices_in_any_order() {
+        for (lua, expected) in [
+            ("{[2]='b',[1]='a'}", vec!["a", "b"]),
+            ("{[3] = 'c', 'a', 'b'}", vec!["a", "b", "c"]),
+            (
+                "{[4] = 'd', [2] = 'b', [1] = 'a', [3] = 'c'}",
+                vec!["a", "b", "c", "d"],
+            ),
+        ] {
+            let value: Value = from_str(lua).unwrap();
+            assert_eq!(Vec::<String>::deserialize(&value).unwrap(), expected);
+            assert_eq!(from_str::<Vec<String>>(lua).unwrap(), expected, "{}", lua);
+            assert_eq!(
+                from_reader::<_, Vec<String>>(lua.as_bytes()).unwrap(),
+                expected,
+                "{}",
+                lua
+            );
+        }
+        assert_eq!(from_str::<Vec<i32>>("{[2]=1,[1]=2}").unwrap(), [2, 1]);
+        assert_eq!(
+            from_str::<Vec<Vec<i32>>>("{[2] = {3}, {1, [2] = 2}}").unwrap(),
+            [vec![1, 2], vec![3]]
+        );
+    }
+
+    #[test]
+    fn skips_entries_without_a_key_in_structs() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Item {
+            a: i32,
+            b: String,
+        }
+
+        #[derive(Deserialize, Debug)]
+        #[serde(deny_unknown_fields)]
+        #[allow(dead_code)]
+        struct Strict {
+            a: i32,
+        }
+
+        let lua = "{a=1,b='x',5}";
+        let expected = Item {
+            a: 1,
+            b: "x".to_owned(),
+        };
+        assert_eq!(from_str::<Item>(lua).unwrap(), expected);
+        assert_eq!(from_reader::<_, Item>(lua.as_bytes()).unwrap(), expected);
+        let value: Value = from_str(lua).unwrap();
+        assert_eq!(Item::deserialize(&value).unwrap(), expected);
+        assert_eq!(Item::deserialize(value).unwrap(), expected);
+
+        assert_eq!(
+            from_str::<Strict>("{a = 1, 'x'}").unwrap_err().to_string(),
+            "unknown field `1`, expected `a` at [1], line 1, column 9"
+        );
+    }
+
+    #[test]
+    fn rejects_indices_out_of_sequence() {
+        for (lua, message) in [
+            ("{[2] = 1}", "expected the key [1] at line 1, column 9"),
+            (
+                "{[1] = 1, [3] = 3}",
+                "expected the key [2] at line 1, column 18",
+            ),
+            (
+                r#"{[1] = 1, ["2"] = 2}"#,
+                "expected the key [2] at line 1, column 16",
+            ),
+            (
+                "{1, a = 2}",
+                "expected a value without a key at line 1, column 5",
+            ),
+            (
+                "{[1] = 1, 2}",
+                "expected an explicit index at line 1, column 11",
+            ),
+        ] {
+            let error = from_str::<Vec<i32>>(lua).unwrap_err();
+            assert_eq!(error.to_string(), message, "{}", lua);
+        }
+    }
+
+    #[test]
+    fn reports_element_paths() {
+        let error = from_str::<Vec<Vec<u8>>>("{{1}, {2, -1}}").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid value: integer `-1`, expected u8 at [2][2], line 1, column 13"
+        );
+    }
+
+    #[test]
+    fn renames_keys_but_not_variants() {
+        use crate::{DeOptions, Deserializer, KeyCase, SliceRead};
+        use serde::Deserialize;
+        use std::collections::BTreeMap;
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        enum Shape {
+            RoundedBox { corner_radius: u8 },
+        }
+
+        let lua = b"{ RoundedBox = { cornerRadius = 2 } }";
+        let options = DeOptions::new().with_key_case(KeyCase::SnakeCase);
+        let mut de = Deserializer::with_options(SliceRead::new(lua), options.clone());
+        assert_eq!(
+            Shape::deserialize(&mut de).unwrap(),
+            Shape::RoundedBox { corner_radius: 2 }
+        );
+
+        let lua = br#"{ ["max-speed"] = 1, maxSpeed = 2, [3] = 3 }"#;
+        let mut de = Deserializer::with_options(SliceRead::new(lua), options);
+        let map = BTreeMap::<crate::value::Key, u8>::deserialize(&mut de).unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map[&"max_speed".into()], 2);
+    }
+
+    #[test]
+    fn applies_the_duplicate_policy_to_keys_written_differently() {
+        use crate::{DeOptions, Deserializer, DuplicateKeys, SliceRead, Value};
+        use serde::Deserialize;
+        use std::collections::BTreeMap;
+
+        let lua = b"{ 'first', a = { 1 }, [1] = 'second', ['a'] = { 2 }, [1.0] = 'third' }";
+        let read = |policy| {
+            let options = DeOptions::new().with_duplicate_keys(policy);
+            let mut de = Deserializer::with_options(SliceRead::new(lua), options);
+            BTreeMap::<crate::value::Key, Value>::deserialize(&mut de)
+        };
+        let first = read(DuplicateKeys::FirstWins).unwrap();
+        assert_eq!(first[&1.into()], Value::from("first"));
+        assert_eq!(
+            first[&"a".into()],
+            crate::from_str::<Value>("{ 1 }").unwrap()
+        );
+        let last = read(DuplicateKeys::LastWins).unwrap();
+        assert_eq!(last[&1.into()], Value::from("third"));
+        assert_eq!(
+            last[&"a".into()],
+            crate::from_str::<Value>("{ 2 }").unwrap()
+        );
+        assert_eq!(
+            read(DuplicateKeys::Error).unwrap_err().to_string(),
+            "duplicate key at [1], line 1, column 28"
+        );
+
+        #[derive(Deserialize)]
+        struct Config {
+            a: u8,
+        }
+        let options = DeOptions::new().with_duplicate_keys(DuplicateKeys::LastWins);
+        let mut de = Deserializer::with_options(SliceRead::new(b"{ a = 1, a = 2 }"), options);
+        assert!(Config::deserialize(&mut de).map(|config| config.a).is_err());
+    }
+}
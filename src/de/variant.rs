@@ -0,0 +1,321 @@
+use super::{table::MapAccess, DeError, Deserializer, Read};
+use serde::de::{
+    self, value::MapAccessDeserializer, DeserializeSeed, IgnoredAny, IntoDeserializer,
+    MapAccess as _, Unexpected, Visitor,
+};
+
+/// Reads a unit variant written as a string or as its index, like `"Unit"` or `0`.
+pub(super) struct UnitVariantAccess<'a, R> {
+    de: &'a mut Deserializer<R>,
+}
+
+impl<'a, R> UnitVariantAccess<'a, R> {
+    pub(super) fn new(de: &'a mut Deserializer<R>) -> Self {
+        UnitVariantAccess { de }
+    }
+}
+
+impl<'de, R> de::EnumAccess<'de> for UnitVariantAccess<'_, R>
+where
+    R: Read<'de>,
+{
+    type Error = DeError;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self), DeError>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(&mut *self.de)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, R> de::VariantAccess<'de> for UnitVariantAccess<'_, R>
+where
+    R: Read<'de>,
+{
+    type Error = DeError;
+
+    fn unit_variant(self) -> Result<(), DeError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, _seed: T) -> Result<T::Value, DeError>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        Err(de::Error::invalid_type(
+            Unexpected::UnitVariant,
+            &"newtype variant",
+        ))
+    }
+
+    fn tuple_variant<V>(self, _len: usize, _visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        Err(de::Error::invalid_type(
+            Unexpected::UnitVariant,
+            &"tuple variant",
+        ))
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        Err(de::Error::invalid_type(
+            Unexpected::UnitVariant,
+            &"struct variant",
+        ))
+    }
+}
+
+/// Reads a variant written as a table with a single key, like `{["Newtype"]=1}`, or with a tag
+/// if [`DeOptions::with_enum_tag`](super::DeOptions::with_enum_tag) is set. The opening brace
+/// has to be consumed already.
+pub(super) struct VariantAccess<'a, 'de, R> {
+    map: MapAccess<'a, 'de, R>,
+    tag: Option<&'static str>,
+    /// Whether the variant was named by the tag, so the other fields belong to it.
+    tagged: bool,
+}
+
+impl<'a, R> VariantAccess<'a, '_, R> {
+    pub(super) fn new(de: &'a mut Deserializer<R>) -> Self {
+        VariantAccess {
+            tag: de.options.enum_tag(),
+            map: MapAccess::variant(de),
+            tagged: false,
+        }
+    }
+}
+
+impl<'de, R> VariantAccess<'_, 'de, R>
+where
+    R: Read<'de>,
+{
+    /// Checks that the variant was the only entry of the table.
+    fn end<T>(mut self, value: T) -> Result<T, DeError> {
+        match self.map.next_key::<IgnoredAny>()? {
+            None => Ok(value),
+            Some(_) => Err(de::Error::invalid_length(2, &"a table with a single key")),
+        }
+    }
+}
+
+impl<'de, R> de::EnumAccess<'de> for VariantAccess<'_, 'de, R>
+where
+    R: Read<'de>,
+{
+    type Error = DeError;
+    type Variant = Self;
+
+    fn variant_seed<V>(mut self, seed: V) -> Result<(V::Value, Self), DeError>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let Some(tag) = self.tag else {
+            return match self.map.next_key_seed(seed)? {
+                Some(variant) => Ok((variant, self)),
+                None => Err(de::Error::invalid_length(0, &"a table with a single key")),
+            };
+        };
+        let key = match self.map.next_key::<String>()? {
+            Some(key) => key,
+            None => return Err(de::Error::invalid_length(0, &"a table with a single key")),
+        };
+        let name = if key == tag {
+            self.tagged = true;
+            self.map.next_value::<String>()?
+        } else {
+            key
+        };
+        let variant = seed.deserialize(name.into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, R> de::VariantAccess<'de> for VariantAccess<'_, 'de, R>
+where
+    R: Read<'de>,
+{
+    type Error = DeError;
+
+    fn unit_variant(mut self) -> Result<(), DeError> {
+        if !self.tagged {
+            self.map.next_value::<()>()?;
+        }
+        self.end(())
+    }
+
+    fn newtype_variant_seed<T>(mut self, seed: T) -> Result<T::Value, DeError>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.tagged {
+            return seed.deserialize(MapAccessDeserializer::new(self.map));
+        }
+        let value = self.map.next_value_seed(seed)?;
+        self.end(value)
+    }
+
+    fn tuple_variant<V>(mut self, _len: usize, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        if self.tagged {
+            return Err(de::Error::invalid_type(
+                Unexpected::Map,
+                &"a tuple variant in a table with a single key",
+            ));
+        }
+        let value = self.map.next_value_seed(Seq(visitor))?;
+        self.end(value)
+    }
+
+    fn struct_variant<V>(
+        mut self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        if self.tagged {
+            return visitor.visit_map(self.map);
+        }
+        let value = self.map.next_value_seed(Map(visitor))?;
+        self.end(value)
+    }
+}
+
+/// Reads the value of a tuple variant with a visitor.
+struct Seq<V>(V);
+
+impl<'de, V> DeserializeSeed<'de> for Seq<V>
+where
+    V: Visitor<'de>,
+{
+    type Value = V::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<V::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self.0)
+    }
+}
+
+/// Reads the value of a struct variant with a visitor.
+struct Map<V>(V);
+
+impl<'de, V> DeserializeSeed<'de> for Map<V>
+where
+    V: Visitor<'de>,
+{
+    type Value = V::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<V::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_str, DeOptions, Deserializer, SliceRead};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Event {
+        Start,
+        Stop,
+        Move { x: i32, y: i32 },
+        Rename(Name),
+        Pair(u8, u8),
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Name {
+        name: String,
+    }
+
+    fn tagged(lua: &str) -> Result<Event, crate::DeError> {
+        let options = DeOptions::new().with_enum_tag("kind");
+        Event::deserialize(&mut Deserializer::with_options(
+            SliceRead::new(lua.as_bytes()),
+            options,
+        ))
+    }
+
+    #[test]
+    fn reads_variants_as_the_serializer_writes_them() {
+        let events = vec![
+            Event::Start,
+            Event::Move { x: -1, y: 2 },
+            Event::Rename(Name {
+                name: "n".to_owned(),
+            }),
+            Event::Pair(3, 4),
+        ];
+        let lua = crate::to_string(&events).unwrap();
+        assert_eq!(from_str::<Vec<Event>>(&lua).unwrap(), events);
+        let lua = crate::to_string_pretty(&events).unwrap();
+        assert_eq!(from_str::<Vec<Event>>(&lua).unwrap(), events);
+
+        for (lua, message) in [
+            (
+                "{ Pair = 1 }",
+                "invalid type: integer `1`, expected tuple variant Event::Pair",
+            ),
+            ("{ Jump = 1 }", "unknown variant `Jump`"),
+            ("{}", "invalid length 0, expected a table with a single key"),
+        ] {
+            let error = from_str::<Event>(lua).unwrap_err().to_string();
+            assert!(error.starts_with(message), "{}: {}", lua, error);
+        }
+    }
+
+    #[test]
+    fn reads_unit_variants_as_strings_or_indices() {
+        assert_eq!(from_str::<Event>("'Stop'").unwrap(), Event::Stop);
+        assert_eq!(from_str::<Event>("1").unwrap(), Event::Stop);
+        assert_eq!(
+            from_str::<Vec<Event>>("{0, 'Start'}").unwrap(),
+            [Event::Start, Event::Start]
+        );
+        assert!(from_str::<Event>("9").is_err());
+        assert!(from_str::<Event>("{ Move = { x = 1, y = 2 }, Stop = true }").is_err());
+    }
+
+    #[test]
+    fn reads_tagged_variants() {
+        assert_eq!(
+            tagged("{ kind = 'Move', x = 1, y = 2 }").unwrap(),
+            Event::Move { x: 1, y: 2 }
+        );
+        assert_eq!(
+            tagged("{ kind = 'Rename', name = 'n' }").unwrap(),
+            Event::Rename(Name {
+                name: "n".to_owned()
+            })
+        );
+        assert_eq!(tagged("{ kind = 'Start' }").unwrap(), Event::Start);
+        assert_eq!(
+            tagged("{ Move = { x = 3, y = 4 } }").unwrap(),
+            Event::Move { x: 3, y: 4 }
+        );
+        assert!(tagged("{ kind = 'Start', extra = 1 }").is_err());
+        assert!(tagged("{ kind = 'Pair' }").is_err());
+        // The tag has to come first, otherwise it's read as a field of the variant.
+        assert!(tagged("{ x = 1, kind = 'Move', y = 2 }").is_err());
+    }
+}
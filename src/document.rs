@@ -0,0 +1,243 @@
+//! Editing lua data files without rewriting the literals that didn't change.
+//!
+//! A [`Document`] is a [`Value`] that remembers how each of its numbers, strings and other
+//! literals was written. When it's written back, literals that still have their parsed value are
+//! copied from the input, so hex numbers stay hex, long bracket strings keep their brackets and
+//! quotes aren't swapped. Only the values that were edited are written like the serializer writes
+//! them, which keeps the diffs of edited files small.
+//!
+//! ```
+//! use serde_lua_table::{document::Document, value::Key, Value};
+//!
+//! let lua = "return {\n  flags = 0xFF,\n  motd = [[hi]],\n  count = 1,\n}";
+//! let mut document = Document::from_str(lua)?;
+//! let table = document.value_mut().as_table_mut().unwrap();
+//! table.insert("count", Value::from(2));
+//! assert_eq!(
+//!     document.to_string()?,
+//!     "return {\n  [\"flags\"] = 0xFF,\n  [\"motd\"] = [[hi]],\n  [\"count\"] = 2\n}"
+//! );
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::{
+    value::{Key, Table},
+    Chunk, DeError, Formatter, Globals, KeyStyle, LazyValue, PrettyFormatter, RawLua, SerError,
+    SerOptions, Serializer, Value, WithHeader,
+};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer as _};
+use std::{collections::HashMap, io};
+
+/// A parsed lua value that writes its unchanged literals like they were written in the input.
+#[derive(Clone, Debug)]
+pub struct Document {
+    chunk: Chunk,
+    value: Value,
+    /// The parsed value and the source of every literal, by the keys leading to it.
+    literals: HashMap<Vec<Key>, (Value, String)>,
+}
+
+impl Document {
+    /// Parses `s` like [`from_str_chunk`](crate::from_str_chunk) does.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Result<Self, DeError> {
+        let root = LazyValue::from_str(s)?;
+        let mut literals = HashMap::new();
+        for (key, field) in root.fields()? {
+            collect(field, &mut vec![key.clone()], &mut literals)?;
+        }
+        Ok(Document {
+            chunk: root.chunk(),
+            value: root.value()?.clone(),
+            literals,
+        })
+    }
+
+    /// How the value was written in the input, which is kept when it's written back.
+    pub fn chunk(&self) -> Chunk {
+        self.chunk
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    pub fn value_mut(&mut self) -> &mut Value {
+        &mut self.value
+    }
+
+    /// Writes the document like the input was written, with the values formatted by
+    /// `formatter`.
+    pub fn to_writer<W, F>(&self, writer: W, formatter: F) -> Result<(), SerError>
+    where
+        W: io::Write,
+        F: Formatter,
+    {
+        let value = Preserved {
+            value: &self.value,
+            path: Vec::new(),
+            literals: &self.literals,
+        };
+        match self.chunk {
+            Chunk::Value => value.serialize(&mut Serializer::with_formatter(writer, formatter)),
+            Chunk::Return => {
+                let formatter = WithHeader::new(formatter, b"return ");
+                value.serialize(&mut Serializer::with_formatter(writer, formatter))
+            }
+            Chunk::Globals => {
+                // Written as a map even without named fields, so an empty table writes no
+                // assignments and positional entries become `_G[1] = ...`.
+                let table = self.value.as_table().ok_or(SerError::GlobalsMustBeTable)?;
+                let options = SerOptions::new().with_key_style(KeyStyle::Bare);
+                let formatter = Globals::new(formatter);
+                let mut serializer = Serializer::with_options(writer, formatter, options);
+                let mut map = (&mut serializer)
+                    .serialize_map(Some(table.sequence().len() + table.fields().len()))?;
+                for (key, field) in table.iter() {
+                    map.serialize_entry(&key, &value.field(key.clone(), field))?;
+                }
+                SerializeMap::end(map)
+            }
+        }
+    }
+
+    /// Writes the document with a [`PrettyFormatter`].
+    pub fn to_string(&self) -> Result<String, SerError> {
+        let mut out = Vec::new();
+        self.to_writer(&mut out, PrettyFormatter::new())?;
+        // The serializer only writes valid UTF-8 and the literals come from a `&str`.
+        Ok(String::from_utf8(out).expect("output is UTF-8"))
+    }
+}
+
+/// Remembers the literals in `value` and its fields.
+fn collect(
+    value: &LazyValue<'_>,
+    path: &mut Vec<Key>,
+    literals: &mut HashMap<Vec<Key>, (Value, String)>,
+) -> Result<(), DeError> {
+    if value.raw().starts_with('{') {
+        for (key, field) in value.fields()? {
+            path.push(key.clone());
+            collect(field, path, literals)?;
+            path.pop();
+        }
+    } else {
+        literals.insert(
+            path.clone(),
+            (value.value()?.clone(), value.raw().to_owned()),
+        );
+    }
+    Ok(())
+}
+
+/// A value that writes the literals that kept their parsed value as they were written.
+struct Preserved<'a> {
+    value: &'a Value,
+    path: Vec<Key>,
+    literals: &'a HashMap<Vec<Key>, (Value, String)>,
+}
+
+impl<'a> Preserved<'a> {
+    fn field(&self, key: Key, value: &'a Value) -> Self {
+        let mut path = self.path.clone();
+        path.push(key);
+        Preserved {
+            value,
+            path,
+            literals: self.literals,
+        }
+    }
+}
+
+/// Tables are written like [`Table`] writes them.
+impl Serialize for Preserved<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let table: &Table = match self.value {
+            Value::Table(table) => table,
+            value => {
+                return match self.literals.get(&self.path) {
+                    Some((parsed, raw)) if parsed == value => RawLua(raw).serialize(serializer),
+                    _ => value.serialize(serializer),
+                }
+            }
+        };
+        if table.fields().is_empty() {
+            let mut seq = serializer.serialize_seq(Some(table.sequence().len()))?;
+            for (key, value) in table.iter() {
+                seq.serialize_element(&self.field(key, value))?;
+            }
+            seq.end()
+        } else {
+            let len = table.sequence().len() + table.fields().len();
+            let mut map = serializer.serialize_map(Some(len))?;
+            for (key, value) in table.iter() {
+                map.serialize_entry(&key, &self.field(key.clone(), value))?;
+            }
+            map.end()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Document;
+    use crate::{value::Key, SerError, Value};
+
+    #[test]
+    fn keeps_unchanged_literals() {
+        let lua = "{ 0x10, 'single', [==[long]==], 1e3, { nested = \"double\\tquoted\" } }";
+        let mut document = Document::from_str(lua).unwrap();
+        assert_eq!(
+            document.to_string().unwrap(),
+            "{\n  0x10,\n  'single',\n  [==[long]==],\n  1e3,\n  {\n    [\"nested\"] = \"double\\tquoted\"\n  }\n}"
+        );
+
+        let table = document.value_mut().as_table_mut().unwrap();
+        table.insert(1, Value::from(17));
+        table.insert(2, Value::from("single"));
+        assert!(document
+            .to_string()
+            .unwrap()
+            .starts_with("{\n  17,\n  'single',"));
+    }
+
+    #[test]
+    fn writes_globals_that_arent_named() {
+        let mut document = Document::from_str("_G = nil").unwrap();
+        assert_eq!(document.to_string().unwrap(), "");
+
+        let table = document.value_mut().as_table_mut().unwrap();
+        table.insert(1, Value::from(0x10));
+        assert_eq!(document.to_string().unwrap(), "_G[1] = 16\n");
+
+        *document.value_mut() = Value::from(1);
+        assert!(matches!(
+            document.to_string(),
+            Err(SerError::GlobalsMustBeTable)
+        ));
+    }
+
+    #[test]
+    fn writes_the_chunk_back() {
+        let lua = "-- saved\nDB = { size = 0x20 }\nOther = 'x'\n";
+        let mut document = Document::from_str(lua).unwrap();
+        let db = document.value_mut().as_table_mut().unwrap();
+        db.insert("Added", Value::from(true));
+        assert_eq!(
+            document.to_string().unwrap(),
+            "DB = {\n  size = 0x20\n}\nOther = 'x'\nAdded = true\n"
+        );
+        assert_eq!(
+            document
+                .value()
+                .as_table()
+                .unwrap()
+                .get(&Key::from("Other")),
+            Some(&Value::from("x"))
+        );
+    }
+}
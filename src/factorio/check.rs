@@ -0,0 +1,499 @@
+use super::{Diagnostic, Severity, MAX_DEPTH};
+use crate::{
+    ser::{
+        key::{Key, KeyRecorder},
+        raw,
+    },
+    LuaVersion, SerError,
+};
+use serde::{ser, Serialize};
+
+/// The largest integer that a double can represent exactly, together with all smaller ones.
+const MAX_EXACT_INTEGER: i128 = 1 << 53;
+
+/// Walks a value like the serializer would and records what the game would reject.
+#[derive(Default)]
+pub(super) struct Checker {
+    path: Vec<String>,
+    depth: usize,
+    in_raw: bool,
+    /// Whether the last value was written as `nil`.
+    nil: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker {
+    pub(super) fn into_diagnostics(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+
+    fn report(&mut self, severity: Severity, message: String) {
+        let path = self.path.concat();
+        self.diagnostics.push(Diagnostic {
+            severity,
+            path: path.strip_prefix('.').unwrap_or(&path).to_owned(),
+            message,
+        });
+    }
+
+    fn enter_table(&mut self) {
+        self.depth += 1;
+        if self.depth == MAX_DEPTH + 1 {
+            self.report(
+                Severity::Error,
+                format!("tables are nested deeper than {} levels", MAX_DEPTH),
+            );
+        }
+    }
+
+    fn leave_table(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// Checks a value inside a table and returns whether it was `nil`.
+    fn check_entry<T>(&mut self, segment: String, value: &T) -> Result<bool, SerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.path.push(segment);
+        self.nil = false;
+        value.serialize(&mut *self)?;
+        self.path.pop();
+        Ok(self.nil)
+    }
+
+    fn check_integer(&mut self, v: i128) {
+        if !(-MAX_EXACT_INTEGER..=MAX_EXACT_INTEGER).contains(&v) {
+            self.report(
+                Severity::Error,
+                format!("{} can't be represented exactly by a double", v),
+            );
+        }
+    }
+
+    fn check_float(&mut self, v: f64) {
+        if !v.is_finite() {
+            self.report(Severity::Error, format!("{} isn't a finite number", v));
+        }
+    }
+}
+
+/// Returns the path segment to a value stored under the string `key`.
+fn field_segment(key: &str) -> String {
+    if LuaVersion::Lua52.is_identifier(key) {
+        format!(".{}", key)
+    } else {
+        format!("[{:?}]", key)
+    }
+}
+
+/// Returns whether `lua` contains the `function` keyword.
+fn defines_function(lua: &str) -> bool {
+    lua.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .any(|word| word == "function")
+}
+
+impl<'a> ser::Serializer for &'a mut Checker {
+    type Ok = ();
+    type Error = SerError;
+    type SerializeSeq = SeqChecker<'a>;
+    type SerializeTuple = SeqChecker<'a>;
+    type SerializeTupleStruct = SeqChecker<'a>;
+    type SerializeTupleVariant = SeqChecker<'a>;
+    type SerializeMap = MapChecker<'a>;
+    type SerializeStruct = MapChecker<'a>;
+    type SerializeStructVariant = MapChecker<'a>;
+
+    fn serialize_bool(self, _v: bool) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.check_integer(v.into());
+        Ok(())
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.check_integer(v.into());
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.check_float(v.into());
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        self.check_float(v);
+        Ok(())
+    }
+
+    fn serialize_char(self, _v: char) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        if self.in_raw && defines_function(v) {
+            self.report(
+                Severity::Error,
+                "functions can't be used in prototypes".to_owned(),
+            );
+        }
+        Ok(())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        self.nil = true;
+        Ok(())
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.nil = true;
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        if name == raw::TOKEN {
+            self.in_raw = true;
+            let result = value.serialize(&mut *self);
+            self.in_raw = false;
+            result
+        } else {
+            value.serialize(self)
+        }
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.enter_table();
+        self.check_entry(field_segment(variant), value)?;
+        self.leave_table();
+        self.nil = false;
+        Ok(())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        self.enter_table();
+        Ok(SeqChecker {
+            checker: self,
+            len: 0,
+            variant: false,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        self.enter_table();
+        self.path.push(field_segment(variant));
+        let mut seq = self.serialize_seq(Some(len))?;
+        seq.variant = true;
+        Ok(seq)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        self.enter_table();
+        Ok(MapChecker {
+            checker: self,
+            segment: String::new(),
+            integer_keys: false,
+            string_keys: false,
+            variant: false,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        self.enter_table();
+        self.path.push(field_segment(variant));
+        let mut map = self.serialize_map(Some(len))?;
+        map.variant = true;
+        Ok(map)
+    }
+}
+
+pub(super) struct SeqChecker<'a> {
+    checker: &'a mut Checker,
+    len: usize,
+    /// Whether the sequence is wrapped in the table of an enum variant.
+    variant: bool,
+}
+
+impl SeqChecker<'_> {
+    fn push<T>(&mut self, value: &T) -> Result<(), SerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.len += 1;
+        if self.checker.check_entry(format!("[{}]", self.len), value)? {
+            self.checker.path.push(format!("[{}]", self.len));
+            self.checker.report(
+                Severity::Warning,
+                "nil leaves a hole in the sequence".to_owned(),
+            );
+            self.checker.path.pop();
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), SerError> {
+        self.checker.leave_table();
+        if self.variant {
+            self.checker.path.pop();
+            self.checker.leave_table();
+        }
+        self.checker.nil = false;
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for SeqChecker<'_> {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqChecker<'_> {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqChecker<'_> {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqChecker<'_> {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+pub(super) struct MapChecker<'a> {
+    checker: &'a mut Checker,
+    /// Path segment of the current key.
+    segment: String,
+    integer_keys: bool,
+    string_keys: bool,
+    /// Whether the map is wrapped in the table of an enum variant.
+    variant: bool,
+}
+
+impl MapChecker<'_> {
+    fn finish(self) -> Result<(), SerError> {
+        if self.integer_keys && self.string_keys {
+            self.checker.report(
+                Severity::Warning,
+                "table mixes integer and string keys".to_owned(),
+            );
+        }
+        self.checker.leave_table();
+        if self.variant {
+            self.checker.path.pop();
+            self.checker.leave_table();
+        }
+        self.checker.nil = false;
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for MapChecker<'_> {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.segment = match key.serialize(KeyRecorder)? {
+            Key::Integer(key) => {
+                self.integer_keys = true;
+                format!("[{}]", key)
+            }
+            Key::String(key) => {
+                self.string_keys = true;
+                field_segment(&key)
+            }
+        };
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let segment = std::mem::take(&mut self.segment);
+        self.checker.check_entry(segment, value)?;
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for MapChecker<'_> {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeMap::serialize_entry(self, key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for MapChecker<'_> {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeMap::serialize_entry(self, key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
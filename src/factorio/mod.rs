@@ -0,0 +1,227 @@
+//! Writing prototypes for the data stage of Factorio.
+//!
+//! The game loads prototypes through `data:extend` in a modified Lua 5.2 and rejects some values
+//! that plain Lua accepts. [`check`] finds them before the game does, and [`to_data_extend`]
+//! refuses to write values with errors.
+//!
+//! ```
+//! use serde_lua_table::factorio::{self, Severity};
+//! use std::collections::BTreeMap;
+//!
+//! let recipe = BTreeMap::from([("energy_required", f64::NAN)]);
+//! let diagnostics = factorio::check(&[recipe]).unwrap();
+//! assert_eq!(diagnostics[0].severity, Severity::Error);
+//! assert_eq!(diagnostics[0].path, "[1].energy_required");
+//!
+//! let recipe = BTreeMap::from([("type", "recipe"), ("name", "iron-gear")]);
+//! assert_eq!(
+//!     factorio::to_data_extend(&[recipe]).unwrap(),
+//!     "data:extend({\n  {\n    name = \"iron-gear\",\n    type = \"recipe\"\n  }\n})\n"
+//! );
+//! ```
+
+mod check;
+
+use crate::{PrettyFormatter, Profile, SerError, Serializer};
+use serde::Serialize;
+use std::{fmt, io};
+
+/// How deep tables can be nested. Lua's parser gives up on deeper table constructors.
+pub const MAX_DEPTH: usize = 200;
+
+/// Whether the game refuses to load a value or only behaves unexpectedly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A problem found by [`check`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Where the value is, e.g. `[1].ingredients[2]`. Empty for the outermost value.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        if self.path.is_empty() {
+            write!(f, "{}: {}", severity, self.message)
+        } else {
+            write!(f, "{}: {}: {}", severity, self.path, self.message)
+        }
+    }
+}
+
+/// Returns every problem the game would have with `value`.
+///
+/// Errors are values the game refuses to load: non-finite numbers, integers that aren't exact
+/// as doubles, tables nested deeper than [`MAX_DEPTH`] and raw lua defining functions. Warnings
+/// are tables that load but are usually not what a prototype expects: holes in sequences and
+/// tables mixing integer and string keys.
+///
+/// # Errors
+///
+/// Fails if `T`'s implementation of `Serialize` fails or if `T` contains a map with keys other
+/// than strings and integers.
+pub fn check<T>(value: &T) -> Result<Vec<Diagnostic>, SerError>
+where
+    T: ?Sized + Serialize,
+{
+    let mut checker = check::Checker::default();
+    value.serialize(&mut checker)?;
+    Ok(checker.into_diagnostics())
+}
+
+/// Creates a serializer that writes values the way the data stage expects them: Lua 5.2 without
+/// integers, identifier keys without brackets, see [`Profile::FactorioData`].
+///
+/// The serializer doesn't [`check`] the values it writes.
+pub fn serializer<W>(writer: W) -> Serializer<W, PrettyFormatter<'static>>
+where
+    W: io::Write,
+{
+    let options = Profile::FactorioData.options();
+    Serializer::with_options(writer, PrettyFormatter::new(), options)
+}
+
+/// Writes `prototypes` as `data:extend(...)` into a string, after checking them.
+///
+/// # Errors
+///
+/// Fails with the first error found by [`check`]. Warnings are ignored.
+pub fn to_data_extend<T>(prototypes: &T) -> Result<String, SerError>
+where
+    T: ?Sized + Serialize,
+{
+    if let Some(error) = check(prototypes)?
+        .into_iter()
+        .find(|d| d.severity == Severity::Error)
+    {
+        return Err(SerError::Custom(error.to_string()));
+    }
+
+    let mut buf = b"data:extend(".to_vec();
+    prototypes.serialize(&mut serializer(&mut buf))?;
+    buf.extend_from_slice(b")\n");
+    // The serializer only writes valid UTF-8.
+    Ok(String::from_utf8(buf).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, to_data_extend, Diagnostic, Severity, MAX_DEPTH};
+    use crate::RawLua;
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct Recipe {
+        name: &'static str,
+        ingredients: Vec<Option<(&'static str, u64)>>,
+        #[serde(rename = "two words")]
+        extra: BTreeMap<i64, f64>,
+        on_built: Option<RawLua<&'static str>>,
+    }
+
+    /// Tables nested as deep as the field says.
+    struct Nested(usize);
+
+    impl Serialize for Nested {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            match self.0 {
+                0 => serializer.serialize_bool(true),
+                depth => [Nested(depth - 1)].serialize(serializer),
+            }
+        }
+    }
+
+    fn diagnostic(severity: Severity, path: &str, message: &str) -> Diagnostic {
+        Diagnostic {
+            severity,
+            path: path.to_owned(),
+            message: message.to_owned(),
+        }
+    }
+
+    #[test]
+    fn reports_errors_and_warnings_with_their_path() {
+        let recipe = Recipe {
+            name: "gear",
+            ingredients: vec![Some(("iron", 1 << 60)), None, Some(("copper", 2))],
+            extra: BTreeMap::from([(1, f64::INFINITY)]),
+            on_built: Some(RawLua("function() end")),
+        };
+        assert_eq!(
+            check(&[recipe]).unwrap(),
+            [
+                diagnostic(
+                    Severity::Error,
+                    "[1].ingredients[1][2]",
+                    "1152921504606846976 can't be represented exactly by a double"
+                ),
+                diagnostic(
+                    Severity::Warning,
+                    "[1].ingredients[2]",
+                    "nil leaves a hole in the sequence"
+                ),
+                diagnostic(
+                    Severity::Error,
+                    "[1][\"two words\"][1]",
+                    "inf isn't a finite number"
+                ),
+                diagnostic(
+                    Severity::Error,
+                    "[1].on_built",
+                    "functions can't be used in prototypes"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_mixed_keys_and_deep_nesting() {
+        let mixed: crate::Value = crate::from_str("{ 1, x = 2 }").unwrap();
+        assert_eq!(
+            check(&mixed).unwrap(),
+            [diagnostic(
+                Severity::Warning,
+                "",
+                "table mixes integer and string keys"
+            )]
+        );
+
+        let diagnostics = check(&Nested(MAX_DEPTH + 1)).unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].message,
+            "tables are nested deeper than 200 levels"
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            format!("error: {}: {}", diagnostics[0].path, diagnostics[0].message)
+        );
+    }
+
+    #[test]
+    fn refuses_to_write_errors_but_not_warnings() {
+        let err = to_data_extend(&[f64::NAN]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Custom error: error: [1]: NaN isn't a finite number"
+        );
+        assert_eq!(
+            to_data_extend(&[None, Some(1)]).unwrap(),
+            "data:extend({\n  nil,\n  1\n})\n"
+        );
+    }
+}
@@ -0,0 +1,99 @@
+//! A [`figment`](::figment) provider for lua files.
+//!
+//! [`Lua`] reads configuration from lua files like [`Toml`](::figment) and the other formats of
+//! figment do, so lua files can be layered with other sources. The files are read like
+//! [`from_str_chunk`](crate::from_str_chunk) reads them, so they may also return the table or
+//! assign its fields as global variables.
+//!
+//! ```
+//! use figment::{providers::{Format, Serialized}, Figment};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Config {
+//!     name: String,
+//!     workers: u32,
+//! }
+//!
+//! let defaults = Config { name: "app".to_owned(), workers: 1 };
+//! let config: Config = Figment::from(Serialized::defaults(defaults))
+//!     .merge(serde_lua_table::figment::Lua::string("workers = 4"))
+//!     .extract()
+//!     .unwrap();
+//! assert_eq!((config.name.as_str(), config.workers), ("app", 4));
+//! ```
+
+use crate::DeError;
+use ::figment::providers::Format;
+use serde::de::DeserializeOwned;
+
+/// The lua format. Use it through the methods of [`Format`], e.g. `Lua::file("config.lua")`.
+#[derive(Clone, Copy, Debug)]
+pub struct Lua;
+
+impl Format for Lua {
+    type Error = DeError;
+
+    const NAME: &'static str = "Lua";
+
+    fn from_str<T: DeserializeOwned>(string: &str) -> Result<T, DeError> {
+        crate::from_str_chunk(string).map(|(_, value)| value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Lua;
+    use figment::{providers::Format, Figment};
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Config {
+        name: String,
+        ports: Vec<u16>,
+        #[serde(default)]
+        debug: bool,
+    }
+
+    #[test]
+    fn reads_values_modules_and_globals() {
+        for lua in [
+            "{ name = 'app', ports = {80, 443} }",
+            "-- config\nreturn { name = 'app', ports = {80, 443} }",
+            "name = 'app'\nports = {80, 443}\n",
+        ] {
+            let config: Config = Figment::from(Lua::string(lua)).extract().unwrap();
+            assert_eq!(
+                config,
+                Config {
+                    name: "app".to_owned(),
+                    ports: vec![80, 443],
+                    debug: false,
+                },
+                "{}",
+                lua
+            );
+        }
+    }
+
+    #[test]
+    fn merges_lua_files_over_each_other() {
+        let config: Config = Figment::from(Lua::string("{ name = 'app', ports = {80} }"))
+            .merge(Lua::string("{ debug = true, ports = {8080} }"))
+            .extract()
+            .unwrap();
+        assert_eq!(config.ports, [8080]);
+        assert!(config.debug);
+    }
+
+    #[test]
+    fn reports_syntax_errors_with_their_position() {
+        let error = Figment::from(Lua::string("{ name = }"))
+            .extract::<Config>()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "expected a value at name, line 1, column 10 in Lua source string"
+        );
+    }
+}
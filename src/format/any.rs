@@ -0,0 +1,419 @@
+use super::{CharEscape, CompactFormatter, Formatter, PrettyFormatter};
+use std::io::{self, Write};
+
+/// A formatter that is picked at runtime from the built-in formatters.
+///
+/// This allows switching between compact and pretty output (e.g. based on a command line flag)
+/// while still using a single `Serializer` type.
+///
+/// ```
+/// use serde::Serialize;
+/// use serde_lua_table::{AnyFormatter, Serializer};
+///
+/// let pretty = false;
+/// let mut ser = Serializer::with_formatter(Vec::new(), AnyFormatter::new(pretty));
+/// [1, 2].serialize(&mut ser).unwrap();
+/// assert_eq!(ser.into_inner(), b"{1,2}");
+/// ```
+#[derive(Clone, Debug)]
+pub enum AnyFormatter<'a> {
+    /// Formats like [`CompactFormatter`].
+    Compact(CompactFormatter),
+    /// Formats like [`PrettyFormatter`].
+    Pretty(PrettyFormatter<'a>),
+}
+
+impl<'a> AnyFormatter<'a> {
+    /// Selects the pretty formatter if `pretty` is set and the compact formatter otherwise.
+    pub fn new(pretty: bool) -> Self {
+        if pretty {
+            AnyFormatter::Pretty(PrettyFormatter::new())
+        } else {
+            AnyFormatter::Compact(CompactFormatter)
+        }
+    }
+}
+
+impl<'a> From<CompactFormatter> for AnyFormatter<'a> {
+    fn from(formatter: CompactFormatter) -> Self {
+        AnyFormatter::Compact(formatter)
+    }
+}
+
+impl<'a> From<PrettyFormatter<'a>> for AnyFormatter<'a> {
+    fn from(formatter: PrettyFormatter<'a>) -> Self {
+        AnyFormatter::Pretty(formatter)
+    }
+}
+
+impl<'a> Formatter for AnyFormatter<'a> {
+    #[inline]
+    fn write_null<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.write_null(writer),
+            AnyFormatter::Pretty(f) => f.write_null(writer),
+        }
+    }
+
+    #[inline]
+    fn write_bool<W>(&mut self, writer: &mut W, value: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.write_bool(writer, value),
+            AnyFormatter::Pretty(f) => f.write_bool(writer, value),
+        }
+    }
+
+    #[inline]
+    fn write_i8<W>(&mut self, writer: &mut W, value: i8) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.write_i8(writer, value),
+            AnyFormatter::Pretty(f) => f.write_i8(writer, value),
+        }
+    }
+
+    #[inline]
+    fn write_i16<W>(&mut self, writer: &mut W, value: i16) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.write_i16(writer, value),
+            AnyFormatter::Pretty(f) => f.write_i16(writer, value),
+        }
+    }
+
+    #[inline]
+    fn write_i32<W>(&mut self, writer: &mut W, value: i32) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.write_i32(writer, value),
+            AnyFormatter::Pretty(f) => f.write_i32(writer, value),
+        }
+    }
+
+    #[inline]
+    fn write_i64<W>(&mut self, writer: &mut W, value: i64) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.write_i64(writer, value),
+            AnyFormatter::Pretty(f) => f.write_i64(writer, value),
+        }
+    }
+
+    #[inline]
+    fn write_u8<W>(&mut self, writer: &mut W, value: u8) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.write_u8(writer, value),
+            AnyFormatter::Pretty(f) => f.write_u8(writer, value),
+        }
+    }
+
+    #[inline]
+    fn write_u16<W>(&mut self, writer: &mut W, value: u16) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.write_u16(writer, value),
+            AnyFormatter::Pretty(f) => f.write_u16(writer, value),
+        }
+    }
+
+    #[inline]
+    fn write_u32<W>(&mut self, writer: &mut W, value: u32) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.write_u32(writer, value),
+            AnyFormatter::Pretty(f) => f.write_u32(writer, value),
+        }
+    }
+
+    #[inline]
+    fn write_u64<W>(&mut self, writer: &mut W, value: u64) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.write_u64(writer, value),
+            AnyFormatter::Pretty(f) => f.write_u64(writer, value),
+        }
+    }
+
+    #[inline]
+    fn write_f32<W>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.write_f32(writer, value),
+            AnyFormatter::Pretty(f) => f.write_f32(writer, value),
+        }
+    }
+
+    #[inline]
+    fn write_f64<W>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.write_f64(writer, value),
+            AnyFormatter::Pretty(f) => f.write_f64(writer, value),
+        }
+    }
+
+    #[inline]
+    fn write_number_str<W>(&mut self, writer: &mut W, value: &str) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.write_number_str(writer, value),
+            AnyFormatter::Pretty(f) => f.write_number_str(writer, value),
+        }
+    }
+
+    #[inline]
+    fn begin_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.begin_string(writer),
+            AnyFormatter::Pretty(f) => f.begin_string(writer),
+        }
+    }
+
+    #[inline]
+    fn end_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.end_string(writer),
+            AnyFormatter::Pretty(f) => f.end_string(writer),
+        }
+    }
+
+    #[inline]
+    fn write_string_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.write_string_fragment(writer, fragment),
+            AnyFormatter::Pretty(f) => f.write_string_fragment(writer, fragment),
+        }
+    }
+
+    #[inline]
+    fn write_char_escape<W>(&mut self, writer: &mut W, char_escape: CharEscape) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.write_char_escape(writer, char_escape),
+            AnyFormatter::Pretty(f) => f.write_char_escape(writer, char_escape),
+        }
+    }
+
+    #[inline]
+    fn write_char_escape_v1<W>(&mut self, writer: &mut W, char_escape: CharEscape) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.write_char_escape_v1(writer, char_escape),
+            AnyFormatter::Pretty(f) => f.write_char_escape_v1(writer, char_escape),
+        }
+    }
+
+    #[inline]
+    fn begin_array<W>(&mut self, writer: &mut W, len: Option<usize>) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.begin_array(writer, len),
+            AnyFormatter::Pretty(f) => f.begin_array(writer, len),
+        }
+    }
+
+    #[inline]
+    fn end_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.end_array(writer),
+            AnyFormatter::Pretty(f) => f.end_array(writer),
+        }
+    }
+
+    #[inline]
+    fn begin_array_value<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.begin_array_value(writer, first),
+            AnyFormatter::Pretty(f) => f.begin_array_value(writer, first),
+        }
+    }
+
+    #[inline]
+    fn end_array_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.end_array_value(writer),
+            AnyFormatter::Pretty(f) => f.end_array_value(writer),
+        }
+    }
+
+    #[inline]
+    fn begin_object<W>(&mut self, writer: &mut W, len: Option<usize>) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.begin_object(writer, len),
+            AnyFormatter::Pretty(f) => f.begin_object(writer, len),
+        }
+    }
+
+    #[inline]
+    fn end_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.end_object(writer),
+            AnyFormatter::Pretty(f) => f.end_object(writer),
+        }
+    }
+
+    #[inline]
+    fn begin_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.begin_object_key(writer, first),
+            AnyFormatter::Pretty(f) => f.begin_object_key(writer, first),
+        }
+    }
+
+    #[inline]
+    fn end_object_key<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.end_object_key(writer),
+            AnyFormatter::Pretty(f) => f.end_object_key(writer),
+        }
+    }
+
+    #[inline]
+    fn begin_bare_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.begin_bare_object_key(writer, first),
+            AnyFormatter::Pretty(f) => f.begin_bare_object_key(writer, first),
+        }
+    }
+
+    #[inline]
+    fn end_bare_object_key<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.end_bare_object_key(writer),
+            AnyFormatter::Pretty(f) => f.end_bare_object_key(writer),
+        }
+    }
+
+    #[inline]
+    fn begin_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.begin_object_value(writer),
+            AnyFormatter::Pretty(f) => f.begin_object_value(writer),
+        }
+    }
+
+    #[inline]
+    fn end_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.end_object_value(writer),
+            AnyFormatter::Pretty(f) => f.end_object_value(writer),
+        }
+    }
+
+    #[inline]
+    fn write_raw_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match self {
+            AnyFormatter::Compact(f) => f.write_raw_fragment(writer, fragment),
+            AnyFormatter::Pretty(f) => f.write_raw_fragment(writer, fragment),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AnyFormatter;
+    use crate::{CompactFormatter, Formatter, PrettyFormatter, Serializer};
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    fn write(formatter: impl Formatter) -> String {
+        let value = BTreeMap::from([("a", vec![Some(1.5), None]), ("b", vec![])]);
+        let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+        value.serialize(&mut ser).unwrap();
+        String::from_utf8(ser.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn writes_like_the_selected_formatter() {
+        assert_eq!(write(AnyFormatter::new(false)), write(CompactFormatter));
+        assert_eq!(
+            write(AnyFormatter::new(true)),
+            write(PrettyFormatter::new())
+        );
+        assert_eq!(
+            write(AnyFormatter::from(PrettyFormatter::with_indent(b"\t"))),
+            write(PrettyFormatter::with_indent(b"\t"))
+        );
+        assert_ne!(write(AnyFormatter::new(true)), write(CompactFormatter));
+    }
+}
@@ -1,4 +1,5 @@
 use super::Formatter;
+use crate::OutputStability;
 use std::io;
 
 /// Represents a character escape code in a type-safe manner.
@@ -20,7 +21,7 @@ pub enum CharEscape {
     /// An escaped tab character (usually escaped as `\t`)
     Tab,
     /// An escaped ASCII plane control character (usually escaped as
-    /// `\u00XX` where `XX` are two hex characters)
+    /// `\DDD` where `DDD` are three decimal digits)
     AsciiControl(u8),
 }
 
@@ -73,10 +74,103 @@ static ESCAPE: [u8; 256] = [
     __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // F
 ];
 
+/// Returns the index of the first byte that needs to be escaped.
+#[inline]
+fn find_escape(bytes: &[u8]) -> Option<usize> {
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    return sse2::find_escape(bytes);
+    #[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+    return find_escape_by_words(bytes);
+}
+
+const ONES: u64 = u64::from_ne_bytes([0x01; 8]);
+const HIGHS: u64 = u64::from_ne_bytes([0x80; 8]);
+
+/// Sets the high bit of a byte of `word` if it is (most likely) below `n`, never misses one.
+#[inline]
+fn any_below(word: u64, n: u8) -> u64 {
+    word.wrapping_sub(ONES * n as u64) & !word & HIGHS
+}
+
+/// Clean text is skipped a word at a time: a word is only looked at byte by byte if it contains
+/// a control character, a quote or a backslash.
+#[inline]
+fn find_escape_by_words(bytes: &[u8]) -> Option<usize> {
+    let mut offset = 0;
+    for chunk in bytes.chunks_exact(8) {
+        let word = u64::from_ne_bytes(chunk.try_into().unwrap());
+        let found = any_below(word, 0x20)
+            | any_below(word ^ (ONES * b'"' as u64), 1)
+            | any_below(word ^ (ONES * b'\\' as u64), 1);
+        if found != 0 {
+            break;
+        }
+        offset += 8;
+    }
+
+    bytes[offset..]
+        .iter()
+        .position(|&byte| ESCAPE[byte as usize] != 0)
+        .map(|i| offset + i)
+}
+
+/// Returns the index of the first byte that is one of `needles`, skipping a word at a time like
+/// [`find_escape_by_words`]. The parser uses it to find the end of strings.
+#[inline]
+pub(crate) fn find_any_of(bytes: &[u8], needles: &[u8]) -> Option<usize> {
+    let mut offset = 0;
+    for chunk in bytes.chunks_exact(8) {
+        let word = u64::from_ne_bytes(chunk.try_into().unwrap());
+        if needles
+            .iter()
+            .any(|&n| any_below(word ^ (ONES * n as u64), 1) != 0)
+        {
+            break;
+        }
+        offset += 8;
+    }
+
+    bytes[offset..]
+        .iter()
+        .position(|byte| needles.contains(byte))
+        .map(|i| offset + i)
+}
+
+/// Compares 16 bytes at once. SSE2 is part of every x86_64 CPU, so no runtime detection is
+/// needed.
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod sse2 {
+    use std::arch::x86_64::*;
+
+    #[inline]
+    pub(super) fn find_escape(bytes: &[u8]) -> Option<usize> {
+        let mut offset = 0;
+        for chunk in bytes.chunks_exact(16) {
+            // Safety: SSE2 is always available on x86_64 and `chunk` has 16 bytes.
+            let mask = unsafe {
+                let block = _mm_loadu_si128(chunk.as_ptr().cast());
+                // `min(b, 0x1F) == b` is an unsigned `b < 0x20`, SSE2 only compares signed.
+                let control = _mm_cmpeq_epi8(_mm_min_epu8(block, _mm_set1_epi8(0x1F)), block);
+                let quote = _mm_cmpeq_epi8(block, _mm_set1_epi8(b'"' as i8));
+                let backslash = _mm_cmpeq_epi8(block, _mm_set1_epi8(b'\\' as i8));
+                _mm_movemask_epi8(_mm_or_si128(control, _mm_or_si128(quote, backslash)))
+            };
+            if mask != 0 {
+                return Some(offset + mask.trailing_zeros() as usize);
+            }
+            offset += 16;
+        }
+
+        super::find_escape_by_words(&bytes[offset..]).map(|i| offset + i)
+    }
+}
+
+/// Writes the contents of a string with the escapes of the given release.
 pub fn format_escaped_str_contents<W, F>(
     writer: &mut W,
     formatter: &mut F,
     value: &str,
+    stability: OutputStability,
 ) -> io::Result<()>
 where
     W: ?Sized + io::Write,
@@ -86,18 +180,18 @@ where
 
     let mut start = 0;
 
-    for (i, &byte) in bytes.iter().enumerate() {
-        let escape = ESCAPE[byte as usize];
-        if escape == 0 {
-            continue;
-        }
-
+    while let Some(offset) = find_escape(&bytes[start..]) {
+        let i = start + offset;
         if start < i {
             formatter.write_string_fragment(writer, &value[start..i])?;
         }
 
-        let char_escape = CharEscape::from_escape_table(escape, byte);
-        formatter.write_char_escape(writer, char_escape)?;
+        let byte = bytes[i];
+        let char_escape = CharEscape::from_escape_table(ESCAPE[byte as usize], byte);
+        match stability {
+            OutputStability::Latest => formatter.write_char_escape(writer, char_escape)?,
+            OutputStability::V1 => formatter.write_char_escape_v1(writer, char_escape)?,
+        }
 
         start = i + 1;
     }
@@ -108,3 +202,62 @@ where
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the index of the first byte that needs to be escaped, a byte at a time.
+    fn find_escape_naive(bytes: &[u8]) -> Option<usize> {
+        bytes.iter().position(|&byte| ESCAPE[byte as usize] != 0)
+    }
+
+    /// Every byte at every position of a string longer than a few words, with clean text and
+    /// multibyte characters around it.
+    fn haystacks() -> impl Iterator<Item = Vec<u8>> {
+        (0..=255u8).flat_map(|byte| {
+            (0..40).map(move |at| {
+                let mut haystack = "aé€ 0~\x7f".repeat(6).into_bytes();
+                haystack.truncate(at + 1 + at % 7);
+                haystack[at] = byte;
+                haystack
+            })
+        })
+    }
+
+    #[test]
+    fn finds_escapes_a_word_at_a_time() {
+        for haystack in haystacks() {
+            assert_eq!(
+                find_escape_by_words(&haystack),
+                find_escape_naive(&haystack),
+                "{haystack:?}"
+            );
+        }
+        assert_eq!(find_escape_by_words(b""), None);
+    }
+
+    #[test]
+    fn finds_any_of_the_needles() {
+        for haystack in haystacks() {
+            let needles = b"]=\n";
+            assert_eq!(
+                find_any_of(&haystack, needles),
+                haystack.iter().position(|byte| needles.contains(byte)),
+                "{haystack:?}"
+            );
+        }
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    #[test]
+    fn finds_escapes_with_sse2() {
+        for haystack in haystacks() {
+            assert_eq!(
+                sse2::find_escape(&haystack),
+                find_escape_naive(&haystack),
+                "{haystack:?}"
+            );
+        }
+    }
+}
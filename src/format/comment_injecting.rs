@@ -0,0 +1,663 @@
+use super::{CharEscape, Formatter};
+use crate::{PathMatcher, PathSegment};
+use std::io::{self, Write};
+
+/// Wraps a formatter and writes comments in front of object keys.
+///
+/// The callback receives the text of every key and returns the comment to write in front of it,
+/// if any. When the inner formatter puts keys on their own lines, the comment is written as `--`
+/// line comments above the key. Otherwise, it's written as a block comment right before the key.
+///
+/// ```
+/// use serde::Serialize;
+/// use serde_lua_table::{CommentInjecting, PrettyFormatter, Serializer};
+/// use std::collections::BTreeMap;
+///
+/// let formatter = CommentInjecting::new(PrettyFormatter::new(), |key: &str| {
+///     (key == "width").then(|| "in pixels".to_owned())
+/// });
+/// let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+/// BTreeMap::from([("width", 800)]).serialize(&mut ser).unwrap();
+/// assert_eq!(ser.into_inner(), b"{\n  -- in pixels\n  [\"width\"] = 800\n}");
+/// ```
+pub struct CommentInjecting<F, C> {
+    inner: F,
+    comment: C,
+    in_key: bool,
+    key: String,
+    key_buf: Vec<u8>,
+    key_start: usize,
+    path_comments: Vec<(PathMatcher, String)>,
+    /// The comment of the next key from `path_comments`.
+    path_comment: Option<String>,
+}
+
+impl<F, C> CommentInjecting<F, C>
+where
+    C: FnMut(&str) -> Option<String>,
+{
+    /// Construct a formatter that formats like `inner` and asks `comment` for the comment of
+    /// each key.
+    pub fn new(inner: F, comment: C) -> Self {
+        CommentInjecting {
+            inner,
+            comment,
+            in_key: false,
+            key: String::new(),
+            key_buf: Vec::new(),
+            key_start: 0,
+            path_comments: Vec::new(),
+            path_comment: None,
+        }
+    }
+
+    /// Writes `comment` in front of the keys whose path matches `pattern`. Unlike the callback,
+    /// this tells apart keys with the same name in different tables. A matching pattern takes
+    /// precedence over the callback.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_lua_table::{CommentInjecting, CompactFormatter, Serializer};
+    /// use std::collections::BTreeMap;
+    ///
+    /// let formatter = CommentInjecting::new(CompactFormatter, |_: &str| None)
+    ///     .with_path_comment("window.width".parse().unwrap(), "in pixels");
+    /// let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+    /// let value = BTreeMap::from([
+    ///     ("icon", BTreeMap::from([("width", 16)])),
+    ///     ("window", BTreeMap::from([("width", 800)])),
+    /// ]);
+    /// value.serialize(&mut ser).unwrap();
+    /// assert_eq!(
+    ///     ser.into_inner(),
+    ///     br#"{["icon"]={["width"]=16},["window"]={--[[ in pixels ]]["width"]=800}}"#
+    /// );
+    /// ```
+    pub fn with_path_comment(mut self, pattern: PathMatcher, comment: impl Into<String>) -> Self {
+        self.path_comments.push((pattern, comment.into()));
+        self
+    }
+
+    /// Unwrap the inner formatter.
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+
+impl<F> CommentInjecting<F, fn(&str) -> Option<String>> {
+    /// Construct a formatter that formats like `inner` and writes the comments of `T`.
+    ///
+    /// Keys are matched by their text only, so the comments apply to any table with such a key,
+    /// not only to `T` itself.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_lua_table::{CommentInjecting, LuaComments, PrettyFormatter, Serializer};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Window {
+    ///     width: u32,
+    /// }
+    ///
+    /// impl LuaComments for Window {
+    ///     fn lua_comment(key: &str) -> Option<&'static str> {
+    ///         (key == "width").then_some("in pixels")
+    ///     }
+    /// }
+    ///
+    /// let formatter = CommentInjecting::for_type::<Window>(PrettyFormatter::new());
+    /// let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+    /// Window { width: 800 }.serialize(&mut ser).unwrap();
+    /// assert_eq!(ser.into_inner(), b"{\n  -- in pixels\n  [\"width\"] = 800\n}");
+    /// ```
+    pub fn for_type<T>(inner: F) -> Self
+    where
+        T: LuaComments,
+    {
+        Self::new(inner, |key| T::lua_comment(key).map(str::to_owned))
+    }
+}
+
+/// Comments for the keys of a type, see [`CommentInjecting::for_type`].
+///
+/// With the `derive` feature, this can be derived from `#[lua(comment = "...")]` attributes on
+/// the fields of a struct:
+///
+/// ```ignore
+/// #[derive(Serialize, LuaComments)]
+/// struct Window {
+///     #[lua(comment = "in pixels")]
+///     width: u32,
+/// }
+/// ```
+pub trait LuaComments {
+    /// Returns the comment of `key`, if any.
+    fn lua_comment(key: &str) -> Option<&'static str>;
+}
+
+impl<F, C> Formatter for CommentInjecting<F, C>
+where
+    F: Formatter,
+    C: FnMut(&str) -> Option<String>,
+{
+    #[inline]
+    fn write_null<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.inner.write_null(&mut self.key_buf)
+        } else {
+            self.inner.write_null(writer)
+        }
+    }
+
+    #[inline]
+    fn write_bool<W>(&mut self, writer: &mut W, value: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.inner.write_bool(&mut self.key_buf, value)
+        } else {
+            self.inner.write_bool(writer, value)
+        }
+    }
+
+    #[inline]
+    fn write_i8<W>(&mut self, writer: &mut W, value: i8) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.key.push_str(itoa::Buffer::new().format(value));
+            self.inner.write_i8(&mut self.key_buf, value)
+        } else {
+            self.inner.write_i8(writer, value)
+        }
+    }
+
+    #[inline]
+    fn write_i16<W>(&mut self, writer: &mut W, value: i16) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.key.push_str(itoa::Buffer::new().format(value));
+            self.inner.write_i16(&mut self.key_buf, value)
+        } else {
+            self.inner.write_i16(writer, value)
+        }
+    }
+
+    #[inline]
+    fn write_i32<W>(&mut self, writer: &mut W, value: i32) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.key.push_str(itoa::Buffer::new().format(value));
+            self.inner.write_i32(&mut self.key_buf, value)
+        } else {
+            self.inner.write_i32(writer, value)
+        }
+    }
+
+    #[inline]
+    fn write_i64<W>(&mut self, writer: &mut W, value: i64) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.key.push_str(itoa::Buffer::new().format(value));
+            self.inner.write_i64(&mut self.key_buf, value)
+        } else {
+            self.inner.write_i64(writer, value)
+        }
+    }
+
+    #[inline]
+    fn write_u8<W>(&mut self, writer: &mut W, value: u8) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.key.push_str(itoa::Buffer::new().format(value));
+            self.inner.write_u8(&mut self.key_buf, value)
+        } else {
+            self.inner.write_u8(writer, value)
+        }
+    }
+
+    #[inline]
+    fn write_u16<W>(&mut self, writer: &mut W, value: u16) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.key.push_str(itoa::Buffer::new().format(value));
+            self.inner.write_u16(&mut self.key_buf, value)
+        } else {
+            self.inner.write_u16(writer, value)
+        }
+    }
+
+    #[inline]
+    fn write_u32<W>(&mut self, writer: &mut W, value: u32) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.key.push_str(itoa::Buffer::new().format(value));
+            self.inner.write_u32(&mut self.key_buf, value)
+        } else {
+            self.inner.write_u32(writer, value)
+        }
+    }
+
+    #[inline]
+    fn write_u64<W>(&mut self, writer: &mut W, value: u64) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.key.push_str(itoa::Buffer::new().format(value));
+            self.inner.write_u64(&mut self.key_buf, value)
+        } else {
+            self.inner.write_u64(writer, value)
+        }
+    }
+
+    #[inline]
+    fn write_f32<W>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.inner.write_f32(&mut self.key_buf, value)
+        } else {
+            self.inner.write_f32(writer, value)
+        }
+    }
+
+    #[inline]
+    fn write_f64<W>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.inner.write_f64(&mut self.key_buf, value)
+        } else {
+            self.inner.write_f64(writer, value)
+        }
+    }
+
+    #[inline]
+    fn write_number_str<W>(&mut self, writer: &mut W, value: &str) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.key.push_str(value);
+            self.inner.write_number_str(&mut self.key_buf, value)
+        } else {
+            self.inner.write_number_str(writer, value)
+        }
+    }
+
+    #[inline]
+    fn begin_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.inner.begin_string(&mut self.key_buf)
+        } else {
+            self.inner.begin_string(writer)
+        }
+    }
+
+    #[inline]
+    fn end_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.inner.end_string(&mut self.key_buf)
+        } else {
+            self.inner.end_string(writer)
+        }
+    }
+
+    #[inline]
+    fn write_string_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.key.push_str(fragment);
+            self.inner
+                .write_string_fragment(&mut self.key_buf, fragment)
+        } else {
+            self.inner.write_string_fragment(writer, fragment)
+        }
+    }
+
+    #[inline]
+    fn write_char_escape<W>(&mut self, writer: &mut W, char_escape: CharEscape) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.key.push(escaped_char(&char_escape));
+            self.inner.write_char_escape(&mut self.key_buf, char_escape)
+        } else {
+            self.inner.write_char_escape(writer, char_escape)
+        }
+    }
+
+    #[inline]
+    fn write_char_escape_v1<W>(&mut self, writer: &mut W, char_escape: CharEscape) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.key.push(escaped_char(&char_escape));
+            self.inner
+                .write_char_escape_v1(&mut self.key_buf, char_escape)
+        } else {
+            self.inner.write_char_escape_v1(writer, char_escape)
+        }
+    }
+
+    #[inline]
+    fn begin_array<W>(&mut self, writer: &mut W, len: Option<usize>) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.inner.begin_array(&mut self.key_buf, len)
+        } else {
+            self.inner.begin_array(writer, len)
+        }
+    }
+
+    #[inline]
+    fn end_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.inner.end_array(&mut self.key_buf)
+        } else {
+            self.inner.end_array(writer)
+        }
+    }
+
+    #[inline]
+    fn begin_array_value<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.inner.begin_array_value(&mut self.key_buf, first)
+        } else {
+            self.inner.begin_array_value(writer, first)
+        }
+    }
+
+    #[inline]
+    fn end_array_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.inner.end_array_value(&mut self.key_buf)
+        } else {
+            self.inner.end_array_value(writer)
+        }
+    }
+
+    #[inline]
+    fn begin_object<W>(&mut self, writer: &mut W, len: Option<usize>) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.inner.begin_object(&mut self.key_buf, len)
+        } else {
+            self.inner.begin_object(writer, len)
+        }
+    }
+
+    #[inline]
+    fn end_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.inner.end_object(&mut self.key_buf)
+        } else {
+            self.inner.end_object(writer)
+        }
+    }
+
+    #[inline]
+    fn begin_object_key<W>(&mut self, _writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.in_key = true;
+        self.key.clear();
+        self.key_buf.clear();
+        self.inner.begin_object_key(&mut self.key_buf, first)?;
+        self.key_start = self.key_buf.len();
+        Ok(())
+    }
+
+    #[inline]
+    fn end_object_key<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.in_key = false;
+        self.inner.end_object_key(&mut self.key_buf)?;
+
+        let comment = self.path_comment.take();
+        match comment.or_else(|| (self.comment)(&self.key)) {
+            Some(comment) => write_commented_key(writer, &self.key_buf, self.key_start, &comment),
+            None => writer.write_all(&self.key_buf),
+        }
+    }
+
+    #[inline]
+    fn begin_bare_object_key<W>(&mut self, _writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.in_key = true;
+        self.key.clear();
+        self.key_buf.clear();
+        self.inner.begin_bare_object_key(&mut self.key_buf, first)?;
+        self.key_start = self.key_buf.len();
+        Ok(())
+    }
+
+    #[inline]
+    fn end_bare_object_key<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.in_key = false;
+        self.inner.end_bare_object_key(&mut self.key_buf)?;
+
+        let comment = self.path_comment.take();
+        match comment.or_else(|| (self.comment)(&self.key)) {
+            Some(comment) => write_commented_key(writer, &self.key_buf, self.key_start, &comment),
+            None => writer.write_all(&self.key_buf),
+        }
+    }
+
+    #[inline]
+    fn begin_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.inner.begin_object_value(&mut self.key_buf)
+        } else {
+            self.inner.begin_object_value(writer)
+        }
+    }
+
+    #[inline]
+    fn end_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.inner.end_object_value(&mut self.key_buf)
+        } else {
+            self.inner.end_object_value(writer)
+        }
+    }
+
+    #[inline]
+    fn write_raw_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.inner.write_raw_fragment(&mut self.key_buf, fragment)
+        } else {
+            self.inner.write_raw_fragment(writer, fragment)
+        }
+    }
+
+    #[inline]
+    fn wants_key_paths(&self) -> bool {
+        !self.path_comments.is_empty() || self.inner.wants_key_paths()
+    }
+
+    #[inline]
+    fn key_path(&mut self, path: &[PathSegment]) {
+        self.path_comment = self
+            .path_comments
+            .iter()
+            .find(|(pattern, _)| pattern.matches(path))
+            .map(|(_, comment)| comment.clone());
+        self.inner.key_path(path)
+    }
+}
+
+/// Returns the character that is represented by an escape.
+pub(super) fn escaped_char(char_escape: &CharEscape) -> char {
+    match *char_escape {
+        CharEscape::Quote => '"',
+        CharEscape::ReverseSolidus => '\\',
+        CharEscape::Solidus => '/',
+        CharEscape::Backspace => '\x08',
+        CharEscape::FormFeed => '\x0C',
+        CharEscape::LineFeed => '\n',
+        CharEscape::CarriageReturn => '\r',
+        CharEscape::Tab => '\t',
+        CharEscape::AsciiControl(byte) => byte as char,
+    }
+}
+
+/// Writes a buffered key with `comment` in front of it.
+///
+/// `key_start` is the offset of the key itself, everything before it was written by
+/// `begin_object_key`.
+fn write_commented_key<W>(
+    writer: &mut W,
+    buf: &[u8],
+    key_start: usize,
+    comment: &str,
+) -> io::Result<()>
+where
+    W: ?Sized + Write,
+{
+    match buf[..key_start].iter().rposition(|&b| b == b'\n') {
+        Some(newline) => {
+            let (head, line) = buf.split_at(newline + 1);
+            let indent = line
+                .iter()
+                .take_while(|&&b| b == b' ' || b == b'\t')
+                .count();
+            writer.write_all(head)?;
+            for comment_line in comment.lines() {
+                writer.write_all(&line[..indent])?;
+                writer.write_all(b"-- ")?;
+                writer.write_all(comment_line.as_bytes())?;
+                writer.write_all(b"\n")?;
+            }
+            writer.write_all(line)
+        }
+        None => {
+            let separator = buf.iter().take_while(|&&b| b == b',' || b == b' ').count();
+            let (head, key) = buf.split_at(separator);
+            writer.write_all(head)?;
+            write_block_comment(writer, comment)?;
+            writer.write_all(key)
+        }
+    }
+}
+
+/// Writes `comment` as a long comment, picking a level that doesn't occur in the comment.
+fn write_block_comment<W>(writer: &mut W, comment: &str) -> io::Result<()>
+where
+    W: ?Sized + Write,
+{
+    let mut level = 0;
+    while comment.contains(&format!("]{}]", "=".repeat(level))) {
+        level += 1;
+    }
+    let equals = "=".repeat(level);
+    write!(writer, "--[{equals}[ {comment} ]{equals}]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{KeyOrder, KeyStyle, PrettyFormatter, SerOptions, Serializer};
+    use serde::Serialize;
+    use std::collections::HashMap;
+
+    #[derive(Serialize)]
+    struct Config {
+        size: u32,
+        windows: Vec<HashMap<&'static str, u32>>,
+    }
+
+    #[test]
+    fn comments_keys_by_path_in_sorted_tables() {
+        let formatter = CommentInjecting::new(PrettyFormatter::new(), |key: &str| {
+            (key == "size").then(|| "in bytes".to_owned())
+        })
+        .with_path_comment("windows[*].size".parse().unwrap(), "in pixels");
+        let options = SerOptions::new()
+            .with_key_style(KeyStyle::Bare)
+            .with_key_order(KeyOrder::Lexicographic);
+        let mut ser = Serializer::with_options(Vec::new(), formatter, options);
+        let config = Config {
+            size: 1,
+            windows: vec![HashMap::from([("size", 2), ("depth", 3)])],
+        };
+        config.serialize(&mut ser).unwrap();
+        assert_eq!(
+            String::from_utf8(ser.into_inner()).unwrap(),
+            "{
+  -- in bytes
+  size = 1,
+  windows = {
+    {
+      depth = 3,
+      -- in pixels
+      size = 2
+    }
+  }
+}"
+        );
+    }
+}
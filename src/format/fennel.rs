@@ -0,0 +1,220 @@
+use super::{CharEscape, Formatter};
+use std::io::{self, Write};
+
+/// This structure writes tables in Fennel syntax instead of lua.
+///
+/// Sequences are written as `[1 2 3]` and maps as `{:key value}`. String keys that can't be
+/// written as `:key` are quoted.
+///
+/// ```
+/// use serde::Serialize;
+/// use serde_lua_table::{FennelFormatter, Serializer};
+/// use std::collections::BTreeMap;
+///
+/// let value = BTreeMap::from([("keys", vec!["a", "b"]), ("two words", vec![])]);
+/// let mut ser = Serializer::with_formatter(Vec::new(), FennelFormatter::new());
+/// value.serialize(&mut ser).unwrap();
+/// assert_eq!(ser.into_inner(), br#"{:keys ["a" "b"] "two words" []}"#);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FennelFormatter {
+    in_key: bool,
+    /// Contents of the string key being written, `None` once it contains an escape.
+    key: Option<String>,
+}
+
+impl FennelFormatter {
+    /// Construct a Fennel formatter.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Returns whether `key` can be written with the `:key` shorthand.
+fn is_shorthand(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+}
+
+impl Formatter for FennelFormatter {
+    #[inline]
+    fn begin_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.in_key {
+            self.key = Some(String::new());
+            Ok(())
+        } else {
+            writer.write_all(b"\"")
+        }
+    }
+
+    #[inline]
+    fn end_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if !self.in_key {
+            return writer.write_all(b"\"");
+        }
+        match self.key.take() {
+            Some(key) if is_shorthand(&key) => {
+                writer.write_all(b":")?;
+                writer.write_all(key.as_bytes())
+            }
+            Some(key) => {
+                writer.write_all(b"\"")?;
+                writer.write_all(key.as_bytes())?;
+                writer.write_all(b"\"")
+            }
+            // The start of the key was already written by `write_char_escape`.
+            None => writer.write_all(b"\""),
+        }
+    }
+
+    #[inline]
+    fn write_string_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match &mut self.key {
+            Some(key) if self.in_key => {
+                key.push_str(fragment);
+                Ok(())
+            }
+            _ => writer.write_all(fragment.as_bytes()),
+        }
+    }
+
+    #[inline]
+    fn write_char_escape<W>(&mut self, writer: &mut W, char_escape: CharEscape) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if let Some(key) = self.key.take() {
+            writer.write_all(b"\"")?;
+            writer.write_all(key.as_bytes())?;
+        }
+        super::CompactFormatter.write_char_escape(writer, char_escape)
+    }
+
+    #[inline]
+    fn write_char_escape_v1<W>(&mut self, writer: &mut W, char_escape: CharEscape) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if let Some(key) = self.key.take() {
+            writer.write_all(b"\"")?;
+            writer.write_all(key.as_bytes())?;
+        }
+        super::CompactFormatter.write_char_escape_v1(writer, char_escape)
+    }
+
+    #[inline]
+    fn begin_array<W>(&mut self, writer: &mut W, _len: Option<usize>) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        writer.write_all(b"[")
+    }
+
+    #[inline]
+    fn end_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        writer.write_all(b"]")
+    }
+
+    #[inline]
+    fn begin_array_value<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if first {
+            Ok(())
+        } else {
+            writer.write_all(b" ")
+        }
+    }
+
+    #[inline]
+    fn begin_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.in_key = true;
+        if first {
+            Ok(())
+        } else {
+            writer.write_all(b" ")
+        }
+    }
+
+    #[inline]
+    fn end_object_key<W>(&mut self, _writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.in_key = false;
+        Ok(())
+    }
+
+    #[inline]
+    fn begin_bare_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.begin_object_key(writer, first)?;
+        self.begin_string(writer)
+    }
+
+    #[inline]
+    fn end_bare_object_key<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.end_string(writer)?;
+        self.end_object_key(writer)
+    }
+
+    #[inline]
+    fn begin_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        writer.write_all(b" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FennelFormatter;
+    use crate::Serializer;
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    fn to_fennel<T: Serialize>(value: &T) -> String {
+        let mut ser = Serializer::with_formatter(Vec::new(), FennelFormatter::new());
+        value.serialize(&mut ser).unwrap();
+        String::from_utf8(ser.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn quotes_keys_that_need_escapes() {
+        let value = BTreeMap::from([("a\"b", 1), ("kebab-case", 2), ("new\nline", 3)]);
+        assert_eq!(
+            to_fennel(&value),
+            r#"{"a\"b" 1 :kebab-case 2 "new\nline" 3}"#
+        );
+    }
+
+    #[test]
+    fn writes_nested_values() {
+        let value = BTreeMap::from([(1, (true, None::<u8>, "x\ty")), (2, (false, Some(1), ""))]);
+        assert_eq!(to_fennel(&value), r#"{1 [true nil "x\ty"] 2 [false 1 ""]}"#);
+    }
+}
@@ -0,0 +1,770 @@
+use super::{comment_injecting::escaped_char, CharEscape, Formatter};
+use crate::{KeyStyle, LuaVersion, PathMatcher, PathSegment};
+use std::io::{self, Write};
+
+/// Wraps a formatter and changes how the entries of some keys are written.
+///
+/// The callback receives the text of every string key and returns the [`FieldStyle`] of the
+/// entry. The style can pick the [`KeyStyle`] of the key, overriding the serializer's option,
+/// and write the integers in the value as hexadecimal literals.
+///
+/// ```
+/// use serde::Serialize;
+/// use serde_lua_table::{CompactFormatter, FieldStyle, FieldStyles, KeyStyle, Serializer};
+/// use std::collections::BTreeMap;
+///
+/// let formatter = FieldStyles::new(CompactFormatter, |key: &str| match key {
+///     "color" => FieldStyle { hex: true, ..FieldStyle::default() },
+///     _ => FieldStyle { key_style: Some(KeyStyle::Bare), ..FieldStyle::default() },
+/// });
+/// let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+/// BTreeMap::from([("color", 0xFF8800), ("width", 800)]).serialize(&mut ser).unwrap();
+/// assert_eq!(ser.into_inner(), br#"{["color"]=0xFF8800,width=800}"#);
+/// ```
+pub struct FieldStyles<F, S> {
+    inner: F,
+    style: S,
+    key: Option<BufferedKey>,
+    /// Number of tables that are currently open.
+    depth: usize,
+    /// The depth of the value whose integers are written in hexadecimal, if any.
+    hex_depth: Option<usize>,
+    path_styles: Vec<(PathMatcher, FieldStyle)>,
+    /// The style of the next key from `path_styles`.
+    path_style: Option<FieldStyle>,
+    /// The paths of the keys the callback is asked about, all keys if `None`.
+    scope: Option<PathMatcher>,
+    /// Whether the next key is in `scope`.
+    in_scope: bool,
+}
+
+/// How the entry of a key is written, see [`FieldStyles`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FieldStyle {
+    /// Overrides the key style of the serializer. Keys that aren't identifiers are always
+    /// written in brackets.
+    pub key_style: Option<KeyStyle>,
+    /// Writes the integers in the value as hexadecimal literals like `0xFF`, including the
+    /// integers of nested tables.
+    pub hex: bool,
+}
+
+/// A key that is held back until its text is known.
+struct BufferedKey {
+    first: bool,
+    bare: bool,
+    /// Whether the key is a string, otherwise it's a number.
+    string: bool,
+    text: String,
+    parts: Vec<KeyPart>,
+}
+
+enum KeyPart {
+    Fragment(String),
+    Escape(CharEscape),
+    EscapeV1(CharEscape),
+}
+
+impl<F, S> FieldStyles<F, S>
+where
+    S: FnMut(&str) -> FieldStyle,
+{
+    /// Construct a formatter that formats like `inner` and asks `style` for the style of each
+    /// string key.
+    pub fn new(inner: F, style: S) -> Self {
+        FieldStyles {
+            inner,
+            style,
+            key: None,
+            depth: 0,
+            hex_depth: None,
+            path_styles: Vec::new(),
+            path_style: None,
+            scope: None,
+            in_scope: true,
+        }
+    }
+
+    /// Only asks the callback about the keys whose path matches `pattern`, e.g. `"[*].*"` for
+    /// the keys of the records in a sequence.
+    pub fn within(mut self, pattern: PathMatcher) -> Self {
+        self.scope = Some(pattern);
+        self
+    }
+
+    /// Writes the keys whose path matches `pattern` in `style`. Unlike the callback, this tells
+    /// apart keys with the same name in different tables and also styles integer keys. A
+    /// matching pattern takes precedence over the callback.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_lua_table::{CompactFormatter, FieldStyle, FieldStyles, Serializer};
+    /// use std::collections::BTreeMap;
+    ///
+    /// let hex = FieldStyle { hex: true, ..FieldStyle::default() };
+    /// let formatter = FieldStyles::new(CompactFormatter, |_: &str| FieldStyle::default())
+    ///     .with_path_style("window.color".parse().unwrap(), hex);
+    /// let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+    /// let value = BTreeMap::from([
+    ///     ("icon", BTreeMap::from([("color", 255)])),
+    ///     ("window", BTreeMap::from([("color", 255)])),
+    /// ]);
+    /// value.serialize(&mut ser).unwrap();
+    /// assert_eq!(
+    ///     ser.into_inner(),
+    ///     br#"{["icon"]={["color"]=255},["window"]={["color"]=0xFF}}"#
+    /// );
+    /// ```
+    pub fn with_path_style(mut self, pattern: PathMatcher, style: FieldStyle) -> Self {
+        self.path_styles.push((pattern, style));
+        self
+    }
+
+    /// Unwrap the inner formatter.
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+
+impl<F> FieldStyles<F, fn(&str) -> FieldStyle> {
+    /// Construct a formatter that formats like `inner` and writes the fields of `T` in their
+    /// style.
+    ///
+    /// The styles only apply to the keys of the outermost table, which is the one of `T`, so a
+    /// nested table with a key of the same name keeps its own style. If `T` is written inside
+    /// another value, point [`within`](Self::within) at its keys.
+    pub fn for_type<T>(inner: F) -> Self
+    where
+        T: LuaFieldStyles,
+    {
+        let fields = "*".parse().expect("`*` is a valid pattern");
+        Self::new(inner, T::lua_field_style as fn(&str) -> FieldStyle).within(fields)
+    }
+}
+
+/// The styles of the fields of a type, see [`FieldStyles::for_type`].
+///
+/// With the `derive` feature, this can be derived from `#[lua(key_style = "bare")]`,
+/// `#[lua(key_style = "bracket")]` and `#[lua(hex)]` attributes on the fields of a struct:
+///
+/// ```ignore
+/// #[derive(Serialize, LuaFieldStyles)]
+/// struct Window {
+///     #[lua(hex)]
+///     color: u32,
+/// }
+/// ```
+pub trait LuaFieldStyles {
+    /// Returns the style of `key`.
+    fn lua_field_style(key: &str) -> FieldStyle;
+}
+
+impl<F, S> FieldStyles<F, S>
+where
+    F: Formatter,
+    S: FnMut(&str) -> FieldStyle,
+{
+    fn begin_key(&mut self, first: bool, bare: bool) {
+        self.key = Some(BufferedKey {
+            first,
+            bare,
+            string: bare,
+            text: String::new(),
+            parts: Vec::new(),
+        });
+    }
+
+    /// Writes the buffered key in the style it is configured with.
+    fn end_key<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let Some(key) = self.key.take() else {
+            return Ok(());
+        };
+        let style = match self.path_style.take() {
+            Some(style) => style,
+            None if key.string && self.in_scope => (self.style)(&key.text),
+            None => FieldStyle::default(),
+        };
+        if style.hex {
+            self.hex_depth = Some(self.depth);
+        }
+        let bare = match style.key_style {
+            // Without the lua version, `goto` is treated as a keyword.
+            Some(KeyStyle::Bare) => key.string && LuaVersion::Lua54.is_identifier(&key.text),
+            Some(KeyStyle::Bracketed) => false,
+            None => key.bare,
+        };
+
+        if bare {
+            self.inner.begin_bare_object_key(writer, key.first)?;
+            self.inner.write_string_fragment(writer, &key.text)?;
+            return self.inner.end_bare_object_key(writer);
+        }
+        self.inner.begin_object_key(writer, key.first)?;
+        if key.string {
+            self.inner.begin_string(writer)?;
+            for part in key.parts {
+                match part {
+                    KeyPart::Fragment(fragment) => {
+                        self.inner.write_string_fragment(writer, &fragment)?
+                    }
+                    KeyPart::Escape(escape) => self.inner.write_char_escape(writer, escape)?,
+                    KeyPart::EscapeV1(escape) => self.inner.write_char_escape_v1(writer, escape)?,
+                }
+            }
+            self.inner.end_string(writer)?;
+        } else {
+            self.inner.write_number_str(writer, &key.text)?;
+        }
+        self.inner.end_object_key(writer)
+    }
+
+    fn hex(&self) -> bool {
+        self.hex_depth.is_some()
+    }
+}
+
+/// Formats `value` as a hexadecimal literal, e.g. `-0xFF`.
+fn hex_literal(value: i128) -> String {
+    if value < 0 {
+        format!("-0x{:X}", value.unsigned_abs())
+    } else {
+        format!("0x{:X}", value)
+    }
+}
+
+impl<F, S> Formatter for FieldStyles<F, S>
+where
+    F: Formatter,
+    S: FnMut(&str) -> FieldStyle,
+{
+    #[inline]
+    fn write_null<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.inner.write_null(writer)
+    }
+
+    #[inline]
+    fn write_bool<W>(&mut self, writer: &mut W, value: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.inner.write_bool(writer, value)
+    }
+
+    #[inline]
+    fn write_i8<W>(&mut self, writer: &mut W, value: i8) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if let Some(key) = &mut self.key {
+            key.text.push_str(itoa::Buffer::new().format(value));
+            Ok(())
+        } else if self.hex() {
+            self.inner
+                .write_number_str(writer, &hex_literal(value.into()))
+        } else {
+            self.inner.write_i8(writer, value)
+        }
+    }
+
+    #[inline]
+    fn write_i16<W>(&mut self, writer: &mut W, value: i16) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if let Some(key) = &mut self.key {
+            key.text.push_str(itoa::Buffer::new().format(value));
+            Ok(())
+        } else if self.hex() {
+            self.inner
+                .write_number_str(writer, &hex_literal(value.into()))
+        } else {
+            self.inner.write_i16(writer, value)
+        }
+    }
+
+    #[inline]
+    fn write_i32<W>(&mut self, writer: &mut W, value: i32) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if let Some(key) = &mut self.key {
+            key.text.push_str(itoa::Buffer::new().format(value));
+            Ok(())
+        } else if self.hex() {
+            self.inner
+                .write_number_str(writer, &hex_literal(value.into()))
+        } else {
+            self.inner.write_i32(writer, value)
+        }
+    }
+
+    #[inline]
+    fn write_i64<W>(&mut self, writer: &mut W, value: i64) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if let Some(key) = &mut self.key {
+            key.text.push_str(itoa::Buffer::new().format(value));
+            Ok(())
+        } else if self.hex() {
+            self.inner
+                .write_number_str(writer, &hex_literal(value.into()))
+        } else {
+            self.inner.write_i64(writer, value)
+        }
+    }
+
+    #[inline]
+    fn write_u8<W>(&mut self, writer: &mut W, value: u8) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if let Some(key) = &mut self.key {
+            key.text.push_str(itoa::Buffer::new().format(value));
+            Ok(())
+        } else if self.hex() {
+            self.inner
+                .write_number_str(writer, &hex_literal(value.into()))
+        } else {
+            self.inner.write_u8(writer, value)
+        }
+    }
+
+    #[inline]
+    fn write_u16<W>(&mut self, writer: &mut W, value: u16) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if let Some(key) = &mut self.key {
+            key.text.push_str(itoa::Buffer::new().format(value));
+            Ok(())
+        } else if self.hex() {
+            self.inner
+                .write_number_str(writer, &hex_literal(value.into()))
+        } else {
+            self.inner.write_u16(writer, value)
+        }
+    }
+
+    #[inline]
+    fn write_u32<W>(&mut self, writer: &mut W, value: u32) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if let Some(key) = &mut self.key {
+            key.text.push_str(itoa::Buffer::new().format(value));
+            Ok(())
+        } else if self.hex() {
+            self.inner
+                .write_number_str(writer, &hex_literal(value.into()))
+        } else {
+            self.inner.write_u32(writer, value)
+        }
+    }
+
+    #[inline]
+    fn write_u64<W>(&mut self, writer: &mut W, value: u64) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if let Some(key) = &mut self.key {
+            key.text.push_str(itoa::Buffer::new().format(value));
+            Ok(())
+        } else if self.hex() {
+            self.inner
+                .write_number_str(writer, &hex_literal(value.into()))
+        } else {
+            self.inner.write_u64(writer, value)
+        }
+    }
+
+    #[inline]
+    fn write_f32<W>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.inner.write_f32(writer, value)
+    }
+
+    #[inline]
+    fn write_f64<W>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.inner.write_f64(writer, value)
+    }
+
+    #[inline]
+    fn write_number_str<W>(&mut self, writer: &mut W, value: &str) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if let Some(key) = &mut self.key {
+            key.text.push_str(value);
+            Ok(())
+        } else {
+            self.inner.write_number_str(writer, value)
+        }
+    }
+
+    #[inline]
+    fn begin_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if let Some(key) = &mut self.key {
+            key.string = true;
+            Ok(())
+        } else {
+            self.inner.begin_string(writer)
+        }
+    }
+
+    #[inline]
+    fn end_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.key.is_some() {
+            Ok(())
+        } else {
+            self.inner.end_string(writer)
+        }
+    }
+
+    #[inline]
+    fn write_string_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if let Some(key) = &mut self.key {
+            key.text.push_str(fragment);
+            key.parts.push(KeyPart::Fragment(fragment.to_owned()));
+            Ok(())
+        } else {
+            self.inner.write_string_fragment(writer, fragment)
+        }
+    }
+
+    #[inline]
+    fn write_char_escape<W>(&mut self, writer: &mut W, char_escape: CharEscape) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if let Some(key) = &mut self.key {
+            key.text.push(escaped_char(&char_escape));
+            key.parts.push(KeyPart::Escape(char_escape));
+            Ok(())
+        } else {
+            self.inner.write_char_escape(writer, char_escape)
+        }
+    }
+
+    #[inline]
+    fn write_char_escape_v1<W>(&mut self, writer: &mut W, char_escape: CharEscape) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if let Some(key) = &mut self.key {
+            key.text.push(escaped_char(&char_escape));
+            key.parts.push(KeyPart::EscapeV1(char_escape));
+            Ok(())
+        } else {
+            self.inner.write_char_escape_v1(writer, char_escape)
+        }
+    }
+
+    #[inline]
+    fn begin_array<W>(&mut self, writer: &mut W, len: Option<usize>) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.depth += 1;
+        self.inner.begin_array(writer, len)
+    }
+
+    #[inline]
+    fn end_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.depth -= 1;
+        self.inner.end_array(writer)
+    }
+
+    #[inline]
+    fn begin_object<W>(&mut self, writer: &mut W, len: Option<usize>) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.depth += 1;
+        self.inner.begin_object(writer, len)
+    }
+
+    #[inline]
+    fn end_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.depth -= 1;
+        self.inner.end_object(writer)
+    }
+
+    #[inline]
+    fn begin_array_value<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.inner.begin_array_value(writer, first)
+    }
+
+    #[inline]
+    fn end_array_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.inner.end_array_value(writer)
+    }
+
+    #[inline]
+    fn begin_object_key<W>(&mut self, _writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.begin_key(first, false);
+        Ok(())
+    }
+
+    #[inline]
+    fn end_object_key<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.end_key(writer)
+    }
+
+    #[inline]
+    fn begin_bare_object_key<W>(&mut self, _writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.begin_key(first, true);
+        Ok(())
+    }
+
+    #[inline]
+    fn end_bare_object_key<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.end_key(writer)
+    }
+
+    #[inline]
+    fn begin_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.inner.begin_object_value(writer)
+    }
+
+    #[inline]
+    fn end_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.hex_depth == Some(self.depth) {
+            self.hex_depth = None;
+        }
+        self.inner.end_object_value(writer)
+    }
+
+    #[inline]
+    fn write_raw_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.inner.write_raw_fragment(writer, fragment)
+    }
+
+    #[inline]
+    fn wants_key_paths(&self) -> bool {
+        !self.path_styles.is_empty() || self.scope.is_some() || self.inner.wants_key_paths()
+    }
+
+    #[inline]
+    fn key_path(&mut self, path: &[PathSegment]) {
+        self.path_style = self
+            .path_styles
+            .iter()
+            .find(|(pattern, _)| pattern.matches(path))
+            .map(|(_, style)| *style);
+        self.in_scope = self.scope.as_ref().is_none_or(|scope| scope.matches(path));
+        self.inner.key_path(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompactFormatter, KeyOrder, SerOptions, Serializer};
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct Window {
+        name: &'static str,
+        color: u32,
+        offset: i8,
+        palette: Vec<u8>,
+        #[serde(rename = "end")]
+        end_: u8,
+        sizes: BTreeMap<u8, u16>,
+    }
+
+    fn window() -> Window {
+        Window {
+            name: "main\n",
+            color: 0xFF8800,
+            offset: -16,
+            palette: vec![1, 255],
+            end_: 1,
+            sizes: BTreeMap::from([(1, 10)]),
+        }
+    }
+
+    fn style(key: &str) -> FieldStyle {
+        match key {
+            "name" | "end" => FieldStyle {
+                key_style: Some(KeyStyle::Bare),
+                hex: false,
+            },
+            "color" | "offset" | "palette" => FieldStyle {
+                key_style: None,
+                hex: true,
+            },
+            _ => FieldStyle::default(),
+        }
+    }
+
+    fn styled(value: &impl Serialize, options: SerOptions) -> String {
+        let formatter = FieldStyles::new(CompactFormatter, style);
+        let mut ser = Serializer::with_options(Vec::new(), formatter, options);
+        value.serialize(&mut ser).unwrap();
+        String::from_utf8(ser.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn writes_fields_in_their_style() {
+        assert_eq!(
+            styled(&window(), SerOptions::new()),
+            r#"{name="main\n",["color"]=0xFF8800,["offset"]=-0x10,["palette"]={0x1,0xFF},["end"]=1,["sizes"]={[1]=10}}"#
+        );
+    }
+
+    #[test]
+    fn styles_keys_by_their_path() {
+        let hex = FieldStyle {
+            key_style: None,
+            hex: true,
+        };
+        let formatter = FieldStyles::new(CompactFormatter, style)
+            .with_path_style("sizes[1]".parse().unwrap(), hex)
+            .with_path_style("color".parse().unwrap(), FieldStyle::default());
+        let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+        window().serialize(&mut ser).unwrap();
+        assert_eq!(
+            String::from_utf8(ser.into_inner()).unwrap(),
+            r#"{name="main\n",["color"]=16746496,["offset"]=-0x10,["palette"]={0x1,0xFF},["end"]=1,["sizes"]={[1]=0xA}}"#
+        );
+    }
+
+    #[test]
+    fn overrides_the_key_style_of_the_serializer() {
+        let options = SerOptions::new()
+            .with_key_style(KeyStyle::Bare)
+            .with_key_order(KeyOrder::Lexicographic);
+        let formatter = FieldStyles::new(CompactFormatter, |key: &str| FieldStyle {
+            key_style: (key == "name").then_some(KeyStyle::Bracketed),
+            hex: false,
+        });
+        let mut ser = Serializer::with_options(Vec::new(), formatter, options);
+        BTreeMap::from([("name", 1), ("width", 2)])
+            .serialize(&mut ser)
+            .unwrap();
+        assert_eq!(ser.into_inner(), br#"{["name"]=1,width=2}"#);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derives_field_styles() {
+        #[derive(Serialize, crate::LuaComments, crate::LuaFieldStyles)]
+        struct Derived {
+            #[lua(key_style = "bare")]
+            name: &'static str,
+            #[lua(hex, comment = "packed rgb")]
+            #[serde(rename = "rgb")]
+            color: u32,
+            width: u32,
+        }
+
+        let value = Derived {
+            name: "a",
+            color: 0xABCDEF,
+            width: 10,
+        };
+        let styles = FieldStyles::for_type::<Derived>(CompactFormatter);
+        let formatter = crate::CommentInjecting::for_type::<Derived>(styles);
+        let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+        value.serialize(&mut ser).unwrap();
+        assert_eq!(
+            ser.into_inner(),
+            br#"{name="a",--[[ packed rgb ]]["rgb"]=0xABCDEF,["width"]=10}"#
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn keeps_the_style_of_nested_keys_with_the_same_name() {
+        #[derive(Serialize, crate::LuaFieldStyles)]
+        struct Outer {
+            #[lua(hex)]
+            color: u32,
+            inner: Inner,
+        }
+
+        #[derive(Serialize)]
+        struct Inner {
+            color: u32,
+        }
+
+        let value = Outer {
+            color: 255,
+            inner: Inner { color: 255 },
+        };
+        let mut ser = Serializer::with_formatter(
+            Vec::new(),
+            FieldStyles::for_type::<Outer>(CompactFormatter),
+        );
+        value.serialize(&mut ser).unwrap();
+        assert_eq!(
+            ser.into_inner(),
+            br#"{["color"]=0xFF,["inner"]={["color"]=255}}"#
+        );
+
+        let formatter =
+            FieldStyles::for_type::<Outer>(CompactFormatter).within("[*].*".parse().unwrap());
+        let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+        [value].serialize(&mut ser).unwrap();
+        assert_eq!(
+            ser.into_inner(),
+            br#"{{["color"]=0xFF,["inner"]={["color"]=255}}}"#
+        );
+    }
+}
@@ -0,0 +1,472 @@
+use super::{CharEscape, Formatter};
+use crate::{LuaVersion, PathSegment};
+use std::io::{self, Write};
+
+/// Wraps a formatter and writes the entries of the outermost table as assignments to global
+/// variables, one per line.
+///
+/// This is the layout of files that are executed to set globals, e.g. `.rockspec` files or
+/// saved variables. Keys that are identifiers are assigned directly, whatever the key style,
+/// and all others through `_G`. The outermost value
+/// must be a table, anything else is an error.
+///
+/// ```
+/// use serde::Serialize;
+/// use serde_lua_table::{Globals, KeyStyle, PrettyFormatter, SerOptions, Serializer};
+/// use std::collections::BTreeMap;
+///
+/// let value = BTreeMap::from([("name", vec!["a"]), ("two words", vec![])]);
+/// let options = SerOptions::new().with_key_style(KeyStyle::Bare);
+/// let mut ser = Serializer::with_options(Vec::new(), Globals::new(PrettyFormatter::new()), options);
+/// value.serialize(&mut ser).unwrap();
+/// assert_eq!(
+///     ser.into_inner(),
+///     b"name = {\n  \"a\"\n}\n_G[\"two words\"] = {}\n"
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct Globals<F> {
+    inner: F,
+    depth: usize,
+    /// Whether the current key of the outermost table is written as a plain variable name.
+    bare_key: bool,
+    /// Whether that plain variable name is being written. Values of sorted maps are written
+    /// before their keys, so `bare_key` alone doesn't say the string is the key.
+    writing_bare_key: bool,
+}
+
+impl<F> Globals<F> {
+    /// Construct a formatter that formats the values of the assignments like `inner`.
+    pub fn new(inner: F) -> Self {
+        Globals {
+            inner,
+            depth: 0,
+            bare_key: false,
+            writing_bare_key: false,
+        }
+    }
+
+    /// Unwrap the inner formatter.
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+
+    /// Returns whether the entries of the outermost table are being written.
+    fn is_top_level(&self) -> bool {
+        self.depth == 1
+    }
+
+    fn expect_table(&self) -> io::Result<()> {
+        if self.depth == 0 {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "only tables can be written as global assignments",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<F> Formatter for Globals<F>
+where
+    F: Formatter,
+{
+    #[inline]
+    fn write_null<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.expect_table()?;
+        self.inner.write_null(writer)
+    }
+
+    #[inline]
+    fn write_bool<W>(&mut self, writer: &mut W, value: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.expect_table()?;
+        self.inner.write_bool(writer, value)
+    }
+
+    #[inline]
+    fn write_i8<W>(&mut self, writer: &mut W, value: i8) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.expect_table()?;
+        self.inner.write_i8(writer, value)
+    }
+
+    #[inline]
+    fn write_i16<W>(&mut self, writer: &mut W, value: i16) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.expect_table()?;
+        self.inner.write_i16(writer, value)
+    }
+
+    #[inline]
+    fn write_i32<W>(&mut self, writer: &mut W, value: i32) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.expect_table()?;
+        self.inner.write_i32(writer, value)
+    }
+
+    #[inline]
+    fn write_i64<W>(&mut self, writer: &mut W, value: i64) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.expect_table()?;
+        self.inner.write_i64(writer, value)
+    }
+
+    #[inline]
+    fn write_u8<W>(&mut self, writer: &mut W, value: u8) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.expect_table()?;
+        self.inner.write_u8(writer, value)
+    }
+
+    #[inline]
+    fn write_u16<W>(&mut self, writer: &mut W, value: u16) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.expect_table()?;
+        self.inner.write_u16(writer, value)
+    }
+
+    #[inline]
+    fn write_u32<W>(&mut self, writer: &mut W, value: u32) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.expect_table()?;
+        self.inner.write_u32(writer, value)
+    }
+
+    #[inline]
+    fn write_u64<W>(&mut self, writer: &mut W, value: u64) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.expect_table()?;
+        self.inner.write_u64(writer, value)
+    }
+
+    #[inline]
+    fn write_f32<W>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.expect_table()?;
+        self.inner.write_f32(writer, value)
+    }
+
+    #[inline]
+    fn write_f64<W>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.expect_table()?;
+        self.inner.write_f64(writer, value)
+    }
+
+    #[inline]
+    fn write_number_str<W>(&mut self, writer: &mut W, value: &str) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.expect_table()?;
+        self.inner.write_number_str(writer, value)
+    }
+
+    #[inline]
+    fn begin_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.expect_table()?;
+        if self.writing_bare_key {
+            return Ok(());
+        }
+        self.inner.begin_string(writer)
+    }
+
+    #[inline]
+    fn end_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.expect_table()?;
+        if self.writing_bare_key {
+            return Ok(());
+        }
+        self.inner.end_string(writer)
+    }
+
+    #[inline]
+    fn write_string_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.expect_table()?;
+        self.inner.write_string_fragment(writer, fragment)
+    }
+
+    #[inline]
+    fn write_char_escape<W>(&mut self, writer: &mut W, char_escape: CharEscape) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.expect_table()?;
+        self.inner.write_char_escape(writer, char_escape)
+    }
+
+    #[inline]
+    fn write_char_escape_v1<W>(&mut self, writer: &mut W, char_escape: CharEscape) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.expect_table()?;
+        self.inner.write_char_escape_v1(writer, char_escape)
+    }
+
+    #[inline]
+    fn begin_array<W>(&mut self, writer: &mut W, len: Option<usize>) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.expect_table()?;
+        self.depth += 1;
+        self.inner.begin_array(writer, len)
+    }
+
+    #[inline]
+    fn end_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.depth -= 1;
+        self.inner.end_array(writer)
+    }
+
+    #[inline]
+    fn begin_array_value<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.expect_table()?;
+        self.inner.begin_array_value(writer, first)
+    }
+
+    #[inline]
+    fn end_array_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.expect_table()?;
+        self.inner.end_array_value(writer)
+    }
+
+    #[inline]
+    fn begin_object<W>(&mut self, writer: &mut W, len: Option<usize>) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.depth += 1;
+        if self.is_top_level() {
+            Ok(())
+        } else {
+            self.inner.begin_object(writer, len)
+        }
+    }
+
+    #[inline]
+    fn end_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let top_level = self.is_top_level();
+        self.depth -= 1;
+        if top_level {
+            Ok(())
+        } else {
+            self.inner.end_object(writer)
+        }
+    }
+
+    #[inline]
+    fn begin_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.bare_key {
+            self.writing_bare_key = true;
+            Ok(())
+        } else if self.is_top_level() {
+            writer.write_all(b"_G[")
+        } else {
+            self.inner.begin_object_key(writer, first)
+        }
+    }
+
+    #[inline]
+    fn end_object_key<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.bare_key {
+            self.bare_key = false;
+            self.writing_bare_key = false;
+            Ok(())
+        } else if self.is_top_level() {
+            writer.write_all(b"]")
+        } else {
+            self.inner.end_object_key(writer)
+        }
+    }
+
+    #[inline]
+    fn begin_bare_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.is_top_level() {
+            Ok(())
+        } else {
+            self.inner.begin_bare_object_key(writer, first)
+        }
+    }
+
+    #[inline]
+    fn end_bare_object_key<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.is_top_level() {
+            self.bare_key = false;
+            Ok(())
+        } else {
+            self.inner.end_bare_object_key(writer)
+        }
+    }
+
+    #[inline]
+    fn begin_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.is_top_level() {
+            writer.write_all(b" = ")
+        } else {
+            self.inner.begin_object_value(writer)
+        }
+    }
+
+    #[inline]
+    fn end_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if self.is_top_level() {
+            writer.write_all(b"\n")
+        } else {
+            self.inner.end_object_value(writer)
+        }
+    }
+
+    #[inline]
+    fn write_raw_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.expect_table()?;
+        self.inner.write_raw_fragment(writer, fragment)
+    }
+
+    #[inline]
+    fn wants_key_paths(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn key_path(&mut self, path: &[PathSegment]) {
+        self.bare_key = self.is_top_level()
+            && matches!(path, [PathSegment::Key(key)] if LuaVersion::default().is_identifier(key));
+        self.inner.key_path(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Globals;
+    use crate::{CompactFormatter, KeyStyle, SerOptions, Serializer};
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    fn to_globals<T: Serialize>(value: &T) -> Result<String, crate::SerError> {
+        let options = SerOptions::new().with_key_style(KeyStyle::Bare);
+        let mut ser = Serializer::with_options(Vec::new(), Globals::new(CompactFormatter), options);
+        value.serialize(&mut ser)?;
+        Ok(String::from_utf8(ser.into_inner()).unwrap())
+    }
+
+    #[test]
+    fn assigns_keys_through_g_when_needed() {
+        let value = BTreeMap::from([(1, vec![BTreeMap::from([("end", 2)])]), (3, vec![])]);
+        assert_eq!(
+            to_globals(&value).unwrap(),
+            "_G[1] = {{[\"end\"]=2}}\n_G[3] = {}\n"
+        );
+        let value = BTreeMap::from([("and", 1), ("x", 2)]);
+        assert_eq!(to_globals(&value).unwrap(), "_G[\"and\"] = 1\nx = 2\n");
+    }
+
+    #[test]
+    fn assigns_identifiers_directly_with_bracketed_keys() {
+        let value = BTreeMap::from([
+            ("db", BTreeMap::from([("x", 1)])),
+            ("not ok", BTreeMap::new()),
+        ]);
+        let mut ser = Serializer::with_formatter(Vec::new(), Globals::new(CompactFormatter));
+        value.serialize(&mut ser).unwrap();
+        assert_eq!(
+            String::from_utf8(ser.into_inner()).unwrap(),
+            "db = {[\"x\"]=1}\n_G[\"not ok\"] = {}\n"
+        );
+    }
+
+    #[test]
+    fn rejects_values_without_keys() {
+        assert!(to_globals(&1).is_err());
+        assert!(to_globals(&vec![1]).is_err());
+        assert_eq!(to_globals(&BTreeMap::<u8, u8>::new()).unwrap(), "");
+    }
+
+    #[test]
+    fn quotes_string_values_of_sorted_maps() {
+        let value = BTreeMap::from([("b", "x"), ("a", "y z")]);
+        for options in [
+            SerOptions::new(),
+            SerOptions::new().with_key_style(KeyStyle::Bare),
+        ] {
+            let options = options.with_key_order(crate::KeyOrder::Lexicographic);
+            let mut ser =
+                Serializer::with_options(Vec::new(), Globals::new(CompactFormatter), options);
+            value.serialize(&mut ser).unwrap();
+            assert_eq!(
+                String::from_utf8(ser.into_inner()).unwrap(),
+                "a = \"y z\"\nb = \"x\"\n"
+            );
+        }
+    }
+}
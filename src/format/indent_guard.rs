@@ -0,0 +1,431 @@
+use super::{CharEscape, Formatter};
+use crate::PathSegment;
+use std::io::{self, Write};
+
+/// Wraps a formatter and indents every line after the first one with a fixed prefix.
+///
+/// This keeps multi-line output aligned when it's embedded into code that is already indented,
+/// for example as the value of a nested assignment in a hand-written file. Only the line breaks
+/// of the formatter are indented, line breaks inside strings and raw lua are kept as they are.
+///
+/// ```
+/// use serde::Serialize;
+/// use serde_lua_table::{IndentGuard, PrettyFormatter, Serializer};
+///
+/// let formatter = IndentGuard::new(PrettyFormatter::new(), b"    ");
+/// let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+/// [1].serialize(&mut ser).unwrap();
+/// assert_eq!(ser.into_inner(), b"{\n      1\n    }");
+/// ```
+#[derive(Clone, Debug)]
+pub struct IndentGuard<'a, F> {
+    inner: F,
+    prefix: &'a [u8],
+    line_start: bool,
+}
+
+impl<'a, F> IndentGuard<'a, F> {
+    /// Construct a formatter that formats like `inner` and prefixes each new line with `prefix`.
+    pub fn new(inner: F, prefix: &'a [u8]) -> Self {
+        IndentGuard {
+            inner,
+            prefix,
+            line_start: false,
+        }
+    }
+
+    /// Unwrap the inner formatter.
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+}
+
+impl<'a, F> Formatter for IndentGuard<'a, F>
+where
+    F: Formatter,
+{
+    #[inline]
+    fn write_null<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.write_null(&mut writer)
+    }
+
+    #[inline]
+    fn write_bool<W>(&mut self, writer: &mut W, value: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.write_bool(&mut writer, value)
+    }
+
+    #[inline]
+    fn write_i8<W>(&mut self, writer: &mut W, value: i8) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.write_i8(&mut writer, value)
+    }
+
+    #[inline]
+    fn write_i16<W>(&mut self, writer: &mut W, value: i16) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.write_i16(&mut writer, value)
+    }
+
+    #[inline]
+    fn write_i32<W>(&mut self, writer: &mut W, value: i32) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.write_i32(&mut writer, value)
+    }
+
+    #[inline]
+    fn write_i64<W>(&mut self, writer: &mut W, value: i64) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.write_i64(&mut writer, value)
+    }
+
+    #[inline]
+    fn write_u8<W>(&mut self, writer: &mut W, value: u8) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.write_u8(&mut writer, value)
+    }
+
+    #[inline]
+    fn write_u16<W>(&mut self, writer: &mut W, value: u16) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.write_u16(&mut writer, value)
+    }
+
+    #[inline]
+    fn write_u32<W>(&mut self, writer: &mut W, value: u32) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.write_u32(&mut writer, value)
+    }
+
+    #[inline]
+    fn write_u64<W>(&mut self, writer: &mut W, value: u64) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.write_u64(&mut writer, value)
+    }
+
+    #[inline]
+    fn write_f32<W>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.write_f32(&mut writer, value)
+    }
+
+    #[inline]
+    fn write_f64<W>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.write_f64(&mut writer, value)
+    }
+
+    #[inline]
+    fn write_number_str<W>(&mut self, writer: &mut W, value: &str) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.write_number_str(&mut writer, value)
+    }
+
+    #[inline]
+    fn begin_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.begin_string(&mut writer)
+    }
+
+    #[inline]
+    fn end_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.end_string(&mut writer)
+    }
+
+    #[inline]
+    fn write_string_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::verbatim(writer, self.prefix, &mut self.line_start);
+        self.inner.write_string_fragment(&mut writer, fragment)
+    }
+
+    #[inline]
+    fn write_char_escape<W>(&mut self, writer: &mut W, char_escape: CharEscape) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::verbatim(writer, self.prefix, &mut self.line_start);
+        self.inner.write_char_escape(&mut writer, char_escape)
+    }
+
+    #[inline]
+    fn write_char_escape_v1<W>(&mut self, writer: &mut W, char_escape: CharEscape) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::verbatim(writer, self.prefix, &mut self.line_start);
+        self.inner.write_char_escape_v1(&mut writer, char_escape)
+    }
+
+    #[inline]
+    fn begin_array<W>(&mut self, writer: &mut W, len: Option<usize>) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.begin_array(&mut writer, len)
+    }
+
+    #[inline]
+    fn end_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.end_array(&mut writer)
+    }
+
+    #[inline]
+    fn begin_array_value<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.begin_array_value(&mut writer, first)
+    }
+
+    #[inline]
+    fn end_array_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.end_array_value(&mut writer)
+    }
+
+    #[inline]
+    fn begin_object<W>(&mut self, writer: &mut W, len: Option<usize>) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.begin_object(&mut writer, len)
+    }
+
+    #[inline]
+    fn end_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.end_object(&mut writer)
+    }
+
+    #[inline]
+    fn begin_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.begin_object_key(&mut writer, first)
+    }
+
+    #[inline]
+    fn end_object_key<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.end_object_key(&mut writer)
+    }
+
+    #[inline]
+    fn begin_bare_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.begin_bare_object_key(&mut writer, first)
+    }
+
+    #[inline]
+    fn end_bare_object_key<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.end_bare_object_key(&mut writer)
+    }
+
+    #[inline]
+    fn begin_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.begin_object_value(&mut writer)
+    }
+
+    #[inline]
+    fn end_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::new(writer, self.prefix, &mut self.line_start);
+        self.inner.end_object_value(&mut writer)
+    }
+
+    #[inline]
+    fn write_raw_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let mut writer = PrefixLines::verbatim(writer, self.prefix, &mut self.line_start);
+        self.inner.write_raw_fragment(&mut writer, fragment)
+    }
+
+    #[inline]
+    fn wants_key_paths(&self) -> bool {
+        self.inner.wants_key_paths()
+    }
+
+    #[inline]
+    fn key_path(&mut self, path: &[PathSegment]) {
+        self.inner.key_path(path)
+    }
+}
+
+/// Writes the prefix before the first byte following a line break.
+///
+/// The prefix is written lazily, so output ending in a newline doesn't end with a dangling
+/// prefix.
+struct PrefixLines<'w, W: ?Sized> {
+    writer: &'w mut W,
+    prefix: &'w [u8],
+    line_start: &'w mut bool,
+    /// Line breaks are part of a string or raw lua, so indenting them would change the value.
+    verbatim: bool,
+}
+
+impl<'w, W> PrefixLines<'w, W>
+where
+    W: ?Sized + Write,
+{
+    fn new(writer: &'w mut W, prefix: &'w [u8], line_start: &'w mut bool) -> Self {
+        PrefixLines {
+            writer,
+            prefix,
+            line_start,
+            verbatim: false,
+        }
+    }
+
+    /// Still writes a pending prefix, but doesn't start new lines after the line breaks in the
+    /// output.
+    fn verbatim(writer: &'w mut W, prefix: &'w [u8], line_start: &'w mut bool) -> Self {
+        PrefixLines {
+            verbatim: true,
+            ..PrefixLines::new(writer, prefix, line_start)
+        }
+    }
+}
+
+impl<'w, W> Write for PrefixLines<'w, W>
+where
+    W: ?Sized + Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        if self.verbatim {
+            if *self.line_start && !buf.is_empty() {
+                self.writer.write_all(self.prefix)?;
+                *self.line_start = false;
+            }
+            return self.writer.write_all(buf);
+        }
+        for line in buf.split_inclusive(|&b| b == b'\n') {
+            if *self.line_start {
+                self.writer.write_all(self.prefix)?;
+                *self.line_start = false;
+            }
+            self.writer.write_all(line)?;
+            *self.line_start = line.ends_with(b"\n");
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PrettyFormatter, RawLua, Serializer};
+    use serde::Serialize;
+
+    fn indented<T: Serialize>(value: &T) -> String {
+        let formatter = IndentGuard::new(PrettyFormatter::new(), b"    ");
+        let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+        value.serialize(&mut ser).unwrap();
+        String::from_utf8(ser.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn keeps_line_breaks_in_raw_lua() {
+        let raw = RawLua("function()\n  return [[a\nb]]\nend");
+        assert_eq!(
+            indented(&(1, raw, 2)),
+            "{\n      1,\n      function()\n  return [[a\nb]]\nend,\n      2\n    }"
+        );
+    }
+
+    #[test]
+    fn keeps_escaped_strings_on_one_line() {
+        assert_eq!(indented(&["a\nb"]), "{\n      \"a\\nb\"\n    }");
+    }
+}
@@ -1,11 +1,26 @@
+mod any;
 mod character_escape;
+mod comment_injecting;
 mod compact;
+mod fennel;
+mod field_styles;
+mod globals;
+mod indent_guard;
 mod pretty;
+mod with_header;
 
+use crate::PathSegment;
+pub use any::*;
 pub use character_escape::*;
+pub use comment_injecting::*;
 pub use compact::*;
+pub use fennel::*;
+pub use field_styles::*;
+pub use globals::*;
+pub use indent_guard::*;
 pub use pretty::*;
 use std::io::{self, Write};
+pub use with_header::*;
 
 /// This trait abstracts away serializing the lua control characters, which allows the user to
 /// optionally pretty print the lua output.
@@ -121,23 +136,31 @@ pub trait Formatter {
         writer.write_all(s.as_bytes())
     }
 
-    /// Writes a floating point value like `-31.26e+12` to the specified writer.
+    /// Writes a floating point value like `-31.26e+12` to the specified writer. Infinities and
+    /// `NaN` are written as the expressions `math.huge`, `-math.huge` and `0/0`.
     #[inline]
     fn write_f32<W>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
     where
         W: ?Sized + Write,
     {
+        if !value.is_finite() {
+            return writer.write_all(non_finite(f64::from(value)));
+        }
         let mut buffer = ryu::Buffer::new();
         let s = buffer.format_finite(value);
         writer.write_all(s.as_bytes())
     }
 
-    /// Writes a floating point value like `-31.26e+12` to the specified writer.
+    /// Writes a floating point value like `-31.26e+12` to the specified writer. Infinities and
+    /// `NaN` are written as the expressions `math.huge`, `-math.huge` and `0/0`.
     #[inline]
     fn write_f64<W>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
     where
         W: ?Sized + Write,
     {
+        if !value.is_finite() {
+            return writer.write_all(non_finite(value));
+        }
         let mut buffer = ryu::Buffer::new();
         let s = buffer.format_finite(value);
         writer.write_all(s.as_bytes())
@@ -200,14 +223,13 @@ pub trait Formatter {
             CarriageReturn => b"\\r",
             Tab => b"\\t",
             AsciiControl(byte) => {
-                static HEX_DIGITS: [u8; 16] = *b"0123456789abcdef";
+                // Decimal escapes are understood by every lua version, unlike `\x` and `\u{}`.
+                // Three digits keep a following digit from being read as part of the escape.
                 let bytes = &[
                     b'\\',
-                    b'u',
-                    b'0',
-                    b'0',
-                    HEX_DIGITS[(byte >> 4) as usize],
-                    HEX_DIGITS[(byte & 0xF) as usize],
+                    b'0' + byte / 100,
+                    b'0' + byte / 10 % 10,
+                    b'0' + byte % 10,
                 ];
                 return writer.write_all(bytes);
             }
@@ -216,10 +238,35 @@ pub trait Formatter {
         writer.write_all(s)
     }
 
+    /// Writes a character escape code the way version 0.1 did, for
+    /// [`OutputStability::V1`](crate::OutputStability::V1).  ASCII control
+    /// characters are written as `\u00XX`, which lua can't load.
+    #[inline]
+    fn write_char_escape_v1<W>(&mut self, writer: &mut W, char_escape: CharEscape) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        match char_escape {
+            CharEscape::AsciiControl(byte) => {
+                static HEX_DIGITS: [u8; 16] = *b"0123456789abcdef";
+                writer.write_all(&[
+                    b'\\',
+                    b'u',
+                    b'0',
+                    b'0',
+                    HEX_DIGITS[(byte >> 4) as usize],
+                    HEX_DIGITS[(byte & 0xF) as usize],
+                ])
+            }
+            char_escape => self.write_char_escape(writer, char_escape),
+        }
+    }
+
     /// Called before every array.  Writes a `{` to the specified
-    /// writer.
+    /// writer.  `len` is the number of elements, if it's known
+    /// upfront.
     #[inline]
-    fn begin_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    fn begin_array<W>(&mut self, writer: &mut W, _len: Option<usize>) -> io::Result<()>
     where
         W: ?Sized + Write,
     {
@@ -260,9 +307,10 @@ pub trait Formatter {
     }
 
     /// Called before every object.  Writes a `{` to the specified
-    /// writer.
+    /// writer.  `len` is the number of entries, if it's known
+    /// upfront.
     #[inline]
-    fn begin_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    fn begin_object<W>(&mut self, writer: &mut W, _len: Option<usize>) -> io::Result<()>
     where
         W: ?Sized + Write,
     {
@@ -303,6 +351,30 @@ pub trait Formatter {
         writer.write_all(b"]")
     }
 
+    /// Called before every object key that is written as a bare identifier
+    /// instead of in brackets. The identifier itself is written with
+    /// `write_string_fragment`.
+    #[inline]
+    fn begin_bare_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if first {
+            Ok(())
+        } else {
+            writer.write_all(b",")
+        }
+    }
+
+    /// Called after every object key that is written as a bare identifier.
+    #[inline]
+    fn end_bare_object_key<W>(&mut self, _writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        Ok(())
+    }
+
     /// Called before every object value.  A `=` should be written to
     /// the specified writer by either this method or
     /// `end_object_key`.
@@ -323,6 +395,21 @@ pub trait Formatter {
         Ok(())
     }
 
+    /// Returns whether the formatter needs [`key_path`](Formatter::key_path).
+    /// Tracking the path allocates, so the serializer only does it when
+    /// asked to.
+    #[inline]
+    fn wants_key_paths(&self) -> bool {
+        false
+    }
+
+    /// Called before every object key with the path to its value, if
+    /// [`wants_key_paths`](Formatter::wants_key_paths) returns `true`.
+    #[inline]
+    fn key_path(&mut self, path: &[PathSegment]) {
+        let _ = path;
+    }
+
     /// Writes a raw Lua fragment that doesn't need any escaping to the
     /// specified writer.
     #[inline]
@@ -333,3 +420,88 @@ pub trait Formatter {
         writer.write_all(fragment.as_bytes())
     }
 }
+
+/// The lua expression of an infinity or `NaN`, the way the deserializer reads them.
+fn non_finite(value: f64) -> &'static [u8] {
+    if value.is_nan() {
+        b"0/0"
+    } else if value > 0.0 {
+        b"math.huge"
+    } else {
+        b"-math.huge"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Formatter;
+    use mlua::Lua;
+    use serde::{Serialize, Serializer as _};
+    use std::io::{self, Write};
+
+    #[test]
+    fn writes_control_characters_as_decimal_escapes() {
+        let value = "\0\x01\x1f1\x7f";
+        let lua = crate::to_string(value).unwrap();
+        assert_eq!(lua, "\"\\000\\001\\0311\u{7f}\"");
+
+        let loaded: String = Lua::new().load(&lua).eval().unwrap();
+        assert_eq!(loaded, value);
+    }
+
+    #[test]
+    fn writes_infinities_and_nan_as_expressions() {
+        let values = [f64::INFINITY, f64::NEG_INFINITY, f64::NAN];
+        let lua = crate::to_string(&values).unwrap();
+        assert_eq!(lua, "{math.huge,-math.huge,0/0}");
+
+        let read: Vec<f64> = crate::from_str(&lua).unwrap();
+        assert_eq!(read[..2], values[..2]);
+        assert!(read[2].is_nan());
+        assert_eq!(crate::to_string(&f32::NEG_INFINITY).unwrap(), "-math.huge");
+    }
+
+    /// Records the length hints of the tables it writes.
+    #[derive(Default)]
+    struct LengthHints(Vec<Option<usize>>);
+
+    impl Formatter for &mut LengthHints {
+        fn begin_array<W>(&mut self, writer: &mut W, len: Option<usize>) -> io::Result<()>
+        where
+            W: ?Sized + Write,
+        {
+            self.0.push(len);
+            writer.write_all(b"{")
+        }
+
+        fn begin_object<W>(&mut self, writer: &mut W, len: Option<usize>) -> io::Result<()>
+        where
+            W: ?Sized + Write,
+        {
+            self.0.push(len);
+            writer.write_all(b"{")
+        }
+    }
+
+    #[test]
+    fn passes_length_hints_to_tables() {
+        #[derive(Serialize)]
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        #[derive(Serialize)]
+        enum Shape {
+            Circle(f64),
+        }
+
+        let mut hints = LengthHints::default();
+        let mut ser = crate::Serializer::with_formatter(Vec::new(), &mut hints);
+        vec![1, 2, 3].serialize(&mut ser).unwrap();
+        Point { x: 1, y: 2 }.serialize(&mut ser).unwrap();
+        Shape::Circle(1.0).serialize(&mut ser).unwrap();
+        ser.collect_seq((0..4).filter(|i| i % 2 == 0)).unwrap();
+        assert_eq!(hints.0, [Some(3), Some(2), Some(1), None]);
+    }
+}
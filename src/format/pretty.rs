@@ -1,12 +1,17 @@
 use super::Formatter;
 use std::io::{self, Write};
 
+/// How many bytes of a newline followed by the indentation are kept ready to be written at once.
+const LINE_CAPACITY: usize = 64;
+
 /// This structure pretty prints a lua value to make it human readable.
 #[derive(Clone, Debug)]
 pub struct PrettyFormatter<'a> {
     current_indent: usize,
     has_value: bool,
     indent: &'a [u8],
+    /// A newline followed by as many `indent`s as fit.
+    line: [u8; LINE_CAPACITY],
 }
 
 impl<'a> PrettyFormatter<'a> {
@@ -17,10 +22,37 @@ impl<'a> PrettyFormatter<'a> {
 
     /// Construct a pretty printer formatter that uses the `indent` string for indentation.
     pub fn with_indent(indent: &'a [u8]) -> Self {
+        let mut line = [0; LINE_CAPACITY];
+        line[0] = b'\n';
+        if !indent.is_empty() {
+            let mut end = 1;
+            while end + indent.len() <= LINE_CAPACITY {
+                line[end..end + indent.len()].copy_from_slice(indent);
+                end += indent.len();
+            }
+        }
+
         PrettyFormatter {
             current_indent: 0,
             has_value: false,
             indent,
+            line,
+        }
+    }
+
+    /// Writes a newline and the current indentation, with a single write unless the value is
+    /// nested very deeply.
+    #[inline]
+    fn newline<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        let len = 1 + self.current_indent * self.indent.len();
+        if len <= LINE_CAPACITY {
+            writer.write_all(&self.line[..len])
+        } else {
+            writer.write_all(b"\n")?;
+            indent(writer, self.current_indent, self.indent)
         }
     }
 }
@@ -33,7 +65,7 @@ impl<'a> Default for PrettyFormatter<'a> {
 
 impl<'a> Formatter for PrettyFormatter<'a> {
     #[inline]
-    fn begin_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    fn begin_array<W>(&mut self, writer: &mut W, _len: Option<usize>) -> io::Result<()>
     where
         W: ?Sized + Write,
     {
@@ -50,8 +82,7 @@ impl<'a> Formatter for PrettyFormatter<'a> {
         self.current_indent -= 1;
 
         if self.has_value {
-            writer.write_all(b"\n")?;
-            indent(writer, self.current_indent, self.indent)?;
+            self.newline(writer)?;
         }
 
         writer.write_all(b"}")
@@ -62,12 +93,10 @@ impl<'a> Formatter for PrettyFormatter<'a> {
     where
         W: ?Sized + Write,
     {
-        if first {
-            writer.write_all(b"\n")?;
-        } else {
-            writer.write_all(b",\n")?;
+        if !first {
+            writer.write_all(b",")?;
         }
-        indent(writer, self.current_indent, self.indent)?;
+        self.newline(writer)?;
         Ok(())
     }
 
@@ -81,7 +110,7 @@ impl<'a> Formatter for PrettyFormatter<'a> {
     }
 
     #[inline]
-    fn begin_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    fn begin_object<W>(&mut self, writer: &mut W, _len: Option<usize>) -> io::Result<()>
     where
         W: ?Sized + Write,
     {
@@ -98,8 +127,7 @@ impl<'a> Formatter for PrettyFormatter<'a> {
         self.current_indent -= 1;
 
         if self.has_value {
-            writer.write_all(b"\n")?;
-            indent(writer, self.current_indent, self.indent)?;
+            self.newline(writer)?;
         }
 
         writer.write_all(b"}")
@@ -110,12 +138,10 @@ impl<'a> Formatter for PrettyFormatter<'a> {
     where
         W: ?Sized + Write,
     {
-        if first {
-            writer.write_all(b"\n")?;
-        } else {
-            writer.write_all(b",\n")?;
+        if !first {
+            writer.write_all(b",")?;
         }
-        indent(writer, self.current_indent, self.indent)?;
+        self.newline(writer)?;
         writer.write_all(b"[")
     }
 
@@ -127,6 +153,25 @@ impl<'a> Formatter for PrettyFormatter<'a> {
         writer.write_all(b"] ")
     }
 
+    #[inline]
+    fn begin_bare_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if !first {
+            writer.write_all(b",")?;
+        }
+        self.newline(writer)
+    }
+
+    #[inline]
+    fn end_bare_object_key<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        writer.write_all(b" ")
+    }
+
     #[inline]
     fn begin_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
     where
@@ -155,3 +200,62 @@ where
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::PrettyFormatter;
+    use crate::Serializer;
+    use serde::{ser::SerializeMap, Serialize};
+
+    /// Sequences and maps alternately, nested as deep as the field says.
+    struct Nested(usize);
+
+    impl Serialize for Nested {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            match self.0 {
+                0 => serializer.serialize_u8(1),
+                depth if depth % 2 == 0 => [Nested(depth - 1)].serialize(serializer),
+                depth => {
+                    let mut map = serializer.serialize_map(Some(1))?;
+                    map.serialize_entry("x", &Nested(depth - 1))?;
+                    map.end()
+                }
+            }
+        }
+    }
+
+    /// The pretty output of `Nested(depth)` at `level`, written line by line.
+    fn expected(depth: usize, level: usize, indent: &str) -> String {
+        let inner = match depth {
+            0 => return "1".to_owned(),
+            depth if depth % 2 == 0 => expected(depth - 1, level + 1, indent),
+            depth => format!("[\"x\"] = {}", expected(depth - 1, level + 1, indent)),
+        };
+        format!(
+            "{{\n{}{}\n{}}}",
+            indent.repeat(level + 1),
+            inner,
+            indent.repeat(level)
+        )
+    }
+
+    #[test]
+    fn indents_beyond_the_line_buffer() {
+        for indent in ["", "\t", "  ", "   "] {
+            let mut ser = Serializer::with_formatter(
+                Vec::new(),
+                PrettyFormatter::with_indent(indent.as_bytes()),
+            );
+            Nested(50).serialize(&mut ser).unwrap();
+            assert_eq!(
+                String::from_utf8(ser.into_inner()).unwrap(),
+                expected(50, 0, indent),
+                "{indent:?}"
+            );
+        }
+        assert_eq!(crate::to_string_pretty(&Vec::<u8>::new()).unwrap(), "{}");
+    }
+}
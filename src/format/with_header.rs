@@ -0,0 +1,345 @@
+use super::{CharEscape, Formatter};
+use crate::PathSegment;
+use std::io::{self, Write};
+
+/// Wraps a formatter and writes a fixed header before the first value.
+///
+/// The header is written as is, so it can contain comments (`-- generated file\n`) or the start
+/// of a chunk (`return `). It is only written once per formatter.
+///
+/// ```
+/// use serde::Serialize;
+/// use serde_lua_table::{CompactFormatter, Serializer, WithHeader};
+///
+/// let formatter = WithHeader::new(CompactFormatter, b"return ");
+/// let mut ser = Serializer::with_formatter(Vec::new(), formatter);
+/// [1, 2].serialize(&mut ser).unwrap();
+/// assert_eq!(ser.into_inner(), b"return {1,2}");
+/// ```
+#[derive(Clone, Debug)]
+pub struct WithHeader<'a, F> {
+    inner: F,
+    header: &'a [u8],
+    written: bool,
+}
+
+impl<'a, F> WithHeader<'a, F> {
+    /// Construct a formatter that writes `header` and then formats like `inner`.
+    pub fn new(inner: F, header: &'a [u8]) -> Self {
+        WithHeader {
+            inner,
+            header,
+            written: false,
+        }
+    }
+
+    /// Unwrap the inner formatter.
+    pub fn into_inner(self) -> F {
+        self.inner
+    }
+
+    fn write_header<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        if !self.written {
+            self.written = true;
+            writer.write_all(self.header)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, F> Formatter for WithHeader<'a, F>
+where
+    F: Formatter,
+{
+    #[inline]
+    fn write_null<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.write_null(writer)
+    }
+
+    #[inline]
+    fn write_bool<W>(&mut self, writer: &mut W, value: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.write_bool(writer, value)
+    }
+
+    #[inline]
+    fn write_i8<W>(&mut self, writer: &mut W, value: i8) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.write_i8(writer, value)
+    }
+
+    #[inline]
+    fn write_i16<W>(&mut self, writer: &mut W, value: i16) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.write_i16(writer, value)
+    }
+
+    #[inline]
+    fn write_i32<W>(&mut self, writer: &mut W, value: i32) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.write_i32(writer, value)
+    }
+
+    #[inline]
+    fn write_i64<W>(&mut self, writer: &mut W, value: i64) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.write_i64(writer, value)
+    }
+
+    #[inline]
+    fn write_u8<W>(&mut self, writer: &mut W, value: u8) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.write_u8(writer, value)
+    }
+
+    #[inline]
+    fn write_u16<W>(&mut self, writer: &mut W, value: u16) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.write_u16(writer, value)
+    }
+
+    #[inline]
+    fn write_u32<W>(&mut self, writer: &mut W, value: u32) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.write_u32(writer, value)
+    }
+
+    #[inline]
+    fn write_u64<W>(&mut self, writer: &mut W, value: u64) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.write_u64(writer, value)
+    }
+
+    #[inline]
+    fn write_f32<W>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.write_f32(writer, value)
+    }
+
+    #[inline]
+    fn write_f64<W>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.write_f64(writer, value)
+    }
+
+    #[inline]
+    fn write_number_str<W>(&mut self, writer: &mut W, value: &str) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.write_number_str(writer, value)
+    }
+
+    #[inline]
+    fn begin_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.begin_string(writer)
+    }
+
+    #[inline]
+    fn end_string<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.end_string(writer)
+    }
+
+    #[inline]
+    fn write_string_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.write_string_fragment(writer, fragment)
+    }
+
+    #[inline]
+    fn write_char_escape<W>(&mut self, writer: &mut W, char_escape: CharEscape) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.write_char_escape(writer, char_escape)
+    }
+
+    #[inline]
+    fn write_char_escape_v1<W>(&mut self, writer: &mut W, char_escape: CharEscape) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.write_char_escape_v1(writer, char_escape)
+    }
+
+    #[inline]
+    fn begin_array<W>(&mut self, writer: &mut W, len: Option<usize>) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.begin_array(writer, len)
+    }
+
+    #[inline]
+    fn end_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.end_array(writer)
+    }
+
+    #[inline]
+    fn begin_array_value<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.begin_array_value(writer, first)
+    }
+
+    #[inline]
+    fn end_array_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.end_array_value(writer)
+    }
+
+    #[inline]
+    fn begin_object<W>(&mut self, writer: &mut W, len: Option<usize>) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.begin_object(writer, len)
+    }
+
+    #[inline]
+    fn end_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.end_object(writer)
+    }
+
+    #[inline]
+    fn begin_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.begin_object_key(writer, first)
+    }
+
+    #[inline]
+    fn end_object_key<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.end_object_key(writer)
+    }
+
+    #[inline]
+    fn begin_bare_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.begin_bare_object_key(writer, first)
+    }
+
+    #[inline]
+    fn end_bare_object_key<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.end_bare_object_key(writer)
+    }
+
+    #[inline]
+    fn begin_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.begin_object_value(writer)
+    }
+
+    #[inline]
+    fn end_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.end_object_value(writer)
+    }
+
+    #[inline]
+    fn write_raw_fragment<W>(&mut self, writer: &mut W, fragment: &str) -> io::Result<()>
+    where
+        W: ?Sized + Write,
+    {
+        self.write_header(writer)?;
+        self.inner.write_raw_fragment(writer, fragment)
+    }
+
+    #[inline]
+    fn wants_key_paths(&self) -> bool {
+        self.inner.wants_key_paths()
+    }
+
+    #[inline]
+    fn key_path(&mut self, path: &[PathSegment]) {
+        self.inner.key_path(path)
+    }
+}
@@ -0,0 +1,143 @@
+//! Adapters for [`chrono::DateTime<Utc>`](::chrono::DateTime).
+//!
+//! Both adapters accept either representation when deserializing.
+//!
+//! ```
+//! use chrono::{DateTime, Utc};
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct Save {
+//!     #[serde(with = "serde_lua_table::helpers::chrono::epoch")]
+//!     created: DateTime<Utc>,
+//!     #[serde(with = "serde_lua_table::helpers::chrono::date_table")]
+//!     updated: DateTime<Utc>,
+//! }
+//!
+//! let time = DateTime::from_timestamp(86400, 0).unwrap();
+//! let lua = serde_lua_table::to_string(&Save { created: time, updated: time }).unwrap();
+//! assert_eq!(
+//!     lua,
+//!     r#"{["created"]=86400,["updated"]={["year"]=1970,["month"]=1,["day"]=2,["hour"]=0,["min"]=0,["sec"]=0,["wday"]=6,["yday"]=2,["isdst"]=false}}"#
+//! );
+//! ```
+
+use super::date_table::{out_of_range, DateTable, Timestamp};
+use ::chrono::{DateTime, Datelike, NaiveDate, Timelike, Utc};
+use serde::{Deserialize, Deserializer};
+
+fn from_timestamp<E: serde::de::Error>(timestamp: Timestamp) -> Result<DateTime<Utc>, E> {
+    match timestamp {
+        Timestamp::Epoch(secs) => DateTime::from_timestamp(secs, 0),
+        Timestamp::Table(table) => {
+            NaiveDate::from_ymd_opt(table.year, table.month.into(), table.day.into())
+                .and_then(|date| {
+                    date.and_hms_opt(table.hour.into(), table.min.into(), table.sec.into())
+                })
+                .map(|time| time.and_utc())
+        }
+    }
+    .ok_or_else(out_of_range)
+}
+
+/// Writes the time as whole seconds since the unix epoch, like `os.time()`.
+pub mod epoch {
+    use super::*;
+    use serde::Serializer;
+
+    pub fn serialize<S>(time: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_i64(time.timestamp())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        from_timestamp(Timestamp::deserialize(deserializer)?)
+    }
+}
+
+/// Writes the time as a table in UTC, like `os.date("!*t")`.
+pub mod date_table {
+    use super::*;
+    use serde::{Serialize, Serializer};
+
+    pub fn serialize<S>(time: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        DateTable {
+            year: time.year(),
+            month: time.month() as u8,
+            day: time.day() as u8,
+            hour: time.hour() as u8,
+            min: time.minute() as u8,
+            sec: time.second() as u8,
+            wday: time.weekday().number_from_sunday() as u8,
+            yday: time.ordinal() as u16,
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        from_timestamp(Timestamp::deserialize(deserializer)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_str, to_string};
+    use ::chrono::{DateTime, Utc};
+    use mlua::Lua;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Save {
+        #[serde(with = "super::epoch")]
+        created: DateTime<Utc>,
+        #[serde(with = "super::date_table")]
+        updated: DateTime<Utc>,
+    }
+
+    #[test]
+    fn round_trips_both_representations() {
+        let save = Save {
+            created: DateTime::from_timestamp(-86400, 0).unwrap(),
+            updated: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        };
+        let lua = to_string(&save).unwrap();
+        assert_eq!(from_str::<Save>(&lua).unwrap(), save);
+    }
+
+    #[test]
+    fn reads_either_representation_and_tables_from_lua() {
+        let lua = Lua::new();
+        let table: mlua::Value = lua.load("os.date('!*t', 1700000000)").eval().unwrap();
+        let table = to_string(&table).unwrap();
+        let save: Save =
+            from_str(&format!("{{created = {table}, updated = 1700000000.0}}")).unwrap();
+        let time = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        assert_eq!(
+            save,
+            Save {
+                created: time,
+                updated: time
+            }
+        );
+
+        let noon: Save =
+            from_str("{created = 0, updated = {year = 2000, month = 1, day = 1}}").unwrap();
+        assert_eq!(noon.updated.to_rfc3339(), "2000-01-01T12:00:00+00:00");
+        assert!(
+            from_str::<Save>("{created = 0, updated = {year = 2000, month = 13, day = 1}}")
+                .is_err()
+        );
+        assert!(from_str::<Save>("{created = 0.5, updated = 0}").is_err());
+    }
+}
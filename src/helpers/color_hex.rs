@@ -0,0 +1,72 @@
+//! Writes colors stored as `0xRRGGBB` integers as `"#rrggbb"` strings.
+//!
+//! Values that don't fit into 24 bits are written with eight digits (`"#rrggbbaa"`).
+
+use serde::{de, Deserializer, Serializer};
+use std::fmt;
+
+pub fn serialize<S>(color: &u32, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let hex = if *color <= 0xFF_FFFF {
+        format!("#{:06x}", color)
+    } else {
+        format!("#{:08x}", color)
+    };
+    serializer.serialize_str(&hex)
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_str(ColorVisitor)
+}
+
+struct ColorVisitor;
+
+impl<'de> de::Visitor<'de> for ColorVisitor {
+    type Value = u32;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a color like \"#rrggbb\" or \"#rrggbbaa\"")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        v.strip_prefix('#')
+            .filter(|hex| hex.len() == 6 || hex.len() == 8)
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(v), &self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_str, to_string};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Color(#[serde(with = "super")] u32);
+
+    #[test]
+    fn round_trips_colors_with_and_without_alpha() {
+        assert_eq!(to_string(&Color(0x00FF80)).unwrap(), "\"#00ff80\"");
+        assert_eq!(to_string(&Color(0x11223344)).unwrap(), "\"#11223344\"");
+        for color in [0, 0x00FF80, 0xFFFFFF, 0x11223344] {
+            let lua = to_string(&Color(color)).unwrap();
+            assert_eq!(from_str::<Color>(&lua).unwrap(), Color(color));
+        }
+        assert_eq!(from_str::<Color>("'#ABCDEF'").unwrap(), Color(0xABCDEF));
+    }
+
+    #[test]
+    fn rejects_malformed_colors() {
+        for lua in ["'ff8800'", "'#ff88'", "'#gg8800'", "16746496"] {
+            assert!(from_str::<Color>(lua).is_err(), "{lua}");
+        }
+    }
+}
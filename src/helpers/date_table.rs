@@ -0,0 +1,158 @@
+use serde::{
+    de::{self, value::MapAccessDeserializer},
+    ser::SerializeStruct,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::fmt;
+
+/// A date in the format of the tables returned by `os.date("*t")` and accepted by `os.time()`.
+pub(crate) struct DateTable {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub min: u8,
+    pub sec: u8,
+    /// Day of the week, starting with sunday as `1`.
+    pub wday: u8,
+    /// Day of the year, starting with january 1st as `1`.
+    pub yday: u16,
+}
+
+impl Serialize for DateTable {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut table = serializer.serialize_struct("DateTable", 9)?;
+        table.serialize_field("year", &self.year)?;
+        table.serialize_field("month", &self.month)?;
+        table.serialize_field("day", &self.day)?;
+        table.serialize_field("hour", &self.hour)?;
+        table.serialize_field("min", &self.min)?;
+        table.serialize_field("sec", &self.sec)?;
+        table.serialize_field("wday", &self.wday)?;
+        table.serialize_field("yday", &self.yday)?;
+        table.serialize_field("isdst", &false)?;
+        table.end()
+    }
+}
+
+/// A point in time, either as seconds since the unix epoch or as a date table.
+pub(crate) enum Timestamp {
+    Epoch(i64),
+    Table(DateTable),
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(TimestampVisitor)
+    }
+}
+
+struct TimestampVisitor;
+
+impl<'de> de::Visitor<'de> for TimestampVisitor {
+    type Value = Timestamp;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("seconds since the unix epoch or a date table")
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Timestamp::Epoch(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        i64::try_from(v)
+            .map(Timestamp::Epoch)
+            .map_err(|_| de::Error::invalid_value(de::Unexpected::Unsigned(v), &self))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        // Lua 5.1 and LuaJIT only have floats.
+        if v.fract() == 0.0 && v >= i64::MIN as f64 && v < i64::MAX as f64 {
+            Ok(Timestamp::Epoch(v as i64))
+        } else {
+            Err(de::Error::invalid_value(de::Unexpected::Float(v), &self))
+        }
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        DateTable::deserialize(MapAccessDeserializer::new(map)).map(Timestamp::Table)
+    }
+}
+
+impl<'de> Deserialize<'de> for DateTable {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(DateTableVisitor)
+    }
+}
+
+struct DateTableVisitor;
+
+impl<'de> de::Visitor<'de> for DateTableVisitor {
+    type Value = DateTable;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a date table")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let mut year = None;
+        let mut month = None;
+        let mut day = None;
+        // `os.time()` uses noon if the hour is missing.
+        let mut hour = 12;
+        let mut min = 0;
+        let mut sec = 0;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "year" => year = Some(map.next_value()?),
+                "month" => month = Some(map.next_value()?),
+                "day" => day = Some(map.next_value()?),
+                "hour" => hour = map.next_value()?,
+                "min" => min = map.next_value()?,
+                "sec" => sec = map.next_value()?,
+                _ => {
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(DateTable {
+            year: year.ok_or_else(|| de::Error::missing_field("year"))?,
+            month: month.ok_or_else(|| de::Error::missing_field("month"))?,
+            day: day.ok_or_else(|| de::Error::missing_field("day"))?,
+            hour,
+            min,
+            sec,
+            wday: 0,
+            yday: 0,
+        })
+    }
+}
+
+pub(crate) fn out_of_range<E: de::Error>() -> E {
+    E::custom("date out of range")
+}
@@ -0,0 +1,157 @@
+//! Adapters for [`rust_decimal::Decimal`](::rust_decimal::Decimal) that don't lose precision
+//! by going through floats.
+//!
+//! ```
+//! use rust_decimal::Decimal;
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct Invoice {
+//!     #[serde(with = "serde_lua_table::helpers::decimal::string")]
+//!     total: Decimal,
+//!     #[serde(serialize_with = "serde_lua_table::helpers::decimal::scaled::serialize::<_, 2>")]
+//!     cents: Decimal,
+//! }
+//!
+//! let total = Decimal::new(1999, 2);
+//! let lua = serde_lua_table::to_string(&Invoice { total, cents: total }).unwrap();
+//! assert_eq!(lua, r#"{["total"]="19.99",["cents"]=1999}"#);
+//! ```
+
+use ::rust_decimal::Decimal;
+use serde::de;
+use std::fmt;
+
+/// Writes the decimal as a string with its exact digits, like `"19.99"`.
+///
+/// Deserializing accepts such strings and integers.
+pub mod string {
+    use super::*;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S>(value: &Decimal, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Decimal, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(DecimalVisitor)
+    }
+
+    struct DecimalVisitor;
+
+    impl<'de> de::Visitor<'de> for DecimalVisitor {
+        type Value = Decimal;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a decimal number as a string or an integer")
+        }
+
+        fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(v.into())
+        }
+
+        fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(v.into())
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Decimal::from_str_exact(v)
+                .map_err(|_| de::Error::invalid_value(de::Unexpected::Str(v), &self))
+        }
+    }
+}
+
+/// Writes the decimal as an integer number of `10^-SCALE` units, e.g. cents for a scale of `2`.
+///
+/// Serializing fails if the value has more decimal places than `SCALE` or if the scaled value
+/// doesn't fit into a lua integer. Use the functions with `serialize_with` and
+/// `deserialize_with`, passing the scale as the last generic argument.
+pub mod scaled {
+    use super::*;
+    use serde::{ser, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S, const SCALE: u32>(value: &Decimal, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if value.normalize().scale() > SCALE {
+            return Err(ser::Error::custom(format_args!(
+                "{} has more than {} decimal places",
+                value, SCALE
+            )));
+        }
+        let mut scaled = *value;
+        scaled.rescale(SCALE);
+        let units = i64::try_from(scaled.mantissa())
+            .map_err(|_| ser::Error::custom(format_args!("{} is out of range", value)))?;
+        serializer.serialize_i64(units)
+    }
+
+    pub fn deserialize<'de, D, const SCALE: u32>(deserializer: D) -> Result<Decimal, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let units = i64::deserialize(deserializer)?;
+        Decimal::try_new(units, SCALE).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_str, to_string};
+    use ::rust_decimal::Decimal;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Invoice {
+        #[serde(with = "super::string")]
+        total: Decimal,
+        #[serde(
+            serialize_with = "super::scaled::serialize::<_, 2>",
+            deserialize_with = "super::scaled::deserialize::<_, 2>"
+        )]
+        cents: Decimal,
+    }
+
+    #[test]
+    fn round_trips_without_losing_digits() {
+        let invoice = Invoice {
+            total: "12345678901234567.89".parse().unwrap(),
+            cents: Decimal::new(-5, 1),
+        };
+        let lua = to_string(&invoice).unwrap();
+        assert_eq!(lua, r#"{["total"]="12345678901234567.89",["cents"]=-50}"#);
+        assert_eq!(from_str::<Invoice>(&lua).unwrap(), invoice);
+        let invoice: Invoice = from_str("{total = 3, cents = 199}").unwrap();
+        assert_eq!(
+            (invoice.total, invoice.cents),
+            (3.into(), Decimal::new(199, 2))
+        );
+    }
+
+    #[test]
+    fn rejects_values_that_would_lose_digits() {
+        let invoice = Invoice {
+            total: Decimal::ONE,
+            cents: Decimal::new(1001, 3),
+        };
+        assert!(to_string(&invoice).is_err());
+        assert!(from_str::<Invoice>("{total = 1.5, cents = 0}").is_err());
+        assert!(from_str::<Invoice>("{total = '1.5.0', cents = 0}").is_err());
+    }
+}
@@ -0,0 +1,253 @@
+//! Adapters for [`Duration`].
+//!
+//! Every adapter reads all three representations: numbers are taken in the adapter's unit, and a
+//! `{secs = ..., nanos = ...}` table is read as is.
+//!
+//! ```
+//! use mlua::{Lua, LuaSerdeExt, Value};
+//! use serde::{Deserialize, Serialize};
+//! use std::time::Duration;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Timers {
+//!     #[serde(with = "serde_lua_table::helpers::duration::seconds")]
+//!     cooldown: Duration,
+//!     #[serde(with = "serde_lua_table::helpers::duration::millis")]
+//!     tick: Duration,
+//!     #[serde(with = "serde_lua_table::helpers::duration::table")]
+//!     uptime: Duration,
+//! }
+//!
+//! let timers = Timers {
+//!     cooldown: Duration::from_millis(1500),
+//!     tick: Duration::from_millis(16),
+//!     uptime: Duration::new(90, 5),
+//! };
+//! assert_eq!(
+//!     serde_lua_table::to_string(&timers).unwrap(),
+//!     r#"{["cooldown"]=1.5,["tick"]=16,["uptime"]={["secs"]=90,["nanos"]=5}}"#
+//! );
+//!
+//! let lua = Lua::new();
+//! let value: Value = lua
+//!     .load("{ cooldown = { secs = 2, nanos = 0 }, tick = 16.5, uptime = 90 }")
+//!     .eval()
+//!     .unwrap();
+//! let timers: Timers = lua.from_value(value).unwrap();
+//! assert_eq!(timers.cooldown, Duration::from_secs(2));
+//! assert_eq!(timers.tick, Duration::from_micros(16_500));
+//! assert_eq!(timers.uptime, Duration::from_secs(90));
+//! ```
+
+use serde::{
+    de::{self, MapAccess},
+    Deserializer,
+};
+use std::{fmt, time::Duration};
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+const NANOS_PER_MILLI: u64 = 1_000_000;
+
+/// Writes the duration as fractional seconds, like `os.clock()` differences.
+pub mod seconds {
+    use super::*;
+    use serde::Serializer;
+
+    pub fn serialize<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_f64(duration.as_secs_f64())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(DurationVisitor {
+            unit_nanos: NANOS_PER_SEC,
+        })
+    }
+}
+
+/// Writes the duration as whole milliseconds. Fractions of a millisecond are truncated.
+pub mod millis {
+    use super::*;
+    use serde::{ser, Serializer};
+
+    pub fn serialize<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let millis = u64::try_from(duration.as_millis())
+            .map_err(|_| ser::Error::custom("duration out of range"))?;
+        serializer.serialize_u64(millis)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(DurationVisitor {
+            unit_nanos: NANOS_PER_MILLI,
+        })
+    }
+}
+
+/// Writes the duration as a table of whole seconds and the remaining nanoseconds.
+pub mod table {
+    use super::*;
+    use serde::{ser::SerializeStruct, Serializer};
+
+    pub fn serialize<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut table = serializer.serialize_struct("Duration", 2)?;
+        table.serialize_field("secs", &duration.as_secs())?;
+        table.serialize_field("nanos", &duration.subsec_nanos())?;
+        table.end()
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(DurationVisitor {
+            unit_nanos: NANOS_PER_SEC,
+        })
+    }
+}
+
+fn out_of_range<E: de::Error>() -> E {
+    E::custom("duration out of range")
+}
+
+/// Reads a number of units or a `{secs, nanos}` table.
+struct DurationVisitor {
+    unit_nanos: u64,
+}
+
+impl<'de> de::Visitor<'de> for DurationVisitor {
+    type Value = Duration;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a non-negative number or a table with `secs` and `nanos`")
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let nanos = u128::from(v) * u128::from(self.unit_nanos);
+        let secs = u64::try_from(nanos / u128::from(NANOS_PER_SEC)).map_err(|_| out_of_range())?;
+        Ok(Duration::new(
+            secs,
+            (nanos % u128::from(NANOS_PER_SEC)) as u32,
+        ))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        match u64::try_from(v) {
+            Ok(v) => self.visit_u64(v),
+            Err(_) => Err(de::Error::invalid_value(de::Unexpected::Signed(v), &self)),
+        }
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let secs = v * self.unit_nanos as f64 / NANOS_PER_SEC as f64;
+        Duration::try_from_secs_f64(secs)
+            .map_err(|_| de::Error::invalid_value(de::Unexpected::Float(v), &self))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut secs = None;
+        let mut nanos = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "secs" => secs = Some(map.next_value::<u64>()?),
+                "nanos" => nanos = Some(map.next_value::<u32>()?),
+                _ => {
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+            }
+        }
+        let secs = secs.ok_or_else(|| de::Error::missing_field("secs"))?;
+        let nanos = nanos.unwrap_or(0);
+        let extra = u64::from(nanos) / NANOS_PER_SEC;
+        let secs = secs.checked_add(extra).ok_or_else(out_of_range)?;
+        Ok(Duration::new(secs, nanos % NANOS_PER_SEC as u32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_str, to_string};
+    use serde::{Deserialize, Serialize};
+    use std::time::Duration;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Timers {
+        #[serde(with = "super::seconds")]
+        seconds: Duration,
+        #[serde(with = "super::millis")]
+        millis: Duration,
+        #[serde(with = "super::table")]
+        table: Duration,
+    }
+
+    #[test]
+    fn round_trips_every_representation() {
+        let timers = Timers {
+            seconds: Duration::from_millis(250),
+            millis: Duration::from_millis(1500),
+            table: Duration::new(3, 999_999_999),
+        };
+        let lua = to_string(&timers).unwrap();
+        assert_eq!(
+            lua,
+            r#"{["seconds"]=0.25,["millis"]=1500,["table"]={["secs"]=3,["nanos"]=999999999}}"#
+        );
+        assert_eq!(from_str::<Timers>(&lua).unwrap(), timers);
+    }
+
+    #[test]
+    fn truncates_fractions_of_a_millisecond() {
+        let timers = Timers {
+            seconds: Duration::ZERO,
+            millis: Duration::from_micros(1999),
+            table: Duration::ZERO,
+        };
+        assert_eq!(
+            to_string(&timers).unwrap(),
+            r#"{["seconds"]=0.0,["millis"]=1,["table"]={["secs"]=0,["nanos"]=0}}"#
+        );
+    }
+
+    #[test]
+    fn reads_any_representation_in_the_adapters_unit() {
+        let timers = from_str::<Timers>(
+            "{ seconds = { secs = 1, nanos = 1500000000 }, millis = 2.5, table = 4 }",
+        )
+        .unwrap();
+        assert_eq!(
+            timers,
+            Timers {
+                seconds: Duration::from_millis(2500),
+                millis: Duration::from_micros(2500),
+                table: Duration::from_secs(4),
+            }
+        );
+        assert!(from_str::<Timers>("{ seconds = -1, millis = 0, table = 0 }").is_err());
+        assert!(from_str::<Timers>("{ seconds = 0, millis = 0, table = { nanos = 1 } }").is_err());
+    }
+}
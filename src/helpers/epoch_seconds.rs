@@ -0,0 +1,68 @@
+//! Writes a [`SystemTime`] as whole seconds since the unix epoch, like lua's `os.time()`.
+//!
+//! Times before the epoch are written as negative numbers. Fractions of a second are truncated
+//! towards the past.
+
+use serde::{ser, Deserialize, Deserializer, Serializer};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub fn serialize<S>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => serializer.serialize_u64(since.as_secs()),
+        Err(err) => {
+            let before = err.duration();
+            let secs = before.as_secs() + u64::from(before.subsec_nanos() > 0);
+            let secs = i64::try_from(secs).map_err(|_| ser::Error::custom("time out of range"))?;
+            serializer.serialize_i64(-secs)
+        }
+    }
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<SystemTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let secs = i64::deserialize(deserializer)?;
+    let offset = Duration::from_secs(secs.unsigned_abs());
+    if secs < 0 {
+        UNIX_EPOCH.checked_sub(offset)
+    } else {
+        UNIX_EPOCH.checked_add(offset)
+    }
+    .ok_or_else(|| serde::de::Error::custom("time out of range"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_str, to_string};
+    use serde::{Deserialize, Serialize};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Time(#[serde(with = "super")] SystemTime);
+
+    #[test]
+    fn writes_whole_seconds_around_the_epoch() {
+        let after = UNIX_EPOCH + Duration::from_millis(1_700_000_000_900);
+        assert_eq!(to_string(&Time(after)).unwrap(), "1700000000");
+        let before = UNIX_EPOCH - Duration::from_millis(1500);
+        assert_eq!(to_string(&Time(before)).unwrap(), "-2");
+        assert_eq!(to_string(&Time(UNIX_EPOCH)).unwrap(), "0");
+    }
+
+    #[test]
+    fn reads_seconds_around_the_epoch() {
+        assert_eq!(
+            from_str::<Time>("1700000000").unwrap(),
+            Time(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+        assert_eq!(
+            from_str::<Time>("-2").unwrap(),
+            Time(UNIX_EPOCH - Duration::from_secs(2))
+        );
+        assert!(from_str::<Time>("'now'").is_err());
+    }
+}
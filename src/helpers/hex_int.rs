@@ -0,0 +1,121 @@
+//! Writes integers as hexadecimal literals like `0xFF`.
+//!
+//! Serializers other than the one in this crate receive the literal as a string, so
+//! deserializing accepts both integers and strings in this format.
+
+use crate::RawLua;
+use serde::{de, ser, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Copy + TryInto<i128>,
+    S: Serializer,
+{
+    let value: i128 = (*value)
+        .try_into()
+        .map_err(|_| ser::Error::custom("integer out of range"))?;
+    let literal = if value < 0 {
+        format!("-0x{:X}", value.unsigned_abs())
+    } else {
+        format!("0x{:X}", value)
+    };
+    RawLua(literal).serialize(serializer)
+}
+
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: TryFrom<i128>,
+    D: Deserializer<'de>,
+{
+    let value = deserializer.deserialize_any(HexIntVisitor)?;
+    T::try_from(value).map_err(|_| {
+        de::Error::invalid_value(de::Unexpected::Other("integer"), &"an integer in range")
+    })
+}
+
+struct HexIntVisitor;
+
+impl<'de> de::Visitor<'de> for HexIntVisitor {
+    type Value = i128;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an integer or a hexadecimal literal")
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(v.into())
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(v.into())
+    }
+
+    fn visit_i128<E>(self, v: i128) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(v)
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let (negative, digits) = match v.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, v),
+        };
+        let value = digits
+            .strip_prefix("0x")
+            .or_else(|| digits.strip_prefix("0X"))
+            .and_then(|hex| i128::from_str_radix(hex, 16).ok())
+            .ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(v), &self))?;
+        Ok(if negative { -value } else { value })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_str, to_string};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Flags {
+        #[serde(with = "super")]
+        mask: u64,
+        #[serde(with = "super")]
+        offset: i16,
+    }
+
+    #[test]
+    fn round_trips_hex_literals() {
+        let flags = Flags {
+            mask: u64::MAX,
+            offset: -0x7F,
+        };
+        let lua = to_string(&flags).unwrap();
+        assert_eq!(lua, r#"{["mask"]=0xFFFFFFFFFFFFFFFF,["offset"]=-0x7F}"#);
+        assert_eq!(from_str::<Flags>(&lua).unwrap(), flags);
+    }
+
+    #[test]
+    fn reads_integers_and_hex_strings() {
+        let flags = from_str::<Flags>("{mask = '0x1f', offset = 3}").unwrap();
+        assert_eq!(
+            flags,
+            Flags {
+                mask: 31,
+                offset: 3
+            }
+        );
+        assert!(from_str::<Flags>("{mask = 'ff', offset = 0}").is_err());
+        assert!(from_str::<Flags>("{mask = 1, offset = 0x8000}").is_err());
+    }
+}
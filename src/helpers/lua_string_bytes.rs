@@ -0,0 +1,124 @@
+//! Writes byte buffers as lua strings instead of arrays of numbers.
+//!
+//! Lua strings can hold arbitrary bytes. Printable ASCII is written as is, everything else is
+//! written as a decimal escape (`\ddd`), which every lua version understands.
+
+use crate::RawLua;
+use serde::{de, Deserializer, Serialize, Serializer};
+use std::{fmt, fmt::Write};
+
+pub fn serialize<T, S>(bytes: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: ?Sized + AsRef<[u8]>,
+    S: Serializer,
+{
+    RawLua(literal(bytes.as_ref())).serialize(serializer)
+}
+
+/// Returns `bytes` as a quoted lua string literal.
+pub(crate) fn literal(bytes: &[u8]) -> String {
+    let mut literal = String::with_capacity(bytes.len() + 2);
+    literal.push('"');
+    for &byte in bytes {
+        match byte {
+            b'"' => literal.push_str("\\\""),
+            b'\\' => literal.push_str("\\\\"),
+            b'\n' => literal.push_str("\\n"),
+            b'\r' => literal.push_str("\\r"),
+            b'\t' => literal.push_str("\\t"),
+            b' '..=b'~' => literal.push(byte as char),
+            _ => write!(literal, "\\{:03}", byte).unwrap(),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: From<Vec<u8>>,
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_byte_buf(BytesVisitor).map(T::from)
+}
+
+struct BytesVisitor;
+
+impl<'de> de::Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string or bytes")
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(v)
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(v.as_bytes().to_vec())
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(v.into_bytes())
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_str, to_string};
+    use mlua::Lua;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Blob(#[serde(with = "super")] Vec<u8>);
+
+    #[test]
+    fn writes_bytes_as_strings_lua_can_load() {
+        let blob = Blob(vec![b'a', b'"', b'\\', b'\n', 0, 0x7F, 0xFF]);
+        let lua = to_string(&blob).unwrap();
+        assert_eq!(lua, r#""a\"\\\n\000\127\255""#);
+
+        let state = Lua::new();
+        let loaded: mlua::String = state.load(&lua).eval().unwrap();
+        assert_eq!(loaded.as_bytes(), blob.0);
+        assert_eq!(from_str::<Blob>(&lua).unwrap(), blob);
+    }
+
+    #[test]
+    fn reads_strings_and_arrays_of_bytes() {
+        assert_eq!(from_str::<Blob>("'hi'").unwrap(), Blob(b"hi".to_vec()));
+        assert_eq!(
+            from_str::<Blob>("{104, 105}").unwrap(),
+            Blob(b"hi".to_vec())
+        );
+        assert!(from_str::<Blob>("{256}").is_err());
+    }
+}
@@ -0,0 +1,125 @@
+//! Reads tables that mix positional entries with named keys, like `{"a", "b", count = 2}`.
+//!
+//! The values at the keys `1..=n` become the sequence, every other field is read into a second
+//! type, e.g. a struct or a map.
+//!
+//! ```
+//! use serde::Deserialize;
+//! use serde_lua_table::helpers::mixed_table::MixedTable;
+//!
+//! #[derive(Deserialize)]
+//! struct Options {
+//!     count: u32,
+//! }
+//!
+//! let table: MixedTable<String, Options> =
+//!     serde_lua_table::from_str(r#"{"a", "b", count = 2}"#).unwrap();
+//! assert_eq!(table.sequence, ["a", "b"]);
+//! assert_eq!(table.fields.count, 2);
+//! ```
+
+use crate::value::{Key, Table, Value};
+use serde::{
+    de::{self, DeserializeOwned, MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use std::{fmt, marker::PhantomData};
+
+/// A table with a sequence of `T` and the remaining fields read as `M`.
+///
+/// The fields are collected into a [`Value`] before `M` is read from them, which is why `M`
+/// can't borrow from the input.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MixedTable<T, M> {
+    /// The values at the keys `1..=n`.
+    pub sequence: Vec<T>,
+    /// Every other field.
+    pub fields: M,
+}
+
+impl<'de, T, M> Deserialize<'de> for MixedTable<T, M>
+where
+    T: Deserialize<'de>,
+    M: DeserializeOwned,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(MixedTableVisitor(PhantomData))
+    }
+}
+
+struct MixedTableVisitor<T, M>(PhantomData<(T, M)>);
+
+impl<'de, T, M> Visitor<'de> for MixedTableVisitor<T, M>
+where
+    T: Deserialize<'de>,
+    M: DeserializeOwned,
+{
+    type Value = MixedTable<T, M>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a table")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut sequence = Vec::new();
+        let mut fields = Table::new();
+        while let Some(key) = map.next_key::<Key>()? {
+            match key {
+                Key::Integer(i) if i == sequence.len() as i64 + 1 => {
+                    sequence.push(map.next_value()?)
+                }
+                key => {
+                    fields.insert(key, map.next_value()?);
+                }
+            }
+        }
+        let fields = M::deserialize(&Value::from(fields)).map_err(de::Error::custom)?;
+        Ok(MixedTable { sequence, fields })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MixedTable;
+    use crate::{from_str, value::Key, Value};
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Options {
+        count: u32,
+    }
+
+    #[test]
+    fn splits_the_sequence_from_the_fields() {
+        let table: MixedTable<&str, BTreeMap<Key, u8>> =
+            from_str("{ 'a', 'b', [3] = 'c', [5] = 5, x = 1 }").unwrap();
+        assert_eq!(table.sequence, ["a", "b", "c"]);
+        assert_eq!(
+            table.fields,
+            BTreeMap::from([(Key::from(5), 5), (Key::from("x"), 1)])
+        );
+
+        let value = from_str::<Value>("{ 1, 2, count = 3 }").unwrap();
+        let table = MixedTable::<u8, Options>::deserialize(&value).unwrap();
+        assert_eq!(table.sequence, [1, 2]);
+        assert_eq!(table.fields, Options { count: 3 });
+    }
+
+    #[test]
+    fn reports_errors_of_the_fields() {
+        let error = from_str::<MixedTable<u8, Options>>("{ 1, counter = 2 }").unwrap_err();
+        assert!(
+            error.to_string().starts_with("missing field `count`"),
+            "{}",
+            error
+        );
+        assert!(from_str::<MixedTable<u8, Options>>("{ 'a', count = 1 }").is_err());
+    }
+}
@@ -0,0 +1,35 @@
+//! Adapters for `#[serde(with = "...")]` that cover common lua representations of values.
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Item {
+//!     #[serde(with = "serde_lua_table::helpers::hex_int")]
+//!     flags: u32,
+//!     #[serde(with = "serde_lua_table::helpers::color_hex")]
+//!     color: u32,
+//! }
+//!
+//! let item = Item { flags: 0x1F, color: 0xFF8800 };
+//! let lua = serde_lua_table::to_string(&item).unwrap();
+//! assert_eq!(lua, r##"{["flags"]=0x1F,["color"]="#ff8800"}"##);
+//! ```
+
+#[cfg(feature = "chrono")]
+pub mod chrono;
+pub mod color_hex;
+#[cfg(any(feature = "chrono", feature = "time"))]
+mod date_table;
+#[cfg(feature = "rust_decimal")]
+pub mod decimal;
+pub mod duration;
+pub mod epoch_seconds;
+pub mod hex_int;
+pub mod lua_string_bytes;
+pub mod mixed_table;
+pub mod set_as_keys;
+#[cfg(feature = "time")]
+pub mod time;
+#[cfg(feature = "uuid")]
+pub mod uuid;
@@ -0,0 +1,195 @@
+//! Writes sets as tables with the elements as keys (`{["a"]=true,["b"]=true}`), which is how lua
+//! code usually checks for membership.
+//!
+//! Deserializing keeps every key whose value is `true`. Sequences like `{"a","b"}` are accepted
+//! as well, since lists are the other common way to write sets.
+//!
+//! ```
+//! use mlua::{Lua, LuaSerdeExt, Value};
+//! use serde::Deserialize;
+//! use std::collections::BTreeSet;
+//!
+//! #[derive(Deserialize)]
+//! struct Filter {
+//!     #[serde(with = "serde_lua_table::helpers::set_as_keys")]
+//!     names: BTreeSet<String>,
+//! }
+//!
+//! let lua = Lua::new();
+//! let value: Value = lua.load(r#"{ names = { a = true, b = false } }"#).eval().unwrap();
+//! let filter: Filter = lua.from_value(value).unwrap();
+//! assert_eq!(filter.names, BTreeSet::from(["a".to_owned()]));
+//!
+//! let value: Value = lua.load(r#"{ names = { "a", "b" } }"#).eval().unwrap();
+//! let filter: Filter = lua.from_value(value).unwrap();
+//! assert_eq!(filter.names.len(), 2);
+//! ```
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, marker::PhantomData};
+
+/// Writes the wrapped collection as a set, for values that aren't fields and can't use
+/// `#[serde(with)]`.
+///
+/// ```
+/// use serde_lua_table::helpers::set_as_keys::AsLuaSet;
+/// use std::collections::BTreeSet;
+///
+/// let enabled = BTreeSet::from(["a", "b"]);
+/// assert_eq!(
+///     serde_lua_table::to_string(&AsLuaSet(&enabled)).unwrap(),
+///     r#"{["a"]=true,["b"]=true}"#
+/// );
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct AsLuaSet<C>(pub C);
+
+impl<C, T> Serialize for AsLuaSet<C>
+where
+    C: Copy + IntoIterator<Item = T>,
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_map(self.0.into_iter().map(|item| (item, true)))
+    }
+}
+
+pub fn serialize<C, T, S>(set: &C, serializer: S) -> Result<S::Ok, S::Error>
+where
+    for<'a> &'a C: IntoIterator<Item = &'a T>,
+    T: Serialize,
+    S: Serializer,
+{
+    serializer.collect_map(set.into_iter().map(|item| (item, true)))
+}
+
+pub fn deserialize<'de, C, T, D>(deserializer: D) -> Result<C, D::Error>
+where
+    C: FromIterator<T>,
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    let items = deserializer.deserialize_any(SetVisitor(PhantomData))?;
+    Ok(items.into_iter().collect())
+}
+
+struct SetVisitor<T>(PhantomData<T>);
+
+impl<'de, T> de::Visitor<'de> for SetVisitor<T>
+where
+    T: Deserialize<'de>,
+{
+    type Value = Vec<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a table with the elements as keys or a sequence")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let mut items = Vec::with_capacity(map.size_hint().unwrap_or(0).min(4096));
+        while let Some((item, present)) = map.next_entry::<T, bool>()? {
+            if present {
+                items.push(item);
+            }
+        }
+        Ok(items)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AsLuaSet;
+    use crate::{from_reader, from_str, to_string};
+    use serde::{Deserialize, Serialize};
+    use std::collections::{BTreeSet, HashSet};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Filter {
+        #[serde(with = "super")]
+        names: BTreeSet<String>,
+        #[serde(with = "super", default)]
+        ids: HashSet<i64>,
+    }
+
+    #[test]
+    fn reads_sets_written_as_keys() {
+        for lua in [
+            r#"{names = {["a"] = true, ["b"] = true, ["c"] = false}}"#,
+            "{names = {a = true, b = true}}",
+            "{names = {'a', 'b'}}",
+            "{names = {[ [[a]] ] = true, b = true}}",
+        ] {
+            let filter = from_str::<Filter>(lua).unwrap();
+            assert_eq!(
+                filter.names,
+                BTreeSet::from(["a".into(), "b".into()]),
+                "{}",
+                lua
+            );
+            assert_eq!(from_reader::<_, Filter>(lua.as_bytes()).unwrap(), filter);
+        }
+    }
+
+    #[test]
+    fn reads_sets_of_integers() {
+        let filter = from_str::<Filter>("{names = {}, ids = {[3] = true, [10] = true}}").unwrap();
+        assert_eq!(filter.ids, HashSet::from([3, 10]));
+        let filter = from_str::<Filter>("{names = {}, ids = {[1] = true, [2] = false}}").unwrap();
+        assert_eq!(filter.ids, HashSet::from([1]));
+    }
+
+    #[test]
+    fn round_trips() {
+        let filter = Filter {
+            names: BTreeSet::from(["x".into(), "y z".into()]),
+            ids: HashSet::from([7]),
+        };
+        let lua = to_string(&filter).unwrap();
+        assert_eq!(from_str::<Filter>(&lua).unwrap(), filter);
+
+        let lua = to_string(&AsLuaSet(&filter.names)).unwrap();
+        assert_eq!(lua, r#"{["x"]=true,["y z"]=true}"#);
+    }
+
+    #[test]
+    fn rejects_values_that_are_not_booleans() {
+        assert!(from_str::<Filter>("{names = {a = 1}}").is_err());
+    }
+
+    #[test]
+    fn writes_any_collection_as_a_set() {
+        assert_eq!(
+            to_string(&AsLuaSet(&[3, 1])).unwrap(),
+            "{[3]=true,[1]=true}"
+        );
+        assert_eq!(to_string(&AsLuaSet(&Vec::<u8>::new())).unwrap(), "{}");
+        let names = vec!["a", "b"];
+        let set = BTreeSet::from(["b", "a"]);
+        let lua = to_string(&(AsLuaSet(&set), AsLuaSet(&names))).unwrap();
+        assert_eq!(lua, r#"{{["a"]=true,["b"]=true},{["a"]=true,["b"]=true}}"#);
+
+        let lua = ::mlua::Lua::new();
+        let set: ::mlua::Table = lua
+            .load(to_string(&AsLuaSet(&names)).unwrap())
+            .eval()
+            .unwrap();
+        assert!(set.get::<_, bool>("a").unwrap() && !set.contains_key("c").unwrap());
+    }
+}
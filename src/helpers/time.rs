@@ -0,0 +1,111 @@
+//! Adapters for [`time::OffsetDateTime`](::time::OffsetDateTime).
+//!
+//! Both adapters accept either representation when deserializing.
+
+use super::date_table::{out_of_range, DateTable, Timestamp};
+use ::time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
+use serde::{Deserialize, Deserializer};
+
+fn from_timestamp<E: serde::de::Error>(timestamp: Timestamp) -> Result<OffsetDateTime, E> {
+    match timestamp {
+        Timestamp::Epoch(secs) => OffsetDateTime::from_unix_timestamp(secs).ok(),
+        Timestamp::Table(table) => Month::try_from(table.month)
+            .and_then(|month| Date::from_calendar_date(table.year, month, table.day))
+            .and_then(|date| {
+                Time::from_hms(table.hour, table.min, table.sec)
+                    .map(|time| PrimitiveDateTime::new(date, time).assume_utc())
+            })
+            .ok(),
+    }
+    .ok_or_else(out_of_range)
+}
+
+/// Writes the time as whole seconds since the unix epoch, like `os.time()`.
+pub mod epoch {
+    use super::*;
+    use serde::Serializer;
+
+    pub fn serialize<S>(time: &OffsetDateTime, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_i64(time.unix_timestamp())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<OffsetDateTime, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        from_timestamp(Timestamp::deserialize(deserializer)?)
+    }
+}
+
+/// Writes the time as a table in UTC, like `os.date("!*t")`.
+pub mod date_table {
+    use super::*;
+    use serde::{Serialize, Serializer};
+
+    pub fn serialize<S>(time: &OffsetDateTime, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let time = time.to_offset(UtcOffset::UTC);
+        DateTable {
+            year: time.year(),
+            month: time.month().into(),
+            day: time.day(),
+            hour: time.hour(),
+            min: time.minute(),
+            sec: time.second(),
+            wday: time.weekday().number_from_sunday(),
+            yday: time.ordinal(),
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<OffsetDateTime, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        from_timestamp(Timestamp::deserialize(deserializer)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_str, to_string};
+    use ::time::OffsetDateTime;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Save {
+        #[serde(with = "super::epoch")]
+        created: OffsetDateTime,
+        #[serde(with = "super::date_table")]
+        updated: OffsetDateTime,
+    }
+
+    #[test]
+    fn round_trips_both_representations() {
+        let save = Save {
+            created: OffsetDateTime::from_unix_timestamp(-86400).unwrap(),
+            updated: OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap(),
+        };
+        let lua = to_string(&save).unwrap();
+        assert!(
+            lua.contains(r#"["year"]=2023,["month"]=11,["day"]=14"#),
+            "{lua}"
+        );
+        assert_eq!(from_str::<Save>(&lua).unwrap(), save);
+    }
+
+    #[test]
+    fn reads_either_representation() {
+        let save: Save =
+            from_str("{created = {year = 1970, month = 1, day = 2, hour = 0}, updated = 86400}")
+                .unwrap();
+        assert_eq!(save.created, save.updated);
+        assert_eq!(save.updated.unix_timestamp(), 86400);
+        assert!(from_str::<Save>("{created = 0, updated = {year = 1970, day = 1}}").is_err());
+    }
+}
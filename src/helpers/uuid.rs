@@ -0,0 +1,75 @@
+//! Writes a [`Uuid`](::uuid::Uuid) as a hyphenated string.
+//!
+//! Deserializing accepts the hyphenated form as well as 32 plain hex digits.
+
+use ::uuid::Uuid;
+use serde::{de, Deserializer, Serializer};
+use std::fmt;
+
+pub fn serialize<S>(uuid: &Uuid, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(uuid.hyphenated().encode_lower(&mut Uuid::encode_buffer()))
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Uuid, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_str(UuidVisitor)
+}
+
+struct UuidVisitor;
+
+impl<'de> de::Visitor<'de> for UuidVisitor {
+    type Value = Uuid;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a hyphenated or plain hex uuid")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        match v.len() {
+            32 | 36 => Uuid::try_parse(v).ok(),
+            _ => None,
+        }
+        .ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(v), &self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_str, to_string};
+    use ::uuid::Uuid;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Id(#[serde(with = "super")] Uuid);
+
+    #[test]
+    fn round_trips_hyphenated_uuids() {
+        let id = Id(Uuid::from_u128(0x0123_4567_89AB_CDEF_0123_4567_89AB_CDEF));
+        let lua = to_string(&id).unwrap();
+        assert_eq!(lua, "\"01234567-89ab-cdef-0123-456789abcdef\"");
+        assert_eq!(from_str::<Id>(&lua).unwrap(), id);
+        assert_eq!(
+            from_str::<Id>("'0123456789ABCDEF0123456789ABCDEF'").unwrap(),
+            id
+        );
+    }
+
+    #[test]
+    fn rejects_other_uuid_forms() {
+        for lua in [
+            "'{01234567-89ab-cdef-0123-456789abcdef}'",
+            "'urn:uuid:01234567-89ab-cdef-0123-456789abcdef'",
+            "'01234567'",
+        ] {
+            assert!(from_str::<Id>(lua).is_err(), "{lua}");
+        }
+    }
+}
@@ -1,15 +1,84 @@
 #![warn(clippy::cargo)]
 
+// Lets the derive macros, which refer to `::serde_lua_table`, be tested inside of this crate.
+#[cfg(all(test, feature = "derive"))]
+extern crate self as serde_lua_table;
+
+#[cfg(feature = "bytecode")]
+pub mod bytecode;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "checksum")]
+pub mod checksum;
+pub mod codegen;
+mod de;
+pub mod document;
+pub mod factorio;
+#[cfg(feature = "figment")]
+pub mod figment;
 mod format;
+pub mod helpers;
+#[cfg(feature = "mlua")]
+pub mod mlua_support;
+#[cfg(any(feature = "mlua", feature = "rlua"))]
+mod policy;
+#[cfg(feature = "rlua")]
+pub mod rlua_support;
+pub mod rockspec;
+pub mod saved_variables;
+#[cfg(feature = "schemars")]
+pub mod schemars;
 mod ser;
+#[cfg(feature = "hmac")]
+pub mod signature;
+pub mod template;
+#[cfg(feature = "testutil")]
+pub mod testutil;
+pub mod typegen;
+pub mod value;
 
-pub use crate::ser::*;
-use crate::ser::{SerError, Serializer};
+use crate::ser::{fmt_writer::FmtWriter, vec_writer::VecWriter};
+pub use crate::{
+    de::*,
+    format::{
+        AnyFormatter, CharEscape, CommentInjecting, CompactFormatter, FennelFormatter, FieldStyle,
+        FieldStyles, Formatter, Globals, IndentGuard, LuaComments, LuaFieldStyles, PrettyFormatter,
+        WithHeader,
+    },
+    ser::*,
+    value::Value,
+};
 use serde::Serialize;
-use std::io;
+#[cfg(feature = "derive")]
+pub use serde_lua_table_derive::{LuaComments, LuaFieldStyles};
+use std::{fmt, io};
+
+/// Serializes `value` with `ser`, inside a span if the `tracing` feature is enabled.
+#[inline]
+fn serialize<W, F, T>(ser: &mut Serializer<W, F>, value: &T, pretty: bool) -> Result<(), SerError>
+where
+    W: io::Write,
+    F: Formatter,
+    T: ?Sized + Serialize,
+{
+    #[cfg(feature = "tracing")]
+    return ser::trace::serialize(ser, value, pretty);
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = pretty;
+        value.serialize(ser)
+    }
+}
+
+/// The capacity of the buffer [`to_writer`] and [`to_writer_pretty`] write tables to.
+const WRITE_BUFFER: usize = 8 * 1024;
 
 /// Serialize the given data structure in lua representation into the IO stream.
 ///
+/// The output is buffered with [`SerOptions::with_write_buffer`], so unbuffered writers like
+/// files don't need an [`io::BufWriter`]. Writers that buffer on their own, like `Vec<u8>`, can
+/// skip the 8 KiB buffer by serializing with [`Serializer::new`], which doesn't buffer.
+///
 /// # Errors
 ///
 /// Serialization can fail if `T`'s implementation of `Serialize` decides to
@@ -20,12 +89,15 @@ where
     W: io::Write,
     T: ?Sized + Serialize,
 {
-    let mut ser = Serializer::new(writer);
-    value.serialize(&mut ser)
+    let options = SerOptions::new().with_write_buffer(WRITE_BUFFER);
+    let mut ser = Serializer::with_options(writer, CompactFormatter, options);
+    serialize(&mut ser, value, false)?;
+    ser.into_inner().flush()?;
+    Ok(())
 }
 
 /// Serialize the given data structure as a pretty-printed lua representation into the IO
-/// stream.
+/// stream. The output is buffered like in [`to_writer`].
 ///
 /// # Errors
 ///
@@ -37,8 +109,11 @@ where
     W: io::Write,
     T: ?Sized + Serialize,
 {
-    let mut ser = Serializer::pretty(writer);
-    value.serialize(&mut ser)
+    let options = SerOptions::new().with_write_buffer(WRITE_BUFFER);
+    let mut ser = Serializer::with_options(writer, PrettyFormatter::new(), options);
+    serialize(&mut ser, value, true)?;
+    ser.into_inner().flush()?;
+    Ok(())
 }
 
 /// Serialize the given data structure in lua representation byte vector.
@@ -52,9 +127,9 @@ pub fn to_vec<T>(value: &T) -> Result<Vec<u8>, SerError>
 where
     T: ?Sized + Serialize,
 {
-    let mut writer = Vec::with_capacity(128);
-    to_writer(&mut writer, value)?;
-    Ok(writer)
+    let mut ser = Serializer::new(VecWriter::new());
+    serialize(&mut ser, value, false)?;
+    Ok(ser.into_inner().0)
 }
 
 /// Serialize the given data structure as a pretty-printed lua representation byte vector.
@@ -68,9 +143,9 @@ pub fn to_vec_pretty<T>(value: &T) -> Result<Vec<u8>, SerError>
 where
     T: ?Sized + Serialize,
 {
-    let mut writer = Vec::with_capacity(128);
-    to_writer_pretty(&mut writer, value)?;
-    Ok(writer)
+    let mut ser = Serializer::pretty(VecWriter::new());
+    serialize(&mut ser, value, true)?;
+    Ok(ser.into_inner().0)
 }
 
 /// Serialize the given data structure as a String in lua representation.
@@ -111,10 +186,239 @@ where
     Ok(string)
 }
 
+/// Quotes `s` as a lua string literal.
+///
+/// ```
+/// assert_eq!(serde_lua_table::quote_lua_string("say \"hi\"\n"), r#""say \"hi\"\n""#);
+/// ```
+pub fn quote_lua_string(s: &str) -> String {
+    let mut vec = Vec::with_capacity(s.len() + 2);
+    s.serialize(&mut Serializer::new(&mut vec))
+        .expect("strings can always be written to a Vec");
+    unsafe {
+        // Safety: We do not emit invalid UTF-8.
+        String::from_utf8_unchecked(vec)
+    }
+}
+
+/// Serialize the given data structure in lua representation, quoted as a lua string literal.
+///
+/// This is for passing data through `load()` or storing it as text inside other lua code.
+///
+/// ```
+/// let embedded = serde_lua_table::to_embedded_string(&["a", "b"]).unwrap();
+/// assert_eq!(embedded, r#""{\"a\",\"b\"}""#);
+///
+/// let lua = mlua::Lua::new();
+/// let second: String = lua
+///     .load(format!("return load('return ' .. {})()[2]", embedded))
+///     .eval()
+///     .unwrap();
+/// assert_eq!(second, "b");
+/// ```
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with non-string keys.
+pub fn to_embedded_string<T>(value: &T) -> Result<String, SerError>
+where
+    T: ?Sized + Serialize,
+{
+    Ok(quote_lua_string(&to_string(value)?))
+}
+
+/// Serialize the given data structure in lua representation into a [`fmt::Write`].
+///
+/// This writes to a `String` or a [`fmt::Formatter`] directly, which is handy on targets like
+/// `wasm32-unknown-unknown` where there is no file or socket to write to.
+///
+/// ```
+/// use std::fmt::Write;
+///
+/// let mut out = String::from("local config = ");
+/// serde_lua_table::to_fmt_writer(&mut out, &[1, 2]).unwrap();
+/// assert_eq!(out, "local config = {1,2}");
+/// ```
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, if `T` contains a map with non-string keys or if the writer fails.
+#[inline]
+pub fn to_fmt_writer<W, T>(writer: W, value: &T) -> Result<(), SerError>
+where
+    W: fmt::Write,
+    T: ?Sized + Serialize,
+{
+    to_writer(FmtWriter(writer), value)
+}
+
+/// Serialize the given data structure as a pretty-printed lua representation into a
+/// [`fmt::Write`].
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, if `T` contains a map with non-string keys or if the writer fails.
+#[inline]
+pub fn to_fmt_writer_pretty<W, T>(writer: W, value: &T) -> Result<(), SerError>
+where
+    W: fmt::Write,
+    T: ?Sized + Serialize,
+{
+    to_writer_pretty(FmtWriter(writer), value)
+}
+
+/// Serialize the given data structure as a function that assigns every field to its parameter.
+///
+/// Maps and structs are descended into, so nested fields are assigned one by one and the tables
+/// holding them must already exist on the parameter. This is how LÖVE's `love.conf` expects its
+/// configuration.
+///
+/// ```
+/// use std::collections::BTreeMap;
+///
+/// let window = BTreeMap::from([("title", "Game"), ("icon", "icon.png")]);
+/// let conf = BTreeMap::from([("window", window)]);
+///
+/// let mut out = Vec::new();
+/// serde_lua_table::to_writer_function_body(&mut out, "love.conf", "t", &conf).unwrap();
+/// assert_eq!(
+///     out,
+///     b"function love.conf(t)\n  t.window.icon = \"icon.png\"\n  t.window.title = \"Game\"\nend\n"
+/// );
+/// ```
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, if `T` contains a map with non-string keys or if `T` isn't a table.
+#[inline]
+pub fn to_writer_function_body<W, T>(
+    writer: W,
+    function: &str,
+    parameter: &str,
+    value: &T,
+) -> Result<(), SerError>
+where
+    W: io::Write,
+    T: ?Sized + Serialize,
+{
+    ser::function_body::write(writer, function, parameter, value)
+}
+
+/// Serialize the given data structure as a String containing a function that assigns every
+/// field to its parameter. See [`to_writer_function_body`].
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, if `T` contains a map with non-string keys or if `T` isn't a table.
+#[inline]
+pub fn to_string_function_body<T>(
+    function: &str,
+    parameter: &str,
+    value: &T,
+) -> Result<String, SerError>
+where
+    T: ?Sized + Serialize,
+{
+    let mut vec = Vec::with_capacity(128);
+    to_writer_function_body(&mut vec, function, parameter, value)?;
+    let string = unsafe {
+        // Safety: We do not emit invalid UTF-8.
+        String::from_utf8_unchecked(vec)
+    };
+    Ok(string)
+}
+
+/// Serialize a large sequence in lua representation into the IO stream, using all cores.
+///
+/// The sequence is split into one chunk per thread of the rayon pool and every chunk is
+/// serialized into its own buffer. The buffers are then written in order, so the output is the
+/// same as the output of [`to_writer`]. Short sequences are written on the calling thread.
+///
+/// ```
+/// let values: Vec<u32> = (0..5000).collect();
+/// let mut out = Vec::new();
+/// serde_lua_table::to_writer_parallel(&mut out, &values).unwrap();
+/// assert_eq!(out, serde_lua_table::to_vec(&values).unwrap());
+/// ```
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with non-string keys.
+#[cfg(feature = "rayon")]
+pub fn to_writer_parallel<W, T>(writer: W, values: &[T]) -> Result<(), SerError>
+where
+    W: io::Write,
+    T: Serialize + Sync,
+{
+    ser::parallel::write(writer, CompactFormatter, values)
+}
+
+/// Serialize a large sequence as a pretty-printed lua representation into the IO stream, using
+/// all cores like [`to_writer_parallel`]. The output is the same as the output of
+/// [`to_writer_pretty`].
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with non-string keys.
+#[cfg(feature = "rayon")]
+pub fn to_writer_parallel_pretty<W, T>(writer: W, values: &[T]) -> Result<(), SerError>
+where
+    W: io::Write,
+    T: Serialize + Sync,
+{
+    ser::parallel::write(writer, PrettyFormatter::new(), values)
+}
+
+/// Serialize a type-erased value in lua representation into the IO stream.
+///
+/// This is useful when the concrete type is only known at runtime, for example when values are
+/// stored as `Box<dyn erased_serde::Serialize>`.
+///
+/// ```
+/// let values: Vec<Box<dyn erased_serde::Serialize>> = vec![Box::new(1), Box::new("two")];
+/// let mut out = Vec::new();
+/// for value in &values {
+///     serde_lua_table::to_writer_erased(&mut out, value.as_ref()).unwrap();
+/// }
+/// assert_eq!(out, b"1\"two\"");
+/// ```
+///
+/// # Errors
+///
+/// Serialization can fail if the value's implementation of `Serialize` decides to
+/// fail, or if it contains a map with non-string keys.
+#[cfg(feature = "erased-serde")]
+#[inline]
+pub fn to_writer_erased<W>(writer: W, value: &dyn erased_serde::Serialize) -> Result<(), SerError>
+where
+    W: io::Write,
+{
+    to_writer(writer, value)
+}
+
+/// Serialize a type-erased value as a String in lua representation.
+///
+/// # Errors
+///
+/// Serialization can fail if the value's implementation of `Serialize` decides to
+/// fail, or if it contains a map with non-string keys.
+#[cfg(feature = "erased-serde")]
+#[inline]
+pub fn to_string_erased(value: &dyn erased_serde::Serialize) -> Result<String, SerError> {
+    to_string(value)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::*;
-    use mlua::{Lua, Value};
+    use ::mlua::{Lua, Value};
 
     #[test]
     fn it_woks() {
@@ -126,4 +430,158 @@ mod tests {
         let table: Value = lua.globals().get("ALIEN").unwrap();
         to_writer_pretty(io::stdout(), &table).unwrap();
     }
+
+    /// Counts the allocations of the current thread, other tests run in parallel.
+    struct CountingAllocator;
+
+    std::thread_local! {
+        static ALLOCATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            std::alloc::System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            std::alloc::System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    #[derive(serde::Serialize)]
+    struct Record<'a> {
+        id: i64,
+        ratio: f64,
+        initial: char,
+        name: &'a str,
+        tags: &'a [u32],
+        scores: &'a std::collections::BTreeMap<&'a str, i32>,
+        kind: Option<Kind>,
+    }
+
+    #[derive(serde::Serialize)]
+    enum Kind {
+        Point { x: u64 },
+    }
+
+    #[test]
+    fn serializing_does_not_allocate() {
+        let scores = std::collections::BTreeMap::from([("a", 1), ("b c", -2)]);
+        let record = Record {
+            id: i64::MIN,
+            ratio: 0.25,
+            initial: 'ä',
+            name: "line\nwith \"quotes\"\0",
+            tags: &[1, 2, 3],
+            scores: &scores,
+            kind: Some(Kind::Point { x: u64::MAX }),
+        };
+        let mut out = Vec::with_capacity(1024);
+        let options = SerOptions::new()
+            .with_version(LuaVersion::LuaJit)
+            .with_key_style(KeyStyle::Bare);
+
+        let before = ALLOCATIONS.with(|count| count.get());
+        record.serialize(&mut Serializer::new(&mut out)).unwrap();
+        record.serialize(&mut Serializer::pretty(&mut out)).unwrap();
+        record
+            .serialize(&mut Serializer::with_options(
+                &mut out,
+                PrettyFormatter::new(),
+                options,
+            ))
+            .unwrap();
+        let after = ALLOCATIONS.with(|count| count.get());
+
+        assert!(out.len() < out.capacity());
+        assert_eq!(after - before, 0);
+    }
+
+    /// Records every write it gets.
+    #[derive(Default)]
+    struct Writes(Vec<Vec<u8>>);
+
+    impl io::Write for Writes {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.push(buf.to_vec());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn buffers_writes_to_the_writer() {
+        let value = std::collections::BTreeMap::from([("a", [1, 2]), ("b", [3, 4])]);
+        let mut writes = Writes::default();
+        to_writer(&mut writes, &value).unwrap();
+        assert_eq!(writes.0, [to_vec(&value).unwrap()]);
+
+        let mut writes = Writes::default();
+        to_writer_pretty(&mut writes, &value).unwrap();
+        assert_eq!(writes.0, [to_vec_pretty(&value).unwrap()]);
+    }
+
+    #[test]
+    fn buffers_tables_in_the_serializer() {
+        let value = std::collections::BTreeMap::from([("a", [1, 2]), ("b", [3, 4])]);
+        let mut writes = Writes::default();
+        let options = SerOptions::new().with_write_buffer(8);
+        let mut ser = Serializer::with_options(&mut writes, CompactFormatter, options);
+        value.serialize(&mut ser).unwrap();
+        // Everything is passed on once the table is closed, without `into_inner`.
+        drop(ser);
+        assert_eq!(writes.0.concat(), to_vec(&value).unwrap());
+        assert!(writes.0.len() > 1);
+        assert!(writes.0.iter().all(|write| write.len() <= 8));
+
+        let mut writes = Writes::default();
+        value.serialize(&mut Serializer::new(&mut writes)).unwrap();
+        assert!(writes.0.len() > 10);
+    }
+
+    #[cfg(feature = "erased-serde")]
+    #[test]
+    fn writes_trait_objects() {
+        let values: Vec<Box<dyn erased_serde::Serialize>> =
+            vec![Box::new(vec![1, 2]), Box::new(Some("a")), Box::new(())];
+        let written: Vec<_> = values
+            .iter()
+            .map(|value| to_string_erased(value.as_ref()).unwrap())
+            .collect();
+        assert_eq!(written, ["{1,2}", "\"a\"", "nil"]);
+    }
+
+    #[test]
+    fn quotes_strings_lua_reads_back() {
+        let lua = Lua::new();
+        for s in [
+            "",
+            "plain",
+            "quote \" and 'apostrophe'",
+            "\\ \n \r \0 \x7f",
+            "]]",
+            "é",
+        ] {
+            let quoted = quote_lua_string(s);
+            assert!(quoted.starts_with('"') && quoted.ends_with('"'), "{quoted}");
+            let read: String = lua.load(format!("return {quoted}")).eval().unwrap();
+            assert_eq!(read, s);
+        }
+    }
+
+    #[test]
+    fn embeds_values_as_string_literals() {
+        let embedded = to_embedded_string(&("a\"b", [1, 2])).unwrap();
+        assert_eq!(embedded, r#""{\"a\\\"b\",{1,2}}""#);
+        let lua = Lua::new();
+        let source: String = lua.load(format!("return {embedded}")).eval().unwrap();
+        assert_eq!(source, to_string(&("a\"b", [1, 2])).unwrap());
+    }
 }
@@ -0,0 +1,375 @@
+//! Serialization of values owned by an [`mlua`](::mlua) state.
+//!
+//! `mlua` implements `Serialize` for its values itself, but fails on anything that has no serde
+//! equivalent. [`Options`] decides what happens to reference cycles, functions and userdata
+//! instead, and writes strings that aren't valid UTF-8 as escaped lua strings.
+//!
+//! ```
+//! use mlua::{Lua, Value};
+//! use serde_lua_table::mlua_support::{Cycles, Options, Unsupported};
+//!
+//! let lua = Lua::new();
+//! let value: Value = lua
+//!     .load("local t = { 1, 2, print }; t[4] = t; return t")
+//!     .eval()
+//!     .unwrap();
+//!
+//! assert!(serde_lua_table::to_string(&Options::new().wrap(value.clone())).is_err());
+//!
+//! let options = Options::new()
+//!     .with_cycles(Cycles::Nil)
+//!     .with_functions(Unsupported::TypeName);
+//! let lua = serde_lua_table::to_string(&options.wrap(value)).unwrap();
+//! assert_eq!(lua, r#"{1,2,"function",nil}"#);
+//! ```
+
+mod to_lua;
+
+pub use crate::policy::{Cycles, Unsupported};
+pub use to_lua::to_lua;
+
+use crate::{helpers::lua_string_bytes, RawLua};
+use ::mlua::{Table, Value};
+use serde::{
+    ser::{self, Error as _, SerializeMap, SerializeSeq},
+    Serialize, Serializer,
+};
+use std::{cell::RefCell, ffi::c_void};
+
+/// Policies for serializing [`mlua::Value`](Value)s.
+///
+/// By default, every value that can't be written as a lua literal is an error.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Options {
+    cycles: Cycles,
+    functions: Unsupported,
+    dump_functions: bool,
+    userdata: Unsupported,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how tables that contain themselves are handled.
+    pub fn with_cycles(mut self, cycles: Cycles) -> Self {
+        self.cycles = cycles;
+        self
+    }
+
+    /// Sets how functions are handled.
+    pub fn with_functions(mut self, functions: Unsupported) -> Self {
+        self.functions = functions;
+        self
+    }
+
+    /// Writes lua functions as `(loadstring or load)("...")`, which recreates them from their
+    /// bytecode, instead of applying the function policy.
+    ///
+    /// Upvalues aren't part of the bytecode, so closures lose their captured values. C functions
+    /// can't be dumped and still follow the function policy.
+    ///
+    /// ```
+    /// use mlua::{Function, Lua, Value};
+    /// use serde_lua_table::mlua_support::Options;
+    ///
+    /// let lua = Lua::new();
+    /// let value: Value = lua
+    ///     .load("return { double = function(x) return x * 2 end }")
+    ///     .eval()
+    ///     .unwrap();
+    /// let options = Options::new().with_dumped_functions(true);
+    /// let source = serde_lua_table::to_string(&options.wrap(value)).unwrap();
+    ///
+    /// let double: Function = lua.load(&format!("return ({}).double", source)).eval().unwrap();
+    /// assert_eq!(double.call::<_, i64>(21).unwrap(), 42);
+    /// ```
+    pub fn with_dumped_functions(mut self, dump_functions: bool) -> Self {
+        self.dump_functions = dump_functions;
+        self
+    }
+
+    /// Sets how userdata is handled. This also applies to light userdata, threads and errors.
+    pub fn with_userdata(mut self, userdata: Unsupported) -> Self {
+        self.userdata = userdata;
+        self
+    }
+
+    /// Wraps `value` so it serializes using these options.
+    pub fn wrap(self, value: Value<'_>) -> LuaValue<'_> {
+        LuaValue {
+            value,
+            options: self,
+            visiting: RefCell::default(),
+        }
+    }
+
+    /// Wraps `table` so it serializes using these options.
+    pub fn wrap_table<'lua>(self, table: &Table<'lua>) -> LuaValue<'lua> {
+        self.wrap(Value::Table(table.clone()))
+    }
+
+    fn policy(&self, value: &Value) -> Option<Unsupported> {
+        match value {
+            Value::Nil
+            | Value::Boolean(_)
+            | Value::Integer(_)
+            | Value::Number(_)
+            | Value::String(_)
+            | Value::Table(_) => None,
+            Value::Function(_) => Some(self.functions),
+            _ => Some(self.userdata),
+        }
+    }
+}
+
+/// A [`mlua::Value`](Value) together with the [`Options`] used to serialize it.
+pub struct LuaValue<'lua> {
+    value: Value<'lua>,
+    options: Options,
+    visiting: RefCell<Vec<*const c_void>>,
+}
+
+impl<'lua> Serialize for LuaValue<'lua> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        Node::new(&self.value, &self.options, &self.visiting).serialize(serializer)
+    }
+}
+
+struct Node<'a, 'lua> {
+    value: &'a Value<'lua>,
+    options: &'a Options,
+    visiting: &'a RefCell<Vec<*const c_void>>,
+    /// The chunk recreating the value if it's a lua function that should be dumped.
+    dumped: Option<RawLua<String>>,
+}
+
+impl<'a, 'lua> Node<'a, 'lua> {
+    fn new(
+        value: &'a Value<'lua>,
+        options: &'a Options,
+        visiting: &'a RefCell<Vec<*const c_void>>,
+    ) -> Self {
+        Self {
+            value,
+            options,
+            visiting,
+            dumped: dump(value, options),
+        }
+    }
+
+    fn child(&self, value: &'a Value<'lua>) -> Self {
+        Self::new(value, self.options, self.visiting)
+    }
+
+    fn is_skipped(&self) -> bool {
+        self.options.policy(self.value) == Some(Unsupported::Skip) && self.dumped.is_none()
+    }
+
+    fn serialize_table<S>(&self, table: &Table<'lua>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let pointer = table.to_pointer();
+        if self.visiting.borrow().contains(&pointer) {
+            return match self.options.cycles {
+                Cycles::Error => Err(S::Error::custom("table contains itself")),
+                Cycles::Nil => serializer.serialize_unit(),
+            };
+        }
+
+        let pairs = table
+            .clone()
+            .pairs::<Value, Value>()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(S::Error::custom)?;
+
+        let _visit = Visit::push(self.visiting, pointer);
+        if table.raw_len() == pairs.len() {
+            let values = (1..=pairs.len())
+                .map(|i| table.raw_get(i))
+                .collect::<Result<Vec<Value>, _>>()
+                .map_err(S::Error::custom)?;
+            let mut seq = serializer.serialize_seq(Some(values.len()))?;
+            for value in &values {
+                seq.serialize_element(&self.child(value))?;
+            }
+            seq.end()
+        } else {
+            let mut map = serializer.serialize_map(None)?;
+            for (key, value) in &pairs {
+                if matches!(key, Value::String(s) if s.to_str().is_err()) {
+                    return Err(S::Error::custom("table key is not valid UTF-8"));
+                }
+                let (key, value) = (self.child(key), self.child(value));
+                if key.is_skipped() || value.is_skipped() {
+                    continue;
+                }
+                map.serialize_entry(&key, &value)?;
+            }
+            map.end()
+        }
+    }
+}
+
+/// Returns the chunk recreating `value` if it's a lua function that should be dumped.
+fn dump(value: &Value, options: &Options) -> Option<RawLua<String>> {
+    match value {
+        Value::Function(function) if options.dump_functions => {
+            let bytecode = function.dump(false);
+            (!bytecode.is_empty()).then(|| {
+                RawLua(format!(
+                    "(loadstring or load)({})",
+                    lua_string_bytes::literal(&bytecode)
+                ))
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Marks a table as being serialized until it's dropped, even if serializing it fails.
+struct Visit<'a> {
+    visiting: &'a RefCell<Vec<*const c_void>>,
+}
+
+impl<'a> Visit<'a> {
+    fn push(visiting: &'a RefCell<Vec<*const c_void>>, pointer: *const c_void) -> Self {
+        visiting.borrow_mut().push(pointer);
+        Self { visiting }
+    }
+}
+
+impl Drop for Visit<'_> {
+    fn drop(&mut self) {
+        self.visiting.borrow_mut().pop();
+    }
+}
+
+impl<'a, 'lua> Serialize for Node<'a, 'lua> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.value {
+            Value::Nil => serializer.serialize_unit(),
+            Value::Boolean(b) => serializer.serialize_bool(*b),
+            Value::Integer(i) => serializer.serialize_i64(*i),
+            Value::Number(n) => serializer.serialize_f64(*n),
+            Value::String(s) => match s.to_str() {
+                Ok(s) => serializer.serialize_str(s),
+                Err(_) => lua_string_bytes::serialize(s.as_bytes(), serializer),
+            },
+            Value::Table(table) => self.serialize_table(table, serializer),
+            other => {
+                if let Some(chunk) = &self.dumped {
+                    return chunk.serialize(serializer);
+                }
+                match self.options.policy(other) {
+                    Some(Unsupported::Nil | Unsupported::Skip) => serializer.serialize_unit(),
+                    Some(Unsupported::TypeName) => serializer.serialize_str(other.type_name()),
+                    _ => Err(ser::Error::custom(format_args!(
+                        "cannot serialize a {}",
+                        other.type_name()
+                    ))),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cycles, Options, Unsupported};
+    use ::mlua::{Lua, Value};
+
+    fn write(lua: &Lua, chunk: &str, options: Options) -> Result<String, crate::SerError> {
+        let value: Value = lua.load(chunk).eval().unwrap();
+        crate::to_string(&options.wrap(value))
+    }
+
+    #[test]
+    fn applies_the_cycle_policy_only_to_cycles() {
+        let lua = Lua::new();
+        let shared = "local s = { 1 }; return { s, s }";
+        assert_eq!(write(&lua, shared, Options::new()).unwrap(), "{{1},{1}}");
+
+        let cycle = "local t = { x = 1 }; t.self = t; return t";
+        assert!(write(&lua, cycle, Options::new()).is_err());
+        let written = write(&lua, cycle, Options::new().with_cycles(Cycles::Nil)).unwrap();
+        let read: crate::Value = crate::from_str(&written).unwrap();
+        assert_eq!(read, crate::from_str("{x = 1, self = nil}").unwrap());
+    }
+
+    #[test]
+    fn applies_the_function_and_userdata_policies() {
+        let lua = Lua::new();
+        let chunk = "return { print, f = print, co = coroutine.create(print), n = 1 }";
+        let options = |policy| Options::new().with_functions(policy).with_userdata(policy);
+        assert!(write(&lua, chunk, options(Unsupported::Error)).is_err());
+        let read = |policy| {
+            let written = write(&lua, chunk, options(policy)).unwrap();
+            let check = format!(
+                "local t = {written}; return tostring(t[1]), tostring(t.f), tostring(t.co), t.n"
+            );
+            lua.load(check)
+                .eval::<(String, String, String, i64)>()
+                .unwrap()
+        };
+        let strings = |a: &str, b: &str, c: &str| (a.to_owned(), b.to_owned(), c.to_owned(), 1);
+        assert_eq!(read(Unsupported::Skip), strings("nil", "nil", "nil"));
+        assert_eq!(
+            read(Unsupported::TypeName),
+            strings("function", "function", "thread")
+        );
+    }
+
+    #[test]
+    fn dumps_lua_functions_but_not_c_functions() {
+        let lua = Lua::new();
+        let chunk = "return { function(a, b) return a .. b end, print }";
+        let options = Options::new()
+            .with_dumped_functions(true)
+            .with_functions(Unsupported::Skip);
+        let written = write(&lua, chunk, options).unwrap();
+        assert!(
+            written.starts_with("{(loadstring or load)(\"\\027Lua"),
+            "{written}"
+        );
+        assert!(written.ends_with(",nil}"), "{written}");
+
+        let check = format!("local t = {written}; return t[1]('a', 'b'), t[2]");
+        let (joined, skipped): (String, Value) = lua.load(check).eval().unwrap();
+        assert_eq!(joined, "ab");
+        assert_eq!(skipped, Value::Nil);
+    }
+
+    #[test]
+    fn writes_strings_that_are_not_utf8_as_escapes() {
+        let lua = Lua::new();
+        let written = write(&lua, r#"return { "\255", ok = "é" }"#, Options::new()).unwrap();
+        let loaded: ::mlua::Table = lua.load(&written).eval().unwrap();
+        assert_eq!(
+            loaded.get::<_, ::mlua::String>(1).unwrap().as_bytes(),
+            b"\xFF"
+        );
+        assert_eq!(loaded.get::<_, String>("ok").unwrap(), "é");
+        assert!(write(&lua, r#"return { ["\255"] = 1 }"#, Options::new()).is_err());
+    }
+
+    #[test]
+    fn serializes_again_after_an_error() {
+        let lua = Lua::new();
+        let value: Value = lua.load("return { { 1, 2 } }").eval().unwrap();
+        let wrapped = Options::new().with_cycles(Cycles::Nil).wrap(value);
+
+        let mut full = [0; 2];
+        let mut serializer = crate::Serializer::new(&mut full[..]);
+        assert!(serde::Serialize::serialize(&wrapped, &mut serializer).is_err());
+        assert_eq!(crate::to_string(&wrapped).unwrap(), "{{1,2}}");
+    }
+}
@@ -0,0 +1,552 @@
+use crate::{
+    ser::{
+        key::{Key, KeyRecorder},
+        raw,
+    },
+    SerError,
+};
+use ::mlua::{Lua, Table, Value};
+use serde::{ser, Serialize};
+
+/// Builds the lua value `value` serializes to in the state `lua`, without writing and loading
+/// lua source.
+///
+/// Values get the same representation as in the serialized text: enum variants with data become
+/// `{Variant = data}`, `None` and `()` become `nil`, bytes become a sequence of integers and
+/// [`RawLua`](crate::RawLua) is evaluated as an expression. Integers that don't fit into a lua
+/// integer become floats.
+///
+/// ```
+/// use mlua::{Lua, Table};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Player {
+///     name: String,
+///     scores: Vec<u32>,
+/// }
+///
+/// let lua = Lua::new();
+/// let player = Player {
+///     name: "ferris".to_owned(),
+///     scores: vec![3, 5],
+/// };
+/// let value = serde_lua_table::mlua_support::to_lua(&lua, &player).unwrap();
+/// lua.globals().set("player", value).unwrap();
+///
+/// let total: u32 = lua
+///     .load("return player.name == 'ferris' and player.scores[1] + player.scores[2]")
+///     .eval()
+///     .unwrap();
+/// assert_eq!(total, 8);
+/// ```
+///
+/// # Errors
+///
+/// Fails if `T`'s implementation of `Serialize` decides to fail, if a key is `nil` or `NaN`, or
+/// if lua fails to allocate.
+pub fn to_lua<'lua, T>(lua: &'lua Lua, value: &T) -> Result<Value<'lua>, SerError>
+where
+    T: ?Sized + Serialize,
+{
+    value.serialize(LuaSerializer { lua })
+}
+
+fn lua_error(e: ::mlua::Error) -> SerError {
+    SerError::Custom(e.to_string())
+}
+
+/// Builds [`Value`]s in a lua state.
+#[derive(Clone, Copy)]
+struct LuaSerializer<'lua> {
+    lua: &'lua Lua,
+}
+
+impl<'lua> LuaSerializer<'lua> {
+    fn table(self, len: Option<usize>, records: usize) -> Result<Table<'lua>, SerError> {
+        self.lua
+            .create_table_with_capacity(len.unwrap_or(0), records)
+            .map_err(lua_error)
+    }
+
+    fn string(self, s: impl AsRef<[u8]>) -> Result<Value<'lua>, SerError> {
+        self.lua
+            .create_string(s)
+            .map(Value::String)
+            .map_err(lua_error)
+    }
+
+    /// Wraps `inner` in the table the serializer writes around enum variants.
+    fn variant(self, variant: &'static str, inner: Value<'lua>) -> Result<Value<'lua>, SerError> {
+        let table = self.table(None, 1)?;
+        table.raw_set(variant, inner).map_err(lua_error)?;
+        Ok(Value::Table(table))
+    }
+}
+
+impl<'lua> ser::Serializer for LuaSerializer<'lua> {
+    type Ok = Value<'lua>;
+    type Error = SerError;
+    type SerializeSeq = SeqBuilder<'lua>;
+    type SerializeTuple = SeqBuilder<'lua>;
+    type SerializeTupleStruct = SeqBuilder<'lua>;
+    type SerializeTupleVariant = SeqBuilder<'lua>;
+    type SerializeMap = TableBuilder<'lua>;
+    type SerializeStruct = TableBuilder<'lua>;
+    type SerializeStructVariant = TableBuilder<'lua>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Boolean(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Integer(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+        match i64::try_from(v) {
+            Ok(v) => Ok(Value::Integer(v)),
+            Err(_) => Ok(Value::Number(v as f64)),
+        }
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i128(v.into())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+        match i64::try_from(v) {
+            Ok(v) => Ok(Value::Integer(v)),
+            Err(_) => Ok(Value::Number(v as f64)),
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Number(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        self.string(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        self.string(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        let table = self.table(Some(v.len()), 0)?;
+        for byte in v {
+            table.raw_push(*byte).map_err(lua_error)?;
+        }
+        Ok(Value::Table(table))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Nil)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Nil)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        Ok(Value::Nil)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.string(variant)
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        if name == raw::TOKEN {
+            let Key::String(source) = value.serialize(KeyRecorder)? else {
+                return Err(SerError::RawMustBeString);
+            };
+            return self
+                .lua
+                .load(format!("return {}", source))
+                .eval()
+                .map_err(lua_error);
+        }
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let inner = value.serialize(self)?;
+        self.variant(variant, inner)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Ok(SeqBuilder {
+            ser: self,
+            table: self.table(len, 0)?,
+            len: 0,
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Ok(SeqBuilder {
+            ser: self,
+            table: self.table(Some(len), 0)?,
+            len: 0,
+            variant: Some(variant),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(TableBuilder {
+            ser: self,
+            table: self.table(None, len.unwrap_or(0))?,
+            key: None,
+            variant: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Ok(TableBuilder {
+            ser: self,
+            table: self.table(None, len)?,
+            key: None,
+            variant: Some(variant),
+        })
+    }
+}
+
+pub(super) struct SeqBuilder<'lua> {
+    ser: LuaSerializer<'lua>,
+    table: Table<'lua>,
+    /// The number of elements pushed, counting `nil`s that leave holes.
+    len: i64,
+    variant: Option<&'static str>,
+}
+
+impl<'lua> SeqBuilder<'lua> {
+    fn push<T>(&mut self, value: &T) -> Result<(), SerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        let value = value.serialize(self.ser)?;
+        self.len += 1;
+        self.table.raw_set(self.len, value).map_err(lua_error)
+    }
+
+    fn finish(self) -> Result<Value<'lua>, SerError> {
+        let table = Value::Table(self.table);
+        match self.variant {
+            Some(variant) => self.ser.variant(variant, table),
+            None => Ok(table),
+        }
+    }
+}
+
+impl<'lua> ser::SerializeSeq for SeqBuilder<'lua> {
+    type Ok = Value<'lua>;
+    type Error = SerError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<'lua> ser::SerializeTuple for SeqBuilder<'lua> {
+    type Ok = Value<'lua>;
+    type Error = SerError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<'lua> ser::SerializeTupleStruct for SeqBuilder<'lua> {
+    type Ok = Value<'lua>;
+    type Error = SerError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<'lua> ser::SerializeTupleVariant for SeqBuilder<'lua> {
+    type Ok = Value<'lua>;
+    type Error = SerError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+pub(super) struct TableBuilder<'lua> {
+    ser: LuaSerializer<'lua>,
+    table: Table<'lua>,
+    /// The key of the entry whose value is serialized next.
+    key: Option<Value<'lua>>,
+    variant: Option<&'static str>,
+}
+
+impl<'lua> TableBuilder<'lua> {
+    fn set<T>(&mut self, key: Value<'lua>, value: &T) -> Result<(), SerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        // Lua raises an error for these keys, which would abort outside of a protected call.
+        match key {
+            Value::Nil => return Err(SerError::KeyMustBeStringOrNumber),
+            Value::Number(n) if n.is_nan() => return Err(SerError::KeyMustBeStringOrNumber),
+            _ => {}
+        }
+        let value = value.serialize(self.ser)?;
+        self.table.raw_set(key, value).map_err(lua_error)
+    }
+
+    fn finish(self) -> Result<Value<'lua>, SerError> {
+        let table = Value::Table(self.table);
+        match self.variant {
+            Some(variant) => self.ser.variant(variant, table),
+            None => Ok(table),
+        }
+    }
+}
+
+impl<'lua> ser::SerializeMap for TableBuilder<'lua> {
+    type Ok = Value<'lua>;
+    type Error = SerError;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.key = Some(key.serialize(self.ser)?);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let key = self.key.take().unwrap_or(Value::Nil);
+        self.set(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<'lua> ser::SerializeStruct for TableBuilder<'lua> {
+    type Ok = Value<'lua>;
+    type Error = SerError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let key = self.ser.string(key)?;
+        self.set(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<'lua> ser::SerializeStructVariant for TableBuilder<'lua> {
+    type Ok = Value<'lua>;
+    type Error = SerError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_lua;
+    use crate::RawLua;
+    use ::mlua::Lua;
+    use serde::{Serialize, Serializer};
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    enum Shape {
+        Circle(f64),
+        Rect { w: u32, h: u32 },
+        Empty,
+    }
+
+    struct Bytes(&'static [u8]);
+
+    /// A table with a single `NaN` key.
+    struct NanKey;
+
+    impl Serialize for NanKey {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_map([(f64::NAN, 1)])
+        }
+    }
+
+    impl Serialize for Bytes {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(self.0)
+        }
+    }
+
+    /// Evaluates `check` with the built value as `v`.
+    fn check<T: ?Sized + Serialize>(value: &T, check: &str) -> bool {
+        let lua = Lua::new();
+        let value = to_lua(&lua, value).unwrap();
+        lua.globals().set("v", value).unwrap();
+        lua.load(check).eval().unwrap()
+    }
+
+    #[test]
+    fn builds_values_like_the_serializer_writes_them() {
+        let shapes = (Shape::Circle(0.5), Shape::Rect { w: 2, h: 3 }, Shape::Empty);
+        assert!(check(
+            &shapes,
+            "return v[1].Circle == 0.5 and v[2].Rect.w == 2 and v[2].Rect.h == 3 and v[3] == 'Empty'"
+        ));
+        assert!(check(
+            &(None::<u8>, (), Bytes(b"hi")),
+            "return v[1] == nil and v[2] == nil and v[3][1] == 104 and v[3][2] == 105"
+        ));
+        assert!(check(
+            &u64::MAX,
+            "return math.type(v) == 'float' and v == 2^64"
+        ));
+        assert!(check(
+            &RawLua("{ math.max(1, 2) }".to_owned()),
+            "return v[1] == 2"
+        ));
+    }
+
+    #[test]
+    fn rejects_keys_lua_cannot_store() {
+        let lua = Lua::new();
+        assert!(to_lua(&lua, &BTreeMap::from([(Some(1), 1), (None, 2)])).is_err());
+        assert!(to_lua(&lua, &NanKey).is_err());
+    }
+}
@@ -0,0 +1,26 @@
+//! Policies shared by the integrations with lua runtimes.
+
+/// What to do when a table contains itself.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Cycles {
+    /// Fail the serialization.
+    #[default]
+    Error,
+    /// Write `nil` in place of the repeated table.
+    Nil,
+}
+
+/// What to do with values that can't be represented in a table literal.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Unsupported {
+    /// Fail the serialization.
+    #[default]
+    Error,
+    /// Write `nil` in place of the value.
+    Nil,
+    /// Leave out the entry holding the value. Sequence elements are written as `nil` to keep the
+    /// following indices intact.
+    Skip,
+    /// Write the lua type name of the value as a string.
+    TypeName,
+}
@@ -0,0 +1,647 @@
+//! Serialization of values owned by an [`rlua`](::rlua) state, and deserialization from them.
+//!
+//! This mirrors the [`mlua`](crate::mlua_support) integration for projects that still use `rlua`. Since
+//! `rlua` values can only be used inside [`Lua::context`](::rlua::Lua::context), both directions
+//! take the [`Context`] the value belongs to. Integers require `rlua` to be built for Lua 5.3 or
+//! 5.4 (the default).
+//!
+//! ```
+//! use rlua::{Lua, Value};
+//! use serde::Deserialize;
+//! use serde_lua_table::rlua_support::{from_value, Options, Unsupported};
+//!
+//! #[derive(Debug, PartialEq, Deserialize)]
+//! struct Item {
+//!     name: String,
+//!     count: u32,
+//! }
+//!
+//! Lua::new().context(|ctx| {
+//!     let value: Value = ctx
+//!         .load(r#"return { name = "apple", count = 3, on_use = print }"#)
+//!         .eval()
+//!         .unwrap();
+//!
+//!     let options = Options::new().with_functions(Unsupported::Skip);
+//!     let lua = serde_lua_table::to_string(&options.wrap(ctx, value.clone())).unwrap();
+//!     assert!(lua.contains(r#"["name"]="apple""#) && !lua.contains("on_use"));
+//!
+//!     let item: Item = from_value(ctx, value).unwrap();
+//!     assert_eq!(item, Item { name: "apple".to_owned(), count: 3 });
+//! });
+//! ```
+
+pub use crate::policy::{Cycles, Unsupported};
+
+use crate::helpers::lua_string_bytes;
+use ::rlua::{Context, Table, Value};
+use serde::{
+    de::{self, DeserializeOwned},
+    forward_to_deserialize_any,
+    ser::{self, Error as _, SerializeMap, SerializeSeq},
+    Serialize, Serializer,
+};
+use std::{fmt, vec};
+use thiserror::Error;
+
+/// Policies for serializing [`rlua::Value`](Value)s.
+///
+/// By default, every value that can't be written as a lua literal is an error.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Options {
+    cycles: Cycles,
+    functions: Unsupported,
+    userdata: Unsupported,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how tables that contain themselves are handled.
+    pub fn with_cycles(mut self, cycles: Cycles) -> Self {
+        self.cycles = cycles;
+        self
+    }
+
+    /// Sets how functions are handled.
+    pub fn with_functions(mut self, functions: Unsupported) -> Self {
+        self.functions = functions;
+        self
+    }
+
+    /// Sets how userdata is handled. This also applies to light userdata, threads and errors.
+    pub fn with_userdata(mut self, userdata: Unsupported) -> Self {
+        self.userdata = userdata;
+        self
+    }
+
+    /// Wraps `value` so it serializes using these options.
+    pub fn wrap<'lua>(self, ctx: Context<'lua>, value: Value<'lua>) -> LuaValue<'lua> {
+        LuaValue {
+            ctx,
+            value,
+            options: self,
+        }
+    }
+
+    /// Wraps `table` so it serializes using these options.
+    pub fn wrap_table<'lua>(self, ctx: Context<'lua>, table: &Table<'lua>) -> LuaValue<'lua> {
+        self.wrap(ctx, Value::Table(table.clone()))
+    }
+
+    fn policy(&self, value: &Value) -> Option<Unsupported> {
+        match value {
+            Value::Nil
+            | Value::Boolean(_)
+            | Value::Integer(_)
+            | Value::Number(_)
+            | Value::String(_)
+            | Value::Table(_) => None,
+            Value::Function(_) => Some(self.functions),
+            _ => Some(self.userdata),
+        }
+    }
+}
+
+/// Tables that are currently being visited, kept as keys of a lua table to compare them by
+/// identity.
+struct Visiting<'lua>(Table<'lua>);
+
+impl<'lua> Visiting<'lua> {
+    fn new(ctx: Context<'lua>) -> ::rlua::Result<Self> {
+        ctx.create_table().map(Self)
+    }
+
+    /// Marks `table` as visited, returning `false` if it already was.
+    fn enter(&self, table: &Table<'lua>) -> ::rlua::Result<bool> {
+        if self.0.raw_get::<_, bool>(table.clone())? {
+            return Ok(false);
+        }
+        self.0.raw_set(table.clone(), true)?;
+        Ok(true)
+    }
+
+    fn leave(&self, table: &Table<'lua>) -> ::rlua::Result<()> {
+        self.0.raw_set(table.clone(), Value::Nil)
+    }
+}
+
+/// Returns the contents of `table`, with the values in order if it's a sequence.
+fn entries<'lua>(table: &Table<'lua>) -> ::rlua::Result<Entries<'lua>> {
+    let pairs = table
+        .clone()
+        .pairs::<Value, Value>()
+        .collect::<::rlua::Result<Vec<_>>>()?;
+    if usize::try_from(table.raw_len()) == Ok(pairs.len()) {
+        let values = (1..=pairs.len())
+            .map(|i| table.raw_get(i))
+            .collect::<::rlua::Result<_>>()?;
+        Ok(Entries::Sequence(values))
+    } else {
+        Ok(Entries::Map(pairs))
+    }
+}
+
+enum Entries<'lua> {
+    Sequence(Vec<Value<'lua>>),
+    Map(Vec<(Value<'lua>, Value<'lua>)>),
+}
+
+/// A [`rlua::Value`](Value) together with the [`Options`] used to serialize it.
+pub struct LuaValue<'lua> {
+    ctx: Context<'lua>,
+    value: Value<'lua>,
+    options: Options,
+}
+
+impl<'lua> Serialize for LuaValue<'lua> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let visiting = Visiting::new(self.ctx).map_err(S::Error::custom)?;
+        Node {
+            value: &self.value,
+            options: &self.options,
+            visiting: &visiting,
+        }
+        .serialize(serializer)
+    }
+}
+
+struct Node<'a, 'lua> {
+    value: &'a Value<'lua>,
+    options: &'a Options,
+    visiting: &'a Visiting<'lua>,
+}
+
+impl<'a, 'lua> Node<'a, 'lua> {
+    fn child(&self, value: &'a Value<'lua>) -> Self {
+        Self {
+            value,
+            options: self.options,
+            visiting: self.visiting,
+        }
+    }
+
+    fn is_skipped(&self) -> bool {
+        self.options.policy(self.value) == Some(Unsupported::Skip)
+    }
+
+    fn serialize_table<S>(&self, table: &Table<'lua>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if !self.visiting.enter(table).map_err(S::Error::custom)? {
+            return match self.options.cycles {
+                Cycles::Error => Err(S::Error::custom("table contains itself")),
+                Cycles::Nil => serializer.serialize_unit(),
+            };
+        }
+
+        let result = match entries(table).map_err(S::Error::custom)? {
+            Entries::Sequence(values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for value in &values {
+                    seq.serialize_element(&self.child(value))?;
+                }
+                seq.end()
+            }
+            Entries::Map(pairs) => {
+                let mut map = serializer.serialize_map(None)?;
+                for (key, value) in &pairs {
+                    if matches!(key, Value::String(s) if s.to_str().is_err()) {
+                        return Err(S::Error::custom("table key is not valid UTF-8"));
+                    }
+                    let (key, value) = (self.child(key), self.child(value));
+                    if key.is_skipped() || value.is_skipped() {
+                        continue;
+                    }
+                    map.serialize_entry(&key, &value)?;
+                }
+                map.end()
+            }
+        };
+        self.visiting.leave(table).map_err(S::Error::custom)?;
+        result
+    }
+}
+
+impl<'a, 'lua> Serialize for Node<'a, 'lua> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.value {
+            Value::Nil => serializer.serialize_unit(),
+            Value::Boolean(b) => serializer.serialize_bool(*b),
+            Value::Integer(i) => serializer.serialize_i64(*i),
+            Value::Number(n) => serializer.serialize_f64(*n),
+            Value::String(s) => match s.to_str() {
+                Ok(s) => serializer.serialize_str(s),
+                Err(_) => lua_string_bytes::serialize(s.as_bytes(), serializer),
+            },
+            Value::Table(table) => self.serialize_table(table, serializer),
+            other => match self.options.policy(other) {
+                Some(Unsupported::Nil | Unsupported::Skip) => serializer.serialize_unit(),
+                Some(Unsupported::TypeName) => serializer.serialize_str(other.type_name()),
+                _ => Err(ser::Error::custom(format_args!(
+                    "cannot serialize a {}",
+                    other.type_name()
+                ))),
+            },
+        }
+    }
+}
+
+/// Errors that can occur when deserializing from a [`rlua::Value`](Value).
+#[derive(Debug, Error)]
+pub enum DeError {
+    #[error("Lua error: {0}")]
+    Lua(#[from] ::rlua::Error),
+    #[error("Table contains itself")]
+    Cycle,
+    #[error("{0}")]
+    Custom(String),
+}
+
+impl de::Error for DeError {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: fmt::Display,
+    {
+        Self::Custom(msg.to_string())
+    }
+}
+
+/// Deserializes a `T` from a value owned by `ctx`.
+///
+/// Sequences (tables whose keys are exactly `1..=n`) deserialize as seqs, all other tables as
+/// maps. Functions, userdata and other values without a serde equivalent are an error.
+///
+/// # Errors
+///
+/// Deserialization fails if `value` doesn't match the shape of `T`, or if it contains itself.
+pub fn from_value<'lua, T>(ctx: Context<'lua>, value: Value<'lua>) -> Result<T, DeError>
+where
+    T: DeserializeOwned,
+{
+    let visiting = Visiting::new(ctx)?;
+    T::deserialize(ValueDeserializer {
+        value,
+        visiting: &visiting,
+    })
+}
+
+struct ValueDeserializer<'a, 'lua> {
+    value: Value<'lua>,
+    visiting: &'a Visiting<'lua>,
+}
+
+impl<'a, 'lua> ValueDeserializer<'a, 'lua> {
+    fn child(&self, value: Value<'lua>) -> Self {
+        Self {
+            value,
+            visiting: self.visiting,
+        }
+    }
+
+    fn unexpected(&self) -> de::Unexpected<'_> {
+        match &self.value {
+            Value::Nil => de::Unexpected::Unit,
+            Value::Boolean(b) => de::Unexpected::Bool(*b),
+            Value::Integer(i) => de::Unexpected::Signed(*i),
+            Value::Number(n) => de::Unexpected::Float(*n),
+            Value::String(s) => match s.to_str() {
+                Ok(s) => de::Unexpected::Str(s),
+                Err(_) => de::Unexpected::Bytes(s.as_bytes()),
+            },
+            Value::Table(_) => de::Unexpected::Map,
+            other => de::Unexpected::Other(other.type_name()),
+        }
+    }
+
+    /// Visits the entries of `table` while it's marked as visited.
+    fn visit_table<'de, V>(&self, table: &Table<'lua>, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        if !self.visiting.enter(table)? {
+            return Err(DeError::Cycle);
+        }
+        let result = match entries(table)? {
+            Entries::Sequence(values) => visitor.visit_seq(SeqAccess {
+                values: values.into_iter(),
+                visiting: self.visiting,
+            }),
+            Entries::Map(pairs) => visitor.visit_map(MapAccess {
+                pairs: pairs.into_iter(),
+                value: None,
+                visiting: self.visiting,
+            }),
+        };
+        self.visiting.leave(table)?;
+        result
+    }
+}
+
+impl<'de, 'a, 'lua> de::Deserializer<'de> for ValueDeserializer<'a, 'lua> {
+    type Error = DeError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match &self.value {
+            Value::Nil => visitor.visit_unit(),
+            Value::Boolean(b) => visitor.visit_bool(*b),
+            Value::Integer(i) => visitor.visit_i64(*i),
+            Value::Number(n) => visitor.visit_f64(*n),
+            Value::String(s) => match s.to_str() {
+                Ok(s) => visitor.visit_str(s),
+                Err(_) => visitor.visit_bytes(s.as_bytes()),
+            },
+            Value::Table(table) => self.visit_table(table, visitor),
+            _ => Err(de::Error::invalid_type(self.unexpected(), &visitor)),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.value {
+            Value::Nil => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match &self.value {
+            Value::String(_) => visitor.visit_enum(EnumAccess {
+                variant: self.child(self.value.clone()),
+                value: None,
+            }),
+            Value::Table(table) => {
+                let mut pairs = table
+                    .clone()
+                    .pairs::<Value, Value>()
+                    .collect::<::rlua::Result<Vec<_>>>()?;
+                if pairs.len() != 1 {
+                    return Err(de::Error::invalid_length(
+                        pairs.len(),
+                        &"a table with a single entry",
+                    ));
+                }
+                let (variant, value) = pairs.remove(0);
+                visitor.visit_enum(EnumAccess {
+                    variant: self.child(variant),
+                    value: Some(self.child(value)),
+                })
+            }
+            _ => Err(de::Error::invalid_type(
+                self.unexpected(),
+                &"a string or table",
+            )),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier
+    }
+}
+
+struct SeqAccess<'a, 'lua> {
+    values: vec::IntoIter<Value<'lua>>,
+    visiting: &'a Visiting<'lua>,
+}
+
+impl<'de, 'a, 'lua> de::SeqAccess<'de> for SeqAccess<'a, 'lua> {
+    type Error = DeError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        self.values
+            .next()
+            .map(|value| {
+                seed.deserialize(ValueDeserializer {
+                    value,
+                    visiting: self.visiting,
+                })
+            })
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len())
+    }
+}
+
+struct MapAccess<'a, 'lua> {
+    pairs: vec::IntoIter<(Value<'lua>, Value<'lua>)>,
+    value: Option<Value<'lua>>,
+    visiting: &'a Visiting<'lua>,
+}
+
+impl<'de, 'a, 'lua> de::MapAccess<'de> for MapAccess<'a, 'lua> {
+    type Error = DeError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        let Some((key, value)) = self.pairs.next() else {
+            return Ok(None);
+        };
+        self.value = Some(value);
+        seed.deserialize(ValueDeserializer {
+            value: key,
+            visiting: self.visiting,
+        })
+        .map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let value = self.value.take().unwrap_or(Value::Nil);
+        seed.deserialize(ValueDeserializer {
+            value,
+            visiting: self.visiting,
+        })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.pairs.len())
+    }
+}
+
+struct EnumAccess<'a, 'lua> {
+    variant: ValueDeserializer<'a, 'lua>,
+    value: Option<ValueDeserializer<'a, 'lua>>,
+}
+
+impl<'de, 'a, 'lua> de::EnumAccess<'de> for EnumAccess<'a, 'lua> {
+    type Error = DeError;
+    type Variant = VariantAccess<'a, 'lua>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(self.variant)?;
+        Ok((variant, VariantAccess { value: self.value }))
+    }
+}
+
+struct VariantAccess<'a, 'lua> {
+    value: Option<ValueDeserializer<'a, 'lua>>,
+}
+
+impl<'de, 'a, 'lua> de::VariantAccess<'de> for VariantAccess<'a, 'lua> {
+    type Error = DeError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        match self.value {
+            None => Ok(()),
+            Some(value) => de::Deserialize::deserialize(value),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.value {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::invalid_type(
+                de::Unexpected::UnitVariant,
+                &"newtype variant",
+            )),
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.value {
+            Some(value) => de::Deserializer::deserialize_seq(value, visitor),
+            None => Err(de::Error::invalid_type(
+                de::Unexpected::UnitVariant,
+                &"tuple variant",
+            )),
+        }
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.value {
+            Some(value) => de::Deserializer::deserialize_map(value, visitor),
+            None => Err(de::Error::invalid_type(
+                de::Unexpected::UnitVariant,
+                &"struct variant",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_value, Cycles, DeError, Options, Unsupported};
+    use ::rlua::{Lua, Value};
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Shape {
+        Point,
+        Circle { radius: f64 },
+    }
+
+    #[test]
+    fn writes_values_with_the_options() {
+        Lua::new().context(|ctx| {
+            let value: Value = ctx
+                .load("local t = { 1, 'two', print }; t[4] = t; return t")
+                .eval()
+                .unwrap();
+            assert!(crate::to_string(&Options::new().wrap(ctx, value.clone())).is_err());
+            let options = Options::new()
+                .with_cycles(Cycles::Nil)
+                .with_functions(Unsupported::TypeName);
+            assert_eq!(
+                crate::to_string(&options.wrap(ctx, value)).unwrap(),
+                r#"{1,"two","function",nil}"#
+            );
+        });
+    }
+
+    #[test]
+    fn reads_sequences_maps_and_enums() {
+        Lua::new().context(|ctx| {
+            let eval = |chunk: &str| ctx.load(chunk).eval::<Value>().unwrap();
+            let list: Vec<Option<i64>> = from_value(ctx, eval("return { 1, 2, 3 }")).unwrap();
+            assert_eq!(list, [Some(1), Some(2), Some(3)]);
+            let map: BTreeMap<String, f64> = from_value(ctx, eval("return { x = 1.5 }")).unwrap();
+            assert_eq!(map["x"], 1.5);
+            let shapes: Vec<Shape> =
+                from_value(ctx, eval("return { 'Point', { Circle = { radius = 2 } } }")).unwrap();
+            assert_eq!(shapes, [Shape::Point, Shape::Circle { radius: 2.0 }]);
+        });
+    }
+
+    #[test]
+    fn rejects_cycles_but_not_shared_tables() {
+        Lua::new().context(|ctx| {
+            let shared: Value = ctx.load("local s = { 1 }; return { s, s }").eval().unwrap();
+            let read: Vec<Vec<i64>> = from_value(ctx, shared).unwrap();
+            assert_eq!(read, [[1], [1]]);
+
+            let cycle: Value = ctx.load("local t = {}; t[1] = t; return t").eval().unwrap();
+            let err = from_value::<Vec<Vec<i64>>>(ctx, cycle).unwrap_err();
+            assert!(matches!(err, DeError::Cycle));
+            let function: Value = ctx.load("return print").eval().unwrap();
+            assert!(from_value::<String>(ctx, function).is_err());
+        });
+    }
+}
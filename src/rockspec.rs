@@ -0,0 +1,327 @@
+//! Writing LuaRocks `.rockspec` files.
+//!
+//! A rockspec is a Lua file that assigns its fields as globals. [`Rockspec`] models the commonly
+//! used fields, and [`to_writer`] writes them in the order LuaRocks itself uses.
+//!
+//! ```
+//! use serde_lua_table::rockspec::{self, Build, Description, Rockspec, Source};
+//!
+//! let spec = Rockspec {
+//!     package: "lua-table".to_owned(),
+//!     version: "0.1.2-1".to_owned(),
+//!     source: Source {
+//!         url: "git+https://github.com/Nerixyz/serde-lua-table.git".to_owned(),
+//!         tag: Some("v0.1.2".to_owned()),
+//!         ..Source::default()
+//!     },
+//!     description: Description {
+//!         summary: Some("Serialize values to a lua representation.".to_owned()),
+//!         license: Some("MIT OR Apache-2.0".to_owned()),
+//!         ..Description::default()
+//!     },
+//!     dependencies: vec!["lua >= 5.1".to_owned()],
+//!     build: Build {
+//!         kind: "rust-mlua".to_owned(),
+//!         modules: vec![("lua_table".to_owned(), "serde_lua_table".to_owned())],
+//!     },
+//!     ..Rockspec::default()
+//! };
+//!
+//! assert_eq!(
+//!     rockspec::to_string(&spec).unwrap(),
+//!     r#"rockspec_format = "3.0"
+//! package = "lua-table"
+//! version = "0.1.2-1"
+//! source = {
+//!   url = "git+https://github.com/Nerixyz/serde-lua-table.git",
+//!   tag = "v0.1.2"
+//! }
+//! description = {
+//!   summary = "Serialize values to a lua representation.",
+//!   license = "MIT OR Apache-2.0"
+//! }
+//! dependencies = {
+//!   "lua >= 5.1"
+//! }
+//! build = {
+//!   type = "rust-mlua",
+//!   modules = {
+//!     lua_table = "serde_lua_table"
+//!   }
+//! }
+//! "#
+//! );
+//! ```
+
+use crate::{Globals, KeyStyle, LuaVersion, PrettyFormatter, SerError, SerOptions, Serializer};
+use serde::ser::{Serialize, SerializeMap, SerializeStruct};
+use std::io;
+
+/// The rockspec format version that is written. `3.0` is required for `test_dependencies` and
+/// `description.labels`.
+pub const ROCKSPEC_FORMAT: &str = "3.0";
+
+/// The contents of a `.rockspec` file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Rockspec {
+    /// Name of the rock.
+    pub package: String,
+    /// Version of the rock including the rockspec revision, e.g. `1.0.0-1`.
+    pub version: String,
+    pub source: Source,
+    pub description: Description,
+    /// Runtime dependencies, e.g. `lua >= 5.1`.
+    pub dependencies: Vec<String>,
+    pub build_dependencies: Vec<String>,
+    pub test_dependencies: Vec<String>,
+    pub build: Build,
+}
+
+/// Where LuaRocks fetches the sources from.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Source {
+    pub url: String,
+    pub tag: Option<String>,
+    pub branch: Option<String>,
+    /// The directory the sources are in after unpacking.
+    pub dir: Option<String>,
+}
+
+/// Metadata shown by LuaRocks and on luarocks.org. All fields are optional.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Description {
+    pub summary: Option<String>,
+    pub detailed: Option<String>,
+    pub homepage: Option<String>,
+    pub license: Option<String>,
+    pub maintainer: Option<String>,
+    pub labels: Vec<String>,
+}
+
+/// How the rock is built.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Build {
+    /// The build backend, written as `type`, e.g. `builtin`, `make` or `rust-mlua`.
+    pub kind: String,
+    /// Module names and the file (or library) that provides them.
+    pub modules: Vec<(String, String)>,
+}
+
+impl Default for Build {
+    fn default() -> Self {
+        Build {
+            kind: "builtin".to_owned(),
+            modules: Vec::new(),
+        }
+    }
+}
+
+/// Creates a serializer that writes tables like a rockspec: the fields of the outermost table
+/// are assigned as globals, identifier keys are written without brackets and the output only
+/// relies on Lua 5.1.
+pub fn serializer<W>(writer: W) -> Serializer<W, Globals<PrettyFormatter<'static>>>
+where
+    W: io::Write,
+{
+    let options = SerOptions::new()
+        .with_version(LuaVersion::Lua51)
+        .with_key_style(KeyStyle::Bare);
+    Serializer::with_options(writer, Globals::new(PrettyFormatter::new()), options)
+}
+
+/// Writes `rockspec` into the IO stream.
+///
+/// # Errors
+///
+/// Fails if writing to `writer` fails.
+pub fn to_writer<W>(writer: W, rockspec: &Rockspec) -> Result<(), SerError>
+where
+    W: io::Write,
+{
+    rockspec.serialize(&mut serializer(writer))
+}
+
+/// Writes `rockspec` into a string.
+///
+/// # Errors
+///
+/// See [`to_writer`].
+pub fn to_string(rockspec: &Rockspec) -> Result<String, SerError> {
+    let mut buf = Vec::new();
+    to_writer(&mut buf, rockspec)?;
+    // The serializer only writes valid UTF-8.
+    Ok(String::from_utf8(buf).unwrap())
+}
+
+impl Serialize for Rockspec {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut s = serializer.serialize_struct("Rockspec", 8)?;
+        s.serialize_field("rockspec_format", ROCKSPEC_FORMAT)?;
+        s.serialize_field("package", &self.package)?;
+        s.serialize_field("version", &self.version)?;
+        s.serialize_field("source", &self.source)?;
+        if !self.description.is_empty() {
+            s.serialize_field("description", &self.description)?;
+        }
+        for (key, dependencies) in [
+            ("dependencies", &self.dependencies),
+            ("build_dependencies", &self.build_dependencies),
+            ("test_dependencies", &self.test_dependencies),
+        ] {
+            if !dependencies.is_empty() {
+                s.serialize_field(key, dependencies)?;
+            }
+        }
+        s.serialize_field("build", &self.build)?;
+        s.end()
+    }
+}
+
+impl Serialize for Source {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut s = serializer.serialize_struct("Source", 4)?;
+        s.serialize_field("url", &self.url)?;
+        serialize_optional(&mut s, "tag", &self.tag)?;
+        serialize_optional(&mut s, "branch", &self.branch)?;
+        serialize_optional(&mut s, "dir", &self.dir)?;
+        s.end()
+    }
+}
+
+impl Description {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Serialize for Description {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut s = serializer.serialize_struct("Description", 6)?;
+        serialize_optional(&mut s, "summary", &self.summary)?;
+        serialize_optional(&mut s, "detailed", &self.detailed)?;
+        serialize_optional(&mut s, "homepage", &self.homepage)?;
+        serialize_optional(&mut s, "license", &self.license)?;
+        serialize_optional(&mut s, "maintainer", &self.maintainer)?;
+        if !self.labels.is_empty() {
+            s.serialize_field("labels", &self.labels)?;
+        }
+        s.end()
+    }
+}
+
+impl Serialize for Build {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut s = serializer.serialize_struct("Build", 2)?;
+        s.serialize_field("type", &self.kind)?;
+        if !self.modules.is_empty() {
+            s.serialize_field("modules", &Modules(&self.modules))?;
+        }
+        s.end()
+    }
+}
+
+/// Serializes the modules of a build as a map while keeping their order.
+struct Modules<'a>(&'a [(String, String)]);
+
+impl Serialize for Modules<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, path) in self.0 {
+            map.serialize_entry(name, path)?;
+        }
+        map.end()
+    }
+}
+
+fn serialize_optional<S>(
+    s: &mut S,
+    key: &'static str,
+    value: &Option<String>,
+) -> Result<(), S::Error>
+where
+    S: SerializeStruct,
+{
+    match value {
+        Some(value) => s.serialize_field(key, value),
+        None => s.skip_field(key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rockspec() -> Rockspec {
+        Rockspec {
+            package: "serde-demo".to_owned(),
+            version: "1.0-1".to_owned(),
+            source: Source {
+                url: "git+https://example.com/demo.git".to_owned(),
+                tag: Some("v1.0".to_owned()),
+                ..Source::default()
+            },
+            dependencies: vec!["lua >= 5.1".to_owned()],
+            build: Build {
+                modules: vec![
+                    ("demo".to_owned(), "src/demo.lua".to_owned()),
+                    ("demo.util".to_owned(), "src/demo/util.lua".to_owned()),
+                ],
+                ..Build::default()
+            },
+            ..Rockspec::default()
+        }
+    }
+
+    #[test]
+    fn writes_globals_and_leaves_out_empty_fields() {
+        assert_eq!(
+            to_string(&rockspec()).unwrap(),
+            r#"rockspec_format = "3.0"
+package = "serde-demo"
+version = "1.0-1"
+source = {
+  url = "git+https://example.com/demo.git",
+  tag = "v1.0"
+}
+dependencies = {
+  "lua >= 5.1"
+}
+build = {
+  type = "builtin",
+  modules = {
+    demo = "src/demo.lua",
+    ["demo.util"] = "src/demo/util.lua"
+  }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn loads_in_lua() {
+        let lua = ::mlua::Lua::new();
+        lua.load(to_string(&rockspec()).unwrap()).exec().unwrap();
+        let globals = lua.globals();
+        assert_eq!(globals.get::<_, String>("rockspec_format").unwrap(), "3.0");
+        let modules: ::mlua::Table = lua.load("build.modules").eval().unwrap();
+        assert_eq!(
+            modules.get::<_, String>("demo.util").unwrap(),
+            "src/demo/util.lua"
+        );
+    }
+}
@@ -0,0 +1,231 @@
+//! Writing World of Warcraft SavedVariables files.
+//!
+//! The game stores the saved variables of an addon as a Lua 5.1 file that assigns every
+//! variable as a global. Account wide variables and per character variables are stored in
+//! separate files, see [`Scope`].
+//!
+//! ```
+//! use serde_lua_table::saved_variables::{AceDb, Writer};
+//!
+//! let db = AceDb::<u32, ()>::new()
+//!     .with_profile("Default", 3)
+//!     .with_profile_key("Thrall", "Draenor", "Default");
+//!
+//! let mut writer = Writer::new(Vec::new());
+//! writer.write_ace_db("MyAddonDB", &db).unwrap();
+//! writer.write_nil("MyAddonCache").unwrap();
+//!
+//! assert_eq!(
+//!     String::from_utf8(writer.into_inner()).unwrap(),
+//!     "MyAddonDB = {\n\t[\"profileKeys\"] = {\n\t\t[\"Thrall - Draenor\"] = \"Default\"\n\t},\n\
+//!      \t[\"profiles\"] = {\n\t\t[\"Default\"] = 3\n\t}\n}\nMyAddonCache = nil\n"
+//! );
+//! ```
+
+use crate::{LuaVersion, PrettyFormatter, Profile, SerError, Serializer};
+use serde::ser::{Serialize, SerializeMap};
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Which file the saved variables of an addon are stored in.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Scope {
+    /// Variables listed in `## SavedVariables`, shared by all characters of an account.
+    Account,
+    /// Variables listed in `## SavedVariablesPerCharacter`.
+    Character { realm: String, name: String },
+}
+
+impl Scope {
+    /// Returns the path of the file for `addon` relative to the `WTF` directory of the game.
+    pub fn path(&self, account: &str, addon: &str) -> PathBuf {
+        let mut path = Path::new("Account").join(account);
+        if let Scope::Character { realm, name } = self {
+            path.push(realm);
+            path.push(name);
+        }
+        path.push("SavedVariables");
+        path.push(format!("{addon}.lua"));
+        path
+    }
+}
+
+/// Writes the globals of a SavedVariables file the way the game does: bracketed keys, tab
+/// indentation and only numbers that are exact in Lua 5.1.
+pub struct Writer<W> {
+    writer: W,
+}
+
+impl<W> Writer<W>
+where
+    W: io::Write,
+{
+    pub fn new(writer: W) -> Self {
+        Writer { writer }
+    }
+
+    /// Writes `name = value`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `name` isn't a valid identifier or if `value` can't be serialized.
+    pub fn write_global<T>(&mut self, name: &str, value: &T) -> Result<(), SerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        if !LuaVersion::Lua51.is_identifier(name) {
+            return Err(SerError::InvalidGlobalName(name.to_owned()));
+        }
+        self.writer.write_all(name.as_bytes())?;
+        self.writer.write_all(b" = ")?;
+        let options = Profile::WowSavedVariables.options();
+        let formatter = PrettyFormatter::with_indent(b"\t");
+        let mut ser = Serializer::with_options(&mut self.writer, formatter, options);
+        value.serialize(&mut ser)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// Writes `name = nil`, which the game writes for variables that were never assigned.
+    ///
+    /// # Errors
+    ///
+    /// See [`write_global`](Self::write_global).
+    pub fn write_nil(&mut self, name: &str) -> Result<(), SerError> {
+        self.write_global(name, &())
+    }
+
+    /// Writes an AceDB-3.0 database as the global `name`.
+    ///
+    /// # Errors
+    ///
+    /// See [`write_global`](Self::write_global).
+    pub fn write_ace_db<P, G>(&mut self, name: &str, db: &AceDb<P, G>) -> Result<(), SerError>
+    where
+        P: Serialize,
+        G: Serialize,
+    {
+        self.write_global(name, db)
+    }
+
+    /// Unwrap the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// The saved variable of an addon using AceDB-3.0.
+///
+/// AceDB stores the settings of every profile in `profiles` and maps characters to the profile
+/// they use in `profileKeys`. Settings shared by all profiles are stored in `global`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AceDb<P, G> {
+    /// Maps `"Character - Realm"` to the name of a profile.
+    pub profile_keys: BTreeMap<String, String>,
+    pub profiles: BTreeMap<String, P>,
+    pub global: Option<G>,
+}
+
+impl<P, G> AceDb<P, G> {
+    pub fn new() -> Self {
+        AceDb {
+            profile_keys: BTreeMap::new(),
+            profiles: BTreeMap::new(),
+            global: None,
+        }
+    }
+
+    /// Adds a profile named `name`.
+    pub fn with_profile(mut self, name: impl Into<String>, profile: P) -> Self {
+        self.profiles.insert(name.into(), profile);
+        self
+    }
+
+    /// Makes the character `name` on `realm` use the profile named `profile`.
+    pub fn with_profile_key(mut self, name: &str, realm: &str, profile: impl Into<String>) -> Self {
+        self.profile_keys
+            .insert(format!("{name} - {realm}"), profile.into());
+        self
+    }
+
+    /// Sets the settings shared by all profiles.
+    pub fn with_global(mut self, global: G) -> Self {
+        self.global = Some(global);
+        self
+    }
+}
+
+impl<P, G> Default for AceDb<P, G> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P, G> Serialize for AceDb<P, G>
+where
+    P: Serialize,
+    G: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        if !self.profile_keys.is_empty() {
+            map.serialize_entry("profileKeys", &self.profile_keys)?;
+        }
+        if let Some(global) = &self.global {
+            map.serialize_entry("global", global)?;
+        }
+        if !self.profiles.is_empty() {
+            map.serialize_entry("profiles", &self.profiles)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SerError;
+
+    #[test]
+    fn places_files_by_scope() {
+        let character = Scope::Character {
+            realm: "Draenor".to_owned(),
+            name: "Thrall".to_owned(),
+        };
+        assert_eq!(
+            Scope::Account.path("ACC", "MyAddon"),
+            Path::new("Account/ACC/SavedVariables/MyAddon.lua")
+        );
+        assert_eq!(
+            character.path("ACC", "MyAddon"),
+            Path::new("Account/ACC/Draenor/Thrall/SavedVariables/MyAddon.lua")
+        );
+    }
+
+    #[test]
+    fn writes_sequences_and_globals_with_tabs() {
+        let db = AceDb::<(), _>::new().with_global(vec![1.5, 2.0]);
+        let mut writer = Writer::new(Vec::new());
+        writer.write_ace_db("DB", &db).unwrap();
+        writer.write_global("Count", &7).unwrap();
+        assert_eq!(
+            String::from_utf8(writer.into_inner()).unwrap(),
+            "DB = {\n\t[\"global\"] = {\n\t\t1.5,\n\t\t2.0\n\t}\n}\nCount = 7\n"
+        );
+    }
+
+    #[test]
+    fn rejects_names_that_are_not_identifiers() {
+        let mut writer = Writer::new(Vec::new());
+        let err = writer.write_nil("not valid").unwrap_err();
+        assert!(matches!(err, SerError::InvalidGlobalName(name) if name == "not valid"));
+        assert!(writer.write_nil("goto").is_ok());
+        assert!(writer.into_inner().starts_with(b"goto = nil"));
+    }
+}
@@ -0,0 +1,259 @@
+//! Rendering of JSON schemas generated by [`schemars`](::schemars).
+//!
+//! A [`Schema`] can be serialized like any other value to get it as a lua table. [`to_luals`]
+//! additionally renders lua-language-server annotations describing the values that match the
+//! schema.
+//!
+//! ```
+//! use schemars::json_schema;
+//!
+//! let schema = json_schema!({
+//!     "type": "object",
+//!     "properties": {
+//!         "title": { "type": ["string", "null"] },
+//!         "width": { "type": "integer" }
+//!     },
+//!     "required": ["width"]
+//! });
+//!
+//! assert_eq!(
+//!     serde_lua_table::to_string(&schema).unwrap(),
+//!     r#"{["type"]="object",["properties"]={["title"]={["type"]={"string","null"}},["width"]={["type"]="integer"}},["required"]={"width"}}"#
+//! );
+//! assert_eq!(
+//!     serde_lua_table::schemars::to_luals(&schema, "Settings"),
+//!     "---@class Settings\n---@field title? string|nil\n---@field width integer\n"
+//! );
+//! ```
+
+use ::schemars::Schema;
+use serde_json::{Map, Value};
+use std::fmt::Write;
+
+/// Renders `---@class` annotations for `schema`, naming the root class `class`.
+///
+/// Definitions in `$defs` (or `definitions`) are rendered as their own classes or aliases, and
+/// references to them use the definition name.
+pub fn to_luals(schema: &Schema, class: &str) -> String {
+    let mut out = String::new();
+    let root = schema.as_value();
+    write_definition(&mut out, class, root);
+
+    let defs = root
+        .get("$defs")
+        .or_else(|| root.get("definitions"))
+        .and_then(Value::as_object);
+    for (name, def) in defs.into_iter().flatten() {
+        out.push('\n');
+        write_definition(&mut out, name, def);
+    }
+    out
+}
+
+fn write_definition(out: &mut String, name: &str, schema: &Value) {
+    if let Some(description) = schema.get("description").and_then(Value::as_str) {
+        for line in description.lines() {
+            writeln!(out, "--- {}", line).unwrap();
+        }
+    }
+
+    let properties = schema.get("properties").and_then(Value::as_object);
+    let Some(properties) = properties else {
+        writeln!(out, "---@alias {} {}", name, type_of(schema)).unwrap();
+        return;
+    };
+
+    writeln!(out, "---@class {}", name).unwrap();
+    let required = required(schema.get("required"));
+    for (field, property) in properties {
+        let optional = if required.contains(&field.as_str()) {
+            ""
+        } else {
+            "?"
+        };
+        write!(
+            out,
+            "---@field {}{} {}",
+            field_name(field),
+            optional,
+            type_of(property)
+        )
+        .unwrap();
+        if let Some(description) = property.get("description").and_then(Value::as_str) {
+            write!(out, " {}", description.replace('\n', " ")).unwrap();
+        }
+        out.push('\n');
+    }
+}
+
+fn required(required: Option<&Value>) -> Vec<&str> {
+    required
+        .and_then(Value::as_array)
+        .map(|required| required.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default()
+}
+
+fn field_name(name: &str) -> String {
+    let is_identifier = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_identifier {
+        name.to_owned()
+    } else {
+        format!("[{:?}]", name)
+    }
+}
+
+/// Returns the annotation type for the values described by `schema`.
+fn type_of(schema: &Value) -> String {
+    let schema = match schema {
+        Value::Bool(true) => return "any".to_owned(),
+        Value::Bool(false) => return "nil".to_owned(),
+        Value::Object(schema) => schema,
+        _ => return "any".to_owned(),
+    };
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        return reference.rsplit('/').next().unwrap_or(reference).to_owned();
+    }
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        return union(values.iter().map(literal));
+    }
+    if let Some(value) = schema.get("const") {
+        return literal(value);
+    }
+    for key in ["anyOf", "oneOf"] {
+        if let Some(variants) = schema.get(key).and_then(Value::as_array) {
+            return union(variants.iter().map(type_of));
+        }
+    }
+    if let Some(Value::Array(all)) = schema.get("allOf") {
+        if let [single] = all.as_slice() {
+            return type_of(single);
+        }
+    }
+
+    match schema.get("type") {
+        Some(Value::String(ty)) => named_type(ty, schema),
+        Some(Value::Array(types)) => union(
+            types
+                .iter()
+                .filter_map(Value::as_str)
+                .map(|ty| named_type(ty, schema)),
+        ),
+        _ => "any".to_owned(),
+    }
+}
+
+fn named_type(ty: &str, schema: &Map<String, Value>) -> String {
+    match ty {
+        "null" => "nil".to_owned(),
+        "boolean" | "integer" | "number" | "string" => ty.to_owned(),
+        "array" => match schema.get("items") {
+            Some(items) => {
+                let item = type_of(items);
+                if item.contains('|') {
+                    format!("({})[]", item)
+                } else {
+                    format!("{}[]", item)
+                }
+            }
+            None => "any[]".to_owned(),
+        },
+        "object" => {
+            if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+                let required = required(schema.get("required"));
+                let fields: Vec<_> = properties
+                    .iter()
+                    .map(|(field, property)| {
+                        let optional = if required.contains(&field.as_str()) {
+                            ""
+                        } else {
+                            "?"
+                        };
+                        format!("{}{}: {}", field_name(field), optional, type_of(property))
+                    })
+                    .collect();
+                format!("{{ {} }}", fields.join(", "))
+            } else if let Some(values) = schema.get("additionalProperties") {
+                format!("table<string, {}>", type_of(values))
+            } else {
+                "table".to_owned()
+            }
+        }
+        _ => "any".to_owned(),
+    }
+}
+
+fn literal(value: &Value) -> String {
+    match value {
+        Value::Null => "nil".to_owned(),
+        Value::String(s) => format!("{:?}", s),
+        other => other.to_string(),
+    }
+}
+
+fn union(types: impl Iterator<Item = String>) -> String {
+    let mut members: Vec<String> = Vec::new();
+    for ty in types {
+        if !members.contains(&ty) {
+            members.push(ty);
+        }
+    }
+    if members.is_empty() {
+        "any".to_owned()
+    } else {
+        members.join("|")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_luals;
+    use ::schemars::json_schema;
+
+    #[test]
+    fn renders_definitions_references_and_unions() {
+        let schema = json_schema!({
+            "description": "The settings.",
+            "type": "object",
+            "properties": {
+                "mode": { "enum": ["fast", "slow"] },
+                "tags": { "type": "array", "items": { "type": ["string", "integer"] } },
+                "theme": { "$ref": "#/$defs/Theme" },
+                "two words": { "type": "boolean", "description": "Quoted\nname." }
+            },
+            "required": ["mode"],
+            "$defs": {
+                "Theme": {
+                    "type": "object",
+                    "properties": { "colors": { "additionalProperties": { "type": "number" }, "type": "object" } }
+                },
+                "Size": { "oneOf": [{ "const": 1 }, { "type": "null" }] }
+            }
+        });
+        assert_eq!(
+            to_luals(&schema, "Settings"),
+            "--- The settings.\n\
+             ---@class Settings\n\
+             ---@field mode \"fast\"|\"slow\"\n\
+             ---@field tags? (string|integer)[]\n\
+             ---@field theme? Theme\n\
+             ---@field [\"two words\"]? boolean Quoted name.\n\
+             \n\
+             ---@class Theme\n\
+             ---@field colors? table<string, number>\n\
+             \n\
+             ---@alias Size 1|nil\n"
+        );
+    }
+
+    #[test]
+    fn renders_schemas_without_properties_as_aliases() {
+        assert_eq!(to_luals(&json_schema!(true), "Any"), "---@alias Any any\n");
+        let schema = json_schema!({ "type": "array" });
+        assert_eq!(to_luals(&schema, "List"), "---@alias List any[]\n");
+    }
+}
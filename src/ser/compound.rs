@@ -1,4 +1,8 @@
-use super::{map_key_serializer::MapKeySerializer, SerError, Serializer};
+use super::{
+    key::{Key, KeyRecorder},
+    map_key_serializer::MapKeySerializer,
+    KeyCase, KeyOrder, PathSegment, SerError, Serializer,
+};
 use crate::format::Formatter;
 use serde::{
     ser::{
@@ -7,7 +11,11 @@ use serde::{
     },
     Serialize,
 };
-use std::io;
+use std::{
+    cmp::Ordering,
+    io::{self, Write},
+    mem,
+};
 
 #[derive(Eq, PartialEq, Copy, Clone)]
 enum State {
@@ -19,6 +27,10 @@ enum State {
 pub struct Compound<'a, W: 'a, F: 'a> {
     ser: &'a mut Serializer<W, F>,
     state: State,
+    /// The entries of a map that is written sorted, with their values already serialized.
+    sorted: Vec<(Key, Vec<u8>)>,
+    /// Number of elements written to a sequence.
+    len: usize,
 }
 
 impl<'a, W, F> Compound<'a, W, F> {
@@ -27,6 +39,8 @@ impl<'a, W, F> Compound<'a, W, F> {
         Self {
             state: State::Empty,
             ser,
+            sorted: Vec::new(),
+            len: 0,
         }
     }
     #[inline]
@@ -34,6 +48,8 @@ impl<'a, W, F> Compound<'a, W, F> {
         Self {
             state: State::First,
             ser,
+            sorted: Vec::new(),
+            len: 0,
         }
     }
     #[inline]
@@ -42,6 +58,65 @@ impl<'a, W, F> Compound<'a, W, F> {
     }
 }
 
+impl<'a, W, F> Compound<'a, W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    /// Serializes `value` into a buffer, so it can be written after the entries are sorted.
+    fn buffer_value<T>(&mut self, value: &T) -> Result<Vec<u8>, SerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.ser.writer.begin_capture();
+        let result = self.ser.serialize_value(value);
+        let buf = self.ser.writer.end_capture();
+        result.map(|()| buf)
+    }
+
+    /// Writes the buffered entries of a sorted map.
+    fn write_sorted(&mut self) -> Result<(), SerError> {
+        let order = self.ser.options.key_order();
+        let mut entries = mem::take(&mut self.sorted);
+        entries.sort_by(|(a, _), (b, _)| compare_keys(order, a, b));
+        for (i, (key, value)) in entries.iter().enumerate() {
+            if self.ser.tracks_path {
+                self.ser.path.push(key.segment());
+                self.ser.formatter.key_path(&self.ser.path);
+                self.ser.path.pop();
+            }
+            key.serialize(MapKeySerializer::new(self.ser, i == 0))?;
+            self.ser
+                .formatter
+                .begin_object_value(&mut self.ser.writer)?;
+            self.ser.writer.write_all(value)?;
+            self.ser.formatter.end_object_value(&mut self.ser.writer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the path segment of a map key, as it is written.
+fn key_segment<T>(key: &T, key_case: KeyCase) -> Result<PathSegment, SerError>
+where
+    T: ?Sized + Serialize,
+{
+    let mut key = key.serialize(KeyRecorder)?;
+    if let Key::String(s) = &mut key {
+        *s = key_case.apply(s).into_owned();
+    }
+    Ok(key.segment())
+}
+
+fn compare_keys(order: KeyOrder, a: &Key, b: &Key) -> Ordering {
+    match (a, b) {
+        (Key::Integer(a), Key::Integer(b)) => a.cmp(b),
+        (Key::Integer(_), Key::String(_)) => Ordering::Less,
+        (Key::String(_), Key::Integer(_)) => Ordering::Greater,
+        (Key::String(a), Key::String(b)) => order.compare(a, b).unwrap_or(Ordering::Equal),
+    }
+}
+
 impl<'a, W, F> SerializeSeq for Compound<'a, W, F>
 where
     W: io::Write,
@@ -51,15 +126,18 @@ where
     type Error = SerError;
 
     #[inline]
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         self.ser
             .formatter
             .begin_array_value(&mut self.ser.writer, self.state == State::First)?;
         self.state = State::Rest;
-        value.serialize(&mut *self.ser)?;
+        self.len += 1;
+        self.ser.push_index(self.len);
+        self.ser.serialize_value(value)?;
+        self.ser.pop_segment();
         self.ser.formatter.end_array_value(&mut self.ser.writer)?;
         Ok(())
     }
@@ -69,6 +147,7 @@ where
         if self.not_empty() {
             self.ser.formatter.end_array(&mut self.ser.writer)?;
         }
+        self.ser.leave_tables(1)?;
         Ok(())
     }
 }
@@ -82,9 +161,9 @@ where
     type Error = SerError;
 
     #[inline]
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         SerializeSeq::serialize_element(self, value)
     }
@@ -104,9 +183,9 @@ where
     type Error = SerError;
 
     #[inline]
-    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         SerializeSeq::serialize_element(self, value)
     }
@@ -126,9 +205,9 @@ where
     type Error = SerError;
 
     #[inline]
-    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         SerializeSeq::serialize_element(self, value)
     }
@@ -138,8 +217,11 @@ where
         if self.not_empty() {
             self.ser.formatter.end_array(&mut self.ser.writer)?;
         }
+        self.ser.leave_tables(1)?;
+        self.ser.pop_segment();
         self.ser.formatter.end_object_value(&mut self.ser.writer)?;
         self.ser.formatter.end_object(&mut self.ser.writer)?;
+        self.ser.leave_tables(1)?;
         Ok(())
     }
 }
@@ -152,35 +234,52 @@ where
     type Ok = ();
     type Error = SerError;
 
-    fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<(), Self::Error>
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        self.ser
-            .formatter
-            .begin_object_key(&mut self.ser.writer, self.state == State::First)?;
+        let first = self.state == State::First;
         self.state = State::Rest;
-        key.serialize(MapKeySerializer::new(self.ser))?;
-        self.ser.formatter.end_object_key(&mut self.ser.writer)?;
-        Ok(())
+        let key_case = self.ser.options.key_case();
+        self.ser.push_key(|| key_segment(key, key_case))?;
+        if let KeyOrder::Unsorted = self.ser.options.key_order() {
+            key.serialize(MapKeySerializer::entry(self.ser, first))
+        } else {
+            let mut key = key.serialize(KeyRecorder)?;
+            if let Key::String(key) = &mut key {
+                *key = self.ser.options.key_case().apply(key).into_owned();
+            }
+            self.sorted.push((key, Vec::new()));
+            Ok(())
+        }
     }
 
-    fn serialize_value<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        self.ser
-            .formatter
-            .begin_object_value(&mut self.ser.writer)?;
-        value.serialize(&mut *self.ser)?;
-        self.ser.formatter.end_object_value(&mut self.ser.writer)?;
+        if let KeyOrder::Unsorted = self.ser.options.key_order() {
+            self.ser
+                .formatter
+                .begin_object_value(&mut self.ser.writer)?;
+            self.ser.serialize_value(value)?;
+            self.ser.formatter.end_object_value(&mut self.ser.writer)?;
+        } else {
+            let buf = self.buffer_value(value)?;
+            if let Some((_, slot)) = self.sorted.last_mut() {
+                *slot = buf;
+            }
+        }
+        self.ser.pop_segment();
         Ok(())
     }
 
-    fn end(self) -> Result<Self::Ok, Self::Error> {
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        self.write_sorted()?;
         if self.not_empty() {
             self.ser.formatter.end_object(&mut self.ser.writer)?;
         }
+        self.ser.leave_tables(1)?;
         Ok(())
     }
 }
@@ -193,13 +292,9 @@ where
     type Ok = ();
     type Error = SerError;
 
-    fn serialize_field<T: ?Sized>(
-        &mut self,
-        key: &'static str,
-        value: &T,
-    ) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         SerializeMap::serialize_entry(self, key, value)
     }
@@ -217,23 +312,23 @@ where
     type Ok = ();
     type Error = SerError;
 
-    fn serialize_field<T: ?Sized>(
-        &mut self,
-        key: &'static str,
-        value: &T,
-    ) -> Result<(), Self::Error>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         SerializeStruct::serialize_field(self, key, value)
     }
 
-    fn end(self) -> Result<Self::Ok, Self::Error> {
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        self.write_sorted()?;
         if self.not_empty() {
             self.ser.formatter.end_object(&mut self.ser.writer)?;
         }
+        self.ser.leave_tables(1)?;
+        self.ser.pop_segment();
         self.ser.formatter.end_object_value(&mut self.ser.writer)?;
         self.ser.formatter.end_object(&mut self.ser.writer)?;
+        self.ser.leave_tables(1)?;
         Ok(())
     }
 }
@@ -8,6 +8,16 @@ pub enum SerError {
     Custom(String),
     #[error("Object key must be a string or a number")]
     KeyMustBeStringOrNumber,
+    #[error("Raw lua must be a string")]
+    RawMustBeString,
+    #[error("Integer {0} can't be represented exactly by the target lua version")]
+    IntegerOutOfRange(i128),
+    #[error("Tables are nested deeper than {0} levels")]
+    DepthLimitExceeded(usize),
+    #[error("{0:?} can't be used as the name of a global")]
+    InvalidGlobalName(String),
+    #[error("Only tables can be written as global assignments")]
+    GlobalsMustBeTable,
 }
 
 impl serde::ser::Error for SerError {
@@ -18,3 +28,44 @@ impl serde::ser::Error for SerError {
         Self::Custom(msg.to_string())
     }
 }
+
+/// Codes and help for applications reporting errors with miette.
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for SerError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        let code = match self {
+            SerError::Io(_) => "serde_lua_table::io",
+            SerError::Custom(_) => "serde_lua_table::custom",
+            SerError::KeyMustBeStringOrNumber => "serde_lua_table::invalid_key",
+            SerError::RawMustBeString => "serde_lua_table::invalid_raw",
+            SerError::IntegerOutOfRange(_) => "serde_lua_table::integer_out_of_range",
+            SerError::DepthLimitExceeded(_) => "serde_lua_table::depth_limit_exceeded",
+            SerError::InvalidGlobalName(_) => "serde_lua_table::invalid_global_name",
+            SerError::GlobalsMustBeTable => "serde_lua_table::globals_must_be_table",
+        };
+        Some(Box::new(code))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        let help = match self {
+            SerError::Io(_) | SerError::Custom(_) => return None,
+            SerError::KeyMustBeStringOrNumber => {
+                "convert keys that aren't strings or integers to strings before serializing"
+            }
+            SerError::RawMustBeString => "`RawLua` only accepts strings",
+            SerError::IntegerOutOfRange(_) => {
+                "write the integer as a string, or pick a lua version with 64-bit integers"
+            }
+            SerError::DepthLimitExceeded(_) => {
+                "check the value for unexpected nesting or raise `SerOptions::with_max_depth`"
+            }
+            SerError::InvalidGlobalName(_) => {
+                "globals need identifiers that aren't keywords of the target lua version"
+            }
+            SerError::GlobalsMustBeTable => {
+                "wrap the value in a table, or write it as a `return` chunk"
+            }
+        };
+        Some(Box::new(help))
+    }
+}
@@ -0,0 +1,62 @@
+use std::{fmt, io, str};
+
+/// Adapts a [`fmt::Write`] to the [`io::Write`] the serializer writes to.
+///
+/// The serializer only writes complete UTF-8 sequences, so every buffer is valid on its own.
+pub(crate) struct FmtWriter<W>(pub(crate) W);
+
+impl<W> io::Write for FmtWriter<W>
+where
+    W: fmt::Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        let s = str::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.0.write_str(s).map_err(io::Error::other)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    struct Lua<'a>(&'a [String]);
+
+    impl fmt::Display for Lua<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            crate::to_fmt_writer_pretty(f, self.0).map_err(|_| fmt::Error)
+        }
+    }
+
+    #[test]
+    fn writes_long_multibyte_strings_to_formatters() {
+        let values = vec!["äöü€".repeat(4000), "tail\n".to_owned()];
+        let expected = format!("{{\n  \"{}\",\n  \"tail\\n\"\n}}", values[0]);
+        assert_eq!(Lua(&values).to_string(), expected);
+
+        let mut out = String::new();
+        crate::to_fmt_writer(&mut out, &values).unwrap();
+        assert_eq!(crate::from_str::<Vec<String>>(&out).unwrap(), values);
+    }
+
+    #[test]
+    fn reports_formatter_errors() {
+        struct Failing;
+
+        impl fmt::Write for Failing {
+            fn write_str(&mut self, _: &str) -> fmt::Result {
+                Err(fmt::Error)
+            }
+        }
+
+        assert!(crate::to_fmt_writer(Failing, &[1]).is_err());
+    }
+}
@@ -0,0 +1,365 @@
+use super::{
+    compound::Compound,
+    key::{Key, KeyRecorder},
+    raw, KeyStyle, SerError, SerOptions, Serializer,
+};
+use crate::{format::CompactFormatter, helpers::lua_string_bytes, LuaVersion};
+use serde::{ser, Serialize};
+use std::io::{self, Write};
+
+/// Writes `function name(parameter)`, an assignment for every field of `value` and `end`.
+pub(crate) fn write<W, T>(
+    writer: W,
+    function: &str,
+    parameter: &str,
+    value: &T,
+) -> Result<(), SerError>
+where
+    W: io::Write,
+    T: ?Sized + Serialize,
+{
+    let options = SerOptions::new().with_key_style(KeyStyle::Bare);
+    let mut assigner = Assigner {
+        ser: Serializer::with_options(writer, CompactFormatter, options),
+        parameter,
+        path: Vec::new(),
+    };
+    write!(assigner.ser.writer, "function {}({})", function, parameter)?;
+    value.serialize(&mut assigner)?;
+    assigner.ser.writer.write_all(b"\nend\n")?;
+    Ok(())
+}
+
+/// Returns the path segment to a value stored under `key`.
+fn segment(key: Key) -> String {
+    match key {
+        Key::Integer(key) => format!("[{}]", key),
+        Key::String(key) if LuaVersion::default().is_identifier(&key) => format!(".{}", key),
+        Key::String(key) => format!("[{}]", lua_string_bytes::literal(key.as_bytes())),
+    }
+}
+
+/// Descends into maps and structs and writes everything else as an assignment to its path.
+struct Assigner<'p, W> {
+    ser: Serializer<W, CompactFormatter>,
+    parameter: &'p str,
+    path: Vec<String>,
+}
+
+impl<'p, W> Assigner<'p, W>
+where
+    W: io::Write,
+{
+    /// Writes the start of an assignment to the current path, the value is written next.
+    fn begin_assignment(&mut self) -> Result<(), SerError> {
+        if self.path.is_empty() {
+            return Err(SerError::Custom(
+                "only the fields of a table can be assigned to a parameter".to_owned(),
+            ));
+        }
+        let writer = &mut self.ser.writer;
+        writer.write_all(b"\n  ")?;
+        writer.write_all(self.parameter.as_bytes())?;
+        for segment in &self.path {
+            writer.write_all(segment.as_bytes())?;
+        }
+        writer.write_all(b" = ")?;
+        Ok(())
+    }
+
+    /// Returns the serializer for a value after writing the start of its assignment.
+    fn value(&mut self) -> Result<&mut Serializer<W, CompactFormatter>, SerError> {
+        self.begin_assignment()?;
+        Ok(&mut self.ser)
+    }
+}
+
+impl<'a, 'p, W> ser::Serializer for &'a mut Assigner<'p, W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = SerError;
+    type SerializeSeq = Compound<'a, W, CompactFormatter>;
+    type SerializeTuple = Compound<'a, W, CompactFormatter>;
+    type SerializeTupleStruct = Compound<'a, W, CompactFormatter>;
+    type SerializeTupleVariant = Compound<'a, W, CompactFormatter>;
+    type SerializeMap = Fields<'a, 'p, W>;
+    type SerializeStruct = Fields<'a, 'p, W>;
+    type SerializeStructVariant = Compound<'a, W, CompactFormatter>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        self.value()?.serialize_bool(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.value()?.serialize_i8(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.value()?.serialize_i16(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.value()?.serialize_i32(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.value()?.serialize_i64(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.value()?.serialize_u8(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.value()?.serialize_u16(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.value()?.serialize_u32(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.value()?.serialize_u64(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.value()?.serialize_f32(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        self.value()?.serialize_f64(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        self.value()?.serialize_char(v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        self.value()?.serialize_str(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        self.value()?.serialize_bytes(v)
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        self.value()?.serialize_none()
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.value()?.serialize_unit()
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.value()?.serialize_unit_struct(name)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.value()?
+            .serialize_unit_variant(name, variant_index, variant)
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        if name == raw::TOKEN {
+            self.value()?.serialize_newtype_struct(name, value)
+        } else {
+            value.serialize(self)
+        }
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.value()?
+            .serialize_newtype_variant(name, variant_index, variant, value)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        self.value()?.serialize_seq(len)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.value()?.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.value()?.serialize_tuple_struct(name, len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        self.value()?
+            .serialize_tuple_variant(name, variant_index, variant, len)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(Fields { assigner: self })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        self.value()?
+            .serialize_struct_variant(name, variant_index, variant, len)
+    }
+}
+
+/// Assigns the fields of a table one by one.
+struct Fields<'a, 'p, W> {
+    assigner: &'a mut Assigner<'p, W>,
+}
+
+impl<'a, 'p, W> ser::SerializeMap for Fields<'a, 'p, W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let key = key.serialize(KeyRecorder)?;
+        self.assigner.path.push(segment(key));
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut *self.assigner)?;
+        self.assigner.path.pop();
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+}
+
+impl<'a, 'p, W> ser::SerializeStruct for Fields<'a, 'p, W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeMap::serialize_entry(self, key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{to_string_function_body, RawLua};
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct Conf {
+        identity: Option<&'static str>,
+        modules: BTreeMap<&'static str, bool>,
+        window: Window,
+        version: RawLua<&'static str>,
+    }
+
+    #[derive(Serialize)]
+    struct Window {
+        size: (u32, u32),
+        #[serde(rename = "two words")]
+        title: &'static str,
+    }
+
+    #[test]
+    fn assigns_nested_fields_and_writes_sequences_whole() {
+        let conf = Conf {
+            identity: None,
+            modules: BTreeMap::from([("audio", false), ("end", true)]),
+            window: Window {
+                size: (800, 600),
+                title: "a\"b",
+            },
+            version: RawLua("love._version"),
+        };
+        assert_eq!(
+            to_string_function_body("love.conf", "t", &conf).unwrap(),
+            r#"function love.conf(t)
+  t.identity = nil
+  t.modules.audio = false
+  t.modules["end"] = true
+  t.window.size = {800,600}
+  t.window["two words"] = "a\"b"
+  t.version = love._version
+end
+"#
+        );
+    }
+
+    #[test]
+    fn rejects_values_that_are_not_tables() {
+        assert!(to_string_function_body("f", "t", &1).is_err());
+        assert!(to_string_function_body("f", "t", &[1]).is_err());
+        assert_eq!(
+            to_string_function_body("f", "t", &BTreeMap::<u8, u8>::new()).unwrap(),
+            "function f(t)\nend\n"
+        );
+    }
+}
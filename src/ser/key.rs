@@ -0,0 +1,211 @@
+use super::{PathSegment, SerError};
+use serde::{
+    ser::{self, Impossible},
+    Serialize,
+};
+
+/// A map key the serializer accepts.
+pub(crate) enum Key {
+    Integer(i128),
+    String(String),
+}
+
+impl Key {
+    /// Returns the path segment of the key, integers that don't fit into an `i64` become keys.
+    pub(crate) fn segment(&self) -> PathSegment {
+        match self {
+            Key::Integer(i) => match i64::try_from(*i) {
+                Ok(i) => PathSegment::Index(i),
+                Err(_) => PathSegment::Key(i.to_string()),
+            },
+            Key::String(s) => PathSegment::Key(s.clone()),
+        }
+    }
+}
+
+/// Writes the key again the way it was recorded. Integers come from 64-bit integers.
+impl Serialize for Key {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        match self {
+            Key::Integer(i) => match i64::try_from(*i) {
+                Ok(i) => serializer.serialize_i64(i),
+                Err(_) => serializer.serialize_u64(*i as u64),
+            },
+            Key::String(s) => serializer.serialize_str(s),
+        }
+    }
+}
+
+/// Returns the key of a map entry.
+pub(crate) struct KeyRecorder;
+
+impl ser::Serializer for KeyRecorder {
+    type Ok = Key;
+    type Error = SerError;
+    type SerializeSeq = Impossible<Key, SerError>;
+    type SerializeTuple = Impossible<Key, SerError>;
+    type SerializeTupleStruct = Impossible<Key, SerError>;
+    type SerializeTupleVariant = Impossible<Key, SerError>;
+    type SerializeMap = Impossible<Key, SerError>;
+    type SerializeStruct = Impossible<Key, SerError>;
+    type SerializeStructVariant = Impossible<Key, SerError>;
+
+    fn serialize_bool(self, _v: bool) -> Result<Self::Ok, Self::Error> {
+        Err(SerError::KeyMustBeStringOrNumber)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        Ok(Key::Integer(v.into()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        Ok(Key::Integer(v.into()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        Ok(Key::Integer(v.into()))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        Ok(Key::Integer(v.into()))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        Ok(Key::Integer(v.into()))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        Ok(Key::Integer(v.into()))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        Ok(Key::Integer(v.into()))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        Ok(Key::Integer(v.into()))
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Self::Ok, Self::Error> {
+        Err(SerError::KeyMustBeStringOrNumber)
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Self::Ok, Self::Error> {
+        Err(SerError::KeyMustBeStringOrNumber)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        Ok(Key::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        Ok(Key::String(v.to_owned()))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        Err(SerError::KeyMustBeStringOrNumber)
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Err(SerError::KeyMustBeStringOrNumber)
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(SerError::KeyMustBeStringOrNumber)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Err(SerError::KeyMustBeStringOrNumber)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        Err(SerError::KeyMustBeStringOrNumber)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(SerError::KeyMustBeStringOrNumber)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Err(SerError::KeyMustBeStringOrNumber)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Err(SerError::KeyMustBeStringOrNumber)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Err(SerError::KeyMustBeStringOrNumber)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(SerError::KeyMustBeStringOrNumber)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Err(SerError::KeyMustBeStringOrNumber)
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Err(SerError::KeyMustBeStringOrNumber)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(SerError::KeyMustBeStringOrNumber)
+    }
+}
@@ -0,0 +1,181 @@
+use std::borrow::Cow;
+
+/// How the string keys of maps and structs are renamed when they are written, or read with
+/// [`DeOptions::with_key_case`](crate::DeOptions::with_key_case).
+///
+/// Keys are split into words at `_`, `-`, spaces and changes from lower to upper case, so
+/// `max_speed`, `maxSpeed` and `MaxSpeed` all consist of the words `max` and `speed`. The names
+/// of enum variants are left as they are.
+///
+/// ```
+/// use serde_lua_table::{CompactFormatter, KeyCase, KeyStyle, SerOptions, Serializer};
+/// use std::collections::BTreeMap;
+///
+/// let options = SerOptions::new()
+///     .with_key_style(KeyStyle::Bare)
+///     .with_key_case(KeyCase::CamelCase);
+/// let mut ser = Serializer::with_options(Vec::new(), CompactFormatter, options);
+/// let value = BTreeMap::from([("max_speed", 1), ("HTTPPort", 2)]);
+/// serde::Serialize::serialize(&value, &mut ser).unwrap();
+/// assert_eq!(ser.into_inner(), b"{httpPort=2,maxSpeed=1}");
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub enum KeyCase {
+    /// Keys are written as they are.
+    #[default]
+    Unchanged,
+    /// `maxSpeed`
+    CamelCase,
+    /// `MaxSpeed`
+    PascalCase,
+    /// `max_speed`
+    SnakeCase,
+    /// `MAX_SPEED`
+    ScreamingSnakeCase,
+    /// `max-speed`, which always has to be written in brackets.
+    KebabCase,
+    /// Keys are renamed with the given function.
+    Custom(fn(&str) -> String),
+}
+
+impl KeyCase {
+    /// Returns `key` renamed, borrowing it if it's unchanged.
+    pub fn apply(self, key: &str) -> Cow<'_, str> {
+        let words = match self {
+            KeyCase::Unchanged => return Cow::Borrowed(key),
+            KeyCase::Custom(rename) => return Cow::Owned(rename(key)),
+            _ => words(key),
+        };
+
+        let mut out = String::with_capacity(key.len());
+        for (i, word) in words.into_iter().enumerate() {
+            match self {
+                KeyCase::CamelCase if i == 0 => out.push_str(&word.to_lowercase()),
+                KeyCase::CamelCase | KeyCase::PascalCase => push_capitalized(&mut out, word),
+                KeyCase::SnakeCase | KeyCase::ScreamingSnakeCase | KeyCase::KebabCase => {
+                    if i != 0 {
+                        out.push(if let KeyCase::KebabCase = self {
+                            '-'
+                        } else {
+                            '_'
+                        });
+                    }
+                    if let KeyCase::ScreamingSnakeCase = self {
+                        out.push_str(&word.to_uppercase());
+                    } else {
+                        out.push_str(&word.to_lowercase());
+                    }
+                }
+                KeyCase::Unchanged | KeyCase::Custom(_) => unreachable!(),
+            }
+        }
+        Cow::Owned(out)
+    }
+}
+
+fn push_capitalized(out: &mut String, word: &str) {
+    let mut chars = word.chars();
+    if let Some(first) = chars.next() {
+        out.extend(first.to_uppercase());
+        out.push_str(&chars.as_str().to_lowercase());
+    }
+}
+
+/// Splits `key` into words. An upper case letter starts a new word after a lower case letter
+/// or a digit, and before a lower case letter in a run of upper case letters (`HTTPPort`).
+fn words(key: &str) -> Vec<&str> {
+    let chars: Vec<(usize, char)> = key.char_indices().collect();
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, &(pos, c)) in chars.iter().enumerate() {
+        if matches!(c, '_' | '-' | ' ') {
+            if let Some(start) = start.take() {
+                words.push(&key[start..pos]);
+            }
+            continue;
+        }
+        match start {
+            None => start = Some(pos),
+            Some(word_start) if c.is_uppercase() => {
+                let prev = chars[i - 1].1;
+                let next_is_lower = chars.get(i + 1).is_some_and(|&(_, n)| n.is_lowercase());
+                if prev.is_lowercase()
+                    || prev.is_ascii_digit()
+                    || (prev.is_uppercase() && next_is_lower)
+                {
+                    words.push(&key[word_start..pos]);
+                    start = Some(pos);
+                }
+            }
+            Some(_) => (),
+        }
+    }
+    if let Some(start) = start {
+        words.push(&key[start..]);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KeyCase;
+    use crate::{CompactFormatter, KeyStyle, SerOptions, Serializer};
+    use serde::Serialize;
+
+    #[test]
+    fn renames_words_in_every_case() {
+        let cases = [
+            (
+                KeyCase::CamelCase,
+                ["maxSpeed", "httpPort", "v2Api", "ümlautKey"],
+            ),
+            (
+                KeyCase::PascalCase,
+                ["MaxSpeed", "HttpPort", "V2Api", "ÜmlautKey"],
+            ),
+            (
+                KeyCase::SnakeCase,
+                ["max_speed", "http_port", "v2_api", "ümlaut_key"],
+            ),
+            (
+                KeyCase::ScreamingSnakeCase,
+                ["MAX_SPEED", "HTTP_PORT", "V2_API", "ÜMLAUT_KEY"],
+            ),
+            (
+                KeyCase::KebabCase,
+                ["max-speed", "http-port", "v2-api", "ümlaut-key"],
+            ),
+        ];
+        for (case, expected) in cases {
+            let renamed =
+                ["max_speed", "HTTPPort", "v2Api", "Ümlaut key"].map(|key| case.apply(key));
+            assert_eq!(renamed, expected, "{case:?}");
+        }
+        assert_eq!(KeyCase::SnakeCase.apply("__a--b  "), "a_b");
+        assert_eq!(KeyCase::CamelCase.apply(""), "");
+        assert_eq!(KeyCase::Custom(|key| key.repeat(2)).apply("ab"), "abab");
+    }
+
+    #[derive(Serialize)]
+    enum Mode {
+        FastMode { max_speed: u8 },
+    }
+
+    #[test]
+    fn renames_fields_but_not_variants() {
+        let written = |key_case| {
+            let options = SerOptions::new()
+                .with_key_style(KeyStyle::Bare)
+                .with_key_case(key_case);
+            let mut ser = Serializer::with_options(Vec::new(), CompactFormatter, options);
+            Mode::FastMode { max_speed: 1 }.serialize(&mut ser).unwrap();
+            String::from_utf8(ser.into_inner()).unwrap()
+        };
+        assert_eq!(written(KeyCase::Unchanged), "{FastMode={max_speed=1}}");
+        assert_eq!(written(KeyCase::CamelCase), "{FastMode={maxSpeed=1}}");
+        assert_eq!(
+            written(KeyCase::KebabCase),
+            "{FastMode={[\"max-speed\"]=1}}"
+        );
+    }
+}
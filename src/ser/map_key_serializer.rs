@@ -1,15 +1,49 @@
-use super::{SerError, Serializer};
+use super::{KeyCase, KeyStyle, SerError, Serializer};
 use crate::format::Formatter;
 use serde::{ser, ser::Impossible, Serialize};
 use std::io;
 
 pub struct MapKeySerializer<'a, W: 'a, F: 'a> {
     ser: &'a mut Serializer<W, F>,
+    first: bool,
+    key_case: KeyCase,
 }
 
-impl<'a, W, F> MapKeySerializer<'a, W, F> {
-    pub(crate) fn new(ser: &'a mut Serializer<W, F>) -> Self {
-        Self { ser }
+impl<'a, W, F> MapKeySerializer<'a, W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    pub(crate) fn new(ser: &'a mut Serializer<W, F>, first: bool) -> Self {
+        Self {
+            ser,
+            first,
+            key_case: KeyCase::Unchanged,
+        }
+    }
+
+    /// Creates a serializer for the key of a map or struct entry, which is renamed according to
+    /// the options.
+    pub(crate) fn entry(ser: &'a mut Serializer<W, F>, first: bool) -> Self {
+        let key_case = ser.options().key_case();
+        Self {
+            ser,
+            first,
+            key_case,
+        }
+    }
+
+    /// Writes a key in brackets, the value itself is written by `key`.
+    fn bracketed(
+        self,
+        key: impl FnOnce(&mut Serializer<W, F>) -> Result<(), SerError>,
+    ) -> Result<(), SerError> {
+        let ser = self.ser;
+        ser.formatter
+            .begin_object_key(&mut ser.writer, self.first)?;
+        key(ser)?;
+        ser.formatter.end_object_key(&mut ser.writer)?;
+        Ok(())
     }
 }
 
@@ -33,35 +67,35 @@ where
     }
 
     fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
-        self.ser.serialize_i8(v)
+        self.bracketed(|ser| ser.serialize_i8(v))
     }
 
     fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
-        self.ser.serialize_i16(v)
+        self.bracketed(|ser| ser.serialize_i16(v))
     }
 
     fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
-        self.ser.serialize_i32(v)
+        self.bracketed(|ser| ser.serialize_i32(v))
     }
 
     fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
-        self.ser.serialize_i64(v)
+        self.bracketed(|ser| ser.serialize_i64(v))
     }
 
     fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
-        self.ser.serialize_u8(v)
+        self.bracketed(|ser| ser.serialize_u8(v))
     }
 
     fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
-        self.ser.serialize_u16(v)
+        self.bracketed(|ser| ser.serialize_u16(v))
     }
 
     fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
-        self.ser.serialize_u32(v)
+        self.bracketed(|ser| ser.serialize_u32(v))
     }
 
     fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
-        self.ser.serialize_u64(v)
+        self.bracketed(|ser| ser.serialize_u64(v))
     }
 
     fn serialize_f32(self, _v: f32) -> Result<Self::Ok, Self::Error> {
@@ -73,11 +107,22 @@ where
     }
 
     fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
-        self.ser.serialize_char(v)
+        self.bracketed(|ser| ser.serialize_char(v))
     }
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
-        self.ser.serialize_str(v)
+        let v = &*self.key_case.apply(v);
+        let options = self.ser.options();
+        if options.key_style() == KeyStyle::Bare && options.version().is_identifier(v) {
+            let ser = self.ser;
+            ser.formatter
+                .begin_bare_object_key(&mut ser.writer, self.first)?;
+            ser.formatter.write_string_fragment(&mut ser.writer, v)?;
+            ser.formatter.end_bare_object_key(&mut ser.writer)?;
+            Ok(())
+        } else {
+            self.bracketed(|ser| ser.serialize_str(v))
+        }
     }
 
     fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, Self::Error> {
@@ -88,9 +133,9 @@ where
         Err(Self::Error::KeyMustBeStringOrNumber)
     }
 
-    fn serialize_some<T: ?Sized>(self, _value: &T) -> Result<Self::Ok, Self::Error>
+    fn serialize_some<T>(self, _value: &T) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         Err(Self::Error::KeyMustBeStringOrNumber)
     }
@@ -109,21 +154,21 @@ where
         _variant_index: u32,
         variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
-        self.ser.serialize_str(variant)
+        self.serialize_str(variant)
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(
+    fn serialize_newtype_struct<T>(
         self,
         _name: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         value.serialize(self)
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         _variant_index: u32,
@@ -131,7 +176,7 @@ where
         _value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         Err(Self::Error::KeyMustBeStringOrNumber)
     }
@@ -1,16 +1,52 @@
 mod compound;
 mod error;
+pub(crate) mod fmt_writer;
+pub(crate) mod function_body;
+pub(crate) mod key;
+mod key_case;
 mod map_key_serializer;
+mod observer;
+mod options;
+mod output;
+#[cfg(feature = "rayon")]
+pub(crate) mod parallel;
+mod path_matcher;
+mod profile;
+pub(crate) mod raw;
+mod skip_defaults;
+#[cfg(feature = "tracing")]
+pub(crate) mod trace;
+pub(crate) mod vec_writer;
 
 use crate::format::{format_escaped_str_contents, CompactFormatter, Formatter, PrettyFormatter};
 use compound::Compound;
 pub use error::*;
+pub use key_case::KeyCase;
+use map_key_serializer::MapKeySerializer;
+pub use observer::{Observer, PathSegment};
+pub use options::*;
+use output::Output;
+pub use path_matcher::{PathMatcher, PathPatternError};
+pub use profile::*;
+use raw::RawEmitter;
+pub use raw::RawLua;
 use serde::Serialize;
+pub use skip_defaults::SkipDefaults;
 use std::io;
 
 pub struct Serializer<W, F = CompactFormatter> {
-    writer: W,
+    writer: Output<W>,
     formatter: F,
+    options: SerOptions,
+    /// Number of tables that are currently open.
+    depth: usize,
+    observer: Option<Box<dyn Observer + Send>>,
+    /// The path to the current value, only tracked if `tracks_path` is set.
+    path: Vec<PathSegment>,
+    /// Whether the observer, the redacted paths or the formatter need the path.
+    tracks_path: bool,
+    #[cfg(feature = "tracing")]
+    stats: trace::Stats,
 }
 
 impl<W> Serializer<W>
@@ -44,13 +80,152 @@ where
     /// specified.
     #[inline]
     pub fn with_formatter(writer: W, formatter: F) -> Self {
-        Serializer { writer, formatter }
+        Serializer::with_options(writer, formatter, SerOptions::default())
+    }
+
+    /// Creates a new Lua visitor whose output will be written to the writer
+    /// specified, targeting the lua version from `options`.
+    #[inline]
+    pub fn with_options(writer: W, formatter: F, options: SerOptions) -> Self {
+        let tracks_path = !options.redacted().is_empty() || formatter.wants_key_paths();
+        Serializer {
+            writer: Output::new(writer, options.write_buffer()),
+            formatter,
+            options,
+            depth: 0,
+            observer: None,
+            path: Vec::new(),
+            tracks_path,
+            #[cfg(feature = "tracing")]
+            stats: trace::Stats::default(),
+        }
+    }
+
+    /// Reports the progress of serialization to `observer`.
+    #[inline]
+    pub fn with_observer(mut self, observer: impl Observer + Send + 'static) -> Self {
+        self.observer = Some(Box::new(observer));
+        self.tracks_path = true;
+        self
+    }
+
+    /// Returns the options this serializer was created with.
+    #[inline]
+    pub fn options(&self) -> &SerOptions {
+        &self.options
     }
 
     /// Unwrap the `Writer` from the `Serializer`.
     #[inline]
     pub fn into_inner(self) -> W {
-        self.writer
+        self.writer.into_inner()
+    }
+}
+
+impl<W, F> Serializer<W, F> {
+    /// Records that a table is opened. Fails if that exceeds the depth limit.
+    fn enter_table(&mut self) -> Result<(), SerError> {
+        self.depth += 1;
+        if self.depth == 1 {
+            self.writer.hold();
+        }
+        #[cfg(feature = "tracing")]
+        {
+            self.stats.tables += 1;
+            self.stats.max_depth = self.stats.max_depth.max(self.depth);
+        }
+        if let Some(observer) = &mut self.observer {
+            observer.enter_table(&self.path);
+        }
+        match self.options.max_depth() {
+            Some(max_depth) if self.depth > max_depth => {
+                Err(SerError::DepthLimitExceeded(max_depth))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Records that `tables` tables are closed. The output is passed on once the outermost one
+    /// is.
+    fn leave_tables(&mut self, tables: usize) -> Result<(), SerError>
+    where
+        W: io::Write,
+    {
+        self.depth -= tables;
+        if let Some(observer) = &mut self.observer {
+            for _ in 0..tables {
+                observer.leave_table(&self.path);
+            }
+        }
+        if self.depth == 0 {
+            self.writer.release()?;
+        }
+        Ok(())
+    }
+
+    /// Appends the segment returned by `segment` to the path and reports the key to the
+    /// observer and the formatter, if the path is tracked. Must be called before the key is
+    /// written.
+    fn push_key(
+        &mut self,
+        segment: impl FnOnce() -> Result<PathSegment, SerError>,
+    ) -> Result<(), SerError>
+    where
+        F: Formatter,
+    {
+        if self.tracks_path {
+            self.path.push(segment()?);
+            if let Some(observer) = &mut self.observer {
+                observer.key(&self.path);
+            }
+            self.formatter.key_path(&self.path);
+        }
+        Ok(())
+    }
+
+    /// Appends an index to the path, if the path is tracked.
+    fn push_index(&mut self, index: usize) {
+        if self.tracks_path {
+            self.path.push(PathSegment::Index(index as i64));
+        }
+    }
+
+    /// Removes the last segment of the path.
+    fn pop_segment(&mut self) {
+        if self.tracks_path {
+            self.path.pop();
+        }
+    }
+
+    /// Serializes the value at the current path, or the placeholder if the path is redacted.
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), SerError>
+    where
+        W: io::Write,
+        F: Formatter,
+        T: ?Sized + Serialize,
+    {
+        let redacted = self.options.redacted();
+        if !redacted.is_empty() && redacted.iter().any(|pattern| pattern.matches(&self.path)) {
+            let stability = self.options.output_stability();
+            return format_escaped_str(&mut self.writer, &mut self.formatter, REDACTED, stability)
+                .map_err(SerError::Io);
+        }
+        value.serialize(self)
+    }
+
+    /// Returns whether `v` has to be written as a LuaJIT 64-bit integer literal.
+    ///
+    /// Fails if the target lua version can't represent `v` exactly otherwise.
+    fn needs_integer_literal(&self, v: i128) -> Result<bool, SerError> {
+        const MAX_EXACT: i128 = 1 << 53;
+        let version = self.options.version();
+        if version.has_integers() || v.abs() <= MAX_EXACT {
+            Ok(false)
+        } else if version == LuaVersion::LuaJit {
+            Ok(true)
+        } else {
+            Err(SerError::IntegerOutOfRange(v))
+        }
     }
 }
 
@@ -90,6 +265,12 @@ impl<'a, W: io::Write, F: Formatter> serde::Serializer for &'a mut Serializer<W,
     }
 
     fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        if self.needs_integer_literal(v.into())? {
+            return self
+                .formatter
+                .write_number_str(&mut self.writer, with_suffix(&mut [0; 24], v, "LL"))
+                .map_err(SerError::Io);
+        }
         self.formatter
             .write_i64(&mut self.writer, v)
             .map_err(SerError::Io)
@@ -114,6 +295,12 @@ impl<'a, W: io::Write, F: Formatter> serde::Serializer for &'a mut Serializer<W,
     }
 
     fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        if self.needs_integer_literal(v.into())? {
+            return self
+                .formatter
+                .write_number_str(&mut self.writer, with_suffix(&mut [0; 24], v, "ULL"))
+                .map_err(SerError::Io);
+        }
         self.formatter
             .write_u64(&mut self.writer, v)
             .map_err(SerError::Io)
@@ -138,7 +325,9 @@ impl<'a, W: io::Write, F: Formatter> serde::Serializer for &'a mut Serializer<W,
     }
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
-        format_escaped_str(&mut self.writer, &mut self.formatter, v).map_err(SerError::Io)
+        let stability = self.options.output_stability();
+        format_escaped_str(&mut self.writer, &mut self.formatter, v, stability)
+            .map_err(SerError::Io)
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
@@ -154,17 +343,21 @@ impl<'a, W: io::Write, F: Formatter> serde::Serializer for &'a mut Serializer<W,
         self.serialize_unit()
     }
 
-    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         value.serialize(self)
     }
 
     fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
-        self.formatter
-            .write_null(&mut self.writer)
-            .map_err(SerError::Io)
+        match self.options.nil_sentinel() {
+            Some(sentinel) => self
+                .formatter
+                .write_raw_fragment(&mut self.writer, sentinel),
+            None => self.formatter.write_null(&mut self.writer),
+        }
+        .map_err(SerError::Io)
     }
 
     fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
@@ -182,18 +375,21 @@ impl<'a, W: io::Write, F: Formatter> serde::Serializer for &'a mut Serializer<W,
         self.serialize_str(variant)
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(
+    fn serialize_newtype_struct<T>(
         self,
-        _name: &'static str,
+        name: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
+        if name == raw::TOKEN {
+            return value.serialize(RawEmitter::new(self));
+        }
         value.serialize(self)
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         _variant_index: u32,
@@ -201,21 +397,24 @@ impl<'a, W: io::Write, F: Formatter> serde::Serializer for &'a mut Serializer<W,
         value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        self.formatter.begin_object(&mut self.writer)?;
-        self.formatter.begin_object_key(&mut self.writer, true)?;
-        self.serialize_str(variant)?;
-        self.formatter.end_object_key(&mut self.writer)?;
+        self.enter_table()?;
+        self.formatter.begin_object(&mut self.writer, Some(1))?;
+        self.push_key(|| Ok(PathSegment::Key(variant.to_owned())))?;
+        MapKeySerializer::new(&mut *self, true).serialize_str(variant)?;
         self.formatter.begin_object_value(&mut self.writer)?;
-        value.serialize(&mut *self)?;
+        self.serialize_value(value)?;
         self.formatter.end_object_value(&mut self.writer)?;
         self.formatter.end_object(&mut self.writer)?;
+        self.pop_segment();
+        self.leave_tables(1)?;
         Ok(())
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
-        self.formatter.begin_array(&mut self.writer)?;
+        self.enter_table()?;
+        self.formatter.begin_array(&mut self.writer, len)?;
         if len == Some(0) {
             self.formatter.end_array(&mut self.writer)?;
             Ok(Compound::empty(self))
@@ -243,16 +442,17 @@ impl<'a, W: io::Write, F: Formatter> serde::Serializer for &'a mut Serializer<W,
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
-        self.formatter.begin_object(&mut self.writer)?;
-        self.formatter.begin_object_key(&mut self.writer, true)?;
-        self.serialize_str(variant)?;
-        self.formatter.end_object_key(&mut self.writer)?;
+        self.enter_table()?;
+        self.formatter.begin_object(&mut self.writer, Some(1))?;
+        self.push_key(|| Ok(PathSegment::Key(variant.to_owned())))?;
+        MapKeySerializer::new(&mut *self, true).serialize_str(variant)?;
         self.formatter.begin_object_value(&mut self.writer)?;
         self.serialize_seq(Some(len))
     }
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
-        self.formatter.begin_object(&mut self.writer)?;
+        self.enter_table()?;
+        self.formatter.begin_object(&mut self.writer, len)?;
         if len == Some(0) {
             self.formatter.end_object(&mut self.writer)?;
             Ok(Compound::empty(self))
@@ -276,22 +476,76 @@ impl<'a, W: io::Write, F: Formatter> serde::Serializer for &'a mut Serializer<W,
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
-        self.formatter.begin_object(&mut self.writer)?;
-        self.formatter.begin_object_key(&mut self.writer, true)?;
-        self.serialize_str(variant)?;
-        self.formatter.end_object_key(&mut self.writer)?;
+        self.enter_table()?;
+        self.formatter.begin_object(&mut self.writer, Some(1))?;
+        self.push_key(|| Ok(PathSegment::Key(variant.to_owned())))?;
+        MapKeySerializer::new(&mut *self, true).serialize_str(variant)?;
         self.formatter.begin_object_value(&mut self.writer)?;
         self.serialize_map(Some(len))
     }
 }
 
-fn format_escaped_str<W, F>(writer: &mut W, formatter: &mut F, value: &str) -> io::Result<()>
+/// Written in place of redacted values.
+const REDACTED: &str = "<redacted>";
+
+/// Formats `value` followed by `suffix` into `buf` without allocating.
+fn with_suffix<'b>(buf: &'b mut [u8; 24], value: impl itoa::Integer, suffix: &str) -> &'b str {
+    let mut digits = itoa::Buffer::new();
+    let digits = digits.format(value).as_bytes();
+    let len = digits.len() + suffix.len();
+    buf[..digits.len()].copy_from_slice(digits);
+    buf[digits.len()..len].copy_from_slice(suffix.as_bytes());
+    // Both parts are ASCII.
+    std::str::from_utf8(&buf[..len]).unwrap()
+}
+
+fn format_escaped_str<W, F>(
+    writer: &mut W,
+    formatter: &mut F,
+    value: &str,
+    stability: OutputStability,
+) -> io::Result<()>
 where
     W: ?Sized + io::Write,
     F: ?Sized + Formatter,
 {
     formatter.begin_string(writer)?;
-    format_escaped_str_contents(writer, formatter, value)?;
+    format_escaped_str_contents(writer, formatter, value, stability)?;
     formatter.end_string(writer)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write<T>(value: &T, options: SerOptions) -> Result<String, SerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut ser = Serializer::with_options(Vec::new(), CompactFormatter, options);
+        value.serialize(&mut ser)?;
+        Ok(String::from_utf8(ser.into_inner()).unwrap())
+    }
+
+    #[test]
+    fn writes_luajit_integer_literals_only_when_needed() {
+        let luajit = SerOptions::new().with_version(LuaVersion::LuaJit);
+        let values = (1i64 << 53, i64::MIN, u64::MAX, -(1i64 << 53));
+        let written = write(&values, luajit).unwrap();
+        assert_eq!(
+            written,
+            "{9007199254740992,-9223372036854775808LL,18446744073709551615ULL,-9007199254740992}"
+        );
+        assert_eq!(
+            crate::from_str::<(i64, i64, u64, i64)>(&written).unwrap(),
+            values
+        );
+
+        let lua51 = SerOptions::new().with_version(LuaVersion::Lua51);
+        assert!(matches!(
+            write(&u64::MAX, lua51),
+            Err(SerError::IntegerOutOfRange(v)) if v == u64::MAX.into()
+        ));
+    }
+}
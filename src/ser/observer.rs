@@ -0,0 +1,149 @@
+use std::fmt;
+
+/// One step of the path from the outermost value to a value inside of it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    /// The index of a sequence element, starting at 1, or an integer key of a map.
+    Index(i64),
+    /// A string key of a map, the name of a struct field or the name of an enum variant.
+    Key(String),
+}
+
+/// Writes the segment the way it is accessed in lua, e.g. `[1]` or `.name`.
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathSegment::Index(i) => write!(f, "[{}]", i),
+            PathSegment::Key(key) => write!(f, ".{}", key),
+        }
+    }
+}
+
+/// Receives events while a value is serialized, e.g. to report progress or to audit the
+/// written keys.
+///
+/// Every method receives the path to the current value. All methods do nothing by default.
+///
+/// ```
+/// use serde::Serialize;
+/// use serde_lua_table::{Observer, PathSegment, Serializer};
+/// use std::collections::BTreeMap;
+/// use std::sync::mpsc;
+///
+/// struct Keys(mpsc::Sender<String>);
+///
+/// impl Observer for Keys {
+///     fn key(&mut self, path: &[PathSegment]) {
+///         let path: String = path.iter().map(|s| s.to_string()).collect();
+///         self.0.send(path).unwrap();
+///     }
+/// }
+///
+/// let (tx, rx) = mpsc::channel();
+/// let mut ser = Serializer::new(Vec::new()).with_observer(Keys(tx));
+/// let value = BTreeMap::from([("items", [BTreeMap::from([("id", 1)])])]);
+/// value.serialize(&mut ser).unwrap();
+/// assert_eq!(rx.try_iter().collect::<Vec<_>>(), [".items", ".items[1].id"]);
+/// ```
+pub trait Observer {
+    /// Called when a table is opened, the path leads to the table.
+    fn enter_table(&mut self, path: &[PathSegment]) {
+        let _ = path;
+    }
+
+    /// Called when a table is closed, the path leads to the table.
+    fn leave_table(&mut self, path: &[PathSegment]) {
+        let _ = path;
+    }
+
+    /// Called before the value of a map entry, struct field or enum variant is written. The
+    /// last segment of the path is the key.
+    fn key(&mut self, path: &[PathSegment]) {
+        let _ = path;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Observer, PathSegment};
+    use crate::{CompactFormatter, KeyOrder, SerOptions, Serializer};
+    use serde::Serialize;
+    use std::{
+        collections::BTreeMap,
+        sync::{Arc, Mutex},
+    };
+
+    /// Records every event as the event's name followed by the path.
+    #[derive(Clone, Default)]
+    struct Events(Arc<Mutex<Vec<String>>>);
+
+    impl Events {
+        fn push(&self, event: &str, path: &[PathSegment]) {
+            let path: String = path.iter().map(|s| s.to_string()).collect();
+            self.0.lock().unwrap().push(format!("{event} {path}"));
+        }
+    }
+
+    impl Observer for Events {
+        fn enter_table(&mut self, path: &[PathSegment]) {
+            self.push("enter", path);
+        }
+
+        fn leave_table(&mut self, path: &[PathSegment]) {
+            self.push("leave", path);
+        }
+
+        fn key(&mut self, path: &[PathSegment]) {
+            self.push("key", path);
+        }
+    }
+
+    #[derive(Serialize)]
+    enum Shape {
+        Circle { radius: f64 },
+    }
+
+    #[test]
+    fn reports_tables_and_keys_with_their_path() {
+        let value = BTreeMap::from([("shapes", vec![Shape::Circle { radius: 1.0 }])]);
+        let events = Events::default();
+        let mut ser = Serializer::new(Vec::new()).with_observer(events.clone());
+        value.serialize(&mut ser).unwrap();
+        assert_eq!(
+            *events.0.lock().unwrap(),
+            [
+                "enter ",
+                "key .shapes",
+                "enter .shapes",
+                "enter .shapes[1]",
+                "key .shapes[1].Circle",
+                "enter .shapes[1].Circle",
+                "key .shapes[1].Circle.radius",
+                "leave .shapes[1].Circle",
+                "leave .shapes[1]",
+                "leave .shapes",
+                "leave ",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_sorted_keys_once_as_they_are_serialized() {
+        let value = BTreeMap::from([("b", 1), ("a", 2)]);
+        let options = SerOptions::new().with_key_order(KeyOrder::Custom(|a, b| b.cmp(a)));
+        let events = Events::default();
+        let mut ser = Serializer::with_options(Vec::new(), CompactFormatter, options)
+            .with_observer(events.clone());
+        value.serialize(&mut ser).unwrap();
+        let keys: Vec<_> = events
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|event| event.starts_with("key"))
+            .cloned()
+            .collect();
+        assert_eq!(keys, ["key .a", "key .b"]);
+        assert_eq!(ser.into_inner(), br#"{["b"]=1,["a"]=2}"#);
+    }
+}
@@ -0,0 +1,540 @@
+use super::{KeyCase, PathMatcher};
+use std::cmp::Ordering;
+
+/// The lua implementation the output is meant to be loaded by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum LuaVersion {
+    Lua51,
+    Lua52,
+    Lua53,
+    #[default]
+    Lua54,
+    /// Roblox' Luau, which is based on Lua 5.1.
+    Luau,
+    /// LuaJIT, which is based on Lua 5.1.
+    ///
+    /// Integers that a double can't represent exactly are written as `LL`/`ULL` literals, which
+    /// LuaJIT loads as 64-bit integer cdata.
+    LuaJit,
+}
+
+impl LuaVersion {
+    /// Returns whether `name` is a reserved word and can't be used as an identifier.
+    pub fn is_keyword(self, name: &str) -> bool {
+        const KEYWORDS: &[&str] = &[
+            "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "if", "in",
+            "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+        ];
+        KEYWORDS.contains(&name) || (name == "goto" && self.has_goto())
+    }
+
+    /// Returns whether `name` can be used as a bare identifier, e.g. as a key in `{name = 1}`.
+    pub fn is_identifier(self, name: &str) -> bool {
+        name.chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !self.is_keyword(name)
+    }
+
+    /// Returns whether numbers have a separate 64-bit integer subtype. Otherwise, all numbers are
+    /// doubles and only integers up to 2^53 are exact.
+    pub fn has_integers(self) -> bool {
+        matches!(self, Self::Lua53 | Self::Lua54)
+    }
+
+    fn has_goto(self) -> bool {
+        matches!(self, Self::Lua52 | Self::Lua53 | Self::Lua54 | Self::LuaJit)
+    }
+}
+
+/// How string keys of tables are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum KeyStyle {
+    /// Every key is written in brackets, e.g. `["name"] = 1`.
+    #[default]
+    Bracketed,
+    /// Keys that are valid identifiers are written without brackets, e.g. `name = 1`. Other keys
+    /// are still written in brackets.
+    Bare,
+}
+
+/// The order the entries of maps and structs are written in.
+///
+/// Integer keys always come first in numeric order, the orders only differ in how they sort
+/// string keys.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum KeyOrder {
+    /// Entries are written in the order `Serialize` produces them.
+    #[default]
+    Unsorted,
+    /// Strings are compared byte by byte, so `item10` comes before `item2`.
+    Lexicographic,
+    /// Runs of digits are compared by their numeric value, so `item2` comes before `item10` and
+    /// `1.9` before `1.10`.
+    Natural,
+    /// Strings are compared with the given function.
+    Custom(fn(&str, &str) -> Ordering),
+}
+
+impl KeyOrder {
+    /// Compares two string keys, returns `None` if entries aren't sorted.
+    pub(crate) fn compare(self, a: &str, b: &str) -> Option<Ordering> {
+        match self {
+            KeyOrder::Unsorted => None,
+            KeyOrder::Lexicographic => Some(a.cmp(b)),
+            KeyOrder::Natural => Some(natural_cmp(a, b)),
+            KeyOrder::Custom(compare) => Some(compare(a, b)),
+        }
+    }
+}
+
+/// Compares runs of ASCII digits by their value and everything else by bytes.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_rest, mut b_rest) = (a.as_bytes(), b.as_bytes());
+    loop {
+        match (a_rest.first(), b_rest.first()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (a_digits, a_tail) = split_digits(a_rest);
+                let (b_digits, b_tail) = split_digits(b_rest);
+                let a_value = trim_zeros(a_digits);
+                let b_value = trim_zeros(b_digits);
+                let ordering = a_value
+                    .len()
+                    .cmp(&b_value.len())
+                    .then_with(|| a_value.cmp(b_value));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a_rest = a_tail;
+                b_rest = b_tail;
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(y);
+                }
+                a_rest = &a_rest[1..];
+                b_rest = &b_rest[1..];
+            }
+        }
+    }
+}
+
+fn split_digits(s: &[u8]) -> (&[u8], &[u8]) {
+    let len = s.iter().take_while(|b| b.is_ascii_digit()).count();
+    s.split_at(len)
+}
+
+fn trim_zeros(digits: &[u8]) -> &[u8] {
+    let zeros = digits.iter().take_while(|&&b| b == b'0').count();
+    &digits[zeros..]
+}
+
+/// Which release's output the serializer reproduces.
+///
+/// Without a fixed version, the output may change between releases of this crate, e.g. when a
+/// different escape is picked. Files that are hashed, signed or checked in should pin a version,
+/// so upgrading doesn't rewrite them. Formatters write pinned escapes through
+/// [`Formatter::write_char_escape_v1`](crate::Formatter::write_char_escape_v1).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum OutputStability {
+    /// The output of the current release.
+    #[default]
+    Latest,
+    /// The output of version 0.1.2, which wrote ASCII control characters as `\u00XX`. Lua can't
+    /// load these escapes, so only pin this version for files that already contain them.
+    V1,
+}
+
+/// Options that change what the [`Serializer`](super::Serializer) writes, independent of the
+/// formatting.
+///
+/// ```
+/// use serde_lua_table::{CompactFormatter, LuaVersion, SerOptions, Serializer};
+///
+/// let options = SerOptions::new().with_version(LuaVersion::Luau);
+/// let mut ser = Serializer::with_options(Vec::new(), CompactFormatter, options);
+/// assert!(serde::Serialize::serialize(&u64::MAX, &mut ser).is_err());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SerOptions {
+    version: LuaVersion,
+    key_style: KeyStyle,
+    nil_sentinel: Option<String>,
+    max_depth: Option<usize>,
+    key_order: KeyOrder,
+    key_case: KeyCase,
+    output_stability: OutputStability,
+    redacted: Vec<PathMatcher>,
+    write_buffer: usize,
+}
+
+impl SerOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the lua version the output is meant for.
+    ///
+    /// On versions without an integer subtype, integers that don't fit into a double exactly are
+    /// an error instead of silently losing precision when loaded. LuaJIT is the exception, it
+    /// gets 64-bit integer literals instead.
+    ///
+    /// ```
+    /// use serde_lua_table::{CompactFormatter, LuaVersion, SerOptions, Serializer};
+    ///
+    /// let options = SerOptions::new().with_version(LuaVersion::LuaJit);
+    /// let mut ser = Serializer::with_options(Vec::new(), CompactFormatter, options);
+    /// serde::Serialize::serialize(&[1, u64::MAX], &mut ser).unwrap();
+    /// assert_eq!(ser.into_inner(), b"{1,18446744073709551615ULL}");
+    /// ```
+    pub fn with_version(mut self, version: LuaVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Sets how string keys are written.
+    ///
+    /// ```
+    /// use serde_lua_table::{CompactFormatter, KeyStyle, SerOptions, Serializer};
+    /// use std::collections::BTreeMap;
+    ///
+    /// let options = SerOptions::new().with_key_style(KeyStyle::Bare);
+    /// let mut ser = Serializer::with_options(Vec::new(), CompactFormatter, options);
+    /// let value = BTreeMap::from([("end", 1), ("name", 2)]);
+    /// serde::Serialize::serialize(&value, &mut ser).unwrap();
+    /// assert_eq!(ser.into_inner(), br#"{["end"]=1,name=2}"#);
+    /// ```
+    pub fn with_key_style(mut self, key_style: KeyStyle) -> Self {
+        self.key_style = key_style;
+        self
+    }
+
+    /// Writes `sentinel` as raw lua in place of `nil`, e.g. `vim.NIL` or `ngx.null`.
+    ///
+    /// Hosts that decode JSON use such a sentinel for `null`, because a `nil` field is the same
+    /// as a missing one.
+    ///
+    /// ```
+    /// use serde_lua_table::{CompactFormatter, SerOptions, Serializer};
+    ///
+    /// let options = SerOptions::new().with_nil_sentinel("vim.NIL");
+    /// let mut ser = Serializer::with_options(Vec::new(), CompactFormatter, options);
+    /// serde::Serialize::serialize(&[Some(1), None], &mut ser).unwrap();
+    /// assert_eq!(ser.into_inner(), b"{1,vim.NIL}");
+    /// ```
+    pub fn with_nil_sentinel(mut self, sentinel: impl Into<String>) -> Self {
+        self.nil_sentinel = Some(sentinel.into());
+        self
+    }
+
+    /// Limits how deep tables can be nested. Deeper tables are an error.
+    ///
+    /// Lua's parser only handles about 200 nested table constructors.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Sets the order the entries of maps and structs are written in.
+    ///
+    /// Sorting buffers the serialized values of a table until all of its entries are known.
+    ///
+    /// ```
+    /// use serde_lua_table::{CompactFormatter, KeyOrder, KeyStyle, SerOptions, Serializer};
+    /// use std::collections::HashMap;
+    ///
+    /// let options = SerOptions::new()
+    ///     .with_key_style(KeyStyle::Bare)
+    ///     .with_key_order(KeyOrder::Natural);
+    /// let mut ser = Serializer::with_options(Vec::new(), CompactFormatter, options);
+    /// let value = HashMap::from([("item10", 10), ("item2", 2), ("item1", 1)]);
+    /// serde::Serialize::serialize(&value, &mut ser).unwrap();
+    /// assert_eq!(ser.into_inner(), b"{item1=1,item2=2,item10=10}");
+    /// ```
+    pub fn with_key_order(mut self, key_order: KeyOrder) -> Self {
+        self.key_order = key_order;
+        self
+    }
+
+    /// Renames the string keys of maps and structs, see [`KeyCase`].
+    pub fn with_key_case(mut self, key_case: KeyCase) -> Self {
+        self.key_case = key_case;
+        self
+    }
+
+    /// Pins the output to a release, see [`OutputStability`].
+    pub fn with_output_stability(mut self, output_stability: OutputStability) -> Self {
+        self.output_stability = output_stability;
+        self
+    }
+
+    /// Writes the string `"<redacted>"` in place of the values whose path matches `pattern`, e.g.
+    /// `**.password`. Can be called more than once to redact several paths.
+    ///
+    /// Sequence elements, map values, struct fields and the values of newtype variants can be
+    /// redacted. Tuple and struct variants are redacted field by field.
+    ///
+    /// ```
+    /// use serde_lua_table::{CompactFormatter, KeyStyle, SerOptions, Serializer};
+    /// use std::collections::BTreeMap;
+    ///
+    /// let options = SerOptions::new()
+    ///     .with_key_style(KeyStyle::Bare)
+    ///     .with_redacted("**.password".parse().unwrap());
+    /// let mut ser = Serializer::with_options(Vec::new(), CompactFormatter, options);
+    /// let value = [BTreeMap::from([("name", "ada"), ("password", "hunter2")])];
+    /// serde::Serialize::serialize(&value, &mut ser).unwrap();
+    /// assert_eq!(ser.into_inner(), br#"{{name="ada",password="<redacted>"}}"#);
+    /// ```
+    pub fn with_redacted(mut self, pattern: PathMatcher) -> Self {
+        self.redacted.push(pattern);
+        self
+    }
+
+    /// Buffers up to `capacity` bytes of the tables before passing them to the writer, so
+    /// unbuffered writers like files don't get a write for every token. What is buffered is
+    /// written once the outermost table is closed.
+    ///
+    /// The serializer doesn't buffer by default, since writers like `Vec<u8>` or an
+    /// [`io::BufWriter`](std::io::BufWriter) don't need it.
+    ///
+    /// ```
+    /// use serde_lua_table::{CompactFormatter, SerOptions, Serializer};
+    /// # let file = std::io::sink();
+    ///
+    /// let options = SerOptions::new().with_write_buffer(8 * 1024);
+    /// let mut ser = Serializer::with_options(file, CompactFormatter, options);
+    /// serde::Serialize::serialize(&[1, 2], &mut ser).unwrap();
+    /// ```
+    pub fn with_write_buffer(mut self, capacity: usize) -> Self {
+        self.write_buffer = capacity;
+        self
+    }
+
+    pub fn version(&self) -> LuaVersion {
+        self.version
+    }
+
+    pub fn key_style(&self) -> KeyStyle {
+        self.key_style
+    }
+
+    pub fn nil_sentinel(&self) -> Option<&str> {
+        self.nil_sentinel.as_deref()
+    }
+
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    pub fn key_order(&self) -> KeyOrder {
+        self.key_order
+    }
+
+    pub fn key_case(&self) -> KeyCase {
+        self.key_case
+    }
+
+    pub fn output_stability(&self) -> OutputStability {
+        self.output_stability
+    }
+
+    pub fn redacted(&self) -> &[PathMatcher] {
+        &self.redacted
+    }
+
+    pub fn write_buffer(&self) -> usize {
+        self.write_buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CompactFormatter, PrettyFormatter, Serializer};
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Serialize)]
+    struct Golden {
+        name: &'static str,
+        escapes: &'static str,
+        count: u64,
+        ratio: f32,
+        missing: Option<i8>,
+        tags: Vec<&'static str>,
+        shapes: Vec<Shape>,
+        by_id: BTreeMap<i32, bool>,
+    }
+
+    fn golden() -> Golden {
+        Golden {
+            name: "golden",
+            escapes: "\"\\\n\r\t\x08\x0c\0\x1b1",
+            count: u64::MAX,
+            ratio: 0.1,
+            missing: None,
+            tags: vec!["a", "b"],
+            shapes: vec![
+                Shape::Point,
+                Shape::Circle(-2.5),
+                Shape::Rect { w: 1, h: 2 },
+            ],
+            by_id: BTreeMap::from([(-1, true), (2, false)]),
+        }
+    }
+
+    /// The output of version 0.1.2 for the same value.
+    const V1_COMPACT: &str = r#"{["name"]="golden",["escapes"]="\"\\\n\r\t\b\f\u0000\u001b1",["count"]=18446744073709551615,["ratio"]=0.1,["missing"]=nil,["tags"]={"a","b"},["shapes"]={"Point",{["Circle"]=-2.5},{["Rect"]={["w"]=1,["h"]=2}}},["by_id"]={[-1]=true,[2]=false}}"#;
+    const V1_PRETTY: &str = r#"{
+  ["name"] = "golden",
+  ["escapes"] = "\"\\\n\r\t\b\f\u0000\u001b1",
+  ["count"] = 18446744073709551615,
+  ["ratio"] = 0.1,
+  ["missing"] = nil,
+  ["tags"] = {
+    "a",
+    "b"
+  },
+  ["shapes"] = {
+    "Point",
+    {
+      ["Circle"] = -2.5
+    },
+    {
+      ["Rect"] = {
+        ["w"] = 1,
+        ["h"] = 2
+      }
+    }
+  },
+  ["by_id"] = {
+    [-1] = true,
+    [2] = false
+  }
+}"#;
+
+    #[test]
+    fn v1_reproduces_the_output_of_version_0_1() {
+        let options = SerOptions::new().with_output_stability(OutputStability::V1);
+
+        let mut ser = Serializer::with_options(Vec::new(), CompactFormatter, options.clone());
+        golden().serialize(&mut ser).unwrap();
+        assert_eq!(String::from_utf8(ser.into_inner()).unwrap(), V1_COMPACT);
+
+        let mut ser = Serializer::with_options(Vec::new(), PrettyFormatter::new(), options);
+        golden().serialize(&mut ser).unwrap();
+        assert_eq!(String::from_utf8(ser.into_inner()).unwrap(), V1_PRETTY);
+    }
+
+    fn redacted(value: &impl Serialize, patterns: &[&str], key_order: KeyOrder) -> String {
+        let mut options = SerOptions::new()
+            .with_key_style(KeyStyle::Bare)
+            .with_key_order(key_order);
+        for pattern in patterns {
+            options = options.with_redacted(pattern.parse().unwrap());
+        }
+        let mut ser = Serializer::with_options(Vec::new(), CompactFormatter, options);
+        value.serialize(&mut ser).unwrap();
+        String::from_utf8(ser.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn redacts_matching_paths() {
+        let value = golden();
+        assert_eq!(
+            redacted(
+                &value,
+                &["escapes", "tags[2]", "shapes.*.Circle", "**.h"],
+                KeyOrder::Unsorted
+            ),
+            r#"{name="golden",escapes="<redacted>",count=18446744073709551615,ratio=0.1,missing=nil,tags={"a","<redacted>"},shapes={"Point",{Circle="<redacted>"},{Rect={w=1,h="<redacted>"}}},by_id={[-1]=true,[2]=false}}"#
+        );
+        assert_eq!(
+            redacted(
+                &value,
+                &["by_id[2]", "escapes", "shapes[3].Rect.w"],
+                KeyOrder::Lexicographic
+            ),
+            r#"{by_id={[-1]=true,[2]="<redacted>"},count=18446744073709551615,escapes="<redacted>",missing=nil,name="golden",ratio=0.1,shapes={"Point",{Circle=-2.5},{Rect={h=2,w="<redacted>"}}},tags={"a","b"}}"#
+        );
+    }
+
+    #[test]
+    fn redacts_whole_tables() {
+        let value = golden();
+        assert_eq!(
+            redacted(&value, &["shapes", "tags[*]", "by_id"], KeyOrder::Unsorted),
+            r#"{name="golden",escapes="\"\\\n\r\t\b\f\000\0271",count=18446744073709551615,ratio=0.1,missing=nil,tags={"<redacted>","<redacted>"},shapes="<redacted>",by_id="<redacted>"}"#
+        );
+    }
+
+    #[test]
+    fn compares_digits_naturally() {
+        let mut keys = [
+            "item10", "item2", "item02", "1.10", "1.9", "b", "a1b", "a01b", "", "item",
+        ];
+        keys.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            keys,
+            ["", "1.9", "1.10", "a01b", "a1b", "b", "item", "item02", "item2", "item10"]
+        );
+    }
+
+    #[test]
+    fn sorts_integer_keys_before_string_keys() {
+        #[derive(Serialize)]
+        struct Entry {
+            zeta: u8,
+            alpha: BTreeMap<&'static str, u8>,
+        }
+
+        let entry = Entry {
+            zeta: 1,
+            alpha: BTreeMap::from([("b", 1), ("a", 2)]),
+        };
+        let written = |key_order| {
+            let options = SerOptions::new()
+                .with_key_style(KeyStyle::Bare)
+                .with_key_order(key_order);
+            let mut ser = Serializer::with_options(Vec::new(), CompactFormatter, options);
+            entry.serialize(&mut ser).unwrap();
+            String::from_utf8(ser.into_inner()).unwrap()
+        };
+        assert_eq!(written(KeyOrder::Unsorted), "{zeta=1,alpha={a=2,b=1}}");
+        assert_eq!(written(KeyOrder::Lexicographic), "{alpha={a=2,b=1},zeta=1}");
+        assert_eq!(
+            written(KeyOrder::Custom(|a, b| b.cmp(a))),
+            "{zeta=1,alpha={b=1,a=2}}"
+        );
+
+        let mixed: crate::Value = crate::from_str("{ b = 1, [3] = 2, a = 3, [-1] = 4 }").unwrap();
+        let options = SerOptions::new()
+            .with_key_style(KeyStyle::Bare)
+            .with_key_order(KeyOrder::Natural);
+        let mut ser = Serializer::with_options(Vec::new(), PrettyFormatter::new(), options);
+        mixed.serialize(&mut ser).unwrap();
+        assert_eq!(
+            String::from_utf8(ser.into_inner()).unwrap(),
+            "{\n  [-1] = 4,\n  [3] = 2,\n  a = 3,\n  b = 1\n}"
+        );
+    }
+
+    #[test]
+    fn latest_writes_decimal_escapes() {
+        let lua = crate::to_string(&golden()).unwrap();
+        let expected = V1_COMPACT.replace(r"\u0000\u001b1", r"\000\0271");
+        assert_eq!(lua, expected);
+    }
+}
@@ -0,0 +1,145 @@
+use std::io;
+
+/// The writer of a [`Serializer`](super::Serializer).
+///
+/// Output can be captured into buffers instead of being written, e.g. to sort the entries of a
+/// table before writing them. Captures nest, only the innermost one receives the output.
+///
+/// While a table is written, the output is also held back in a buffer of up to `capacity` bytes,
+/// so writers like files don't get a write for every token.
+pub(crate) struct Output<W> {
+    inner: W,
+    captures: Vec<Vec<u8>>,
+    /// The output that is held back, grown as needed.
+    buffer: Vec<u8>,
+    capacity: usize,
+    holding: bool,
+    /// Number of bytes written to `inner`.
+    #[cfg(feature = "tracing")]
+    written: usize,
+}
+
+impl<W> Output<W> {
+    pub(crate) fn new(inner: W, capacity: usize) -> Self {
+        Output {
+            inner,
+            captures: Vec::new(),
+            buffer: Vec::new(),
+            capacity,
+            holding: false,
+            #[cfg(feature = "tracing")]
+            written: 0,
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    pub(crate) fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Returns `inner` after writing what is still held back, which only happens if writing a
+    /// table failed. Errors are ignored like [`io::BufWriter`] ignores them when it's dropped.
+    pub(crate) fn into_inner(mut self) -> W
+    where
+        W: io::Write,
+    {
+        let _ = self.write_held();
+        self.inner
+    }
+
+    /// Holds the output back until [`release`](Self::release) is called, unless the buffer has
+    /// no capacity.
+    pub(crate) fn hold(&mut self) {
+        self.holding = self.capacity > 0;
+    }
+
+    /// Writes the output that was held back and stops holding it.
+    pub(crate) fn release(&mut self) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        self.holding = false;
+        self.write_held()
+    }
+
+    fn write_held(&mut self) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let result = self.inner.write_all(&self.buffer);
+        #[cfg(feature = "tracing")]
+        if result.is_ok() {
+            self.written += self.buffer.len();
+        }
+        self.buffer.clear();
+        result
+    }
+
+    /// Starts capturing the output until [`end_capture`](Self::end_capture) is called.
+    pub(crate) fn begin_capture(&mut self) {
+        self.captures.push(Vec::new());
+    }
+
+    /// Returns everything written since the matching [`begin_capture`](Self::begin_capture).
+    pub(crate) fn end_capture(&mut self) -> Vec<u8> {
+        self.captures.pop().unwrap_or_default()
+    }
+}
+
+impl<W> io::Write for Output<W>
+where
+    W: io::Write,
+{
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.captures.last_mut() {
+            Some(capture) => capture.write(buf),
+            None if self.holding => {
+                self.write_all(buf)?;
+                Ok(buf.len())
+            }
+            None => {
+                let written = self.inner.write(buf)?;
+                #[cfg(feature = "tracing")]
+                {
+                    self.written += written;
+                }
+                Ok(written)
+            }
+        }
+    }
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        match self.captures.last_mut() {
+            Some(capture) => capture.write_all(buf),
+            None if self.holding && self.buffer.len() + buf.len() <= self.capacity => {
+                self.buffer.extend_from_slice(buf);
+                Ok(())
+            }
+            None if self.holding && buf.len() < self.capacity => {
+                self.write_held()?;
+                self.buffer.extend_from_slice(buf);
+                Ok(())
+            }
+            None => {
+                self.write_held()?;
+                self.inner.write_all(buf)?;
+                #[cfg(feature = "tracing")]
+                {
+                    self.written += buf.len();
+                }
+                Ok(())
+            }
+        }
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_held()?;
+        self.inner.flush()
+    }
+}
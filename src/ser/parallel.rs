@@ -0,0 +1,97 @@
+use super::{SerError, Serializer};
+use crate::format::Formatter;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::io;
+
+/// Sequences shorter than this are written on the calling thread.
+const MIN_PARALLEL_LEN: usize = 1024;
+
+/// Writes `values` like a serializer with `formatter` writes a sequence, serializing chunks of it
+/// on the rayon thread pool.
+///
+/// Every chunk gets a copy of the formatter as it is after the opening brace, so indentation
+/// continues where the previous chunk stopped.
+pub(crate) fn write<W, F, T>(mut writer: W, mut formatter: F, values: &[T]) -> Result<(), SerError>
+where
+    W: io::Write,
+    F: Formatter + Clone + Send + Sync,
+    T: Serialize + Sync,
+{
+    let threads = rayon::current_num_threads();
+    if threads == 1 || values.len() < MIN_PARALLEL_LEN {
+        return values.serialize(&mut Serializer::with_formatter(writer, formatter));
+    }
+
+    formatter.begin_array(&mut writer, Some(values.len()))?;
+    let chunk_len = values.len().div_ceil(threads);
+    let chunks = values
+        .par_chunks(chunk_len)
+        .enumerate()
+        .map(|(i, chunk)| write_elements(formatter.clone(), chunk, i == 0))
+        .collect::<Result<Vec<_>, _>>()?;
+    for (buf, _) in &chunks {
+        writer.write_all(buf)?;
+    }
+    // The formatter of the last chunk knows that values were written.
+    if let Some((_, mut formatter)) = chunks.into_iter().last() {
+        formatter.end_array(&mut writer)?;
+    }
+    Ok(())
+}
+
+/// Writes the elements with their separators, without the braces of the sequence. Returns the
+/// output and the formatter after the last element.
+fn write_elements<F, T>(formatter: F, values: &[T], first: bool) -> Result<(Vec<u8>, F), SerError>
+where
+    F: Formatter,
+    T: Serialize,
+{
+    let mut ser = Serializer::with_formatter(Vec::with_capacity(values.len() * 16), formatter);
+    for (i, value) in values.iter().enumerate() {
+        ser.formatter
+            .begin_array_value(&mut ser.writer, first && i == 0)?;
+        value.serialize(&mut ser)?;
+        ser.formatter.end_array_value(&mut ser.writer)?;
+    }
+    let formatter = ser.formatter;
+    Ok((ser.writer.into_inner(), formatter))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MIN_PARALLEL_LEN;
+    use crate::{to_vec, to_vec_pretty, to_writer_parallel, to_writer_parallel_pretty};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn writes_like_to_writer() {
+        for len in [0, 3, MIN_PARALLEL_LEN * 3 + 1] {
+            let values: Vec<_> = (0..len)
+                .map(|i| (i % 3 != 0).then(|| format!("{i}\n\"")))
+                .collect();
+            let mut out = Vec::new();
+            to_writer_parallel(&mut out, &values).unwrap();
+            assert_eq!(out, to_vec(&values).unwrap());
+        }
+    }
+
+    #[test]
+    fn writes_like_to_writer_pretty() {
+        for len in [0, 3, MIN_PARALLEL_LEN * 3 + 1] {
+            let values: Vec<_> = (0..len)
+                .map(|i| BTreeMap::from([("id", vec![i; i % 3])]))
+                .collect();
+            let mut out = Vec::new();
+            to_writer_parallel_pretty(&mut out, &values).unwrap();
+            assert_eq!(out, to_vec_pretty(&values).unwrap());
+        }
+    }
+
+    #[test]
+    fn fails_if_any_element_fails() {
+        let mut values = vec![BTreeMap::new(); MIN_PARALLEL_LEN * 3];
+        values[MIN_PARALLEL_LEN * 2].insert(vec![1], 1);
+        assert!(to_writer_parallel(Vec::new(), &values).is_err());
+    }
+}
@@ -0,0 +1,282 @@
+use super::PathSegment;
+use std::{fmt, str::FromStr};
+
+/// A pattern matching paths to values, written like the paths themselves.
+///
+/// - `name` and `.name` match the key `name`, `["odd key"]` matches keys that aren't
+///   identifiers.
+/// - `[1]` matches the index 1 and `[*]` any index.
+/// - `*` matches any single key or index.
+/// - `**` matches any number of keys and indices, including none.
+///
+/// Patterns select the values that [`SerOptions::with_redacted`](crate::SerOptions::with_redacted)
+/// redacts, the keys that
+/// [`CommentInjecting::with_path_comment`](crate::CommentInjecting::with_path_comment) comments
+/// and [`FieldStyles::with_path_style`](crate::FieldStyles::with_path_style) styles, and the
+/// values [`LazyValue::select`](crate::LazyValue::select) reads.
+///
+/// ```
+/// use serde_lua_table::{PathMatcher, PathSegment};
+///
+/// let matcher: PathMatcher = "**.password".parse().unwrap();
+/// let path = [
+///     PathSegment::Key("accounts".to_owned()),
+///     PathSegment::Index(2),
+///     PathSegment::Key("password".to_owned()),
+/// ];
+/// assert!(matcher.matches(&path));
+/// assert!("accounts[*].*".parse::<PathMatcher>().unwrap().matches(&path));
+/// assert!(!"accounts[1].password".parse::<PathMatcher>().unwrap().matches(&path));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PathMatcher {
+    patterns: Vec<Pattern>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Pattern {
+    Key(String),
+    Index(i64),
+    AnyIndex,
+    Any,
+    AnyDepth,
+}
+
+/// Why a path pattern couldn't be parsed.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("invalid path pattern at byte {position}: {message}")]
+pub struct PathPatternError {
+    pub position: usize,
+    pub message: &'static str,
+}
+
+impl PathMatcher {
+    /// Returns whether `path` matches the whole pattern.
+    pub fn matches(&self, path: &[PathSegment]) -> bool {
+        matches(&self.patterns, path)
+    }
+
+    /// Returns whether `path` or a path below it can match the pattern, so tables that can't
+    /// hold a match don't need to be read.
+    pub fn matches_below(&self, path: &[PathSegment]) -> bool {
+        matches_below(&self.patterns, path)
+    }
+}
+
+fn matches_below(patterns: &[Pattern], path: &[PathSegment]) -> bool {
+    let Some((segment, path)) = path.split_first() else {
+        return true;
+    };
+    match patterns.split_first() {
+        None => false,
+        Some((Pattern::AnyDepth, _)) => true,
+        Some((pattern, rest)) => segment_matches(pattern, segment) && matches_below(rest, path),
+    }
+}
+
+fn segment_matches(pattern: &Pattern, segment: &PathSegment) -> bool {
+    match (pattern, segment) {
+        (Pattern::Key(expected), PathSegment::Key(key)) => expected == key,
+        (Pattern::Index(expected), PathSegment::Index(index)) => expected == index,
+        (Pattern::AnyIndex, PathSegment::Index(_)) | (Pattern::Any, _) => true,
+        _ => false,
+    }
+}
+
+fn matches(patterns: &[Pattern], path: &[PathSegment]) -> bool {
+    let Some((pattern, rest)) = patterns.split_first() else {
+        return path.is_empty();
+    };
+    if let Pattern::AnyDepth = pattern {
+        return (0..=path.len()).any(|skip| matches(rest, &path[skip..]));
+    }
+    let Some((segment, path)) = path.split_first() else {
+        return false;
+    };
+    segment_matches(pattern, segment) && matches(rest, path)
+}
+
+impl FromStr for PathMatcher {
+    type Err = PathPatternError;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        let bytes = pattern.as_bytes();
+        let error = |position, message| Err(PathPatternError { position, message });
+        let mut patterns = Vec::new();
+        let mut pos = 0;
+        while pos < bytes.len() {
+            match bytes[pos] {
+                b'[' => {
+                    let (pattern, end) = parse_bracket(pattern, pos)?;
+                    patterns.push(pattern);
+                    pos = end;
+                }
+                b'.' if pos == 0 || !patterns.is_empty() => {
+                    pos += 1;
+                    if pos == bytes.len() || matches!(bytes[pos], b'.' | b'[') {
+                        return error(pos, "expected a key after `.`");
+                    }
+                }
+                _ if pos != 0 && bytes[pos - 1] != b'.' => {
+                    return error(pos, "expected `.` or `[`");
+                }
+                _ => {
+                    let len = pattern[pos..]
+                        .find(['.', '['])
+                        .unwrap_or(pattern.len() - pos);
+                    patterns.push(match &pattern[pos..pos + len] {
+                        "*" => Pattern::Any,
+                        "**" => Pattern::AnyDepth,
+                        key => Pattern::Key(key.to_owned()),
+                    });
+                    pos += len;
+                }
+            }
+        }
+        Ok(PathMatcher { patterns })
+    }
+}
+
+/// Parses `[1]`, `[*]` or `["key"]` starting at `start`, returns the pattern and the position
+/// after the closing bracket.
+fn parse_bracket(pattern: &str, start: usize) -> Result<(Pattern, usize), PathPatternError> {
+    let error = |position, message| PathPatternError { position, message };
+    let inner = &pattern[start + 1..];
+    if let Some(rest) = inner.strip_prefix('"') {
+        let mut key = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    let end = start + 2 + i + 1;
+                    return match pattern[end..].starts_with(']') {
+                        true => Ok((Pattern::Key(key), end + 1)),
+                        false => Err(error(end, "expected `]`")),
+                    };
+                }
+                '\\' => match chars.next() {
+                    Some((_, c)) => key.push(c),
+                    None => break,
+                },
+                c => key.push(c),
+            }
+        }
+        return Err(error(pattern.len(), "unterminated string"));
+    }
+
+    let Some(len) = inner.find(']') else {
+        return Err(error(pattern.len(), "expected `]`"));
+    };
+    let pattern = match &inner[..len] {
+        "*" => Pattern::AnyIndex,
+        index => Pattern::Index(
+            index
+                .parse()
+                .map_err(|_| error(start + 1, "expected an integer, `*` or a string"))?,
+        ),
+    };
+    Ok((pattern, start + 1 + len + 1))
+}
+
+impl fmt::Display for PathMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, pattern) in self.patterns.iter().enumerate() {
+            let dot = if i == 0 { "" } else { "." };
+            match pattern {
+                Pattern::Key(key) if is_plain_key(key) => write!(f, "{}{}", dot, key)?,
+                Pattern::Key(key) => write!(f, "[{:?}]", key)?,
+                Pattern::Index(index) => write!(f, "[{}]", index)?,
+                Pattern::AnyIndex => f.write_str("[*]")?,
+                Pattern::Any => write!(f, "{}*", dot)?,
+                Pattern::AnyDepth => write!(f, "{}**", dot)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns whether `key` can be written without brackets and quotes.
+fn is_plain_key(key: &str) -> bool {
+    !key.is_empty() && key != "*" && key != "**" && !key.contains(['.', '[', ']', '"'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(segments: &[&str]) -> Vec<PathSegment> {
+        segments
+            .iter()
+            .map(|s| match s.parse() {
+                Ok(i) => PathSegment::Index(i),
+                Err(_) => PathSegment::Key(s.to_string()),
+            })
+            .collect()
+    }
+
+    fn matches(pattern: &str, segments: &[&str]) -> bool {
+        pattern
+            .parse::<PathMatcher>()
+            .unwrap()
+            .matches(&path(segments))
+    }
+
+    #[test]
+    fn matches_exact_segments() {
+        assert!(matches("a.b", &["a", "b"]));
+        assert!(matches(".a.b", &["a", "b"]));
+        assert!(matches("a[2]", &["a", "2"]));
+        assert!(matches(r#"["a.b"][1]"#, &["a.b", "1"]));
+        assert!(!matches("a.b", &["a"]));
+        assert!(!matches("a", &["a", "b"]));
+        assert!(!matches("a[2]", &["a", "3"]));
+        assert!(matches("", &[]));
+    }
+
+    #[test]
+    fn matches_below_prefixes() {
+        let below = |pattern: &str, segments: &[&str]| {
+            pattern
+                .parse::<PathMatcher>()
+                .unwrap()
+                .matches_below(&path(segments))
+        };
+        assert!(below("a.b", &[]));
+        assert!(below("a.b", &["a"]));
+        assert!(below("a.b", &["a", "b"]));
+        assert!(!below("a.b", &["a", "b", "c"]));
+        assert!(!below("a.b", &["b"]));
+        assert!(below("a[*].x", &["a", "3"]));
+        assert!(!below("a[*].x", &["a", "x"]));
+        assert!(below("a.**", &["a", "b", "c"]));
+        assert!(!below("a.**", &["b"]));
+    }
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(matches("a.*", &["a", "b"]));
+        assert!(matches("a.*", &["a", "1"]));
+        assert!(matches("a[*]", &["a", "1"]));
+        assert!(!matches("a[*]", &["a", "b"]));
+        assert!(matches("**", &[]));
+        assert!(matches("**.id", &["id"]));
+        assert!(matches("**.id", &["a", "1", "id"]));
+        assert!(matches("a.**.id", &["a", "id"]));
+        assert!(!matches("**.id", &["id", "x"]));
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        for pattern in ["a..b", "a.", "a[", "a[x]", r#"a["b"#, r#"a["b"x"#, "a[1]b"] {
+            assert!(pattern.parse::<PathMatcher>().is_err(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn displays_as_parsed() {
+        for pattern in ["a.b[1]", "**.id", r#"["a.b"][*].*"#, "a"] {
+            let matcher: PathMatcher = pattern.parse().unwrap();
+            assert_eq!(matcher.to_string(), pattern);
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use super::{KeyOrder, KeyStyle, LuaVersion, SerError, SerOptions, Serializer};
+use crate::format::{CompactFormatter, Globals, PrettyFormatter};
+use serde::Serialize;
+use std::io;
+
+/// Settings for the hosts that commonly load serialized tables.
+///
+/// Every profile picks the lua version, key style, `nil` sentinel, depth limit and the chunk
+/// the value is wrapped in.
+///
+/// ```
+/// use serde_lua_table::Profile;
+///
+/// let lua = Profile::NeovimModule.to_string(&[Some(1), None]).unwrap();
+/// assert_eq!(lua, "return {\n  1,\n  vim.NIL\n}\n");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Profile {
+    /// A World of Warcraft SavedVariables file: the entries of the outermost table are written
+    /// as globals, formatted like the game does.
+    WowSavedVariables,
+    /// A `data.lua` file for the data stage of Factorio, calling `data:extend` with the value.
+    FactorioData,
+    /// A module for OpenResty returning the value, with `ngx.null` for `nil`.
+    OpenResty,
+    /// A module for Neovim returning the value, with `vim.NIL` for `nil`.
+    NeovimModule,
+    /// The value as a compact expression with the keys sorted, so equal values are written the
+    /// same way whatever order their maps iterate in.
+    Canonical,
+}
+
+impl Profile {
+    /// Returns the options the serializer uses for this profile.
+    pub fn options(self) -> SerOptions {
+        match self {
+            Profile::WowSavedVariables => SerOptions::new().with_version(LuaVersion::Lua51),
+            Profile::FactorioData => SerOptions::new()
+                .with_version(LuaVersion::Lua52)
+                .with_key_style(KeyStyle::Bare)
+                .with_max_depth(crate::factorio::MAX_DEPTH),
+            Profile::OpenResty => SerOptions::new()
+                .with_version(LuaVersion::LuaJit)
+                .with_key_style(KeyStyle::Bare)
+                .with_nil_sentinel("ngx.null"),
+            Profile::NeovimModule => SerOptions::new()
+                .with_version(LuaVersion::LuaJit)
+                .with_key_style(KeyStyle::Bare)
+                .with_nil_sentinel("vim.NIL"),
+            Profile::Canonical => SerOptions::new().with_key_order(KeyOrder::Lexicographic),
+        }
+    }
+
+    /// Serialize the given data structure for this profile into the IO stream.
+    ///
+    /// # Errors
+    ///
+    /// Serialization can fail if `T`'s implementation of `Serialize` decides to
+    /// fail, if `T` contains a map with non-string keys or if `T` violates the
+    /// limits of the profile.
+    pub fn to_writer<W, T>(self, mut writer: W, value: &T) -> Result<(), SerError>
+    where
+        W: io::Write,
+        T: ?Sized + Serialize,
+    {
+        let options = self.options();
+        match self {
+            Profile::WowSavedVariables => {
+                let formatter = Globals::new(PrettyFormatter::with_indent(b"\t"));
+                value.serialize(&mut Serializer::with_options(writer, formatter, options))
+            }
+            Profile::FactorioData => {
+                writer.write_all(b"data:extend(")?;
+                let formatter = PrettyFormatter::new();
+                value.serialize(&mut Serializer::with_options(
+                    &mut writer,
+                    formatter,
+                    options,
+                ))?;
+                writer.write_all(b")\n")?;
+                Ok(())
+            }
+            Profile::OpenResty | Profile::NeovimModule => {
+                writer.write_all(b"return ")?;
+                let formatter = PrettyFormatter::new();
+                value.serialize(&mut Serializer::with_options(
+                    &mut writer,
+                    formatter,
+                    options,
+                ))?;
+                writer.write_all(b"\n")?;
+                Ok(())
+            }
+            Profile::Canonical => value.serialize(&mut Serializer::with_options(
+                writer,
+                CompactFormatter,
+                options,
+            )),
+        }
+    }
+
+    /// Serialize the given data structure for this profile as a String.
+    ///
+    /// # Errors
+    ///
+    /// See [`to_writer`](Self::to_writer).
+    pub fn to_string<T>(self, value: &T) -> Result<String, SerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut vec = Vec::with_capacity(128);
+        self.to_writer(&mut vec, value)?;
+        let string = unsafe {
+            // Safety: We do not emit invalid UTF-8.
+            String::from_utf8_unchecked(vec)
+        };
+        Ok(string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Profile;
+    use crate::{value::Table, SerError, Value};
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    enum Shape {
+        Circle { radius: Option<f64> },
+    }
+
+    #[test]
+    fn wraps_the_value_for_each_host() {
+        let value = BTreeMap::from([("shape", Shape::Circle { radius: None })]);
+        let written = |profile: Profile| profile.to_string(&value).unwrap();
+        assert_eq!(
+            written(Profile::WowSavedVariables),
+            "shape = {\n\t[\"Circle\"] = {\n\t\t[\"radius\"] = nil\n\t}\n}\n"
+        );
+        assert_eq!(
+            written(Profile::FactorioData),
+            "data:extend({\n  shape = {\n    Circle = {\n      radius = nil\n    }\n  }\n})\n"
+        );
+        assert_eq!(
+            written(Profile::OpenResty),
+            "return {\n  shape = {\n    Circle = {\n      radius = ngx.null\n    }\n  }\n}\n"
+        );
+        assert_eq!(
+            written(Profile::Canonical),
+            r#"{["shape"]={["Circle"]={["radius"]=nil}}}"#
+        );
+    }
+
+    #[test]
+    fn writes_canonical_maps_in_the_same_order() {
+        use std::collections::HashMap;
+
+        // Every map gets its own random hasher, so the maps iterate in different orders.
+        let map = || {
+            (0..20)
+                .map(|i| (format!("k{i}"), i))
+                .collect::<HashMap<_, _>>()
+        };
+        let first = Profile::Canonical.to_string(&map()).unwrap();
+        assert_eq!(first, Profile::Canonical.to_string(&map()).unwrap());
+        assert!(
+            first.starts_with(r#"{["k0"]=0,["k1"]=1,["k10"]=10,"#),
+            "{first}"
+        );
+        assert_eq!(
+            Profile::Canonical.to_string("\u{1}é").unwrap(),
+            r#""\001é""#
+        );
+    }
+
+    #[test]
+    fn limits_the_depth_of_tables() {
+        let wrap = |value| {
+            let mut table = Table::new();
+            table.push(value);
+            Value::Table(table)
+        };
+        let mut nested = Value::Table(Table::new());
+        for _ in 1..crate::factorio::MAX_DEPTH {
+            nested = wrap(nested);
+        }
+        assert!(Profile::FactorioData.to_string(&nested).is_ok());
+        assert!(matches!(
+            Profile::FactorioData.to_string(&wrap(nested)),
+            Err(SerError::DepthLimitExceeded(200))
+        ));
+    }
+}
@@ -0,0 +1,672 @@
+use serde::{
+    ser::{self, Impossible, SerializeStruct, SerializeStructVariant},
+    Serialize,
+};
+use std::fmt::Display;
+
+/// Wraps a value and leaves out every struct field that is equal to the same field of a default
+/// instance when serializing.
+///
+/// Fields are compared by their compact lua representation, so two values are considered equal
+/// if they serialize to the same output. Only the fields of the outermost struct are compared,
+/// nested structs are written completely unless they are equal as a whole.
+///
+/// The default instance must be a struct or a struct variant of an enum, anything else fails to
+/// serialize. Fields of a struct variant are only skipped if the value is the same variant as the
+/// default, other variants are written with all their fields.
+///
+/// ```
+/// # use serde::Serialize;
+/// #[derive(Serialize, Default)]
+/// struct Config {
+///     width: u32,
+///     title: String,
+/// }
+///
+/// let config = Config { width: 0, title: "app".to_owned() };
+/// let lua = serde_lua_table::to_string(&serde_lua_table::SkipDefaults::new(&config)).unwrap();
+/// assert_eq!(lua, r#"{["title"]="app"}"#);
+/// ```
+pub struct SkipDefaults<'a, T> {
+    value: &'a T,
+    default: DefaultInstance<'a, T>,
+}
+
+enum DefaultInstance<'a, T> {
+    Owned(T),
+    Borrowed(&'a T),
+}
+
+impl<'a, T> SkipDefaults<'a, T>
+where
+    T: Serialize + Default,
+{
+    /// Compares the fields of `value` against `T::default()`.
+    pub fn new(value: &'a T) -> Self {
+        Self {
+            value,
+            default: DefaultInstance::Owned(T::default()),
+        }
+    }
+}
+
+impl<'a, T> SkipDefaults<'a, T>
+where
+    T: Serialize,
+{
+    /// Compares the fields of `value` against the fields of `default`.
+    pub fn with_default(value: &'a T, default: &'a T) -> Self {
+        Self {
+            value,
+            default: DefaultInstance::Borrowed(default),
+        }
+    }
+
+    fn default(&self) -> &T {
+        match &self.default {
+            DefaultInstance::Owned(default) => default,
+            DefaultInstance::Borrowed(default) => default,
+        }
+    }
+}
+
+impl<'a, T> Serialize for SkipDefaults<'a, T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        let defaults = self
+            .default()
+            .serialize(FieldRecorder)
+            .map_err(ser::Error::custom)?;
+        self.value.serialize(SkipFields {
+            inner: serializer,
+            defaults: &defaults,
+        })
+    }
+}
+
+/// Renders a field value the same way it is compared against the recorded defaults.
+fn render<T>(value: &T) -> Result<Vec<u8>, crate::SerError>
+where
+    T: ?Sized + Serialize,
+{
+    crate::to_vec(value)
+}
+
+/// The rendered fields of the default instance.
+type Fields = Vec<(&'static str, Vec<u8>)>;
+
+/// The default instance, with the name of its variant if it is a struct variant.
+struct Defaults {
+    variant: Option<&'static str>,
+    fields: Fields,
+}
+
+impl Defaults {
+    /// The fields to compare a struct or variant against, none if it isn't the same kind of value.
+    fn fields_of(&self, variant: Option<&'static str>) -> &[(&'static str, Vec<u8>)] {
+        if self.variant == variant {
+            &self.fields
+        } else {
+            &[]
+        }
+    }
+}
+
+fn is_default<T>(
+    defaults: &[(&'static str, Vec<u8>)],
+    key: &'static str,
+    value: &T,
+) -> Result<bool, crate::SerError>
+where
+    T: ?Sized + Serialize,
+{
+    match defaults.iter().find(|(name, _)| *name == key) {
+        Some((_, rendered)) => Ok(render(value)? == *rendered),
+        None => Ok(false),
+    }
+}
+
+/// Collects the rendered fields of a struct.
+struct FieldRecorder;
+
+struct RecordStruct {
+    variant: Option<&'static str>,
+    fields: Fields,
+}
+
+impl SerializeStruct for RecordStruct {
+    type Ok = Defaults;
+    type Error = crate::SerError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.fields.push((key, render(value)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Defaults {
+            variant: self.variant,
+            fields: self.fields,
+        })
+    }
+}
+
+impl SerializeStructVariant for RecordStruct {
+    type Ok = Defaults;
+    type Error = crate::SerError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        SerializeStruct::end(self)
+    }
+}
+
+impl ser::Serializer for FieldRecorder {
+    type Ok = Defaults;
+    type Error = crate::SerError;
+    type SerializeSeq = Impossible<Defaults, crate::SerError>;
+    type SerializeTuple = Impossible<Defaults, crate::SerError>;
+    type SerializeTupleStruct = Impossible<Defaults, crate::SerError>;
+    type SerializeTupleVariant = Impossible<Defaults, crate::SerError>;
+    type SerializeMap = Impossible<Defaults, crate::SerError>;
+    type SerializeStruct = RecordStruct;
+    type SerializeStructVariant = RecordStruct;
+
+    fn serialize_bool(self, _v: bool) -> Result<Self::Ok, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<Self::Ok, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<Self::Ok, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<Self::Ok, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<Self::Ok, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<Self::Ok, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<Self::Ok, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<Self::Ok, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<Self::Ok, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Self::Ok, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Self::Ok, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_char(self, _v: char) -> Result<Self::Ok, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<Self::Ok, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(no_fields())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Err(no_fields())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Ok(RecordStruct {
+            variant: None,
+            fields: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Ok(RecordStruct {
+            variant: Some(variant),
+            fields: Vec::with_capacity(len),
+        })
+    }
+}
+
+fn no_fields() -> crate::SerError {
+    <crate::SerError as ser::Error>::custom("the default value must be a struct or struct variant")
+}
+
+/// Forwards everything to `inner`, except for struct fields equal to the recorded defaults.
+struct SkipFields<'d, S> {
+    inner: S,
+    defaults: &'d Defaults,
+}
+
+struct SkipStruct<'d, S> {
+    inner: S,
+    defaults: &'d [(&'static str, Vec<u8>)],
+}
+
+fn custom<E: ser::Error>(err: impl Display) -> E {
+    E::custom(err)
+}
+
+impl<'d, S> SerializeStruct for SkipStruct<'d, S>
+where
+    S: SerializeStruct,
+{
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        if is_default(self.defaults, key, value).map_err(custom)? {
+            self.inner.skip_field(key)
+        } else {
+            self.inner.serialize_field(key, value)
+        }
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.inner.end()
+    }
+}
+
+impl<'d, S> SerializeStructVariant for SkipStruct<'d, S>
+where
+    S: SerializeStructVariant,
+{
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        if is_default(self.defaults, key, value).map_err(custom)? {
+            self.inner.skip_field(key)
+        } else {
+            self.inner.serialize_field(key, value)
+        }
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.inner.end()
+    }
+}
+
+impl<'d, S> ser::Serializer for SkipFields<'d, S>
+where
+    S: ser::Serializer,
+{
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = S::SerializeSeq;
+    type SerializeTuple = S::SerializeTuple;
+    type SerializeTupleStruct = S::SerializeTupleStruct;
+    type SerializeTupleVariant = S::SerializeTupleVariant;
+    type SerializeMap = S::SerializeMap;
+    type SerializeStruct = SkipStruct<'d, S::SerializeStruct>;
+    type SerializeStructVariant = SkipStruct<'d, S::SerializeStructVariant>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_bool(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_i8(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_i16(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_i32(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_i64(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_u8(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_u16(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_u32(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_u64(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_f32(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_f64(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_char(v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_str(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_bytes(v)
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_none()
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.inner.serialize_some(value)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_unit()
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.inner.serialize_unit_struct(name)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.inner
+            .serialize_unit_variant(name, variant_index, variant)
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.inner.serialize_newtype_struct(name, value)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.inner
+            .serialize_newtype_variant(name, variant_index, variant, value)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        self.inner.serialize_seq(len)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.inner.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.inner.serialize_tuple_struct(name, len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        self.inner
+            .serialize_tuple_variant(name, variant_index, variant, len)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        self.inner.serialize_map(len)
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Ok(SkipStruct {
+            inner: self.inner.serialize_struct(name, len)?,
+            defaults: self.defaults.fields_of(None),
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Ok(SkipStruct {
+            inner: self
+                .inner
+                .serialize_struct_variant(name, variant_index, variant, len)?,
+            defaults: self.defaults.fields_of(Some(variant)),
+        })
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Default)]
+    struct Window {
+        width: u32,
+        title: String,
+        size: (u32, u32),
+    }
+
+    #[derive(Serialize)]
+    enum Shape {
+        Circle { radius: f64, filled: bool },
+        Rect { width: f64, filled: bool },
+    }
+
+    #[test]
+    fn skips_default_fields_and_keeps_changed_ones() {
+        let window = Window {
+            width: 0,
+            title: "app".to_owned(),
+            size: (0, 0),
+        };
+        let lua = crate::to_string(&SkipDefaults::new(&window)).unwrap();
+        assert_eq!(lua, r#"{["title"]="app"}"#);
+
+        let window = Window {
+            width: 0,
+            title: String::new(),
+            size: (0, 1),
+        };
+        let lua = crate::to_string(&SkipDefaults::new(&window)).unwrap();
+        assert_eq!(lua, r#"{["size"]={0,1}}"#);
+    }
+
+    #[test]
+    fn skips_fields_of_the_same_struct_variant() {
+        let default = Shape::Circle {
+            radius: 1.0,
+            filled: false,
+        };
+        let circle = Shape::Circle {
+            radius: 2.0,
+            filled: false,
+        };
+        let lua = crate::to_string(&SkipDefaults::with_default(&circle, &default)).unwrap();
+        assert_eq!(lua, r#"{["Circle"]={["radius"]=2.0}}"#);
+    }
+
+    #[test]
+    fn keeps_all_fields_of_other_variants() {
+        let default = Shape::Circle {
+            radius: 1.0,
+            filled: false,
+        };
+        let rect = Shape::Rect {
+            width: 1.0,
+            filled: false,
+        };
+        let lua = crate::to_string(&SkipDefaults::with_default(&rect, &default)).unwrap();
+        assert_eq!(lua, r#"{["Rect"]={["width"]=1.0,["filled"]=false}}"#);
+    }
+
+    #[test]
+    fn rejects_defaults_without_fields() {
+        let err = crate::to_string(&SkipDefaults::new(&5u32)).unwrap_err();
+        assert!(err.to_string().contains("struct"), "{err}");
+    }
+}
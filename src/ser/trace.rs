@@ -0,0 +1,145 @@
+//! Reporting top-level serialization to `tracing`.
+
+use super::{SerError, Serializer};
+use crate::format::Formatter;
+use serde::Serialize;
+use std::io;
+use tracing::field::Empty;
+
+/// What a serializer has written so far.
+#[derive(Default)]
+pub(crate) struct Stats {
+    /// Number of tables opened.
+    pub(crate) tables: usize,
+    /// The deepest nesting of tables.
+    pub(crate) max_depth: usize,
+}
+
+/// Serializes `value` inside a `serialize` span, which records the size of the output, the
+/// number of tables and how deeply they are nested. Subscribers measure the duration of the span.
+pub(crate) fn serialize<W, F, T>(
+    ser: &mut Serializer<W, F>,
+    value: &T,
+    pretty: bool,
+) -> Result<(), SerError>
+where
+    W: io::Write,
+    F: Formatter,
+    T: ?Sized + Serialize,
+{
+    let span = tracing::debug_span!(
+        "serialize",
+        pretty,
+        bytes = Empty,
+        tables = Empty,
+        max_depth = Empty
+    )
+    .entered();
+    let result = value.serialize(&mut *ser);
+    span.record("bytes", ser.writer.written());
+    span.record("tables", ser.stats.tables);
+    span.record("max_depth", ser.stats.max_depth);
+    if let Err(error) = &result {
+        tracing::debug!(%error, "serialization failed");
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fmt,
+        sync::{Arc, Mutex},
+    };
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Metadata, Subscriber,
+    };
+
+    /// Records the fields of every span and event as `name=value`.
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl Visit for Recorder {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("{}={value:?}", field.name()));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+            span.record(&mut self.clone());
+            span::Id::from_u64(1)
+        }
+
+        fn record(&self, _: &span::Id, values: &span::Record<'_>) {
+            values.record(&mut self.clone());
+        }
+
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            event.record(&mut self.clone());
+        }
+
+        fn enter(&self, _: &span::Id) {}
+
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    fn record(f: impl FnOnce()) -> Vec<String> {
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), f);
+        let fields = recorder.0.lock().unwrap().clone();
+        fields
+    }
+
+    #[test]
+    fn records_the_size_of_the_output() {
+        let fields = record(|| {
+            assert_eq!(
+                crate::to_string(&vec![vec![1], vec![2]]).unwrap(),
+                "{{1},{2}}"
+            );
+        });
+        assert_eq!(
+            fields,
+            ["pretty=false", "bytes=9", "tables=3", "max_depth=2"]
+        );
+    }
+
+    #[test]
+    fn records_failures() {
+        let fields = record(|| {
+            assert!(crate::to_string_pretty(&f64::NAN).is_ok());
+            assert!(crate::to_string(
+                &[((), 1)]
+                    .into_iter()
+                    .collect::<std::collections::BTreeMap<_, _>>()
+            )
+            .is_err());
+        });
+        assert_eq!(
+            fields,
+            [
+                "pretty=true",
+                "bytes=3",
+                "tables=0",
+                "max_depth=0",
+                "pretty=false",
+                "bytes=1",
+                "tables=1",
+                "max_depth=1",
+                "message=serialization failed",
+                "error=Object key must be a string or a number",
+            ]
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use std::io;
+
+/// The writer of [`to_vec`](crate::to_vec) and the functions built on it.
+///
+/// Appending to a `Vec` can't fail, so every method returns `Ok` after an inlined append. The
+/// serializer's error checks on writes compile away, and the buffer is owned by the serializer
+/// instead of being reached through a reference on every write.
+pub(crate) struct VecWriter(pub(crate) Vec<u8>);
+
+impl VecWriter {
+    #[inline]
+    pub(crate) fn new() -> Self {
+        VecWriter(Vec::with_capacity(128))
+    }
+}
+
+impl io::Write for VecWriter {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    #[inline(always)]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.0.extend_from_slice(buf);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{to_vec, to_vec_pretty, Serializer};
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn writes_like_a_plain_vec() {
+        let value = BTreeMap::from([("a\nb", vec![Some(1.5), None]), ("c", vec![])]);
+        let mut ser = Serializer::new(Vec::new());
+        value.serialize(&mut ser).unwrap();
+        assert_eq!(to_vec(&value).unwrap(), ser.into_inner());
+
+        let mut ser = Serializer::pretty(Vec::new());
+        value.serialize(&mut ser).unwrap();
+        assert_eq!(to_vec_pretty(&value).unwrap(), ser.into_inner());
+    }
+}
@@ -0,0 +1,229 @@
+//! A trailing HMAC comment to reject files that were modified without the key.
+//!
+//! Works like the [`checksum`](crate::checksum) comment, but the HMAC-SHA256 can only be computed
+//! with the caller's key, so whoever edits the file can't write a matching comment. The comment
+//! is `-- hmac-sha256: <hex>` and covers the text before it in the form
+//! [`normalize_str`](crate::normalize_str) writes it, so reformatting the file keeps the
+//! signature valid.
+//!
+//! ```
+//! use serde_lua_table::signature;
+//!
+//! let key = b"server secret";
+//! let written = signature::to_string(&[1, 2, 3], key).unwrap();
+//! assert!(written.starts_with("{1,2,3}\n-- hmac-sha256: "));
+//! assert!(signature::verify_signature(&written, key).is_ok());
+//!
+//! let reformatted = written.replace("{1,2,3}", "{\n  1,\n  2,\n  3,\n}");
+//! assert!(signature::verify_signature(&reformatted, key).is_ok());
+//!
+//! let edited = written.replace('3', "4");
+//! assert!(signature::verify_signature(&edited, key).is_err());
+//! assert!(signature::verify_signature(&written, b"other key").is_err());
+//! ```
+
+use crate::{SerError, Serializer};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::io;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const PREFIX: &str = "\n-- hmac-sha256: ";
+
+/// Why a signature couldn't be verified.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum SignatureError {
+    #[error("no signature comment found")]
+    Missing,
+    #[error("malformed signature {0:?}")]
+    Malformed(String),
+    /// The text before the comment isn't lua data, so it has no normalized form to sign.
+    #[error("the signed text can't be read: {0}")]
+    Unreadable(String),
+    /// The text was changed after it was signed, or it was signed with a different key.
+    #[error("signature mismatch")]
+    Mismatch,
+}
+
+/// Passes writes through to the inner writer and keeps them to sign their normalized form.
+pub struct SigningWriter<W> {
+    inner: W,
+    written: Vec<u8>,
+    mac: HmacSha256,
+}
+
+impl<W> SigningWriter<W>
+where
+    W: io::Write,
+{
+    pub fn new(inner: W, key: &[u8]) -> Self {
+        SigningWriter {
+            inner,
+            written: Vec::new(),
+            mac: HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length"),
+        }
+    }
+
+    /// Appends the signature comment and returns the inner writer.
+    ///
+    /// # Errors
+    ///
+    /// Fails if writing fails or if the text written so far isn't lua data.
+    pub fn finish(mut self) -> Result<W, SerError> {
+        let normalized = std::str::from_utf8(&self.written)
+            .map_err(|e| e.to_string())
+            .and_then(|text| crate::normalize_str(text).map_err(|e| e.to_string()))
+            .map_err(SerError::Custom)?;
+        self.mac.update(normalized.as_bytes());
+        let tag = self.mac.finalize().into_bytes();
+        self.inner.write_all(PREFIX.as_bytes())?;
+        for byte in tag {
+            write!(self.inner, "{:02x}", byte)?;
+        }
+        self.inner.write_all(b"\n")?;
+        Ok(self.inner)
+    }
+}
+
+impl<W> io::Write for SigningWriter<W>
+where
+    W: io::Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written.extend_from_slice(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Serialize the given data structure as a string of lua followed by its signature with `key`.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with non-string keys.
+pub fn to_string<T>(value: &T, key: &[u8]) -> Result<String, SerError>
+where
+    T: ?Sized + Serialize,
+{
+    let mut writer = SigningWriter::new(Vec::with_capacity(128), key);
+    value.serialize(&mut Serializer::new(&mut writer))?;
+    into_string(writer)
+}
+
+/// Serialize the given data structure as a pretty-printed string of lua followed by its
+/// signature with `key`.
+///
+/// # Errors
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// fail, or if `T` contains a map with non-string keys.
+pub fn to_string_pretty<T>(value: &T, key: &[u8]) -> Result<String, SerError>
+where
+    T: ?Sized + Serialize,
+{
+    let mut writer = SigningWriter::new(Vec::with_capacity(128), key);
+    value.serialize(&mut Serializer::pretty(&mut writer))?;
+    into_string(writer)
+}
+
+fn into_string(writer: SigningWriter<Vec<u8>>) -> Result<String, SerError> {
+    let vec = writer.finish()?;
+    let string = unsafe {
+        // Safety: We do not emit invalid UTF-8 and the comment is ASCII.
+        String::from_utf8_unchecked(vec)
+    };
+    Ok(string)
+}
+
+/// Checks that `text` ends with a signature comment made with `key` over the normalized text
+/// before it.
+///
+/// The comparison takes the same time no matter where the signatures differ.
+///
+/// # Errors
+///
+/// Fails if there is no signature comment, it or the text before it can't be read, or it
+/// doesn't match.
+pub fn verify_signature(text: &str, key: &[u8]) -> Result<(), SignatureError> {
+    let (body, comment) = text.rsplit_once(PREFIX).ok_or(SignatureError::Missing)?;
+    let hex = comment.trim_end();
+    let tag = decode_hex(hex).ok_or_else(|| SignatureError::Malformed(hex.to_owned()))?;
+    let normalized =
+        crate::normalize_str(body).map_err(|e| SignatureError::Unreadable(e.to_string()))?;
+
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(normalized.as_bytes());
+    mac.verify_slice(&tag).map_err(|_| SignatureError::Mismatch)
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_string, to_string_pretty, verify_signature, SignatureError, SigningWriter};
+    use std::io::Write;
+
+    #[test]
+    fn signs_the_normalized_text_with_hmac_sha256() {
+        let mut writer = SigningWriter::new(Vec::new(), b"key");
+        writer
+            .write_all(b"{ fox = 'The quick brown fox jumps over the lazy dog' } -- note")
+            .unwrap();
+        let signed = String::from_utf8(writer.finish().unwrap()).unwrap();
+        // The HMAC of `{["fox"]="The quick brown fox jumps over the lazy dog"}`.
+        assert!(signed.ends_with(
+            "\n-- hmac-sha256: e112528ff088d8c60d90219ca3961cd2ffe4428609ed91b4959d3f2953932811\n"
+        ));
+        verify_signature(&signed, b"key").unwrap();
+
+        let mut writer = SigningWriter::new(Vec::new(), b"key");
+        writer.write_all(b"not lua").unwrap();
+        assert!(writer.finish().is_err());
+    }
+
+    #[test]
+    fn rejects_other_keys_and_changed_files() {
+        let value = vec![("name", 1)];
+        for lua in [
+            to_string(&value, b"secret").unwrap(),
+            to_string_pretty(&value, b"secret").unwrap(),
+        ] {
+            verify_signature(&lua, b"secret").unwrap();
+            assert_eq!(
+                verify_signature(&lua, b"other"),
+                Err(SignatureError::Mismatch)
+            );
+            assert_eq!(
+                verify_signature(&lua.replace('1', "2"), b"secret"),
+                Err(SignatureError::Mismatch)
+            );
+        }
+        assert_eq!(verify_signature("{}", b"k"), Err(SignatureError::Missing));
+        assert_eq!(
+            verify_signature("{}\n-- hmac-sha256: abc", b"k"),
+            Err(SignatureError::Malformed("abc".to_owned()))
+        );
+        assert_eq!(
+            verify_signature("{}\n-- hmac-sha256: abcd", b"k"),
+            Err(SignatureError::Mismatch)
+        );
+        assert!(matches!(
+            verify_signature("{\n-- hmac-sha256: abcd", b"k"),
+            Err(SignatureError::Unreadable(_))
+        ));
+        let lua = to_string_pretty(&value, b"secret").unwrap();
+        verify_signature(&crate::format_str(&lua).unwrap(), b"secret").unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+//! Embedding serialized values into hand-written lua files.
+//!
+//! A template is lua source with markers like `--@data:settings@--` where values go. The marker
+//! is a comment, so the template itself is valid lua and can be edited with lua tooling.
+//!
+//! ```
+//! use serde_lua_table::template::Template;
+//! use std::collections::BTreeMap;
+//!
+//! let source = "local M = {}\n\nM.defaults = --@data:defaults@--\n\nreturn M\n";
+//! let rendered = Template::new(source)
+//!     .insert("defaults", &BTreeMap::from([("volume", 80)]))
+//!     .unwrap()
+//!     .render()
+//!     .unwrap();
+//!
+//! assert_eq!(
+//!     rendered,
+//!     "local M = {}\n\nM.defaults = {\n  [\"volume\"] = 80\n}\n\nreturn M\n"
+//! );
+//! ```
+
+use crate::{to_string_pretty, SerError};
+use serde::Serialize;
+use std::collections::HashMap;
+
+const MARKER_START: &str = "--@data:";
+const MARKER_END: &str = "@--";
+
+/// Why a template couldn't be rendered.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum TemplateError {
+    /// The template has a marker for a value that wasn't inserted.
+    #[error("no value for the marker {0:?}")]
+    MissingValue(String),
+    #[error("unterminated marker at byte {0}")]
+    UnterminatedMarker(usize),
+}
+
+/// Lua source with markers that are replaced by serialized values.
+pub struct Template<'a> {
+    source: &'a str,
+    values: HashMap<String, String>,
+}
+
+impl<'a> Template<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Template {
+            source,
+            values: HashMap::new(),
+        }
+    }
+
+    /// Serializes `value` pretty-printed for the markers called `name`.
+    ///
+    /// # Errors
+    ///
+    /// Serialization can fail if `T`'s implementation of `Serialize` decides to
+    /// fail, or if `T` contains a map with non-string keys.
+    pub fn insert<T>(mut self, name: &str, value: &T) -> Result<Self, SerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.values
+            .insert(name.to_owned(), to_string_pretty(value)?);
+        Ok(self)
+    }
+
+    /// Replaces every marker with its value.
+    ///
+    /// Lines after the first line of a value are indented like the line of the marker, so values
+    /// inside nested code line up.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a marker isn't closed or no value was inserted for it.
+    pub fn render(&self) -> Result<String, TemplateError> {
+        let mut out = String::with_capacity(self.source.len());
+        let mut rest = self.source;
+        let mut offset = 0;
+        while let Some(start) = rest.find(MARKER_START) {
+            out.push_str(&rest[..start]);
+            let name_start = start + MARKER_START.len();
+            let name_len = rest[name_start..]
+                .find(MARKER_END)
+                .ok_or(TemplateError::UnterminatedMarker(offset + start))?;
+            let name = &rest[name_start..name_start + name_len];
+            let value = self
+                .values
+                .get(name)
+                .ok_or_else(|| TemplateError::MissingValue(name.to_owned()))?;
+
+            let indent = line_indent(&out);
+            for (i, line) in value.split('\n').enumerate() {
+                if i > 0 {
+                    out.push('\n');
+                    out.push_str(&indent);
+                }
+                out.push_str(line);
+            }
+
+            let end = name_start + name_len + MARKER_END.len();
+            offset += end;
+            rest = &rest[end..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+}
+
+/// Returns the leading whitespace of the last line of `out`.
+fn line_indent(out: &str) -> String {
+    let line = &out[out.rfind('\n').map_or(0, |i| i + 1)..];
+    line.chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Template, TemplateError};
+
+    #[test]
+    fn indents_values_like_the_marker_line() {
+        let source = "return {\n\tlist = --@data:list@--,\n\tagain = --@data:list@--,\n}";
+        let rendered = Template::new(source)
+            .insert("list", &[1, 2])
+            .unwrap()
+            .render()
+            .unwrap();
+        assert_eq!(
+            rendered,
+            "return {\n\tlist = {\n\t  1,\n\t  2\n\t},\n\tagain = {\n\t  1,\n\t  2\n\t},\n}"
+        );
+    }
+
+    #[test]
+    fn reports_missing_values_and_unterminated_markers() {
+        let template = Template::new("a = --@data:a@--\nb = --@data:b@--")
+            .insert("a", &1)
+            .unwrap();
+        assert_eq!(
+            template.render(),
+            Err(TemplateError::MissingValue("b".to_owned()))
+        );
+        let template = Template::new("a = --@data:a@-- b = --@data:b")
+            .insert("a", &1)
+            .unwrap();
+        assert_eq!(
+            template.render(),
+            Err(TemplateError::UnterminatedMarker(21))
+        );
+    }
+}
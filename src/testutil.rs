@@ -0,0 +1,205 @@
+//! Conformance checks for downstream tests.
+//!
+//! [`assert_loads_in_lua`] runs the serialized output in an embedded Lua 5.4 VM provided by
+//! `mlua`, so this feature can't be combined with an `mlua` dependency built for a different lua
+//! version. [`assert_roundtrip`] reads the output back with this crate's deserializer.
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Config {
+//!     name: String,
+//!     scale: f64,
+//!     tags: Vec<String>,
+//! }
+//!
+//! serde_lua_table::testutil::assert_loads_in_lua(&Config {
+//!     name: "main \"window\"".to_owned(),
+//!     scale: 1.5,
+//!     tags: vec!["a".to_owned(), "b".to_owned()],
+//! });
+//! ```
+
+use crate::{PrettyFormatter, SerOptions, Serializer};
+use ::mlua::{Lua, LuaSerdeExt, Value};
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt::{Debug, Write};
+
+/// Asserts that `value` serializes to a lua table that loads in a lua VM and reads back as
+/// `value`.
+///
+/// Both the compact and the pretty-printed output are checked.
+///
+/// # Panics
+///
+/// Panics if serialization fails, if lua rejects the output, or if the table lua produced doesn't
+/// deserialize to a value equal to `value`.
+pub fn assert_loads_in_lua<T>(value: &T)
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let lua = Lua::new();
+    for pretty in [false, true] {
+        let chunk = if pretty {
+            crate::to_string_pretty(value)
+        } else {
+            crate::to_string(value)
+        }
+        .expect("value failed to serialize");
+        assert_reads_back(&lua, value, &chunk);
+    }
+}
+
+/// Asserts that `value` written with `options` reads back as `value` with [`from_str`], showing a
+/// line diff of the two values if it doesn't.
+///
+/// Options whose output isn't plain data, like a nil sentinel, don't read back.
+///
+/// [`from_str`]: crate::from_str
+///
+/// ```
+/// use serde_lua_table::{testutil, KeyOrder, KeyStyle, SerOptions};
+/// use std::collections::HashMap;
+///
+/// let scores = HashMap::from([("ann".to_owned(), 3), ("bob".to_owned(), 5)]);
+/// testutil::assert_roundtrip(
+///     &scores,
+///     SerOptions::new()
+///         .with_key_style(KeyStyle::Bare)
+///         .with_key_order(KeyOrder::Lexicographic),
+/// );
+/// ```
+///
+/// # Panics
+///
+/// Panics if serialization fails, or if the output doesn't deserialize to a value equal to
+/// `value`.
+pub fn assert_roundtrip<T>(value: &T, options: SerOptions)
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let mut ser = Serializer::with_options(Vec::new(), PrettyFormatter::new(), options);
+    value
+        .serialize(&mut ser)
+        .expect("value failed to serialize");
+    let chunk = String::from_utf8(ser.into_inner()).expect("output is valid UTF-8");
+    let read_back: T = match crate::from_str(&chunk) {
+        Ok(read_back) => read_back,
+        Err(e) => panic!("the output failed to deserialize: {}\n{}", e, chunk),
+    };
+    assert_equal(value, &read_back, &chunk);
+}
+
+fn assert_reads_back<T>(lua: &Lua, value: &T, chunk: &str)
+where
+    T: DeserializeOwned + PartialEq + Debug,
+{
+    let loaded: Value = match lua.load(format!("return {}", chunk)).eval() {
+        Ok(loaded) => loaded,
+        Err(e) => panic!("lua failed to load the output: {}\n{}", e, chunk),
+    };
+    let read_back: T = match lua.from_value(loaded) {
+        Ok(read_back) => read_back,
+        Err(e) => panic!("the loaded table failed to deserialize: {}\n{}", e, chunk),
+    };
+    assert_equal(value, &read_back, chunk);
+}
+
+/// Panics with a line diff if `read_back` differs from `value`.
+fn assert_equal<T>(value: &T, read_back: &T, chunk: &str)
+where
+    T: PartialEq + Debug,
+{
+    if read_back != value {
+        let diff = line_diff(&format!("{:#?}", value), &format!("{:#?}", read_back));
+        panic!(
+            "the value read back differs (- expected, + read back)\n{}\n{}",
+            diff, chunk
+        );
+    }
+}
+
+/// Returns the lines of `expected` and `actual` prefixed with `-`, `+` or a space, depending on
+/// whether they are only in one of them or in both.
+fn line_diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<_> = expected.lines().collect();
+    let actual: Vec<_> = actual.lines().collect();
+
+    // lengths of the longest common subsequences of the suffixes
+    let mut lcs = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lcs[i][j] = if expected[i] == actual[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            writeln!(out, "  {}", expected[i]).unwrap();
+            i += 1;
+            j += 1;
+        } else if j < actual.len() && (i == expected.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            writeln!(out, "+ {}", actual[j]).unwrap();
+            j += 1;
+        } else {
+            writeln!(out, "- {}", expected[i]).unwrap();
+            i += 1;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{KeyOrder, KeyStyle, LuaVersion};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn diffs_lines() {
+        assert_eq!(line_diff("a\nb\nc", "a\nc\nd"), "  a\n- b\n  c\n+ d\n");
+    }
+
+    #[test]
+    fn loads_strings_and_nested_tables_in_lua() {
+        let value = BTreeMap::from([
+            ("quotes \"\\\n]]".to_owned(), vec!["\0tab\t".to_owned()]),
+            ("nested".to_owned(), vec![]),
+        ]);
+        assert_loads_in_lua(&value);
+    }
+
+    #[test]
+    #[should_panic(expected = "the value read back differs")]
+    fn reports_values_that_lua_reads_differently() {
+        assert_loads_in_lua(&f64::NAN);
+    }
+
+    #[test]
+    fn round_trips_with_options() {
+        let value = BTreeMap::from([
+            ("end".to_owned(), vec![1.5, -2.0]),
+            ("a".to_owned(), vec![]),
+        ]);
+        assert_roundtrip(
+            &value,
+            SerOptions::new()
+                .with_version(LuaVersion::Lua51)
+                .with_key_style(KeyStyle::Bare)
+                .with_key_order(KeyOrder::Natural),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "the value read back differs")]
+    fn reports_values_that_read_back_differently() {
+        assert_roundtrip(&Some(()), SerOptions::new());
+    }
+}
@@ -0,0 +1,163 @@
+use super::{Record, Shape};
+use crate::{
+    value::{Key, Table, Value},
+    KeyCase, LuaVersion,
+};
+
+/// Infers the shape of an untyped value, for example data loaded from an addon's saved
+/// variables.
+///
+/// Tables with only string keys become records named after the field they are in, the root is
+/// called `Root`. Records with the same name are merged, so fields missing from some elements of
+/// a sequence become optional. To infer the shape of several samples, merge their shapes with
+/// [`Shape::merge`].
+///
+/// ```
+/// use serde_lua_table::{
+///     typegen,
+///     value::{Table, Value},
+/// };
+///
+/// let player = |name: &str, level: Option<i64>| {
+///     let mut player = Table::new();
+///     player.insert("name", Value::from(name));
+///     player.insert("level", Value::from(level));
+///     Value::from(player)
+/// };
+/// let mut root = Table::new();
+/// root.insert(
+///     "players",
+///     Value::from(Table::from_iter([player("a", Some(3)), player("b", None)])),
+/// );
+///
+/// assert_eq!(
+///     typegen::to_luals(&typegen::infer_schema(&Value::from(root))),
+///     "---@meta\n\n---@class Players\n---@field name string\n---@field level? integer\n\n\
+///      ---@class Root\n---@field players Players[]\n"
+/// );
+/// ```
+pub fn infer_schema(value: &Value) -> Shape {
+    infer(value, "Root")
+}
+
+/// Infers the shape of `value` like [`infer_schema`], with the root record called `name`.
+pub(crate) fn infer(value: &Value, name: &str) -> Shape {
+    match value {
+        Value::Nil => Shape::Nil,
+        Value::Boolean(_) => Shape::Boolean,
+        Value::Integer(_) => Shape::Integer,
+        Value::Number(_) => Shape::Number,
+        Value::RawNumber(_) if value.as_i64().is_some() => Shape::Integer,
+        Value::RawNumber(_) => Shape::Number,
+        Value::String(_) | Value::Bytes(_) => Shape::String,
+        Value::Table(table) => infer_table(table, name),
+    }
+}
+
+fn infer_table(table: &Table, name: &str) -> Shape {
+    if table.is_empty() {
+        return Shape::Unknown;
+    }
+    let string_keys = table
+        .fields()
+        .iter()
+        .all(|(key, _)| matches!(key, Key::String(_)));
+
+    if table.fields().is_empty() {
+        let element = merge_all(table.sequence().iter().map(|v| infer(v, name)));
+        Shape::Array(Box::new(element))
+    } else if table.sequence().is_empty() && string_keys {
+        let fields = table
+            .fields()
+            .iter()
+            .filter_map(|(key, value)| match key {
+                Key::String(key) => Some((key.to_string(), infer(value, &record_name(key)))),
+                Key::Integer(_) => None,
+            })
+            .collect();
+        Shape::Record(Record {
+            name: name.to_owned(),
+            fields,
+        })
+    } else {
+        let key = merge_all(table.iter().map(|(key, _)| match key {
+            Key::Integer(_) => Shape::Integer,
+            Key::String(_) => Shape::String,
+        }));
+        let value = merge_all(table.iter().map(|(_, v)| infer(v, name)));
+        Shape::Map(Box::new(key), Box::new(value))
+    }
+}
+
+fn merge_all(shapes: impl Iterator<Item = Shape>) -> Shape {
+    shapes.fold(Shape::Unknown, Shape::merge)
+}
+
+/// Returns the name of the record stored at the field `key`.
+fn record_name(key: &str) -> String {
+    let name = KeyCase::PascalCase.apply(key).into_owned();
+    if LuaVersion::Lua54.is_identifier(&name) {
+        name
+    } else {
+        "Record".to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::infer_schema;
+    use crate::typegen::{Record, Shape};
+    use crate::Value;
+
+    fn infer(lua: &str) -> Shape {
+        infer_schema(&crate::from_str::<Value>(lua).unwrap())
+    }
+
+    #[test]
+    fn infers_arrays_maps_and_scalars() {
+        assert_eq!(
+            infer("{ 1, 2.5, 3 }"),
+            Shape::Array(Box::new(Shape::Number))
+        );
+        assert_eq!(infer("{}"), Shape::Unknown);
+        assert_eq!(
+            infer("{ [10] = 'a', [20] = 'b' }"),
+            Shape::Map(Box::new(Shape::Integer), Box::new(Shape::String))
+        );
+        assert_eq!(
+            infer("{ 'a', x = true }"),
+            Shape::Map(Box::new(Shape::Any), Box::new(Shape::Any))
+        );
+        assert_eq!(infer("'text'"), Shape::String);
+    }
+
+    #[test]
+    fn names_records_after_their_field() {
+        let record = |name: &str, fields: Vec<(&str, Shape)>| {
+            Shape::Record(Record {
+                name: name.to_owned(),
+                fields: fields
+                    .into_iter()
+                    .map(|(name, shape)| (name.to_owned(), shape))
+                    .collect(),
+            })
+        };
+        assert_eq!(
+            infer("{ window_size = { w = 1 }, ['1st'] = { x = 'a' }, items = { { n = 1 }, {} } }"),
+            record(
+                "Root",
+                vec![
+                    (
+                        "window_size",
+                        record("WindowSize", vec![("w", Shape::Integer)])
+                    ),
+                    ("1st", record("Record", vec![("x", Shape::String)])),
+                    (
+                        "items",
+                        Shape::Array(Box::new(record("Items", vec![("n", Shape::Integer)])))
+                    ),
+                ]
+            )
+        );
+    }
+}
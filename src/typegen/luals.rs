@@ -0,0 +1,113 @@
+use super::{Record, Shape};
+use crate::LuaVersion;
+use std::fmt::Write;
+
+/// Renders a lua-language-server stub file with a `---@class` block for every record in `shape`.
+///
+/// ```
+/// use serde::Serialize;
+/// use serde_lua_table::typegen;
+///
+/// #[derive(Serialize)]
+/// struct Keybind {
+///     key: String,
+///     modifiers: Option<Vec<String>>,
+/// }
+///
+/// let shape = typegen::shape_of(&Keybind {
+///     key: "F5".to_owned(),
+///     modifiers: Some(vec!["ctrl".to_owned()]),
+/// })
+/// .unwrap();
+///
+/// assert_eq!(
+///     typegen::to_luals(&shape),
+///     "---@meta\n\n---@class Keybind\n---@field key string\n---@field modifiers? string[]\n"
+/// );
+/// ```
+pub fn to_luals(shape: &Shape) -> String {
+    let mut out = String::from("---@meta\n");
+    for record in Record::collect(shape) {
+        writeln!(out, "\n---@class {}", record.name).unwrap();
+        for (name, field) in &record.fields {
+            out.push_str("---@field ");
+            if LuaVersion::Lua54.is_identifier(name) {
+                out.push_str(name);
+            } else {
+                write!(out, "[{:?}]", name).unwrap();
+            }
+            match field {
+                Shape::Optional(inner) => writeln!(out, "? {}", type_name(inner)).unwrap(),
+                other => writeln!(out, " {}", type_name(other)).unwrap(),
+            }
+        }
+    }
+    out
+}
+
+fn type_name(shape: &Shape) -> String {
+    match shape {
+        Shape::Unknown | Shape::Any => "any".to_owned(),
+        Shape::Nil => "nil".to_owned(),
+        Shape::Boolean => "boolean".to_owned(),
+        Shape::Integer => "integer".to_owned(),
+        Shape::Number => "number".to_owned(),
+        Shape::String => "string".to_owned(),
+        Shape::Optional(inner) => format!("{}?", type_name(inner)),
+        Shape::Array(element) => match **element {
+            Shape::Optional(_) => format!("({})[]", type_name(element)),
+            _ => format!("{}[]", type_name(element)),
+        },
+        Shape::Tuple(elements) => {
+            let elements: Vec<_> = elements.iter().map(type_name).collect();
+            format!("[{}]", elements.join(", "))
+        }
+        Shape::Map(key, value) => format!("table<{}, {}>", type_name(key), type_name(value)),
+        Shape::Record(record) => record.name.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_luals;
+    use crate::typegen::{Record, Shape};
+
+    #[test]
+    fn renders_optional_fields_and_nested_types() {
+        let point = Record {
+            name: "Point".to_owned(),
+            fields: vec![("x".to_owned(), Shape::Number)],
+        };
+        let shape = Shape::Record(Record {
+            name: "Path".to_owned(),
+            fields: vec![
+                (
+                    "points".to_owned(),
+                    Shape::Array(Box::new(Shape::Optional(Box::new(Shape::Record(point))))),
+                ),
+                (
+                    "two words".to_owned(),
+                    Shape::Optional(Box::new(Shape::Tuple(vec![Shape::Integer, Shape::Any]))),
+                ),
+                (
+                    "names".to_owned(),
+                    Shape::Map(Box::new(Shape::String), Box::new(Shape::Boolean)),
+                ),
+            ],
+        });
+        assert_eq!(
+            to_luals(&shape),
+            "---@meta\n\n---@class Point\n---@field x number\n\n\
+             ---@class Path\n---@field points (Point?)[]\n---@field [\"two words\"]? [integer, any]\n\
+             ---@field names table<string, boolean>\n"
+        );
+    }
+
+    #[test]
+    fn renders_only_the_header_without_records() {
+        assert_eq!(
+            to_luals(&Shape::Array(Box::new(Shape::Integer))),
+            "---@meta\n"
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use super::{Record, Shape};
+use crate::LuaVersion;
+use std::fmt::Write;
+
+/// Renders an `export type` declaration for every record in `shape`.
+///
+/// Luau has no integer type and no tuple tables, so integers become `number` and tuples become
+/// arrays of the union of their element types.
+///
+/// ```
+/// use serde::Serialize;
+/// use serde_lua_table::typegen;
+///
+/// #[derive(Serialize)]
+/// struct Spawn {
+///     model: String,
+///     position: (f32, f32, f32),
+///     team: Option<u8>,
+/// }
+///
+/// let shape = typegen::shape_of(&Spawn {
+///     model: "Soldier".to_owned(),
+///     position: (0.0, 5.0, 0.0),
+///     team: Some(1),
+/// })
+/// .unwrap();
+///
+/// assert_eq!(
+///     typegen::to_luau(&shape),
+///     "export type Spawn = {\n\tmodel: string,\n\tposition: {number},\n\tteam: number?,\n}\n"
+/// );
+/// ```
+pub fn to_luau(shape: &Shape) -> String {
+    let mut out = String::new();
+    for record in Record::collect(shape) {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        writeln!(out, "export type {} = {{", record.name).unwrap();
+        for (name, field) in &record.fields {
+            if LuaVersion::Luau.is_identifier(name) {
+                writeln!(out, "\t{}: {},", name, type_name(field)).unwrap();
+            } else {
+                writeln!(out, "\t[{:?}]: {},", name, type_name(field)).unwrap();
+            }
+        }
+        out.push_str("}\n");
+    }
+    out
+}
+
+fn type_name(shape: &Shape) -> String {
+    match shape {
+        Shape::Unknown | Shape::Any => "any".to_owned(),
+        Shape::Nil => "nil".to_owned(),
+        Shape::Boolean => "boolean".to_owned(),
+        Shape::Integer | Shape::Number => "number".to_owned(),
+        Shape::String => "string".to_owned(),
+        Shape::Optional(inner) => match **inner {
+            Shape::Unknown | Shape::Any => "any".to_owned(),
+            _ => format!("{}?", type_name(inner)),
+        },
+        Shape::Array(element) => format!("{{{}}}", type_name(element)),
+        Shape::Tuple(elements) => {
+            let mut members: Vec<String> = Vec::new();
+            for element in elements {
+                let element = type_name(element);
+                if !members.contains(&element) {
+                    members.push(element);
+                }
+            }
+            match members.len() {
+                0 => "{any}".to_owned(),
+                _ => format!("{{{}}}", members.join(" | ")),
+            }
+        }
+        Shape::Map(key, value) => format!("{{[{}]: {}}}", type_name(key), type_name(value)),
+        Shape::Record(record) => record.name.clone(),
+    }
+}
@@ -0,0 +1,297 @@
+//! Type declarations for the lua side of serialized data.
+//!
+//! serde only describes values, not types, so the shape is recorded from a sample value with
+//! [`shape_of`], or inferred from untyped data with [`infer_schema`]. Values that don't show their type, like `None` or empty collections, are typed as
+//! `any`, so the sample should have every field filled in.
+//!
+//! ```
+//! use serde::Serialize;
+//! use serde_lua_table::typegen;
+//!
+//! #[derive(Serialize)]
+//! struct Window {
+//!     title: String,
+//!     size: (u32, u32),
+//!     tags: Vec<String>,
+//! }
+//!
+//! let shape = typegen::shape_of(&Window {
+//!     title: "main".to_owned(),
+//!     size: (800, 600),
+//!     tags: vec!["resizable".to_owned()],
+//! })
+//! .unwrap();
+//!
+//! assert_eq!(
+//!     typegen::to_teal(&shape),
+//!     "local record Window\n   title: string\n   size: {integer, integer}\n   tags: {string}\nend\n\nreturn Window\n"
+//! );
+//! ```
+
+mod infer;
+mod luals;
+mod luau;
+mod shape;
+mod teal;
+
+pub(crate) use infer::infer;
+pub use infer::infer_schema;
+pub use luals::to_luals;
+pub use luau::to_luau;
+pub use teal::to_teal;
+
+use crate::SerError;
+use serde::Serialize;
+
+/// The type of a serialized value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    /// Nothing is known about the type, for example the elements of an empty sequence.
+    Unknown,
+    /// Values of different types were seen in the same place.
+    Any,
+    Nil,
+    Boolean,
+    Integer,
+    Number,
+    String,
+    /// A value that may be `nil`.
+    Optional(Box<Shape>),
+    /// A sequence of elements with the same type.
+    Array(Box<Shape>),
+    /// A sequence with a fixed number of elements.
+    Tuple(Vec<Shape>),
+    /// A table with keys and values of one type each.
+    Map(Box<Shape>, Box<Shape>),
+    Record(Record),
+}
+
+/// A table with a fixed set of named fields.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub name: String,
+    pub fields: Vec<(String, Shape)>,
+}
+
+/// Records the shape of `value` as it would be serialized.
+///
+/// # Errors
+///
+/// Fails if `T`'s implementation of `Serialize` decides to fail.
+pub fn shape_of<T>(value: &T) -> Result<Shape, SerError>
+where
+    T: ?Sized + Serialize,
+{
+    value.serialize(shape::ShapeSerializer)
+}
+
+impl Shape {
+    /// Combines two shapes seen in the same place into one that describes both.
+    pub fn merge(self, other: Shape) -> Shape {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Shape::Unknown, other) | (other, Shape::Unknown) => other,
+            (Shape::Nil, Shape::Optional(inner)) | (Shape::Optional(inner), Shape::Nil) => {
+                Shape::Optional(inner)
+            }
+            (Shape::Nil, other) | (other, Shape::Nil) => Shape::Optional(Box::new(other)),
+            (Shape::Optional(a), Shape::Optional(b)) => Shape::Optional(Box::new(a.merge(*b))),
+            (Shape::Optional(a), b) | (b, Shape::Optional(a)) => {
+                Shape::Optional(Box::new(a.merge(b)))
+            }
+            (Shape::Integer, Shape::Number) | (Shape::Number, Shape::Integer) => Shape::Number,
+            (Shape::Array(a), Shape::Array(b)) => Shape::Array(Box::new(a.merge(*b))),
+            (Shape::Tuple(a), Shape::Tuple(b)) if a.len() == b.len() => {
+                Shape::Tuple(a.into_iter().zip(b).map(|(a, b)| a.merge(b)).collect())
+            }
+            (Shape::Map(ak, av), Shape::Map(bk, bv)) => {
+                Shape::Map(Box::new(ak.merge(*bk)), Box::new(av.merge(*bv)))
+            }
+            (Shape::Record(a), Shape::Record(b)) if a.name == b.name => Shape::Record(a.merge(b)),
+            _ => Shape::Any,
+        }
+    }
+}
+
+impl Record {
+    fn merge(mut self, other: Record) -> Record {
+        let mut seen = vec![false; self.fields.len()];
+        for (name, shape) in other.fields {
+            match self.fields.iter().position(|(n, _)| *n == name) {
+                Some(i) => {
+                    seen[i] = true;
+                    let existing = std::mem::replace(&mut self.fields[i].1, Shape::Unknown);
+                    self.fields[i].1 = existing.merge(shape);
+                }
+                None => self.fields.push((name, Shape::Nil.merge(shape))),
+            }
+        }
+        for ((_, shape), seen) in self.fields.iter_mut().zip(seen) {
+            if !seen {
+                *shape = Shape::Nil.merge(std::mem::replace(shape, Shape::Unknown));
+            }
+        }
+        self
+    }
+
+    /// Returns all records reachable from `shape`, each after the records its fields refer to.
+    ///
+    /// Records with the same name are merged into one.
+    pub fn collect(shape: &Shape) -> Vec<Record> {
+        let mut merged = Vec::new();
+        visit_records(shape, &mut |record| match merged
+            .iter()
+            .position(|r: &Record| r.name == record.name)
+        {
+            Some(i) => merged[i] = merged[i].clone().merge(record.clone()),
+            None => merged.push(record.clone()),
+        });
+
+        let mut ordered = Vec::with_capacity(merged.len());
+        for record in &merged {
+            order(&record.name, &merged, &mut Vec::new(), &mut ordered);
+        }
+        ordered
+    }
+}
+
+/// Calls `f` for every record in `shape`, outermost first.
+fn visit_records(shape: &Shape, f: &mut impl FnMut(&Record)) {
+    match shape {
+        Shape::Optional(inner) | Shape::Array(inner) => visit_records(inner, f),
+        Shape::Tuple(elements) => {
+            for element in elements {
+                visit_records(element, f);
+            }
+        }
+        Shape::Map(key, value) => {
+            visit_records(key, f);
+            visit_records(value, f);
+        }
+        Shape::Record(record) => {
+            f(record);
+            for (_, field) in &record.fields {
+                visit_records(field, f);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Appends the merged record called `name` to `ordered`, after the records it refers to.
+fn order(name: &str, merged: &[Record], visiting: &mut Vec<String>, ordered: &mut Vec<Record>) {
+    if ordered.iter().any(|r| r.name == name) || visiting.iter().any(|n| n == name) {
+        return;
+    }
+    let Some(record) = merged.iter().find(|r| r.name == name) else {
+        return;
+    };
+
+    visiting.push(record.name.clone());
+    let mut dependencies = Vec::new();
+    for (_, field) in &record.fields {
+        visit_records(field, &mut |r| dependencies.push(r.name.clone()));
+    }
+    for dependency in dependencies {
+        order(&dependency, merged, visiting, ordered);
+    }
+    visiting.pop();
+    ordered.push(record.clone());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{shape_of, Record, Shape};
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct Item {
+        name: String,
+        price: Option<f64>,
+    }
+
+    #[derive(Serialize)]
+    struct Shop {
+        items: Vec<Item>,
+        stock: BTreeMap<String, u32>,
+    }
+
+    #[test]
+    fn records_nested_shapes_and_merges_elements() {
+        let shop = Shop {
+            items: vec![
+                Item {
+                    name: "apple".to_owned(),
+                    price: None,
+                },
+                Item {
+                    name: "pear".to_owned(),
+                    price: Some(1.5),
+                },
+            ],
+            stock: BTreeMap::from([("apple".to_owned(), 3)]),
+        };
+        let item = Record {
+            name: "Item".to_owned(),
+            fields: vec![
+                ("name".to_owned(), Shape::String),
+                ("price".to_owned(), Shape::Optional(Box::new(Shape::Number))),
+            ],
+        };
+        let shop_record = Record {
+            name: "Shop".to_owned(),
+            fields: vec![
+                (
+                    "items".to_owned(),
+                    Shape::Array(Box::new(Shape::Record(item.clone()))),
+                ),
+                (
+                    "stock".to_owned(),
+                    Shape::Map(Box::new(Shape::String), Box::new(Shape::Integer)),
+                ),
+            ],
+        };
+        let shape = shape_of(&shop).unwrap();
+        assert_eq!(shape, Shape::Record(shop_record.clone()));
+        assert_eq!(Record::collect(&shape), [item, shop_record]);
+    }
+
+    #[test]
+    fn merges_shapes_seen_in_the_same_place() {
+        let optional = |shape| Shape::Optional(Box::new(shape));
+        assert_eq!(Shape::Integer.merge(Shape::Number), Shape::Number);
+        assert_eq!(Shape::Unknown.merge(Shape::String), Shape::String);
+        assert_eq!(Shape::Nil.merge(Shape::Integer), optional(Shape::Integer));
+        assert_eq!(
+            optional(Shape::Integer).merge(Shape::Number),
+            optional(Shape::Number)
+        );
+        assert_eq!(Shape::String.merge(Shape::Boolean), Shape::Any);
+        assert_eq!(
+            Shape::Tuple(vec![Shape::String]).merge(Shape::Tuple(vec![])),
+            Shape::Any
+        );
+    }
+
+    #[test]
+    fn merges_records_with_missing_fields_as_optional() {
+        let record = |fields: &[(&str, Shape)]| {
+            Shape::Record(Record {
+                name: "R".to_owned(),
+                fields: fields
+                    .iter()
+                    .map(|(name, shape)| (name.to_string(), shape.clone()))
+                    .collect(),
+            })
+        };
+        let merged = record(&[("a", Shape::Integer)]).merge(record(&[("b", Shape::String)]));
+        assert_eq!(
+            merged,
+            record(&[
+                ("a", Shape::Optional(Box::new(Shape::Integer))),
+                ("b", Shape::Optional(Box::new(Shape::String))),
+            ])
+        );
+    }
+}
@@ -0,0 +1,404 @@
+use super::{Record, Shape};
+use crate::{ser::raw, SerError};
+use serde::{ser, Serialize};
+
+/// Records the [`Shape`] of the value passed to it instead of writing it.
+pub(super) struct ShapeSerializer;
+
+/// Wraps `inner` in the table the serializer writes around enum variants.
+fn variant(name: &str, variant: &str, inner: Shape) -> Shape {
+    Shape::Record(Record {
+        name: format!("{}{}", name, variant),
+        fields: vec![(variant.to_owned(), inner)],
+    })
+}
+
+impl ser::Serializer for ShapeSerializer {
+    type Ok = Shape;
+    type Error = SerError;
+    type SerializeSeq = SeqShape;
+    type SerializeTuple = SeqShape;
+    type SerializeTupleStruct = SeqShape;
+    type SerializeTupleVariant = SeqShape;
+    type SerializeMap = MapShape;
+    type SerializeStruct = RecordShape;
+    type SerializeStructVariant = RecordShape;
+
+    fn serialize_bool(self, _v: bool) -> Result<Self::Ok, Self::Error> {
+        Ok(Shape::Boolean)
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<Self::Ok, Self::Error> {
+        Ok(Shape::Integer)
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<Self::Ok, Self::Error> {
+        Ok(Shape::Integer)
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<Self::Ok, Self::Error> {
+        Ok(Shape::Integer)
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<Self::Ok, Self::Error> {
+        Ok(Shape::Integer)
+    }
+
+    fn serialize_i128(self, _v: i128) -> Result<Self::Ok, Self::Error> {
+        Ok(Shape::Integer)
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<Self::Ok, Self::Error> {
+        Ok(Shape::Integer)
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<Self::Ok, Self::Error> {
+        Ok(Shape::Integer)
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<Self::Ok, Self::Error> {
+        Ok(Shape::Integer)
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<Self::Ok, Self::Error> {
+        Ok(Shape::Integer)
+    }
+
+    fn serialize_u128(self, _v: u128) -> Result<Self::Ok, Self::Error> {
+        Ok(Shape::Integer)
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Self::Ok, Self::Error> {
+        Ok(Shape::Number)
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Self::Ok, Self::Error> {
+        Ok(Shape::Number)
+    }
+
+    fn serialize_char(self, _v: char) -> Result<Self::Ok, Self::Error> {
+        Ok(Shape::String)
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<Self::Ok, Self::Error> {
+        Ok(Shape::String)
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        Ok(Shape::Array(Box::new(Shape::Integer)))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Shape::Optional(Box::new(Shape::Unknown)))
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(Shape::Nil.merge(value.serialize(self)?))
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Shape::Nil)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        Ok(Shape::Nil)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        Ok(Shape::String)
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        if name == raw::TOKEN {
+            return Ok(Shape::Any);
+        }
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant_name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(variant(name, variant_name, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Ok(SeqShape {
+            elements: Vec::with_capacity(len.unwrap_or(0)),
+            tuple: false,
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Ok(SeqShape {
+            elements: Vec::with_capacity(len),
+            tuple: true,
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Ok(SeqShape {
+            elements: Vec::with_capacity(len),
+            tuple: true,
+            variant: Some((name, variant)),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(MapShape {
+            key: Shape::Unknown,
+            value: Shape::Unknown,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Ok(RecordShape {
+            record: Record {
+                name: name.to_owned(),
+                fields: Vec::with_capacity(len),
+            },
+            variant: None,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Ok(RecordShape {
+            record: Record {
+                name: format!("{}{}Data", name, variant),
+                fields: Vec::with_capacity(len),
+            },
+            variant: Some((name, variant)),
+        })
+    }
+}
+
+pub(super) struct SeqShape {
+    elements: Vec<Shape>,
+    tuple: bool,
+    variant: Option<(&'static str, &'static str)>,
+}
+
+impl SeqShape {
+    fn push<T>(&mut self, value: &T) -> Result<(), SerError>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.elements.push(value.serialize(ShapeSerializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Shape {
+        let shape = if self.tuple {
+            Shape::Tuple(self.elements)
+        } else {
+            let element = self.elements.into_iter().fold(Shape::Unknown, Shape::merge);
+            Shape::Array(Box::new(element))
+        };
+        match self.variant {
+            Some((name, variant_name)) => variant(name, variant_name, shape),
+            None => shape,
+        }
+    }
+}
+
+impl ser::SerializeSeq for SeqShape {
+    type Ok = Shape;
+    type Error = SerError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTuple for SeqShape {
+    type Ok = Shape;
+    type Error = SerError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqShape {
+    type Ok = Shape;
+    type Error = SerError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqShape {
+    type Ok = Shape;
+    type Error = SerError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.finish())
+    }
+}
+
+pub(super) struct MapShape {
+    key: Shape,
+    value: Shape,
+}
+
+impl ser::SerializeMap for MapShape {
+    type Ok = Shape;
+    type Error = SerError;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let key = key.serialize(ShapeSerializer)?;
+        self.key = std::mem::replace(&mut self.key, Shape::Unknown).merge(key);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let value = value.serialize(ShapeSerializer)?;
+        self.value = std::mem::replace(&mut self.value, Shape::Unknown).merge(value);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Shape::Map(Box::new(self.key), Box::new(self.value)))
+    }
+}
+
+pub(super) struct RecordShape {
+    record: Record,
+    variant: Option<(&'static str, &'static str)>,
+}
+
+impl RecordShape {
+    fn finish(self) -> Shape {
+        let shape = Shape::Record(self.record);
+        match self.variant {
+            Some((name, variant_name)) => variant(name, variant_name, shape),
+            None => shape,
+        }
+    }
+}
+
+impl ser::SerializeStruct for RecordShape {
+    type Ok = Shape;
+    type Error = SerError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let shape = value.serialize(ShapeSerializer)?;
+        self.record.fields.push((key.to_owned(), shape));
+        Ok(())
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        let shape = Shape::Optional(Box::new(Shape::Unknown));
+        self.record.fields.push((key.to_owned(), shape));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStructVariant for RecordShape {
+    type Ok = Shape;
+    type Error = SerError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        ser::SerializeStruct::skip_field(self, key)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.finish())
+    }
+}
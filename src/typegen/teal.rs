@@ -0,0 +1,94 @@
+use super::{Record, Shape};
+use crate::LuaVersion;
+use std::fmt::Write;
+
+/// Renders a Teal declaration file (`.d.tl`) for a chunk returning a value of the given shape.
+///
+/// Every record is declared as a `local record`, and the chunk returns the root record.
+pub fn to_teal(shape: &Shape) -> String {
+    let mut out = String::new();
+    for record in Record::collect(shape) {
+        writeln!(out, "local record {}", record.name).unwrap();
+        for (name, field) in &record.fields {
+            if LuaVersion::Lua54.is_identifier(name) {
+                writeln!(out, "   {}: {}", name, type_name(field)).unwrap();
+            } else {
+                writeln!(out, "   [{:?}]: {}", name, type_name(field)).unwrap();
+            }
+        }
+        out.push_str("end\n\n");
+    }
+
+    match shape {
+        Shape::Record(record) => writeln!(out, "return {}", record.name).unwrap(),
+        other => writeln!(out, "local value: {}\n\nreturn value", type_name(other)).unwrap(),
+    }
+    out
+}
+
+/// Returns the Teal type of `shape`. All Teal types include `nil`, so optional values aren't
+/// marked.
+fn type_name(shape: &Shape) -> String {
+    match shape {
+        Shape::Unknown | Shape::Any => "any".to_owned(),
+        Shape::Nil => "nil".to_owned(),
+        Shape::Boolean => "boolean".to_owned(),
+        Shape::Integer => "integer".to_owned(),
+        Shape::Number => "number".to_owned(),
+        Shape::String => "string".to_owned(),
+        Shape::Optional(inner) => type_name(inner),
+        Shape::Array(element) => format!("{{{}}}", type_name(element)),
+        Shape::Tuple(elements) => {
+            let elements: Vec<_> = elements.iter().map(type_name).collect();
+            format!("{{{}}}", elements.join(", "))
+        }
+        Shape::Map(key, value) => format!("{{{}:{}}}", type_name(key), type_name(value)),
+        Shape::Record(record) => record.name.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_teal;
+    use crate::typegen::shape_of;
+    use serde::Serialize;
+    use std::collections::HashMap;
+
+    #[derive(Serialize)]
+    struct Node {
+        #[serde(rename = "end")]
+        end: Option<u32>,
+        children: Vec<Leaf>,
+    }
+
+    #[derive(Serialize)]
+    struct Leaf {
+        weights: HashMap<String, f64>,
+    }
+
+    #[test]
+    fn declares_nested_records_before_their_users() {
+        let shape = shape_of(&Node {
+            end: Some(1),
+            children: vec![Leaf {
+                weights: HashMap::from([("x".to_owned(), 0.5)]),
+            }],
+        })
+        .unwrap();
+        assert_eq!(
+            to_teal(&shape),
+            "local record Leaf\n   weights: {string:number}\nend\n\n\
+             local record Node\n   [\"end\"]: integer\n   children: {Leaf}\nend\n\n\
+             return Node\n"
+        );
+    }
+
+    #[test]
+    fn returns_values_that_are_not_records() {
+        let shape = shape_of(&vec![(1, "a")]).unwrap();
+        assert_eq!(
+            to_teal(&shape),
+            "local value: {{integer, string}}\n\nreturn value\n"
+        );
+    }
+}
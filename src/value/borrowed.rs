@@ -0,0 +1,258 @@
+use super::{
+    de::{KeySeed, Keys},
+    Key, Table, Value,
+};
+use serde::de::{Deserialize, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use std::{borrow::Cow, fmt};
+
+/// A [`Value`] that borrows its strings from the input.
+///
+/// Strings without escapes are borrowed when deserializing from a `&str` or `&[u8]`, so tools
+/// that mostly read large files don't copy their text. Strings that have to be decoded, and all
+/// strings read from an [`IoRead`](crate::IoRead), are owned.
+///
+/// ```
+/// use serde_lua_table::value::{BorrowedValue, Key};
+/// use std::borrow::Cow;
+///
+/// let value: BorrowedValue = serde_lua_table::from_str(r#"{ name = "ferris", motto = "a\tb" }"#)?;
+/// assert!(matches!(value.get(&Key::from("name")), Some(BorrowedValue::String(Cow::Borrowed("ferris")))));
+/// assert!(matches!(value.get(&Key::from("motto")), Some(BorrowedValue::String(Cow::Owned(_)))));
+/// # Ok::<(), serde_lua_table::DeError>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub enum BorrowedValue<'de> {
+    #[default]
+    Nil,
+    Boolean(bool),
+    Integer(i64),
+    Number(f64),
+    String(Cow<'de, str>),
+    /// A string that isn't valid UTF-8.
+    Bytes(Cow<'de, [u8]>),
+    /// The fields of a table in the order they are written, positional values at their index.
+    /// Fields that are `nil` are left out.
+    Table(Vec<(Key, BorrowedValue<'de>)>),
+}
+
+impl<'de> BorrowedValue<'de> {
+    /// Returns `true` if the value is `nil`.
+    pub fn is_nil(&self) -> bool {
+        matches!(self, BorrowedValue::Nil)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            BorrowedValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the fields of a table.
+    pub fn as_table(&self) -> Option<&[(Key, BorrowedValue<'de>)]> {
+        match self {
+            BorrowedValue::Table(fields) => Some(fields),
+            _ => None,
+        }
+    }
+
+    /// Returns the field at `key`, if the value is a table.
+    pub fn get(&self, key: &Key) -> Option<&BorrowedValue<'de>> {
+        self.as_table()?
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+    }
+
+    /// Copies the borrowed strings and returns the value as a [`Value`].
+    pub fn into_owned(self) -> Value {
+        match self {
+            BorrowedValue::Nil => Value::Nil,
+            BorrowedValue::Boolean(b) => Value::Boolean(b),
+            BorrowedValue::Integer(i) => Value::Integer(i),
+            BorrowedValue::Number(n) => Value::Number(n),
+            BorrowedValue::String(s) => Value::String(s.into_owned()),
+            BorrowedValue::Bytes(b) => Value::Bytes(b.into_owned()),
+            BorrowedValue::Table(fields) => Value::Table(
+                fields
+                    .into_iter()
+                    .map(|(k, v)| (k, v.into_owned()))
+                    .collect::<Table>(),
+            ),
+        }
+    }
+}
+
+impl<'de> From<BorrowedValue<'de>> for Value {
+    fn from(v: BorrowedValue<'de>) -> Self {
+        v.into_owned()
+    }
+}
+
+impl<'de> Deserialize<'de> for BorrowedValue<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        ValueSeed(&mut Keys::default()).deserialize(deserializer)
+    }
+}
+
+struct ValueSeed<'a>(&'a mut Keys);
+
+impl<'de> DeserializeSeed<'de> for ValueSeed<'_> {
+    type Value = BorrowedValue<'de>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ValueVisitor(self.0))
+    }
+}
+
+struct ValueVisitor<'a>(&'a mut Keys);
+
+impl<'de> Visitor<'de> for ValueVisitor<'_> {
+    type Value = BorrowedValue<'de>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any lua value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(BorrowedValue::Boolean(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(BorrowedValue::Integer(v))
+    }
+
+    // Integers above `i64::MAX` don't fit into a lua integer and become floats.
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(i64::try_from(v).map_or(BorrowedValue::Number(v as f64), BorrowedValue::Integer))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(BorrowedValue::Number(v))
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E> {
+        Ok(BorrowedValue::String(Cow::Borrowed(v)))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(BorrowedValue::String(Cow::Owned(v.to_owned())))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+        Ok(BorrowedValue::String(Cow::Owned(v)))
+    }
+
+    fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+        Ok(
+            std::str::from_utf8(v).map_or(BorrowedValue::Bytes(Cow::Borrowed(v)), |s| {
+                BorrowedValue::String(Cow::Borrowed(s))
+            }),
+        )
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(owned_bytes(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(owned_bytes(v))
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(BorrowedValue::Nil)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        ValueSeed(self.0).deserialize(deserializer)
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(BorrowedValue::Nil)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut fields = Vec::new();
+        let mut index = 0;
+        while let Some(value) = seq.next_element_seed(ValueSeed(&mut *self.0))? {
+            index += 1;
+            if !value.is_nil() {
+                fields.push((Key::Integer(index), value));
+            }
+        }
+        Ok(BorrowedValue::Table(fields))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut fields = Vec::new();
+        while let Some(key) = map.next_key_seed(KeySeed(&mut *self.0))? {
+            let value = map.next_value_seed(ValueSeed(&mut *self.0))?;
+            if !value.is_nil() {
+                fields.push((key, value));
+            }
+        }
+        Ok(BorrowedValue::Table(fields))
+    }
+}
+
+/// Bytes that are valid UTF-8 become a string, like [`Value::from`] does.
+fn owned_bytes<'de>(v: Vec<u8>) -> BorrowedValue<'de> {
+    String::from_utf8(v).map_or_else(
+        |e| BorrowedValue::Bytes(Cow::Owned(e.into_bytes())),
+        |s| BorrowedValue::String(Cow::Owned(s)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BorrowedValue;
+    use crate::{from_str, value::Value, Deserializer};
+    use serde::Deserialize;
+    use std::borrow::Cow;
+
+    #[test]
+    fn borrows_strings_without_escapes() {
+        let lua = r#"{ "plain", 'a\nb', [[long]], "\xFF", x = { "nested" } }"#;
+        let value = from_str::<BorrowedValue>(lua).unwrap();
+        let fields = value.as_table().unwrap();
+        assert!(matches!(
+            fields[0].1,
+            BorrowedValue::String(Cow::Borrowed("plain"))
+        ));
+        assert!(matches!(fields[1].1, BorrowedValue::String(Cow::Owned(_))));
+        assert!(matches!(
+            fields[2].1,
+            BorrowedValue::String(Cow::Borrowed("long"))
+        ));
+        assert!(matches!(fields[3].1, BorrowedValue::Bytes(Cow::Owned(_))));
+        assert_eq!(value.into_owned(), from_str::<Value>(lua).unwrap());
+    }
+
+    #[test]
+    fn owns_strings_read_from_a_reader() {
+        let mut de = Deserializer::from_reader(&b"{ 'plain', nil, 3 }"[..]);
+        let value = BorrowedValue::deserialize(&mut de).unwrap();
+        let fields = value.as_table().unwrap();
+        assert!(matches!(fields[0].1, BorrowedValue::String(Cow::Owned(_))));
+        assert_eq!(fields.len(), 2);
+        assert_eq!(
+            value.into_owned(),
+            from_str::<Value>("{ 'plain', nil, 3 }").unwrap()
+        );
+    }
+}
@@ -0,0 +1,291 @@
+use super::{Key, Table, Value};
+use crate::de::raw_number;
+use serde::de::{self, Deserialize, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use std::{collections::HashSet, fmt, sync::Arc};
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        ValueSeed(&mut Keys::default()).deserialize(deserializer)
+    }
+}
+
+/// The string keys seen while deserializing one value, so repeated keys share an allocation.
+#[derive(Default)]
+pub(super) struct Keys(HashSet<Arc<str>>);
+
+impl Keys {
+    fn intern(&mut self, key: &str) -> Arc<str> {
+        if let Some(key) = self.0.get(key) {
+            return key.clone();
+        }
+        let key: Arc<str> = key.into();
+        self.0.insert(key.clone());
+        key
+    }
+}
+
+struct ValueSeed<'a>(&'a mut Keys);
+
+impl<'de> DeserializeSeed<'de> for ValueSeed<'_> {
+    type Value = Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ValueVisitor(self.0))
+    }
+}
+
+struct ValueVisitor<'a>(&'a mut Keys);
+
+impl<'de> Visitor<'de> for ValueVisitor<'_> {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any lua value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Boolean(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Integer(v))
+    }
+
+    // Integers above `i64::MAX` don't fit into a lua integer and become floats.
+    fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+        Ok(i64::try_from(v).map_or(Value::Number(v as f64), Value::Integer))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Number(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_owned()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Value, E> {
+        Ok(Value::from(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Value, E> {
+        Ok(Value::from(v))
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Nil)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        ValueSeed(self.0).deserialize(deserializer)
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Nil)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        // Elements keep their index, a `nil` leaves a hole instead of moving the rest down.
+        let mut table = Table::new();
+        let mut index = 0;
+        while let Some(value) = seq.next_element_seed(ValueSeed(&mut *self.0))? {
+            index += 1;
+            table.insert(index, value);
+        }
+        Ok(Value::Table(table))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut table = Table::new();
+        while let Some(key) = map.next_key_seed(KeySeed(&mut *self.0))? {
+            if table.is_empty() && matches!(&key, Key::String(k) if &**k == raw_number::TOKEN) {
+                return Ok(Value::RawNumber(map.next_value()?));
+            }
+            let value = map.next_value_seed(ValueSeed(&mut *self.0))?;
+            table.insert(key, value);
+        }
+        Ok(Value::Table(table))
+    }
+}
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        KeySeed(&mut Keys::default()).deserialize(deserializer)
+    }
+}
+
+pub(super) struct KeySeed<'a>(pub(super) &'a mut Keys);
+
+impl<'de> DeserializeSeed<'de> for KeySeed<'_> {
+    type Value = Key;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Key, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(KeyVisitor(self.0))
+    }
+}
+
+struct KeyVisitor<'a>(&'a mut Keys);
+
+impl<'de> Visitor<'de> for KeyVisitor<'_> {
+    type Value = Key;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an integer or a string")
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Key, E> {
+        Ok(Key::Integer(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Key, E>
+    where
+        E: de::Error,
+    {
+        i64::try_from(v)
+            .map(Key::Integer)
+            .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(v), &self))
+    }
+
+    // Floats with an integral value are the same key as the integer in lua.
+    fn visit_f64<E>(self, v: f64) -> Result<Key, E>
+    where
+        E: de::Error,
+    {
+        if v.fract() == 0.0 && v >= i64::MIN as f64 && v < i64::MAX as f64 {
+            Ok(Key::Integer(v as i64))
+        } else {
+            Err(E::invalid_value(de::Unexpected::Float(v), &self))
+        }
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Key, E> {
+        Ok(Key::String(self.0.intern(v)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        from_str,
+        value::{Key, Value},
+    };
+
+    #[test]
+    fn keeps_nil_holes_in_sequences() {
+        let value = from_str::<Value>("{nil, 1}").unwrap();
+        let table = value.as_table().unwrap();
+        assert!(table.sequence().is_empty());
+        assert_eq!(table.get(&Key::from(1)), None);
+        assert_eq!(table.get(&Key::from(2)), Some(&Value::from(1)));
+
+        let value = from_str::<Value>("{1, nil, 3, 4}").unwrap();
+        let table = value.as_table().unwrap();
+        assert_eq!(table.sequence(), [Value::from(1)]);
+        assert_eq!(table.get(&Key::from(3)), Some(&Value::from(3)));
+        assert_eq!(table.get(&Key::from(4)), Some(&Value::from(4)));
+    }
+
+    #[test]
+    fn reads_every_kind_of_value() {
+        let value = from_str::<Value>(r#"{true, 1, 1.5, "s", {}, x = nil}"#).unwrap();
+        assert_eq!(
+            value.as_table().unwrap().sequence(),
+            [
+                Value::from(true),
+                Value::from(1),
+                Value::from(1.5),
+                Value::from("s"),
+                Value::Table(Default::default()),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_strings_that_are_not_utf8_as_bytes() {
+        let value = from_str::<Value>(r#""\xFF\0a""#).unwrap();
+        assert_eq!(value, Value::Bytes(vec![0xFF, 0, b'a']));
+        assert_eq!(value.as_str(), None);
+        assert_eq!(value.as_bytes(), Some(&[0xFF, 0, b'a'][..]));
+        assert_eq!(
+            from_str::<Value>(r#""\xC3\xA4""#).unwrap(),
+            Value::from("ä")
+        );
+
+        let lua = crate::to_string(&value).unwrap();
+        assert_eq!(lua, r#""\255\000a""#);
+        assert_eq!(from_str::<Value>(&lua).unwrap(), value);
+    }
+
+    #[test]
+    fn keeps_raw_numbers() {
+        use crate::{DeOptions, Deserializer, SliceRead};
+        use serde::Deserialize;
+
+        let lua = b"{ -12345678901234567890.5, 1e400, .5, 0x10, -math.huge, { 7 } }";
+        let options = DeOptions::new().with_raw_numbers(true);
+        let mut de = Deserializer::with_options(SliceRead::new(lua), options);
+        let value = Value::deserialize(&mut de).unwrap();
+        let sequence = value.as_table().unwrap().sequence();
+        assert_eq!(
+            sequence[0],
+            Value::RawNumber("-12345678901234567890.5".to_owned())
+        );
+        assert_eq!(sequence[2].as_f64(), Some(0.5));
+        assert_eq!(sequence[3], Value::Integer(16));
+        assert_eq!(sequence[4], Value::Number(f64::NEG_INFINITY));
+        assert_eq!(
+            sequence[5].as_table().unwrap().sequence()[0].as_i64(),
+            Some(7)
+        );
+        assert_eq!(
+            crate::to_string(&value).unwrap(),
+            "{-12345678901234567890.5,1e400,.5,16,-math.huge,{7}}"
+        );
+        assert_eq!(f64::deserialize(&sequence[2]).unwrap(), 0.5);
+        let max = Value::RawNumber(u64::MAX.to_string());
+        assert_eq!(u64::deserialize(&max).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn shares_repeated_keys() {
+        let value = from_str::<Value>("{ { name = 'a' }, { name = 'b', extra = { name = 'c' } } }")
+            .unwrap();
+        let keys: Vec<_> = value
+            .as_table()
+            .unwrap()
+            .sequence()
+            .iter()
+            .map(|record| match &record.as_table().unwrap().fields()[0].0 {
+                Key::String(key) => key.clone(),
+                Key::Integer(_) => unreachable!(),
+            })
+            .collect();
+        assert_eq!(&*keys[0], "name");
+        assert!(std::sync::Arc::ptr_eq(&keys[0], &keys[1]));
+    }
+}
@@ -0,0 +1,632 @@
+//! Deserializing typed values out of a [`Value`] without cloning it.
+
+use super::{Key, Table, Value};
+use crate::DeError;
+use serde::{
+    de::{
+        self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, Unexpected,
+        VariantAccess, Visitor,
+    },
+    forward_to_deserialize_any, Deserializer,
+};
+
+impl Value {
+    fn unexpected(&self) -> Unexpected<'_> {
+        match self {
+            Value::Nil => Unexpected::Unit,
+            Value::Boolean(b) => Unexpected::Bool(*b),
+            Value::Integer(i) => Unexpected::Signed(*i),
+            Value::Number(n) => Unexpected::Float(*n),
+            Value::RawNumber(_) => Unexpected::Other("number"),
+            Value::String(s) => Unexpected::Str(s),
+            Value::Bytes(b) => Unexpected::Bytes(b),
+            Value::Table(_) => Unexpected::Map,
+        }
+    }
+
+    /// Returns the value as an integer if it is one, or a float without a fraction.
+    fn integral(&self) -> Option<i64> {
+        match *self {
+            Value::Integer(i) => Some(i),
+            Value::Number(n) if n.fract() == 0.0 && n >= i64::MIN as f64 && n < i64::MAX as f64 => {
+                Some(n as i64)
+            }
+            _ => None,
+        }
+    }
+}
+
+macro_rules! deserialize_integer {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, DeError>
+            where
+                V: Visitor<'de>,
+            {
+                // Lua 5.1 and LuaJIT only have floats.
+                match self.integral() {
+                    Some(i) => visitor.visit_i64(i),
+                    None => self.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+/// Deserializes borrowing from the value, so `&str` fields point into its strings.
+///
+/// Tables without fields are read as sequences, all others as maps. Floats without a fraction
+/// are accepted as integers, and enum variants are read from the representations the serializer
+/// writes: a string for unit variants and a table with a single key for the others.
+///
+/// ```
+/// use serde::Deserialize;
+/// use serde_lua_table::value::{Table, Value};
+///
+/// #[derive(Deserialize)]
+/// struct Player<'a> {
+///     name: &'a str,
+///     level: u32,
+/// }
+///
+/// let mut table = Table::new();
+/// table.insert("name", Value::from("ferris"));
+/// table.insert("level", Value::from(3.0));
+/// let value = Value::from(table);
+///
+/// let player = Player::deserialize(&value).unwrap();
+/// assert_eq!((player.name, player.level), ("ferris", 3));
+/// ```
+impl<'de> Deserializer<'de> for &'de Value {
+    type Error = DeError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Nil => visitor.visit_unit(),
+            Value::Boolean(b) => visitor.visit_bool(*b),
+            Value::Integer(i) => visitor.visit_i64(*i),
+            Value::Number(n) => visitor.visit_f64(*n),
+            Value::RawNumber(n) => match (n.parse(), n.parse()) {
+                (Ok(i), _) => visitor.visit_i64(i),
+                (_, Ok(u)) => visitor.visit_u64(u),
+                _ => visitor.visit_f64(n.parse().unwrap_or(f64::NAN)),
+            },
+            Value::String(s) => visitor.visit_borrowed_str(s),
+            Value::Bytes(b) => visitor.visit_borrowed_bytes(b),
+            Value::Table(table) if table.fields().is_empty() => {
+                visitor.visit_seq(SeqDeserializer::new(table.sequence()))
+            }
+            Value::Table(table) => visitor.visit_map(TableDeserializer::new(table)),
+        }
+    }
+
+    deserialize_integer! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::String(s) => visitor.visit_borrowed_bytes(s.as_bytes()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Nil => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Table(table) => visitor.visit_map(TableDeserializer::new(table)),
+            other => Err(de::Error::invalid_type(other.unexpected(), &visitor)),
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Table(table) => visitor.visit_map(TableDeserializer::fields(table)),
+            other => Err(de::Error::invalid_type(other.unexpected(), &visitor)),
+        }
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::String(variant) => visitor.visit_enum(variant.as_str().into_deserializer()),
+            Value::Table(table) => match (table.sequence(), table.fields()) {
+                ([], [(Key::String(variant), value)]) => visitor.visit_enum(VariantDeserializer {
+                    variant,
+                    value: Some(value),
+                }),
+                _ => Err(de::Error::invalid_value(
+                    Unexpected::Map,
+                    &"a table with a single string key",
+                )),
+            },
+            other => Err(de::Error::invalid_type(
+                other.unexpected(),
+                &"an enum variant",
+            )),
+        }
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i128 u128 f32 f64 char str string unit unit_struct seq tuple tuple_struct identifier
+    }
+}
+
+/// Deserializes an owned value like `&Value` does, except that strings can't be borrowed.
+impl<'de> Deserializer<'de> for Value {
+    type Error = DeError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Nil => visitor.visit_unit(),
+            Value::Boolean(b) => visitor.visit_bool(b),
+            Value::Integer(i) => visitor.visit_i64(i),
+            Value::Number(n) => visitor.visit_f64(n),
+            Value::RawNumber(n) => match (n.parse(), n.parse()) {
+                (Ok(i), _) => visitor.visit_i64(i),
+                (_, Ok(u)) => visitor.visit_u64(u),
+                _ => visitor.visit_f64(n.parse().unwrap_or(f64::NAN)),
+            },
+            Value::String(s) => visitor.visit_string(s),
+            Value::Bytes(b) => visitor.visit_byte_buf(b),
+            Value::Table(table) if table.fields.is_empty() => {
+                let mut seq = de::value::SeqDeserializer::new(table.sequence.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Value::Table(table) => {
+                visitor.visit_map(de::value::MapDeserializer::new(owned_entries(table)))
+            }
+        }
+    }
+
+    deserialize_integer! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Nil => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Table(table) => {
+                visitor.visit_map(de::value::MapDeserializer::new(owned_entries(table)))
+            }
+            other => Err(de::Error::invalid_type(other.unexpected(), &visitor)),
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Table(table) => {
+                let entries = owned_entries(table).map(|(key, value)| match key {
+                    Key::Integer(i) => (Key::String(i.to_string().into()), value),
+                    key => (key, value),
+                });
+                visitor.visit_map(de::value::MapDeserializer::new(entries))
+            }
+            other => Err(de::Error::invalid_type(other.unexpected(), &visitor)),
+        }
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Value::Table(table)
+                if table.sequence.is_empty()
+                    && matches!(table.fields[..], [(Key::String(_), _)]) =>
+            {
+                let entries = de::value::MapDeserializer::new(owned_entries(table));
+                visitor.visit_enum(de::value::MapAccessDeserializer::new(entries))
+            }
+            Value::Table(_) => Err(de::Error::invalid_value(
+                Unexpected::Map,
+                &"a table with a single string key",
+            )),
+            other => Err(de::Error::invalid_type(
+                other.unexpected(),
+                &"an enum variant",
+            )),
+        }
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i128 u128 f32 f64 char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct identifier
+    }
+}
+
+impl<'de> IntoDeserializer<'de, DeError> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// The sequence of an owned table with its keys, followed by its fields.
+fn owned_entries(table: Table) -> impl Iterator<Item = (Key, Value)> {
+    let sequence = (1..).map(Key::Integer).zip(table.sequence);
+    sequence.chain(table.fields)
+}
+
+/// Deserializes an owned key.
+impl<'de> Deserializer<'de> for Key {
+    type Error = DeError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Key::Integer(i) => visitor.visit_i64(i),
+            Key::String(s) => visitor.visit_str(&s),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, DeError> for Key {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Deserializes a key borrowing from its table.
+#[derive(Clone, Copy)]
+enum KeyRef<'de> {
+    Integer(i64),
+    String(&'de str),
+}
+
+impl<'de> Deserializer<'de> for KeyRef<'de> {
+    type Error = DeError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            KeyRef::Integer(i) => visitor.visit_i64(i),
+            KeyRef::String(s) => visitor.visit_borrowed_str(s),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct SeqDeserializer<'de> {
+    iter: std::slice::Iter<'de, Value>,
+}
+
+impl<'de> SeqDeserializer<'de> {
+    fn new(sequence: &'de [Value]) -> Self {
+        SeqDeserializer {
+            iter: sequence.iter(),
+        }
+    }
+}
+
+impl<'de> SeqAccess<'de> for SeqDeserializer<'de> {
+    type Error = DeError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, DeError>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.iter
+            .next()
+            .map(|value| seed.deserialize(value))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+/// Visits the sequence of a table with its keys, then its fields.
+struct TableDeserializer<'de> {
+    table: &'de Table,
+    /// Index into the sequence followed by the fields.
+    next: usize,
+    /// Whether the table is read into a struct, which gets integer keys as their digits like
+    /// [`from_str`](crate::from_str) passes them.
+    fields: bool,
+}
+
+impl<'de> TableDeserializer<'de> {
+    fn new(table: &'de Table) -> Self {
+        TableDeserializer {
+            table,
+            next: 0,
+            fields: false,
+        }
+    }
+
+    fn fields(table: &'de Table) -> Self {
+        TableDeserializer {
+            fields: true,
+            ..TableDeserializer::new(table)
+        }
+    }
+
+    fn entry(&self, index: usize) -> Option<(KeyRef<'de>, &'de Value)> {
+        let sequence = self.table.sequence();
+        if let Some(value) = sequence.get(index) {
+            return Some((KeyRef::Integer(index as i64 + 1), value));
+        }
+        let (key, value) = self.table.fields().get(index - sequence.len())?;
+        let key = match key {
+            Key::Integer(i) => KeyRef::Integer(*i),
+            Key::String(s) => KeyRef::String(s),
+        };
+        Some((key, value))
+    }
+}
+
+impl<'de> MapAccess<'de> for TableDeserializer<'de> {
+    type Error = DeError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, DeError>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.entry(self.next) {
+            Some((KeyRef::Integer(i), _)) if self.fields => seed
+                .deserialize(i.to_string().into_deserializer())
+                .map(Some),
+            Some((key, _)) => seed.deserialize(key).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, DeError>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let (_, value) = self
+            .entry(self.next)
+            .ok_or_else(|| de::Error::custom("value is missing"))?;
+        self.next += 1;
+        seed.deserialize(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.table.sequence().len() + self.table.fields().len() - self.next)
+    }
+}
+
+struct VariantDeserializer<'de> {
+    variant: &'de str,
+    value: Option<&'de Value>,
+}
+
+impl<'de> EnumAccess<'de> for VariantDeserializer<'de> {
+    type Error = DeError;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self), DeError>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(KeyRef::String(self.variant))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for VariantDeserializer<'de> {
+    type Error = DeError;
+
+    fn unit_variant(self) -> Result<(), DeError> {
+        match self.value {
+            None | Some(Value::Nil) => Ok(()),
+            Some(other) => Err(de::Error::invalid_type(other.unexpected(), &"unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, DeError>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.value.unwrap_or(&Value::Nil))
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        self.value.unwrap_or(&Value::Nil).deserialize_any(visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError>
+    where
+        V: Visitor<'de>,
+    {
+        self.value.unwrap_or(&Value::Nil).deserialize_map(visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Value;
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    enum Shape<'a> {
+        Empty,
+        Circle(f64),
+        Line(i32, i32),
+        Label { text: &'a str },
+    }
+
+    fn value(lua: &str) -> Value {
+        crate::from_str(lua).unwrap()
+    }
+
+    #[test]
+    fn borrows_strings_from_nested_tables() {
+        let value = value("{ { 'a', 'b' }, { name = 'c', tags = { 'd' } } }");
+        let (names, named): (Vec<&str>, BTreeMap<&str, Value>) =
+            Deserialize::deserialize(&value).unwrap();
+        assert_eq!(names, ["a", "b"]);
+        assert_eq!(<&str>::deserialize(&named["name"]).unwrap(), "c");
+        assert!(Vec::<&str>::deserialize(&named["name"]).is_err());
+    }
+
+    #[test]
+    fn reads_integers_from_whole_floats_and_integer_keys() {
+        let map = BTreeMap::<i64, u8>::deserialize(&value("{ [1] = 2.0, [3] = 4 }")).unwrap();
+        assert_eq!(map, BTreeMap::from([(1, 2), (3, 4)]));
+        assert!(BTreeMap::<i64, u8>::deserialize(&value("{ 1, x = 2 }")).is_err());
+        let ints = Vec::<u8>::deserialize(&value("{ 1.0, 2, 3.0 }")).unwrap();
+        assert_eq!(ints, [1, 2, 3]);
+        assert!(u8::deserialize(&value("1.5")).is_err());
+    }
+
+    #[test]
+    fn reads_enums_and_options_as_the_serializer_writes_them() {
+        let value = value(
+            "{ 'Empty', { Circle = 1.5 }, { Line = { 1, 2 } }, { Label = { text = 'hi' } } }",
+        );
+        let shapes = Vec::<Shape>::deserialize(&value).unwrap();
+        assert_eq!(
+            shapes,
+            [
+                Shape::Empty,
+                Shape::Circle(1.5),
+                Shape::Line(1, 2),
+                Shape::Label { text: "hi" }
+            ]
+        );
+        assert!(Shape::deserialize(&self::value("{ Circle = 1, Empty = true }")).is_err());
+        assert!(Shape::deserialize(&self::value("1")).is_err());
+
+        assert_eq!(Option::<u8>::deserialize(&Value::Nil).unwrap(), None);
+        assert_eq!(Option::<u8>::deserialize(&Value::from(7)).unwrap(), Some(7));
+    }
+}
@@ -0,0 +1,159 @@
+use super::Value;
+use crate::PathSegment;
+
+/// A difference between two values, see [`diff`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// The new value has a field the old one doesn't have.
+    Added {
+        path: Vec<PathSegment>,
+        value: Value,
+    },
+    /// The old value has a field the new one doesn't have.
+    Removed {
+        path: Vec<PathSegment>,
+        value: Value,
+    },
+    /// The value at the path differs, and isn't a table in both values.
+    Changed {
+        path: Vec<PathSegment>,
+        old: Value,
+        new: Value,
+    },
+}
+
+impl Change {
+    /// The path from the outermost value to the changed one.
+    pub fn path(&self) -> &[PathSegment] {
+        match self {
+            Change::Added { path, .. }
+            | Change::Removed { path, .. }
+            | Change::Changed { path, .. } => path,
+        }
+    }
+}
+
+/// Compares two values and returns the fields that were added, removed or changed, so only the
+/// data is compared and not how it was written.
+///
+/// Tables are compared field by field, the fields of the old table come first. Any other values
+/// that differ are a single change, including a table that replaces something else.
+///
+/// ```
+/// use serde_lua_table::{value::{self, Change}, PathSegment, Value};
+///
+/// let old: Value = serde_lua_table::from_str("{ size = 1, tags = {'a'} }").unwrap();
+/// let new: Value = serde_lua_table::from_str("{ size = 2, tags = {'a', 'b'} }").unwrap();
+/// assert_eq!(
+///     value::diff(&old, &new),
+///     [
+///         Change::Changed {
+///             path: vec![PathSegment::Key("size".into())],
+///             old: Value::Integer(1),
+///             new: Value::Integer(2),
+///         },
+///         Change::Added {
+///             path: vec![PathSegment::Key("tags".into()), PathSegment::Index(2)],
+///             value: Value::from("b"),
+///         },
+///     ]
+/// );
+/// ```
+pub fn diff(old: &Value, new: &Value) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_at(&mut Vec::new(), old, new, &mut changes);
+    changes
+}
+
+fn diff_at(path: &mut Vec<PathSegment>, old: &Value, new: &Value, changes: &mut Vec<Change>) {
+    let (Value::Table(old_table), Value::Table(new_table)) = (old, new) else {
+        if old != new {
+            changes.push(Change::Changed {
+                path: path.clone(),
+                old: old.clone(),
+                new: new.clone(),
+            });
+        }
+        return;
+    };
+
+    for (key, old) in old_table.iter() {
+        path.push(key.segment());
+        match new_table.get(&key) {
+            Some(new) => diff_at(path, old, new, changes),
+            None => changes.push(Change::Removed {
+                path: path.clone(),
+                value: old.clone(),
+            }),
+        }
+        path.pop();
+    }
+    for (key, new) in new_table.iter() {
+        if old_table.get(&key).is_none() {
+            path.push(key.segment());
+            changes.push(Change::Added {
+                path: path.clone(),
+                value: new.clone(),
+            });
+            path.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, Change};
+    use crate::{PathSegment, Value};
+
+    fn parse(lua: &str) -> Value {
+        crate::from_str(lua).unwrap()
+    }
+
+    #[test]
+    fn ignores_formatting_and_field_order() {
+        let old = parse("{ a = 1, b = { 'x', 'y' } }");
+        let new = parse("{\n  ['b'] = {\n    [1] = \"x\", [2] = [[y]]\n  },\n  a = 0x1,\n}");
+        assert!(diff(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn reports_removed_fields_before_added_ones() {
+        let old = parse("{ a = 1, b = 2 }");
+        let new = parse("{ c = 3, a = 1 }");
+        assert_eq!(
+            diff(&old, &new),
+            [
+                Change::Removed {
+                    path: vec![PathSegment::Key("b".into())],
+                    value: Value::Integer(2),
+                },
+                Change::Added {
+                    path: vec![PathSegment::Key("c".into())],
+                    value: Value::Integer(3),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_replaced_tables_as_one_change() {
+        let old = parse("{ a = { 1 } }");
+        let new = parse("{ a = 'none' }");
+        let changes = diff(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path(), [PathSegment::Key("a".into())]);
+        assert!(matches!(
+            &changes[0],
+            Change::Changed {
+                old: Value::Table(_),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn tells_integers_and_floats_apart() {
+        assert_eq!(diff(&parse("1"), &parse("1.0")).len(), 1);
+        assert!(diff(&parse("0/0"), &parse("0/0")).is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+use super::{Key, Value};
+use serde::{
+    de::{MapAccess, Visitor},
+    ser::SerializeMap,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::fmt;
+
+/// The fields of a table that a struct doesn't know about, so tools can write them back.
+///
+/// Used with `#[serde(flatten)]`, it collects every key the other fields don't take when
+/// deserializing, and writes them after the other fields when serializing.
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use serde_lua_table::value::{Extras, Key, Table, Value};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Settings {
+///     volume: u8,
+///     #[serde(flatten)]
+///     extras: Extras,
+/// }
+///
+/// let table: Table = [
+///     ("volume", Value::from(80)),
+///     ("theme", Value::from("dark")),
+/// ]
+/// .into_iter()
+/// .collect();
+/// let mut settings = Settings::deserialize(&Value::from(table)).unwrap();
+/// assert_eq!(settings.extras.get(&Key::from("theme")), Some(&Value::from("dark")));
+///
+/// settings.volume = 50;
+/// assert_eq!(
+///     serde_lua_table::to_string(&settings).unwrap(),
+///     r#"{["volume"]=50,["theme"]="dark"}"#
+/// );
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Extras {
+    fields: Vec<(Key, Value)>,
+}
+
+impl Extras {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn get(&self, key: &Key) -> Option<&Value> {
+        self.fields.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Sets the value at `key` and returns the previous value. Inserting `nil` removes the key.
+    pub fn insert(&mut self, key: impl Into<Key>, value: Value) -> Option<Value> {
+        let key = key.into();
+        if value.is_nil() {
+            return self.remove(&key);
+        }
+        match self.fields.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, slot)) => Some(std::mem::replace(slot, value)),
+            None => {
+                self.fields.push((key, value));
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &Key) -> Option<Value> {
+        let position = self.fields.iter().position(|(k, _)| k == key)?;
+        Some(self.fields.remove(position).1)
+    }
+
+    /// Iterates over the fields in the order they were read.
+    pub fn iter(&self) -> impl Iterator<Item = (&Key, &Value)> {
+        self.fields.iter().map(|(k, v)| (k, v))
+    }
+}
+
+impl Serialize for Extras {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.fields.len()))?;
+        for (key, value) in &self.fields {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Extras {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(ExtrasVisitor)
+    }
+}
+
+struct ExtrasVisitor;
+
+impl<'de> Visitor<'de> for ExtrasVisitor {
+    type Value = Extras;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a table")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Extras, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut extras = Extras::new();
+        while let Some((key, value)) = map.next_entry::<Key, Value>()? {
+            extras.insert(key, value);
+        }
+        Ok(extras)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Extras, Key, Value};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Settings {
+        volume: u8,
+        #[serde(flatten)]
+        extras: Extras,
+    }
+
+    #[test]
+    fn round_trips_unknown_fields_in_order() {
+        let lua = "{ theme = 'dark', volume = 80, [2] = true, nested = { 1, 2 } }";
+        let settings: Settings = crate::from_str(lua).unwrap();
+        assert_eq!(settings.volume, 80);
+        let keys: Vec<_> = settings.extras.iter().map(|(key, _)| key.clone()).collect();
+        assert_eq!(
+            keys,
+            [Key::from("theme"), Key::from(2), Key::from("nested")]
+        );
+        assert_eq!(
+            crate::to_string(&settings).unwrap(),
+            r#"{["volume"]=80,["theme"]="dark",[2]=true,["nested"]={1,2}}"#
+        );
+    }
+
+    #[test]
+    fn inserting_nil_removes_the_field() {
+        let mut extras = Extras::new();
+        assert_eq!(extras.insert("a", Value::from(1)), None);
+        assert_eq!(extras.insert("a", Value::from(2)), Some(Value::from(1)));
+        assert_eq!(extras.len(), 1);
+        assert_eq!(extras.insert("a", Value::Nil), Some(Value::from(2)));
+        assert!(extras.is_empty());
+    }
+}
@@ -0,0 +1,250 @@
+//! Upgrading old versions of a document.
+
+use super::{Key, Value};
+use crate::DeError;
+use serde::de::DeserializeOwned;
+
+/// Why a document couldn't be migrated.
+#[derive(Debug, thiserror::Error)]
+pub enum MigrationError {
+    /// Only tables can have a version field.
+    #[error("expected a table")]
+    NotATable,
+    /// The version field isn't a non-negative integer.
+    #[error("invalid version: {0:?}")]
+    InvalidVersion(Value),
+    /// The document was written by a newer version than the migrations know about.
+    #[error("version {found} is newer than the latest version {latest}")]
+    UnknownVersion { found: i64, latest: i64 },
+    /// The input isn't lua or the migrated document doesn't match the expected type.
+    #[error(transparent)]
+    Deserialize(#[from] DeError),
+}
+
+/// An ordered list of migrations between versions of a document.
+///
+/// The version is read from a field of the root table, documents without it have version 0. The
+/// first registered migration upgrades version 0 to 1, the next one 1 to 2 and so on. After
+/// migrating, the version field is set to the latest version, so the document can be written back
+/// in the current format.
+///
+/// [`from_str`](Migrations::from_str) reads a document, migrates it and deserializes the result
+/// into the current version of a type in one step.
+///
+/// ```
+/// use serde_lua_table::value::{Key, Migrations, Table, Value};
+///
+/// let migrations = Migrations::new("version")
+///     // version 1 renamed `colour` to `color`
+///     .with(|mut value| {
+///         if let Some(table) = value.as_table_mut() {
+///             if let Some(color) = table.remove(&Key::from("colour")) {
+///                 table.insert("color", color);
+///             }
+///         }
+///         value
+///     });
+///
+/// let old: Table = [("colour", Value::from("red"))].into_iter().collect();
+/// let new = migrations.migrate(Value::from(old)).unwrap();
+///
+/// assert_eq!(
+///     serde_lua_table::to_string(&new).unwrap(),
+///     r#"{["color"]="red",["version"]=1}"#
+/// );
+/// ```
+pub struct Migrations {
+    field: Key,
+    steps: Vec<fn(Value) -> Value>,
+}
+
+impl Migrations {
+    /// Creates an empty list of migrations reading the version from `field`.
+    pub fn new(field: impl Into<Key>) -> Self {
+        Migrations {
+            field: field.into(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Registers a migration from the current latest version to the next one.
+    pub fn with(mut self, migration: fn(Value) -> Value) -> Self {
+        self.steps.push(migration);
+        self
+    }
+
+    /// The version documents have after migrating.
+    pub fn latest(&self) -> i64 {
+        self.steps.len() as i64
+    }
+
+    /// Returns the version of `value`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `value` isn't a table or its version field isn't a non-negative integer.
+    pub fn version_of(&self, value: &Value) -> Result<i64, MigrationError> {
+        let table = value.as_table().ok_or(MigrationError::NotATable)?;
+        match table.get(&self.field) {
+            None => Ok(0),
+            Some(Value::Integer(version)) if *version >= 0 => Ok(*version),
+            Some(other) => Err(MigrationError::InvalidVersion(other.clone())),
+        }
+    }
+
+    /// Applies the migrations `value` hasn't seen yet and sets its version to the latest one.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the version can't be read or is newer than [`latest`](Self::latest).
+    pub fn migrate(&self, mut value: Value) -> Result<Value, MigrationError> {
+        let found = self.version_of(&value)?;
+        let latest = self.latest();
+        if found > latest {
+            return Err(MigrationError::UnknownVersion { found, latest });
+        }
+
+        for migration in &self.steps[found as usize..] {
+            value = migration(value);
+        }
+        let table = value.as_table_mut().ok_or(MigrationError::NotATable)?;
+        table.insert(self.field.clone(), Value::Integer(latest));
+        Ok(value)
+    }
+
+    /// Migrates `value` and deserializes the result into `T`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `value` can't be migrated or the migrated document doesn't match `T`.
+    pub fn deserialize<T>(&self, value: Value) -> Result<T, MigrationError>
+    where
+        T: DeserializeOwned,
+    {
+        let value = self.migrate(value)?;
+        Ok(T::deserialize(&value)?)
+    }
+
+    /// Reads a document from a string of lua, migrates it and deserializes the result into `T`.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_lua_table::value::{Key, Migrations, Value};
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Config {
+    ///     version: i64,
+    ///     timeout_ms: u64,
+    /// }
+    ///
+    /// let migrations = Migrations::new("version")
+    ///     // version 1 changed `timeout` from seconds to `timeout_ms`
+    ///     .with(|mut value| {
+    ///         if let Some(table) = value.as_table_mut() {
+    ///             if let Some(Value::Integer(secs)) = table.remove(&Key::from("timeout")) {
+    ///                 table.insert("timeout_ms", Value::Integer(secs * 1000));
+    ///             }
+    ///         }
+    ///         value
+    ///     });
+    ///
+    /// let config: Config = migrations.from_str("{ timeout = 3 }").unwrap();
+    /// assert_eq!((config.version, config.timeout_ms), (1, 3000));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the input isn't a lua value, can't be migrated or the migrated document doesn't
+    /// match `T`.
+    pub fn from_str<T>(&self, s: &str) -> Result<T, MigrationError>
+    where
+        T: DeserializeOwned,
+    {
+        self.deserialize(crate::from_str(s)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config {
+        version: i64,
+        name: String,
+        #[serde(default)]
+        retries: u32,
+    }
+
+    fn migrations() -> Migrations {
+        Migrations::new("version")
+            // version 1 renamed `title` to `name`
+            .with(|mut value| {
+                if let Some(table) = value.as_table_mut() {
+                    if let Some(title) = table.remove(&Key::from("title")) {
+                        table.insert("name", title);
+                    }
+                }
+                value
+            })
+            // version 2 added `retries`, old documents retried once
+            .with(|mut value| {
+                if let Some(table) = value.as_table_mut() {
+                    table.insert("retries", Value::Integer(1));
+                }
+                value
+            })
+    }
+
+    #[test]
+    fn applies_all_migrations_to_unversioned_documents() {
+        let config: Config = migrations().from_str("{ title = 'old' }").unwrap();
+        assert_eq!(
+            config,
+            Config {
+                version: 2,
+                name: "old".to_owned(),
+                retries: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn skips_migrations_a_document_has_seen() {
+        let config: Config = migrations()
+            .from_str("{ version = 1, name = 'new', retries = 5 }")
+            .unwrap();
+        assert_eq!(config.retries, 1);
+
+        let config: Config = migrations()
+            .from_str("{ version = 2, name = 'new', retries = 5 }")
+            .unwrap();
+        assert_eq!(config.retries, 5);
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let err = migrations()
+            .from_str::<Config>("{ version = 3, name = 'future' }")
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            MigrationError::UnknownVersion {
+                found: 3,
+                latest: 2
+            }
+        ));
+    }
+
+    #[test]
+    fn reports_documents_not_matching_the_type() {
+        let err = migrations()
+            .from_str::<Config>("{ version = 2 }")
+            .unwrap_err();
+        assert!(matches!(err, MigrationError::Deserialize(_)), "{err:?}");
+
+        let err = migrations().from_str::<Config>("{ version = ").unwrap_err();
+        assert!(matches!(err, MigrationError::Deserialize(_)), "{err:?}");
+    }
+}
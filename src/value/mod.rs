@@ -0,0 +1,556 @@
+//! A loosely typed representation of lua values.
+//!
+//! [`Value`] holds any value the serializer can write. Tables keep their sequence separate
+//! from their other fields, so `{1, 2, x = 3}` is stored as the sequence `1, 2` and the field
+//! `x`. Keys are restricted to integers and strings like the keys of serialized maps.
+//!
+//! ```
+//! use serde_lua_table::value::{Key, Table, Value};
+//!
+//! let mut table = Table::new();
+//! table.push(Value::from("first"));
+//! table.insert("name", Value::from("ferris"));
+//! table.insert(2, Value::from("second"));
+//!
+//! assert_eq!(table.sequence().len(), 2);
+//! assert_eq!(table.get(&Key::from("name")), Some(&Value::from("ferris")));
+//! assert_eq!(
+//!     serde_lua_table::to_string(&Value::Table(table)).unwrap(),
+//!     r#"{[1]="first",[2]="second",["name"]="ferris"}"#
+//! );
+//! ```
+
+mod borrowed;
+mod de;
+mod deserializer;
+mod diff;
+mod extras;
+mod migrate;
+mod ser;
+#[cfg(feature = "yaml")]
+mod yaml;
+
+pub use borrowed::BorrowedValue;
+pub use diff::{diff, Change};
+pub use extras::Extras;
+pub use migrate::{MigrationError, Migrations};
+#[cfg(feature = "yaml")]
+pub use yaml::FromYamlError;
+
+use std::{
+    hash::{Hash, Hasher},
+    mem,
+    sync::Arc,
+};
+
+/// Any lua value the serializer can write.
+///
+/// Values are compared like `OrderedFloat` compares floats: `NaN` equals itself and `0.0` equals
+/// `-0.0`. That makes equality an equivalence relation, so values can be used as keys of hash
+/// maps, e.g. to deduplicate documents. Integers and floats are never equal, even if lua would
+/// consider `1` and `1.0` the same.
+///
+/// ```
+/// use serde_lua_table::Value;
+/// use std::collections::HashSet;
+///
+/// let values = [Value::from(f64::NAN), Value::from(-0.0), Value::from(f64::NAN), Value::from(0.0)];
+/// assert_eq!(values.into_iter().collect::<HashSet<_>>().len(), 2);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub enum Value {
+    #[default]
+    Nil,
+    Boolean(bool),
+    Integer(i64),
+    Number(f64),
+    /// A decimal number as it was written, kept by
+    /// [`DeOptions::with_raw_numbers`](crate::DeOptions::with_raw_numbers) so digits that don't
+    /// fit into an `i64` or `f64` aren't lost. It's written back unchanged.
+    RawNumber(String),
+    String(String),
+    /// A string that isn't valid UTF-8. Lua strings can hold any bytes, e.g. from `\xFF` escapes.
+    Bytes(Vec<u8>),
+    Table(Table),
+}
+
+impl Value {
+    /// Returns `true` if the value is `nil`.
+    pub fn is_nil(&self) -> bool {
+        matches!(self, Value::Nil)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Returns the integer, or a raw number that is an integer in range.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Integer(i) => Some(*i),
+            Value::RawNumber(n) => n.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a float, converting integers and raw numbers like lua does.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Integer(i) => Some(*i as f64),
+            Value::Number(n) => Some(*n),
+            Value::RawNumber(n) => n.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns the float as an `OrderedFloat`, converting integers like lua does.
+    #[cfg(feature = "ordered-float")]
+    pub fn as_ordered_f64(&self) -> Option<ordered_float::OrderedFloat<f64>> {
+        self.as_f64().map(ordered_float::OrderedFloat)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the bytes of a string, whether or not it is valid UTF-8.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::String(s) => Some(s.as_bytes()),
+            Value::Bytes(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_table(&self) -> Option<&Table> {
+        match self {
+            Value::Table(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    pub fn as_table_mut(&mut self) -> Option<&mut Table> {
+        match self {
+            Value::Table(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    /// Merges `other` into the value: the fields of a table are merged one by one, everything
+    /// else replaces the value. Sequences are replaced as a whole, so a shorter list doesn't
+    /// keep the elements after its end.
+    pub fn merge(&mut self, other: Value) {
+        match (self, other) {
+            (Value::Table(table), Value::Table(other))
+                if table.sequence.is_empty() && other.sequence.is_empty() =>
+            {
+                for (key, value) in other.fields {
+                    match table.get_mut(&key) {
+                        Some(slot) => slot.merge(value),
+                        None => {
+                            table.insert(key, value);
+                        }
+                    }
+                }
+            }
+            (slot, other) => *slot = other,
+        }
+    }
+}
+
+/// Returns the bits of `v`, with all `NaN`s and zeros mapped to one representation each.
+fn float_bits(v: f64) -> u64 {
+    if v.is_nan() {
+        f64::NAN.to_bits()
+    } else if v == 0.0 {
+        0
+    } else {
+        v.to_bits()
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            (Value::Integer(a), Value::Integer(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => float_bits(*a) == float_bits(*b),
+            (Value::RawNumber(a), Value::RawNumber(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Bytes(a), Value::Bytes(b)) => a == b,
+            (Value::Table(a), Value::Table(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match self {
+            Value::Nil => (),
+            Value::Boolean(b) => b.hash(state),
+            Value::Integer(i) => i.hash(state),
+            Value::Number(n) => float_bits(*n).hash(state),
+            Value::RawNumber(n) => n.hash(state),
+            Value::String(s) => s.hash(state),
+            Value::Bytes(b) => b.hash(state),
+            Value::Table(t) => t.hash(state),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(v: bool) -> Self {
+        Value::Boolean(v)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(v: i64) -> Self {
+        Value::Integer(v)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(v: f64) -> Self {
+        Value::Number(v)
+    }
+}
+
+#[cfg(feature = "ordered-float")]
+impl From<ordered_float::OrderedFloat<f64>> for Value {
+    fn from(v: ordered_float::OrderedFloat<f64>) -> Self {
+        Value::Number(v.into_inner())
+    }
+}
+
+impl From<String> for Value {
+    fn from(v: String) -> Self {
+        Value::String(v)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(v: &str) -> Self {
+        Value::String(v.to_owned())
+    }
+}
+
+/// Bytes that are valid UTF-8 become a [`Value::String`].
+impl From<Vec<u8>> for Value {
+    fn from(v: Vec<u8>) -> Self {
+        String::from_utf8(v).map_or_else(|e| Value::Bytes(e.into_bytes()), Value::String)
+    }
+}
+
+impl From<Table> for Value {
+    fn from(v: Table) -> Self {
+        Value::Table(v)
+    }
+}
+
+impl<T> From<Option<T>> for Value
+where
+    T: Into<Value>,
+{
+    fn from(v: Option<T>) -> Self {
+        v.map_or(Value::Nil, Into::into)
+    }
+}
+
+/// The key of a table field.
+///
+/// String keys are shared: deserializing a [`Value`] reuses one allocation for every occurrence
+/// of the same key, so large sequences of records don't store their field names once per record.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Key {
+    Integer(i64),
+    String(Arc<str>),
+}
+
+impl Key {
+    /// Returns the key as a segment of the path to its value.
+    pub(crate) fn segment(&self) -> crate::PathSegment {
+        match self {
+            Key::Integer(i) => crate::PathSegment::Index(*i),
+            Key::String(s) => crate::PathSegment::Key(s.to_string()),
+        }
+    }
+}
+
+impl From<i64> for Key {
+    fn from(v: i64) -> Self {
+        Key::Integer(v)
+    }
+}
+
+impl From<String> for Key {
+    fn from(v: String) -> Self {
+        Key::String(v.into())
+    }
+}
+
+impl From<&str> for Key {
+    fn from(v: &str) -> Self {
+        Key::String(v.into())
+    }
+}
+
+impl From<Arc<str>> for Key {
+    fn from(v: Arc<str>) -> Self {
+        Key::String(v)
+    }
+}
+
+/// A lua table.
+///
+/// The values at the keys `1..=n` are stored as the sequence, every other field in insertion
+/// order. A table never stores `nil`: inserting it removes the field like assigning `nil` does
+/// in lua.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Table {
+    sequence: Vec<Value>,
+    fields: Vec<(Key, Value)>,
+}
+
+impl Table {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The values at the keys `1..=n`.
+    pub fn sequence(&self) -> &[Value] {
+        &self.sequence
+    }
+
+    /// The fields that aren't part of the sequence, in insertion order.
+    pub fn fields(&self) -> &[(Key, Value)] {
+        &self.fields
+    }
+
+    /// Returns `true` if the table has neither a sequence nor fields.
+    pub fn is_empty(&self) -> bool {
+        self.sequence.is_empty() && self.fields.is_empty()
+    }
+
+    /// Appends `value` to the sequence, like `table.insert(t, value)`.
+    pub fn push(&mut self, value: Value) {
+        if !value.is_nil() {
+            self.insert(Key::Integer(self.sequence.len() as i64 + 1), value);
+        }
+    }
+
+    /// Returns the value at `key`.
+    pub fn get(&self, key: &Key) -> Option<&Value> {
+        match self.sequence_index(key) {
+            Some(index) if index < self.sequence.len() => self.sequence.get(index),
+            _ => self.fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+        }
+    }
+
+    /// Returns the value at `key` mutably.
+    pub fn get_mut(&mut self, key: &Key) -> Option<&mut Value> {
+        match self.sequence_index(key) {
+            Some(index) if index < self.sequence.len() => self.sequence.get_mut(index),
+            _ => self
+                .fields
+                .iter_mut()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v),
+        }
+    }
+
+    /// Sets the value at `key` and returns the previous value.
+    pub fn insert(&mut self, key: impl Into<Key>, value: Value) -> Option<Value> {
+        let key = key.into();
+        if value.is_nil() {
+            return self.remove(&key);
+        }
+
+        if let Some(index) = self.sequence_index(&key) {
+            if let Some(slot) = self.sequence.get_mut(index) {
+                return Some(mem::replace(slot, value));
+            }
+            if index == self.sequence.len() {
+                self.sequence.push(value);
+                self.extend_sequence();
+                return None;
+            }
+        }
+
+        match self.fields.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, slot)) => Some(mem::replace(slot, value)),
+            None => {
+                self.fields.push((key, value));
+                None
+            }
+        }
+    }
+
+    /// Removes the value at `key`. Removing from the middle of the sequence ends it there, the
+    /// values after the key become fields.
+    pub fn remove(&mut self, key: &Key) -> Option<Value> {
+        match self.sequence_index(key) {
+            Some(index) if index < self.sequence.len() => {
+                let tail = self.sequence.split_off(index + 1);
+                let removed = self.sequence.pop();
+                for (i, value) in tail.into_iter().enumerate() {
+                    let key = Key::Integer((index + i) as i64 + 2);
+                    self.fields.push((key, value));
+                }
+                removed
+            }
+            _ => {
+                let position = self.fields.iter().position(|(k, _)| k == key)?;
+                Some(self.fields.remove(position).1)
+            }
+        }
+    }
+
+    /// Iterates over the sequence with its keys, then over the fields.
+    pub fn iter(&self) -> impl Iterator<Item = (Key, &Value)> {
+        let sequence = self
+            .sequence
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (Key::Integer(i as i64 + 1), v));
+        let fields = self.fields.iter().map(|(k, v)| (k.clone(), v));
+        sequence.chain(fields)
+    }
+
+    /// Returns the index into the sequence `key` would have.
+    fn sequence_index(&self, key: &Key) -> Option<usize> {
+        match key {
+            Key::Integer(i) if *i >= 1 => usize::try_from(*i - 1).ok(),
+            _ => None,
+        }
+    }
+
+    /// Moves fields continuing the sequence into it.
+    fn extend_sequence(&mut self) {
+        loop {
+            let next = Key::Integer(self.sequence.len() as i64 + 1);
+            match self.fields.iter().position(|(k, _)| *k == next) {
+                Some(position) => {
+                    let (_, value) = self.fields.remove(position);
+                    self.sequence.push(value);
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+impl FromIterator<Value> for Table {
+    fn from_iter<I: IntoIterator<Item = Value>>(iter: I) -> Self {
+        let mut table = Table::new();
+        for value in iter {
+            table.push(value);
+        }
+        table
+    }
+}
+
+impl<K> FromIterator<(K, Value)> for Table
+where
+    K: Into<Key>,
+{
+    fn from_iter<I: IntoIterator<Item = (K, Value)>>(iter: I) -> Self {
+        let mut table = Table::new();
+        for (key, value) in iter {
+            table.insert(key, value);
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gets_integer_fields_after_the_sequence() {
+        let mut table = Table::new();
+        table.insert(4, Value::from("a"));
+        table.push(Value::from("b"));
+        assert_eq!(table.sequence(), [Value::from("b")]);
+        assert_eq!(table.get(&Key::from(1)), Some(&Value::from("b")));
+        assert_eq!(table.get(&Key::from(4)), Some(&Value::from("a")));
+        assert_eq!(table.get(&Key::from(2)), None);
+
+        *table.get_mut(&Key::from(4)).unwrap() = Value::from("c");
+        assert_eq!(table.get(&Key::from(4)), Some(&Value::from("c")));
+    }
+
+    #[test]
+    fn moves_fields_into_the_sequence() {
+        let mut table = Table::new();
+        table.insert(2, Value::from(2));
+        table.insert(3, Value::from(3));
+        assert!(table.sequence().is_empty());
+        table.insert(1, Value::from(1));
+        assert_eq!(table.sequence().len(), 3);
+        assert!(table.fields().is_empty());
+    }
+
+    #[test]
+    fn merges_tables_and_replaces_sequences() {
+        let mut value =
+            crate::from_str::<Value>("{ a = { x = 1, y = 2 }, list = { 1, 2, 3 } }").unwrap();
+        value.merge(crate::from_str("{ a = { y = 3 }, list = { 4 }, b = true }").unwrap());
+        assert_eq!(
+            value,
+            crate::from_str("{ a = { x = 1, y = 3 }, list = { 4 }, b = true }").unwrap()
+        );
+    }
+
+    #[test]
+    fn compares_floats_totally() {
+        use std::collections::HashMap;
+
+        assert_eq!(Value::from(f64::NAN), Value::from(-f64::NAN));
+        assert_eq!(Value::from(0.0), Value::from(-0.0));
+        assert_ne!(Value::from(1), Value::from(1.0));
+
+        let mut counts = HashMap::new();
+        for lua in ["{ 0/0, -0.0 }", "{ 0/0, 0.0 }", "{ 0/0, 0 }"] {
+            *counts
+                .entry(crate::from_str::<Value>(lua).unwrap())
+                .or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 2);
+        assert_eq!(
+            counts[&crate::from_str::<Value>("{ 0/0, 0.0 }").unwrap()],
+            2
+        );
+    }
+
+    #[cfg(feature = "ordered-float")]
+    #[test]
+    fn converts_ordered_floats() {
+        use ordered_float::OrderedFloat;
+
+        assert_eq!(Value::from(OrderedFloat(1.5)), Value::from(1.5));
+        assert_eq!(Value::from(2).as_ordered_f64(), Some(OrderedFloat(2.0)));
+        assert_eq!(Value::from("2").as_ordered_f64(), None);
+    }
+
+    #[test]
+    fn removing_from_the_sequence_ends_it() {
+        let mut table: Table = [1, 2, 3].into_iter().map(Value::from).collect();
+        assert_eq!(table.insert(2, Value::Nil), Some(Value::from(2)));
+        assert_eq!(table.sequence(), [Value::from(1)]);
+        assert_eq!(table.fields(), [(Key::from(3), Value::from(3))]);
+        assert_eq!(table.get(&Key::from(3)), Some(&Value::from(3)));
+    }
+}
@@ -0,0 +1,56 @@
+use super::{Key, Table, Value};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Value::Nil => serializer.serialize_unit(),
+            Value::Boolean(b) => serializer.serialize_bool(*b),
+            Value::Integer(i) => serializer.serialize_i64(*i),
+            Value::Number(n) => serializer.serialize_f64(*n),
+            Value::RawNumber(n) => crate::RawLua(n).serialize(serializer),
+            Value::String(s) => serializer.serialize_str(s),
+            Value::Bytes(b) => crate::helpers::lua_string_bytes::serialize(b, serializer),
+            Value::Table(t) => t.serialize(serializer),
+        }
+    }
+}
+
+impl Serialize for Key {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Key::Integer(i) => serializer.serialize_i64(*i),
+            Key::String(s) => serializer.serialize_str(s),
+        }
+    }
+}
+
+/// Tables without fields are written as sequences, all others as maps with the sequence at
+/// explicit keys.
+impl Serialize for Table {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if self.fields.is_empty() {
+            let mut seq = serializer.serialize_seq(Some(self.sequence.len()))?;
+            for value in &self.sequence {
+                seq.serialize_element(value)?;
+            }
+            seq.end()
+        } else {
+            let len = self.sequence.len() + self.fields.len();
+            let mut map = serializer.serialize_map(Some(len))?;
+            for (key, value) in self.iter() {
+                map.serialize_entry(&key, value)?;
+            }
+            map.end()
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! Conversions between [`Value`] and [`serde_yaml::Value`].
+
+use super::{Key, Table, Value};
+use serde_yaml::{value::TaggedValue, Mapping, Number};
+
+/// Why a YAML value can't be converted to a lua value.
+#[derive(Debug, thiserror::Error)]
+pub enum FromYamlError {
+    /// Lua tables only use integers and strings as keys here, `null`, booleans, floats with a
+    /// fraction and collections can't be used.
+    #[error("unsupported key: {0:?}")]
+    UnsupportedKey(serde_yaml::Value),
+    /// A `<<` merge key couldn't be applied.
+    #[error(transparent)]
+    Merge(#[from] serde_yaml::Error),
+}
+
+/// Converts a YAML document to a lua value.
+///
+/// Anchors have already been resolved by the YAML parser, so every alias becomes a copy of the
+/// anchored value. `<<` merge keys are applied before converting.
+///
+/// - `null` becomes `nil`, which removes the entry from its table or sequence.
+/// - Integers above `i64::MAX` become floats.
+/// - Sequences become the sequence of a table, mappings its fields.
+/// - A tagged value `!Tag value` becomes `{ Tag = value }`, which is how the serializer writes
+///   the enum variant that `serde_yaml` would write as the tagged value.
+///
+/// ```
+/// use serde_lua_table::value::Value;
+///
+/// let yaml: serde_yaml::Value = serde_yaml::from_str(
+///     "base: &base { level: 1 }\nderived:\n  <<: *base\n  tag: !Rgb [255, 0, 0]\n",
+/// )
+/// .unwrap();
+/// let value = Value::try_from(yaml).unwrap();
+///
+/// assert_eq!(
+///     serde_lua_table::to_string(&value).unwrap(),
+///     r#"{["base"]={["level"]=1},["derived"]={["tag"]={["Rgb"]={255,0,0}},["level"]=1}}"#
+/// );
+/// ```
+impl TryFrom<serde_yaml::Value> for Value {
+    type Error = FromYamlError;
+
+    fn try_from(mut value: serde_yaml::Value) -> Result<Self, Self::Error> {
+        value.apply_merge()?;
+        convert(value)
+    }
+}
+
+fn convert(value: serde_yaml::Value) -> Result<Value, FromYamlError> {
+    Ok(match value {
+        serde_yaml::Value::Null => Value::Nil,
+        serde_yaml::Value::Bool(b) => Value::Boolean(b),
+        serde_yaml::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Number(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        serde_yaml::Value::String(s) => Value::String(s),
+        serde_yaml::Value::Sequence(seq) => {
+            let mut table = Table::new();
+            for value in seq {
+                table.push(convert(value)?);
+            }
+            Value::Table(table)
+        }
+        serde_yaml::Value::Mapping(mapping) => {
+            let mut table = Table::new();
+            for (key, value) in mapping {
+                table.insert(convert_key(key)?, convert(value)?);
+            }
+            Value::Table(table)
+        }
+        serde_yaml::Value::Tagged(tagged) => {
+            let TaggedValue { tag, value } = *tagged;
+            let tag = tag.to_string();
+            let name = tag.strip_prefix('!').unwrap_or(&tag);
+            let mut table = Table::new();
+            table.insert(name, convert(value)?);
+            Value::Table(table)
+        }
+    })
+}
+
+fn convert_key(key: serde_yaml::Value) -> Result<Key, FromYamlError> {
+    match &key {
+        serde_yaml::Value::String(s) => return Ok(Key::from(s.as_str())),
+        serde_yaml::Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                return Ok(Key::Integer(i));
+            }
+            if let Some(f) = n.as_f64() {
+                if f.fract() == 0.0 && f >= i64::MIN as f64 && f < i64::MAX as f64 {
+                    return Ok(Key::Integer(f as i64));
+                }
+            }
+        }
+        _ => (),
+    }
+    Err(FromYamlError::UnsupportedKey(key))
+}
+
+/// Converts a lua value to a YAML document.
+///
+/// Tables without fields become sequences, all others mappings with the sequence at integer
+/// keys. Tags aren't restored: a table converted from a tagged value stays a mapping. Strings that
+/// aren't valid UTF-8 are converted lossily.
+impl From<Value> for serde_yaml::Value {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Nil => serde_yaml::Value::Null,
+            Value::Boolean(b) => serde_yaml::Value::Bool(b),
+            Value::Integer(i) => serde_yaml::Value::Number(i.into()),
+            Value::Number(n) => serde_yaml::Value::Number(Number::from(n)),
+            Value::RawNumber(n) => serde_yaml::from_str(&n).unwrap_or(serde_yaml::Value::String(n)),
+            Value::String(s) => serde_yaml::Value::String(s),
+            Value::Bytes(b) => serde_yaml::Value::String(String::from_utf8_lossy(&b).into_owned()),
+            Value::Table(table) if table.fields.is_empty() => {
+                serde_yaml::Value::Sequence(table.sequence.into_iter().map(Into::into).collect())
+            }
+            Value::Table(table) => {
+                let mut mapping = Mapping::with_capacity(table.sequence.len() + table.fields.len());
+                for (i, value) in table.sequence.into_iter().enumerate() {
+                    mapping.insert((i as i64 + 1).into(), value.into());
+                }
+                for (key, value) in table.fields {
+                    mapping.insert(key.into(), value.into());
+                }
+                serde_yaml::Value::Mapping(mapping)
+            }
+        }
+    }
+}
+
+impl From<Key> for serde_yaml::Value {
+    fn from(key: Key) -> Self {
+        match key {
+            Key::Integer(i) => serde_yaml::Value::Number(i.into()),
+            Key::String(s) => serde_yaml::Value::String(s.to_string()),
+        }
+    }
+}